@@ -1,16 +1,23 @@
 use std::sync::Arc;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tauri::AppHandle;
-use serde::Serialize;
-use crate::models::{WatermarkConfig, WatermarkSource};
+use serde::{Deserialize, Serialize};
+use rayon::prelude::*;
+use crate::models::{BlindMarkError, ModePolicy, WatermarkConfig, WatermarkSource};
 use super::excel::read_excel_core;
+use super::json_list::read_json_watermarks_core;
 use crate::core::{
-    compression::ArchiveProcessor,
-    file_ops::{temp_manager::TempWorkspace, scanner::FileScanner},
-    watermark::{JsonWatermarker, json_marker::DEFAULT_WATERMARK_KEY},
+    compression::{ArchiveProcessor, common::{effective_root, find_case_collisions}, var_package::{self, VarValidationReport}},
+    file_ops::{temp_manager::{TempWorkspace, ensure_writable, estimate_output_size, check_disk_space}, scanner::FileScanner},
+    watermark::{JsonWatermarker, SvgWatermarker, IniWatermarker, TomlWatermarker, config_marker::{CONFIG_WATERMARK_KEY, CONFIG_WATERMARK_SECTION}, json_marker::{self, DEFAULT_WATERMARK_KEY, SEMI_OBFUSCATED_KEYS}, svg_marker::SVG_WATERMARK_ATTRIBUTE},
 };
-use crate::utils::{progress::ProgressEmitter, parallel::ParallelProcessor};
-use crate::core::watermark::extractor::WatermarkExtractor;
+use crate::utils::{
+    progress::{ProgressEmitter, ProgressSink, ThrottledSink, TaskProgressSink, TaskProgress, ProgressRegistry, BatchFailure, BatchSummaryEvent, DetailProgressEvent, PackagingProgressEvent, ScanSummaryEvent},
+    parallel::ParallelProcessor,
+    decode_limits::{open_limited, DecodeLimits},
+};
+use crate::core::watermark::{extractor::{ExtractedWatermark, WatermarkExtractor}, encoder::WatermarkEncoder, dct::{password_seed, DEFAULT_PASSWORD}, profile::Profile};
 
 /// 单个文件的水印提取结果
 #[derive(Debug, Serialize)]
@@ -20,10 +27,29 @@ pub struct WatermarkFinding {
     pub file: String,
     /// 解码后的显示值（明文/MD5哈希/解密原文）
     pub value: String,
-    /// 水印编码模式："md5" / "plaintext" / "aes" / "unknown"
+    /// 水印编码模式："md5" / "plaintext" / "aes" / "hmac" / "unknown"
     pub mode: String,
-    /// AES 模式下是否成功解密；其他模式始终为 true
+    /// AES 模式下是否成功解密；HMAC 模式下签名是否校验通过（密钥错误或原文被篡改时为 false）；其他模式始终为 true
     pub decrypted: bool,
+    /// 水印字段在文件内的 JSON Pointer（如 `/meta/info/xHash`）；SVG 文件为属性名 `bm:watermark`，
+    /// TOML / INI 文件为 `[blindmark].watermark`
+    pub pointer: String,
+    /// 文件类型："json"（含 VAJ/VMI/VAM/VAP）/ "svg" / "config"（TOML / INI）
+    pub file_type: &'static str,
+}
+
+/// `classify_json_watermarks` 中单个文件的水印模式分类
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonWatermarkClassification {
+    /// 文件在压缩包中的相对路径
+    pub file: String,
+    /// 检测到的水印模式（去重，按首次出现顺序）；为空表示未找到水印
+    pub modes: Vec<String>,
+    /// 文件中的水印字段数量
+    pub watermark_count: usize,
+    /// 含 AES 水印时，是否全部用提供的密钥解密成功；不含 AES 水印时为 None
+    pub aes_decrypted: Option<bool>,
 }
 
 /// 图片盲水印提取结果
@@ -34,9 +60,64 @@ pub struct ImageWatermarkFinding {
     pub file: String,
     /// 提取的原始文本水印内容
     pub text: String,
+    /// 提取置信度（0.0 - 1.0），越低越可能是偶然匹配的误报
+    pub confidence: f32,
+}
+
+/// `batch_verify` 中单张图片的校验结论
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum VerifyOutcome {
+    /// 水印属于期望列表（MD5 水印已反查为对应的期望文本）
+    Match { expected: String },
+    /// 找到水印但不属于期望列表；`found` 为文本水印内容或 MD5 哈希
+    Mismatch { found: String },
+    /// 图片可读，但未找到可信的水印
+    NotFound,
+    /// 图片无法读取或处理
+    Error { message: String },
+}
+
+/// `batch_verify` 中单张图片的校验结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageVerifyResult {
+    /// 图片相对于目录 / 压缩包根的路径
+    pub file: String,
+    #[serde(flatten)]
+    pub outcome: VerifyOutcome,
+}
+
+/// `process_archive` / `process_directory` 的处理结果
+///
+/// 单个文件或单个水印的失败不再中断整批处理：失败的文件原样保留，
+/// 失败的水印不生成输出，并在 `failures` 中逐项列出。
+/// 解压失败、配置无效等无法开始处理的错误仍以 `Err` 返回。
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ProcessOutcome {
+    /// 全部成功
+    Success { output: String },
+    /// 已生成输出，但部分文件 / 水印失败
+    Partial { output: String, failures: Vec<BatchFailure> },
+    /// 没有生成任何输出，或没有任何文件被成功嵌入水印
+    Failed { failures: Vec<BatchFailure> },
+}
+
+impl ProcessOutcome {
+    /// 根据输出路径与批次汇总判定结果
+    fn from_run(output: Option<String>, summary: &BatchSummaryEvent) -> Self {
+        let failures = summary.failures.clone();
+        let watermarked: usize = summary.watermarked_by_type.values().sum();
+        match output {
+            Some(output) if failures.is_empty() => ProcessOutcome::Success { output },
+            Some(output) if watermarked > 0 => ProcessOutcome::Partial { output, failures },
+            _ => ProcessOutcome::Failed { failures },
+        }
+    }
 }
 
-/// 处理压缩包，批量添加水印
+/// 处理压缩包，批量添加水印（处理选项见 `ProcessRequest`）
 ///
 /// # 流程
 /// 1. 读取全部水印文本（单条 或 Excel 所有行）
@@ -44,149 +125,938 @@ pub struct ImageWatermarkFinding {
 /// 3. 扫描文件（仅一次）
 /// 4. 对每个水印文本：
 ///    a. 处理图片 / JSON / VAJ / VMI / VAM / VAP（写入独立临时目录）
-///    b. 打包输出：
-///       - 单水印 → output_dir/<archive>_watermarked.<ext>
-///       - 多水印 → output_dir/<水印文本>/<archive>_watermarked.<ext>
-/// 5. 清理临时文件
+///    b. `dedup_outputs` 时若处理结果与此前某个水印完全相同，硬链接复用其输出并记入警告，跳过 c、d
+///    c. 预估输出大小，磁盘空间不足时发送 `disk_space_low` 状态并记入警告
+///    d. 打包输出到 output_dir/<水印文本>/<原文件名>（`flat_output` 且单水印时直接输出到 output_dir/<原文件名>；
+///    `dir_template` 可自定义子目录层级，如 `{date}/{watermark}`，见 `render_dir_template`）
+/// 5. `write_checksums` 时在 output_dir 写入全部输出的 `SHA256SUMS`（兼容 `sha256sum -c`）
+/// 6. 发送批次汇总事件（`watermark-batch-summary`），清理临时文件，返回 `ProcessOutcome`
+///
+/// 单个文件失败时原样保留，单个水印失败时跳过其输出，均记入失败列表。
+///
+/// 输出格式默认沿用输入格式；指定 `output_format`（如 `"zip"` / `"7z"`）时所有输出统一打包为该格式。
+///
+/// 未开启 `obfuscate` 时可通过 `semi_obfuscated_keys` 改用半混淆字段名（见 `JsonWatermarker::semi_obfuscated_key`）。
+/// `strip_fields` 中的根字段（如 `buildTime`）在嵌入 JSON 水印前移除，使输出可复现。
+/// `require_work` 时若已启用的类型中没有任何可嵌入水印的文件（如只处理图片但包内无图片），直接报错。
+/// 传入 `task_id` 时运行期间可通过 `get_task_progress` 查询最新进度，结束后自动清除。
+/// `mode_policy` 为 `contentAware` 时按文件类型选择模式：JSON 类文件用 AES，SVG / 配置文件用 MD5，
+/// 图片始终嵌入文本盲水印；未指定时所有类型统一使用 `watermark_mode`。
+/// `profile` 选择图片盲水印的预设档位（`maxRobustness` / `balanced` / `maxImperceptibility`，见 `Profile`），
+/// 默认 `balanced`；提取时须使用相同档位。
+/// `nested_archive_depth` 大于 0 时，包内受支持的嵌套压缩包（如 `.var` 中的 `.zip`）会被解压、
+/// 以相同水印处理后重新打包，最多递归 `MAX_NESTED_ARCHIVE_DEPTH` 层；未指定时嵌套压缩包原样复制。
+/// `idempotent` 时已含一致水印的文件原样保留、混淆字段名固定、输出沿用源文件修改时间，
+/// 对同一份内容（包括本命令的输出）重复运行得到逐字节相同的 ZIP / VAR 输出（AES 模式首次嵌入的密文除外）。
 #[tauri::command]
 pub async fn process_archive(
     app: AppHandle,
     archive_path: String,
     config: WatermarkConfig,
-    process_images: bool,
-    process_json: bool,
-    process_vaj: bool,
-    process_vmi: bool,
-    process_vam: bool,
-    process_vap: bool,
-    output_dir: Option<String>,
-    obfuscate: bool,
-    watermark_mode: String,
-    aes_key: Option<String>,
-    selected_images: Option<Vec<String>>,
-    fast_mode: bool,
-) -> Result<String, String> {
-    let archive_path_buf = std::path::PathBuf::from(&archive_path);
-    let progress = Arc::new(ProgressEmitter::new(app));
-
-    // === 读取全部水印文本 ===
-    let watermarks: Vec<String> = match &config.watermark_source {
-        WatermarkSource::SingleText { content } => vec![content.clone()],
-        WatermarkSource::ExcelFile { path } => read_excel_core(path)?,
+    request: ProcessRequest,
+    task_id: Option<String>,
+) -> Result<ProcessOutcome, String> {
+    let (progress, watermarks, output_folders) = prepare_request(app, &config, &request)?;
+    let options = request.pipeline_options(&output_folders);
+    preflight_watermark_texts(&watermarks, &options, progress.as_ref())?;
+    // 逐图进度合并为每秒约 30 次，避免大批量时事件洪泛
+    let sink: Arc<dyn ProgressSink> = Arc::new(ThrottledSink::new(progress));
+    // 指定 task_id 时记录最新进度，供前端重新加载后通过 `get_task_progress` 恢复
+    let sink: Arc<dyn ProgressSink> = match task_id {
+        Some(task_id) => Arc::new(TaskProgressSink::new(task_id, sink)),
+        None => sink,
     };
-    let is_batch = watermarks.len() > 1;
-    let total_watermarks = watermarks.len();
+    process_archive_core(
+        Path::new(&archive_path),
+        request.output_dir.as_deref().map(Path::new),
+        &config,
+        &watermarks,
+        &options,
+        request.output_format.as_deref(),
+        sink,
+    )
+}
 
-    // 解析水印字段名（未设置时使用默认值 "_watermark"）
-    let wm_key: String = config
-        .watermark_key
-        .as_deref()
-        .filter(|k| !k.trim().is_empty())
-        .unwrap_or(DEFAULT_WATERMARK_KEY)
-        .to_string();
+/// 查询 `process_archive`（传入 `task_id` 时）正在运行的任务的最新进度
+///
+/// 任务未知或已结束时返回 `None`；供前端在运行中重新加载后重新接入进度。
+#[tauri::command]
+pub async fn get_task_progress(task_id: String) -> Option<TaskProgress> {
+    ProgressRegistry::shared().get(&task_id)
+}
 
-    let archive_name = archive_path_buf
+/// 解压前按模式预检全部水印文本（见 `WatermarkEncoder::validate_watermark_text`）
+///
+/// 图片水印超长时直接报错；MD5 模式下的长文本只发送 `text_warning` 状态提示。
+/// 批量模式开启 `append_index` 时按追加序号（及截断）后实际嵌入图片的文本校验。
+/// 同时检查输出目录模板，避免在解压之后才发现模板无效。
+fn preflight_watermark_texts(
+    watermarks: &[String],
+    options: &PipelineOptions,
+    progress: &dyn ProgressSink,
+) -> Result<(), String> {
+    if let Some(template) = options.dir_template {
+        render_dir_template(template, &[("watermark", "watermark"), ("archive_stem", "archive"), ("date", "date")])?;
+    }
+    let total = watermarks.len();
+    // 按文件类型选择模式时，仅 SVG / 配置文件使用 MD5
+    let text_mode = options.mode_for(if options.process_svg || options.process_config { "svg" } else { "json" });
+    for (idx, text) in watermarks.iter().enumerate() {
+        let image_text = if total > 1 && options.append_index {
+            WatermarkEncoder::append_index(text, idx + 1, total)
+        } else {
+            text.clone()
+        };
+        WatermarkEncoder::validate_watermark_text(&image_text, options.watermark_mode, options.process_images)
+            .map_err(|e| e.to_string())?;
+        if let Some(warning) = WatermarkEncoder::validate_watermark_text(text, text_mode, false)
+            .map_err(|e| e.to_string())?
+        {
+            progress
+                .emit_status("text_warning".to_string(), warning)
+                .map_err(|e| format!("Progress error: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// `process_archive` 的同步实现（供批量命令与测试复用）
+fn process_archive_core(
+    archive_path: &Path,
+    output_dir: Option<&Path>,
+    config: &WatermarkConfig,
+    watermarks: &[String],
+    options: &PipelineOptions,
+    output_format: Option<&str>,
+    sink: Arc<dyn ProgressSink>,
+) -> Result<ProcessOutcome, String> {
+    let started = std::time::Instant::now();
+    let mut summary = BatchSummaryEvent::default();
+    let is_batch = watermarks.len() > 1;
+
+    let archive_name = archive_path
         .file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("archive");
 
-    // 输出文件名与原始包名保持一致
-    let archive_output_filename = archive_path_buf
+    // 输出文件名与原始包名保持一致（只能解压的格式如 RAR 改为 .zip）；
+    // 指定 output_format（如 "7z"）时所有输出统一使用该格式
+    let archive_output_filename = ArchiveProcessor::shared()
+        .repack_path_as(archive_path, output_format.filter(|f| !f.trim().is_empty()))
+        .map_err(|e| e.to_string())?
         .file_name()
         .and_then(|s| s.to_str())
         .unwrap_or("archive")
         .to_string();
 
     // 输出基础目录（未指定时与源文件同目录）
-    let base_output_dir: std::path::PathBuf = match &output_dir {
-        Some(dir) => std::path::PathBuf::from(dir),
-        None => archive_path_buf
+    let base_output_dir: std::path::PathBuf = match output_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => archive_path
             .parent()
             .map(|p| p.to_path_buf())
             .unwrap_or_else(|| std::path::PathBuf::from(".")),
     };
 
+    // === 预检：输出目录不能落在源压缩包路径或临时工作区内（否则输出会被再次当作输入处理）===
+    ensure_output_not_recursive(&base_output_dir, archive_path)?;
+
+    // === 预检：输出目录可写（避免在解压/嵌入之后才因权限问题失败）===
+    std::fs::create_dir_all(&base_output_dir)
+        .map_err(|e| format!("创建输出目录失败 {}: {}", base_output_dir.display(), e))?;
+    ensure_writable(&base_output_dir).map_err(|e| e.to_string())?;
+    // 平铺输出且输出目录即源文件目录时，同名输出会覆盖原压缩包
+    if options.flat_output && !is_batch {
+        let target = base_output_dir.join(&archive_output_filename);
+        if let (Ok(target), Ok(source)) = (std::fs::canonicalize(&target), std::fs::canonicalize(archive_path)) {
+            if target == source {
+                return Err(format!("输出会覆盖原压缩包: {}", source.display()));
+            }
+        }
+    }
+
     // === Step 1: 创建工作区并解压（仅一次）===
-    progress
+    sink
         .emit_status("initializing".to_string(), "正在创建工作区...".to_string())
         .map_err(|e| format!("Progress error: {}", e))?;
 
     let workspace = TempWorkspace::new(archive_name)
         .map_err(|e| format!("创建工作区失败: {}", e))?;
 
-    progress
+    sink
         .emit_status("extracting".to_string(), format!("正在解压 {}...", archive_name))
         .map_err(|e| format!("Progress error: {}", e))?;
 
-    let archive_processor = ArchiveProcessor::new();
+    let archive_processor = if options.preserve_permissions {
+        ArchiveProcessor::shared_preserving_permissions()
+    } else {
+        ArchiveProcessor::shared()
+    };
     archive_processor
-        .extract(&archive_path_buf, workspace.extracted_path())
+        .extract(archive_path, workspace.extracted_path())
         .map_err(|e| format!("解压失败: {}", e))?;
+    // 区分大小写的文件系统上两者均已保留并会原样打包，但在 Windows 等系统上解压输出时会互相覆盖
+    for (first, later) in case_collisions(workspace.extracted_path()) {
+        summary.record_warning(later, format!("与 {} 仅大小写不同，在不区分大小写的文件系统上会互相覆盖", first));
+    }
+
+    // === Step 2-3: 扫描并对每个水印文本处理，打包到以水印文本命名的子文件夹 ===
+    // 磁盘空间 / 输出复用警告，待流水线结束后记入汇总
+    let mut space_warnings = Vec::new();
+    // 处理结果内容摘要 → 已打包的输出路径（仅 dedup_outputs 时使用）
+    let mut packaged: HashMap<String, PathBuf> = HashMap::new();
+    let outputs = run_watermark_pipeline(
+        workspace.extracted_path(),
+        config,
+        watermarks,
+        options,
+        &sink,
+        &mut summary,
+        |watermark_text, processed_path| {
+            // 批量模式始终按水印文本分子文件夹，避免输出互相覆盖
+            let subfolder = if options.flat_output && !is_batch {
+                base_output_dir.clone()
+            } else {
+                base_output_dir.join(output_subdir(options, watermark_text, archive_name)?)
+            };
+            std::fs::create_dir_all(&subfolder)
+                .map_err(|e| format!("创建输出目录失败 {}: {}", subfolder.display(), e))?;
+            let output_path = subfolder.join(&archive_output_filename);
+
+            // 内容与此前某个水印的结果完全相同（如 MD5 模式下文本相同）时复用已打包的输出
+            let content_hash = if options.dedup_outputs {
+                Some(hash_dir_contents(processed_path).map_err(|e| format!("计算输出摘要失败: {}", e))?)
+            } else {
+                None
+            };
+            if let Some(existing) = content_hash.as_ref().and_then(|hash| packaged.get(hash)) {
+                if *existing != output_path {
+                    link_or_copy(existing, &output_path)
+                        .map_err(|e| format!("复用输出失败 {}: {}", output_path.display(), e))?;
+                }
+                let reason = format!("输出与 {} 内容相同，已复用", existing.display());
+                sink
+                    .emit_status("output_deduplicated".to_string(), format!("{}: {}", watermark_text, reason))
+                    .map_err(|e| format!("Progress error: {}", e))?;
+                space_warnings.push((watermark_text.to_string(), reason));
+                return Ok(output_path.to_string_lossy().to_string());
+            }
+
+            // 打包前预估输出大小，磁盘空间可能不足时提前警告（仍继续尝试打包）
+            if let Some(warning) = estimate_output_size(processed_path)
+                .ok()
+                .and_then(|estimate| check_disk_space(&subfolder, estimate))
+            {
+                sink
+                    .emit_status("disk_space_low".to_string(), warning.clone())
+                    .map_err(|e| format!("Progress error: {}", e))?;
+                space_warnings.push((watermark_text.to_string(), warning));
+            }
+
+            sink
+                .emit_status("packaging".to_string(), format!("正在打包：{}...", &archive_output_filename))
+                .map_err(|e| format!("Progress error: {}", e))?;
+
+            archive_processor
+                .create_with_progress(processed_path, &output_path, &|bytes_written, total_bytes| {
+                    // 进度事件发送失败不影响打包
+                    let _ = sink.emit_packaging_progress(PackagingProgressEvent {
+                        filename: archive_output_filename.clone(),
+                        bytes_written,
+                        total_bytes,
+                    });
+                })
+                .map_err(|e| format!("打包失败: {}", e))?;
+            if let Some(hash) = content_hash {
+                packaged.insert(hash, output_path.clone());
+            }
+
+            Ok(output_path.to_string_lossy().to_string())
+        },
+    )?;
+    for (watermark_text, warning) in space_warnings {
+        summary.record_warning(watermark_text, warning);
+    }
+
+    // === Step 4: 输出校验清单（可选）===
+    if options.write_checksums && !outputs.is_empty() {
+        match write_checksums(&base_output_dir, &outputs) {
+            Ok(manifest) => sink
+                .emit_status("checksums_written".to_string(), format!("已写入校验清单：{}", manifest.display()))
+                .map_err(|e| format!("Progress error: {}", e))?,
+            Err(e) => summary.record_warning(CHECKSUM_FILE_NAME, format!("写入校验清单失败: {}", e)),
+        }
+    }
+
+    // 批量模式返回输出基础目录，单条模式返回输出文件路径
+    let output = match outputs.last() {
+        Some(_) if is_batch => Some(base_output_dir.to_string_lossy().to_string()),
+        last => last.cloned(),
+    };
+    let outcome = ProcessOutcome::from_run(output, &summary);
+
+    summary.watermark_count = watermarks.len();
+    finish_batch_summary(sink.as_ref(), summary, started)
+        .map_err(|e| format!("Progress error: {}", e))?;
+
+    match &outcome {
+        ProcessOutcome::Success { output } | ProcessOutcome::Partial { output, .. } => {
+            sink.emit_status("complete".to_string(), format!("Processing complete: {}", output))
+        }
+        ProcessOutcome::Failed { .. } => sink.emit_status("error".to_string(), "所有文件均处理失败".to_string()),
+    }
+    .map_err(|e| format!("Progress error: {}", e))?;
+
+    Ok(outcome)
+}
+
+/// `process_archives_batch` 中单个压缩包的处理结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveBatchResult {
+    /// 输入压缩包路径
+    pub archive: String,
+    /// 处理结果；整个压缩包失败（如无法解压）时为 `Failed`，`failures` 中只有一项
+    pub outcome: ProcessOutcome,
+}
+
+/// 依次处理多个压缩包，选项与 `process_archive` 相同（忽略 `selected_images`）
+///
+/// 每个压缩包的输出与单独调用 `process_archive` 一致。某个压缩包整体失败（损坏、无法解压、
+/// 打包出错）时：
+/// - `continue_on_error = true`：发送 `archive_skipped` 状态事件，记为 `Failed` 并继续处理其余压缩包
+/// - 否则（默认）：立即返回该错误
+///
+/// 返回与输入顺序一致的逐个压缩包结果。
+#[tauri::command]
+pub async fn process_archives_batch(
+    app: AppHandle,
+    archive_paths: Vec<String>,
+    config: WatermarkConfig,
+    request: ProcessRequest,
+    continue_on_error: Option<bool>,
+) -> Result<Vec<ArchiveBatchResult>, String> {
+    let (progress, watermarks, output_folders) = prepare_request(app, &config, &request)?;
+    let options = PipelineOptions { selected_images: None, ..request.pipeline_options(&output_folders) };
+    preflight_watermark_texts(&watermarks, &options, progress.as_ref())?;
+    let sink: Arc<dyn ProgressSink> = Arc::new(ThrottledSink::new(progress));
+    let archive_paths: Vec<std::path::PathBuf> = archive_paths.iter().map(std::path::PathBuf::from).collect();
+    process_archives_batch_core(
+        &archive_paths,
+        request.output_dir.as_deref().map(Path::new),
+        &config,
+        &watermarks,
+        &options,
+        request.output_format.as_deref(),
+        continue_on_error.unwrap_or(false),
+        sink,
+    )
+}
+
+/// `process_archives_batch` 的同步实现
+#[allow(clippy::too_many_arguments)]
+fn process_archives_batch_core(
+    archive_paths: &[std::path::PathBuf],
+    output_dir: Option<&Path>,
+    config: &WatermarkConfig,
+    watermarks: &[String],
+    options: &PipelineOptions,
+    output_format: Option<&str>,
+    continue_on_error: bool,
+    sink: Arc<dyn ProgressSink>,
+) -> Result<Vec<ArchiveBatchResult>, String> {
+    let mut results = Vec::with_capacity(archive_paths.len());
+    for (index, archive_path) in archive_paths.iter().enumerate() {
+        let archive = archive_path.to_string_lossy().to_string();
+        sink.emit_status(
+            "archive_started".to_string(),
+            format!("正在处理压缩包 {}/{}：{}", index + 1, archive_paths.len(), archive),
+        )
+        .map_err(|e| format!("Progress error: {}", e))?;
+
+        let outcome = match process_archive_core(
+            archive_path,
+            output_dir,
+            config,
+            watermarks,
+            options,
+            output_format,
+            Arc::clone(&sink),
+        ) {
+            Ok(outcome) => outcome,
+            Err(e) if continue_on_error => {
+                sink.emit_status("archive_skipped".to_string(), format!("已跳过 {}：{}", archive, e))
+                    .map_err(|e| format!("Progress error: {}", e))?;
+                ProcessOutcome::Failed { failures: vec![BatchFailure { item: archive.clone(), reason: e }] }
+            }
+            Err(e) => return Err(format!("{}: {}", archive, e)),
+        };
+        results.push(ArchiveBatchResult { archive, outcome });
+    }
+    Ok(results)
+}
+
+/// 对已解压的文件夹就地批量添加水印（无需解压/重新打包）
+///
+/// 扫描与嵌入流程与 `process_archive` 完全一致，区别在于：
+/// - 输入为目录，源目录本身不会被修改
+/// - 输出为目录：`output_dir/<水印文本>/<目录名>/`（未指定 output_dir 时与源目录同级）
+/// - 目标目录已存在且非空时报错，避免覆盖或混入旧文件
+///
+/// 结果中的输出路径：单条模式为输出目录，批量模式为输出基础目录。
+/// 选项与 `process_archive` 相同；仅适用于压缩包输出的 `output_format`、`flat_output`、
+/// `preserve_permissions`、`dedup_outputs` 与 `write_checksums` 被忽略。
+#[tauri::command]
+pub async fn process_directory(
+    app: AppHandle,
+    dir_path: String,
+    config: WatermarkConfig,
+    request: ProcessRequest,
+) -> Result<ProcessOutcome, String> {
+    let (progress, watermarks, output_folders) = prepare_request(app, &config, &request)?;
+    // 以下选项仅适用于压缩包输出
+    let options = PipelineOptions {
+        flat_output: false,
+        preserve_permissions: false,
+        dedup_outputs: false,
+        write_checksums: false,
+        ..request.pipeline_options(&output_folders)
+    };
+    preflight_watermark_texts(&watermarks, &options, progress.as_ref())?;
+    let sink: Arc<dyn ProgressSink> = Arc::new(ThrottledSink::new(progress));
+    process_directory_core(
+        Path::new(&dir_path),
+        request.output_dir.as_deref().map(Path::new),
+        &config,
+        &watermarks,
+        &options,
+        sink,
+    )
+}
+
+/// `process_directory` 的同步实现（不依赖 AppHandle，便于测试）
+fn process_directory_core(
+    dir_path: &Path,
+    output_dir: Option<&Path>,
+    config: &WatermarkConfig,
+    watermarks: &[String],
+    options: &PipelineOptions,
+    sink: Arc<dyn ProgressSink>,
+) -> Result<ProcessOutcome, String> {
+    let started = std::time::Instant::now();
+    let mut summary = BatchSummaryEvent::default();
+
+    if !dir_path.is_dir() {
+        return Err(format!("目录不存在: {}", dir_path.display()));
+    }
+    let source_dir = std::fs::canonicalize(dir_path)
+        .map_err(|e| format!("读取目录失败 {}: {}", dir_path.display(), e))?;
+    let dir_name = source_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "output".to_string());
+
+    // 输出基础目录（未指定时与源目录同级）
+    let base_output_dir: std::path::PathBuf = match output_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => source_dir
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| std::path::PathBuf::from(".")),
+    };
+    std::fs::create_dir_all(&base_output_dir)
+        .map_err(|e| format!("创建输出目录失败 {}: {}", base_output_dir.display(), e))?;
+    ensure_writable(&base_output_dir).map_err(|e| e.to_string())?;
+    // 输出位于源目录内会被下一次扫描当作输入，直接拒绝
+    let base_output_dir = std::fs::canonicalize(&base_output_dir)
+        .map_err(|e| format!("读取目录失败 {}: {}", base_output_dir.display(), e))?;
+    if base_output_dir.starts_with(&source_dir) {
+        return Err(format!("输出目录不能位于源目录内: {}", base_output_dir.display()));
+    }
+
+    let outputs = run_watermark_pipeline(
+        &source_dir,
+        config,
+        watermarks,
+        options,
+        &sink,
+        &mut summary,
+        |watermark_text, processed_path| {
+            let target = base_output_dir
+                .join(output_subdir(options, watermark_text, &dir_name)?)
+                .join(&dir_name);
+            let occupied = std::fs::read_dir(&target).map(|mut d| d.next().is_some()).unwrap_or(false);
+            if occupied {
+                return Err(format!("输出目录已存在且非空: {}", target.display()));
+            }
+            sink.emit_status("writing".to_string(), format!("正在写入：{}...", target.display()))
+                .map_err(|e| format!("Progress error: {}", e))?;
+            // processed 目录只包含处理结果，整体复制（保留修改时间与包内符号链接）
+            copy_other_files(processed_path, &target, &[], &[], &[], &[], &[], &[], &[], &[])
+                .map_err(|e| format!("写入输出目录失败: {}", e))?;
+            Ok(target.to_string_lossy().to_string())
+        },
+    )?;
+
+    let output = match outputs.last() {
+        Some(_) if watermarks.len() > 1 => Some(base_output_dir.to_string_lossy().to_string()),
+        last => last.cloned(),
+    };
+    let outcome = ProcessOutcome::from_run(output, &summary);
+
+    summary.watermark_count = watermarks.len();
+    finish_batch_summary(sink.as_ref(), summary, started)
+        .map_err(|e| format!("Progress error: {}", e))?;
+    match &outcome {
+        ProcessOutcome::Success { output } | ProcessOutcome::Partial { output, .. } => {
+            sink.emit_status("complete".to_string(), format!("Processing complete: {}", output))
+        }
+        ProcessOutcome::Failed { .. } => sink.emit_status("error".to_string(), "所有文件均处理失败".to_string()),
+    }
+    .map_err(|e| format!("Progress error: {}", e))?;
+
+    Ok(outcome)
+}
+
+/// 解析路径中的符号链接与相对路径；路径尚不存在时以最近的已存在祖先目录为准
+fn resolve_lenient(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(resolved) = std::fs::canonicalize(existing) {
+            return rest.iter().rev().fold(resolved, |acc, name| acc.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// 拒绝与源压缩包或临时工作区重合的输出目录
+///
+/// - 输出目录即源压缩包本身或位于其"下方"（如 `pkg.zip/out`）
+/// - 输出目录位于系统临时目录下的 `blindmark_*` 工作区内，输出会随工作区一起被清理，
+///   或在后续运行中被当作解压结果重新扫描
+fn ensure_output_not_recursive(output_dir: &Path, archive_path: &Path) -> Result<(), String> {
+    let output = resolve_lenient(output_dir);
+    let source = resolve_lenient(archive_path);
+    if output.starts_with(&source) {
+        return Err(format!("输出目录不能位于源压缩包路径内: {}", output.display()));
+    }
+    let temp_root = resolve_lenient(&std::env::temp_dir());
+    let in_workspace = output
+        .strip_prefix(&temp_root)
+        .ok()
+        .and_then(|rel| rel.components().next())
+        .is_some_and(|first| first.as_os_str().to_string_lossy().starts_with("blindmark_"));
+    if in_workspace {
+        return Err(format!("输出目录不能位于临时工作区内: {}", output.display()));
+    }
+    Ok(())
+}
+
+/// 读取全部水印文本（单条 / Excel 所有行 / JSON 列表）
+///
+/// 同时返回水印文本 → 输出子文件夹名的映射（仅 JSON 列表中带 `folder` 的条目）。
+fn read_watermark_texts(
+    config: &WatermarkConfig,
+    progress: &dyn ProgressSink,
+) -> Result<(Vec<String>, HashMap<String, String>), String> {
+    match &config.watermark_source {
+        WatermarkSource::SingleText { content } => Ok((vec![content.clone()], HashMap::new())),
+        WatermarkSource::ExcelFile { path, has_header } => read_excel_core(path, None, has_header.unwrap_or(true), |rows| {
+            let _ = progress.emit_status("reading_excel".to_string(), format!("已读取 {} 行...", rows));
+        })
+        .map(|texts| (texts, HashMap::new())),
+        WatermarkSource::JsonFile { path } => {
+            let entries = read_json_watermarks_core(path)?;
+            let folders = entries
+                .iter()
+                .filter_map(|e| e.folder.clone().map(|folder| (e.text.clone(), folder)))
+                .collect();
+            Ok((entries.into_iter().map(|e| e.text).collect(), folders))
+        }
+    }
+}
+
+/// 默认输出目录模板：output_dir/<水印文本>/<原文件名>
+const DEFAULT_DIR_TEMPLATE: &str = "{watermark}";
+
+/// 水印对应的输出子目录（相对输出基础目录），按 `dir_template` 渲染
+///
+/// `{watermark}` 为输出子文件夹名（JSON 列表指定了 `folder` 时使用之，否则为水印文本），
+/// `{archive_stem}` 为源压缩包 / 目录名（不含扩展名），`{date}` 为当天日期（UTC，`YYYY-MM-DD`）。
+fn output_subdir(options: &PipelineOptions, watermark_text: &str, archive_stem: &str) -> Result<PathBuf, String> {
+    let folder = options
+        .output_folders
+        .and_then(|folders| folders.get(watermark_text))
+        .map(String::as_str)
+        .unwrap_or(watermark_text);
+    let date = utc_date_string(std::time::SystemTime::now());
+    render_dir_template(
+        options.dir_template.unwrap_or(DEFAULT_DIR_TEMPLATE),
+        &[("watermark", folder), ("archive_stem", archive_stem), ("date", &date)],
+    )
+}
+
+/// 渲染输出目录模板（如 `{date}/{watermark}`）
+///
+/// 按 `/` 或 `\` 拆分为多级目录，每级分别替换占位符后做路径清理（见 `sanitize_path_component`），
+/// 因此占位符的值中含有路径分隔符时不会产生额外的目录层级。空的层级被忽略；
+/// 含未知占位符、未闭合的 `{` 或渲染结果为空时报错。
+fn render_dir_template(template: &str, vars: &[(&str, &str)]) -> Result<PathBuf, String> {
+    let mut path = PathBuf::new();
+    for segment in template.split(['/', '\\']).filter(|segment| !segment.trim().is_empty()) {
+        let mut rendered = String::new();
+        let mut rest = segment;
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("输出目录模板缺少 '}}': {}", template))?;
+            let name = &rest[start + 1..start + end];
+            let value = vars
+                .iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| *value)
+                .ok_or_else(|| format!("输出目录模板含未知占位符 {{{}}}（可用：{{watermark}}、{{archive_stem}}、{{date}}）", name))?;
+            rendered.push_str(value);
+            rest = &rest[start + end + 1..];
+        }
+        rendered.push_str(rest);
+        path.push(sanitize_path_component(&rendered));
+    }
+    if path.as_os_str().is_empty() {
+        return Err(format!("输出目录模板为空: {:?}", template));
+    }
+    Ok(path)
+}
+
+/// `time` 对应的 UTC 日期（`YYYY-MM-DD`）
+fn utc_date_string(time: std::time::SystemTime) -> String {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    // civil_from_days（proleptic Gregorian calendar）
+    let z = secs.div_euclid(86400) + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `process_archive` / `process_archives_batch` / `process_directory` 的处理选项（前端以 camelCase 对象传入）
+///
+/// 省略的可选项取默认值（关闭 / 未设置）。各字段含义见 `PipelineOptions`。
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessRequest {
+    pub process_images: bool,
+    pub process_json: bool,
+    pub process_vaj: bool,
+    pub process_vmi: bool,
+    pub process_vam: bool,
+    pub process_vap: bool,
+    #[serde(default)]
+    pub process_svg: bool,
+    #[serde(default)]
+    pub process_config: bool,
+    pub output_dir: Option<String>,
+    /// 统一的输出压缩包格式（如 `"zip"` / `"7z"`），未指定时沿用输入格式
+    pub output_format: Option<String>,
+    pub obfuscate: bool,
+    pub watermark_mode: String,
+    #[serde(default)]
+    pub mode_policy: ModePolicy,
+    pub aes_key: Option<String>,
+    pub selected_images: Option<Vec<String>>,
+    pub fast_mode: bool,
+    #[serde(default)]
+    pub append_index: bool,
+    #[serde(default)]
+    pub lenient_json: bool,
+    #[serde(default)]
+    pub metadata_fallback: bool,
+    /// 图片盲水印密码（打乱种子）；未设置时使用默认值，提取时须提供相同密码
+    pub image_password: Option<String>,
+    #[serde(default)]
+    pub normalize_orientation: bool,
+    #[serde(default)]
+    pub flat_output: bool,
+    #[serde(default)]
+    pub preserve_permissions: bool,
+    #[serde(default)]
+    pub dedup_outputs: bool,
+    pub semi_obfuscated_keys: Option<Vec<String>>,
+    pub md5_salt: Option<String>,
+    #[serde(default)]
+    pub write_checksums: bool,
+    pub strip_fields: Option<Vec<String>>,
+    #[serde(default)]
+    pub require_work: bool,
+    pub dir_template: Option<String>,
+    #[serde(default)]
+    pub profile: Profile,
+    /// 嵌套压缩包的递归处理层数，超过 `MAX_NESTED_ARCHIVE_DEPTH` 时按上限处理
+    #[serde(default)]
+    pub nested_archive_depth: usize,
+    #[serde(default)]
+    pub idempotent: bool,
+}
+
+impl ProcessRequest {
+    /// 解压前校验配置，发现无效组合（如 AES 模式缺少密钥）
+    fn validate(&self, config: &WatermarkConfig) -> Result<(), String> {
+        config
+            .validate(&self.watermark_mode, self.aes_key.as_deref())
+            .map_err(|e| e.to_string())?;
+        self.mode_policy.validate(self.aes_key.as_deref()).map_err(|e| e.to_string())
+    }
+
+    /// 构建流水线选项；`output_folders` 为 `read_watermark_texts` 读出的水印 → 子文件夹映射
+    fn pipeline_options<'a>(&'a self, output_folders: &'a HashMap<String, String>) -> PipelineOptions<'a> {
+        PipelineOptions {
+            process_images: self.process_images,
+            process_json: self.process_json,
+            process_vaj: self.process_vaj,
+            process_vmi: self.process_vmi,
+            process_vam: self.process_vam,
+            process_vap: self.process_vap,
+            process_svg: self.process_svg,
+            process_config: self.process_config,
+            obfuscate: self.obfuscate,
+            watermark_mode: &self.watermark_mode,
+            mode_policy: self.mode_policy,
+            aes_key: self.aes_key.as_deref(),
+            selected_images: self.selected_images.as_deref(),
+            fast_mode: self.fast_mode,
+            append_index: self.append_index,
+            lenient: self.lenient_json,
+            metadata_fallback: self.metadata_fallback,
+            normalize_orientation: self.normalize_orientation,
+            flat_output: self.flat_output,
+            preserve_permissions: self.preserve_permissions,
+            dedup_outputs: self.dedup_outputs,
+            semi_obfuscated_keys: self.semi_obfuscated_keys.as_deref(),
+            output_folders: Some(output_folders),
+            dir_template: self.dir_template.as_deref(),
+            md5_salt: self.md5_salt.as_deref(),
+            strip_fields: self.strip_fields.as_deref(),
+            require_work: self.require_work,
+            write_checksums: self.write_checksums,
+            image_seed: password_seed(self.image_password.as_deref().unwrap_or("")),
+            profile: self.profile,
+            nested_depth: self.nested_archive_depth.min(MAX_NESTED_ARCHIVE_DEPTH),
+            idempotent: self.idempotent,
+        }
+    }
+}
+
+/// 处理命令的公共前置步骤：校验配置并读取全部水印文本
+///
+/// # 返回
+/// `(进度发送器, 水印文本列表, 水印 → 输出子文件夹映射)`
+#[allow(clippy::type_complexity)]
+fn prepare_request(
+    app: AppHandle,
+    config: &WatermarkConfig,
+    request: &ProcessRequest,
+) -> Result<(Arc<ProgressEmitter>, Vec<String>, HashMap<String, String>), String> {
+    request.validate(config)?;
+    let progress = Arc::new(ProgressEmitter::new(app));
+    let (watermarks, output_folders) = read_watermark_texts(config, progress.as_ref())?;
+    Ok((progress, watermarks, output_folders))
+}
+
+/// `process_archive` / `process_archives_batch` / `process_directory` 共用的嵌入选项
+struct PipelineOptions<'a> {
+    process_images: bool,
+    process_json: bool,
+    process_vaj: bool,
+    process_vmi: bool,
+    process_vam: bool,
+    process_vap: bool,
+    process_svg: bool,
+    /// 处理 TOML / INI 配置文件（水印写入 `[blindmark]` 节）
+    process_config: bool,
+    obfuscate: bool,
+    watermark_mode: &'a str,
+    /// 按文件类型选择 JSON / SVG / 配置文件的水印模式（见 `ModePolicy`）；图片始终嵌入文本盲水印
+    mode_policy: ModePolicy,
+    aes_key: Option<&'a str>,
+    selected_images: Option<&'a [String]>,
+    fast_mode: bool,
+    append_index: bool,
+    lenient: bool,
+    metadata_fallback: bool,
+    /// 嵌入 / 复制前按 EXIF 方向旋转图片并去除方向标记
+    normalize_orientation: bool,
+    /// 单水印模式下压缩包直接输出到输出目录，不建以水印文本命名的子文件夹（仅压缩包输出）
+    flat_output: bool,
+    /// 输出文件沿用源文件的 Unix 权限位，并写入输出压缩包条目（仅压缩包输出）
+    preserve_permissions: bool,
+    /// 批量模式下处理结果与此前某个水印完全相同时，硬链接复用已打包的输出而不重复打包（仅压缩包输出）
+    dedup_outputs: bool,
+    /// 未开启 `obfuscate` 时的半混淆模式：从候选字段名中按顺序选出固定字段名写入水印
+    /// （`Some(空列表)` 使用 `SEMI_OBFUSCATED_KEYS`），提取时按名称查找；`None` 时写入 `watermark_key`
+    semi_obfuscated_keys: Option<&'a [String]>,
+    /// 水印文本 → 输出子文件夹名（JSON 水印列表的 `folder`），未列出的水印以水印文本命名
+    output_folders: Option<&'a HashMap<String, String>>,
+    /// 输出子目录模板（如 `{date}/{watermark}`，见 `render_dir_template`）；`None` 时为 `DEFAULT_DIR_TEMPLATE`
+    dir_template: Option<&'a str>,
+    /// MD5 模式的盐值：JSON / SVG 中存储 `md5(salt || 文本)`，校验与反查须使用相同盐值（改变存储值）
+    md5_salt: Option<&'a str>,
+    /// 打包完成后在输出目录写入全部输出压缩包的 `SHA256SUMS`（仅压缩包输出）
+    write_checksums: bool,
+    /// JSON 类文件嵌入水印前移除的根字段（如 `buildTime`），使输出可复现；适用于所有嵌入模式
+    strip_fields: Option<&'a [String]>,
+    /// 扫描后按已启用的类型没有任何可嵌入水印的文件时直接报错，而不是输出未加水印的副本
+    require_work: bool,
+    image_seed: u64,
+    /// 图片盲水印的预设档位（见 `Profile`），提取时须使用相同档位
+    profile: Profile,
+    /// 包内嵌套压缩包的递归处理层数（0 为不处理，原样复制；上限 `MAX_NESTED_ARCHIVE_DEPTH`）
+    nested_depth: usize,
+    /// 幂等模式：已含一致水印的文件原样保留，混淆字段名由内容决定，输出文件沿用源文件修改时间，
+    /// 重复处理同一份内容（含已处理过的输出）得到逐字节相同的结果
+    idempotent: bool,
+}
+
+impl PipelineOptions<'_> {
+    /// `file_type`（如 "json"、"svg"）文件实际使用的水印模式
+    fn mode_for(&self, file_type: &str) -> &str {
+        self.mode_policy.mode_for(file_type, self.watermark_mode)
+    }
+}
+
+/// 嵌套压缩包的最大递归层数，防止压缩包炸弹式的无限嵌套
+const MAX_NESTED_ARCHIVE_DEPTH: usize = 3;
+
+/// 扫描 `source_dir` 并对每个水印文本生成一份处理结果
+///
+/// 每个水印的结果先写入独立的临时 processed 目录，再交给 `finalize(水印文本, processed 目录)`
+/// 输出（打包 / 复制到目标目录），返回所有成功的 `finalize` 结果。
+///
+/// 单个文件失败时原样保留该文件；单个水印失败（写入 / 打包出错）时跳过该水印的输出。
+/// 两者都记入 `summary.failures`，不会中断其余处理。扫描失败仍直接返回错误。
+///
+/// `options.nested_depth > 0` 时包内受支持的嵌套压缩包由 `process_nested_archive` 递归处理，
+/// 替换原样复制的副本；处理失败时保留原副本。
+fn run_watermark_pipeline<F>(
+    source_dir: &Path,
+    config: &WatermarkConfig,
+    watermarks: &[String],
+    options: &PipelineOptions,
+    progress: &Arc<dyn ProgressSink>,
+    summary: &mut BatchSummaryEvent,
+    mut finalize: F,
+) -> Result<Vec<String>, String>
+where
+    F: FnMut(&str, &Path) -> Result<String, String>,
+{
+    let is_batch = watermarks.len() > 1;
+    let total_watermarks = watermarks.len();
+    let lenient = options.lenient;
+
+    // 解析水印字段名（未设置时使用默认值 "_watermark"）
+    let wm_key: String = config
+        .watermark_key
+        .as_deref()
+        .filter(|k| !k.trim().is_empty())
+        .unwrap_or(DEFAULT_WATERMARK_KEY)
+        .to_string();
 
-    // === Step 2: 扫描文件（仅一次）===
-    let scanner = FileScanner::new();
+    // === 扫描文件（仅一次）===
+    let scanner = FileScanner::shared();
 
-    let images = if process_images {
+    let images = if options.process_images {
         progress
             .emit_status("scanning".to_string(), "正在扫描图片...".to_string())
             .map_err(|e| format!("Progress error: {}", e))?;
         let all_images = scanner
-            .scan(workspace.extracted_path())
+            .scan(source_dir)
             .map_err(|e| format!("扫描图片失败: {}", e))?;
         // 若前端指定了选中图片，则只处理选中的
-        if let Some(ref sel) = selected_images {
-            if !sel.is_empty() {
+        match options.selected_images {
+            Some(sel) if !sel.is_empty() => {
                 all_images.into_iter().filter(|f| sel.contains(&f.relative_path)).collect()
-            } else {
-                all_images
             }
-        } else {
-            all_images
+            _ => all_images,
         }
     } else {
         vec![]
     };
 
-    let json_files = if process_json {
+    let json_files = if options.process_json {
         scanner
-            .scan_json_files(workspace.extracted_path())
+            .scan_json_files(source_dir)
             .map_err(|e| format!("扫描 JSON 失败: {}", e))?
     } else {
         vec![]
     };
 
-    let vaj_files = if process_vaj {
+    let vaj_files = if options.process_vaj {
         scanner
-            .scan_vaj_files(workspace.extracted_path())
+            .scan_vaj_files(source_dir)
             .map_err(|e| format!("扫描 VAJ 失败: {}", e))?
     } else {
         vec![]
     };
 
-    let vmi_files = if process_vmi {
+    let vmi_files = if options.process_vmi {
         scanner
-            .scan_vmi_files(workspace.extracted_path())
+            .scan_vmi_files(source_dir)
             .map_err(|e| format!("扫描 VMI 失败: {}", e))?
     } else {
         vec![]
     };
 
-    let vam_files = if process_vam {
+    let vam_files = if options.process_vam {
         scanner
-            .scan_vam_files(workspace.extracted_path())
+            .scan_vam_files(source_dir)
             .map_err(|e| format!("扫描 VAM 失败: {}", e))?
     } else {
         vec![]
     };
 
-    let vap_files = if process_vap {
+    let vap_files = if options.process_vap {
         scanner
-            .scan_vap_files(workspace.extracted_path())
+            .scan_vap_files(source_dir)
             .map_err(|e| format!("扫描 VAP 失败: {}", e))?
     } else {
         vec![]
     };
 
+    let svg_files = if options.process_svg {
+        scanner
+            .scan_svg_files(source_dir)
+            .map_err(|e| format!("扫描 SVG 失败: {}", e))?
+    } else {
+        vec![]
+    };
+
+    let (toml_files, ini_files) = if options.process_config {
+        let toml_files = scanner
+            .scan_toml_files(source_dir)
+            .map_err(|e| format!("扫描 TOML 失败: {}", e))?;
+        let ini_files = scanner
+            .scan_ini_files(source_dir)
+            .map_err(|e| format!("扫描 INI 失败: {}", e))?;
+        (toml_files, ini_files)
+    } else {
+        (vec![], vec![])
+    };
+
     // 预计算用于 copy_other_files 的引用切片（扫描结果整个函数内有效）
     let image_rel_strs: Vec<&str> = images.iter().map(|f| f.relative_path.as_str()).collect();
     let json_rel_paths: Vec<&Path> = json_files.iter().map(|(_, r)| r.as_path()).collect();
@@ -194,206 +1064,340 @@ pub async fn process_archive(
     let vmi_rel_paths: Vec<&Path> = vmi_files.iter().map(|(_, r)| r.as_path()).collect();
     let vam_rel_paths: Vec<&Path> = vam_files.iter().map(|(_, r)| r.as_path()).collect();
     let vap_rel_paths: Vec<&Path> = vap_files.iter().map(|(_, r)| r.as_path()).collect();
+    let svg_rel_paths: Vec<&Path> = svg_files.iter().map(|(_, r)| r.as_path()).collect();
+    let config_rel_paths: Vec<&Path> = toml_files.iter().chain(&ini_files).map(|(_, r)| r.as_path()).collect();
+    let nested_archives = if options.nested_depth > 0 { find_nested_archives(source_dir) } else { vec![] };
 
     // 扫描完成后发送汇总，让前端知道各类型文件数量
     progress
-        .emit_scan_summary(
-            json_files.len(), vaj_files.len(), vmi_files.len(), images.len(),
-            vam_files.len(), vap_files.len(),
-        )
+        .emit_scan_summary(ScanSummaryEvent {
+            json_count: json_files.len(),
+            vaj_count: vaj_files.len(),
+            vmi_count: vmi_files.len(),
+            image_count: images.len(),
+            vam_count: vam_files.len(),
+            vap_count: vap_files.len(),
+            svg_count: svg_files.len(),
+            toml_count: toml_files.len(),
+            ini_count: ini_files.len(),
+        })
         .map_err(|e| format!("Progress error: {}", e))?;
 
-    let mut final_output = String::new();
-
-    // === Step 3: 对每个水印文本处理并打包 ===
-    for (idx, watermark_text) in watermarks.iter().enumerate() {
-        if is_batch {
-            let label: String = if watermark_text.chars().count() > 24 {
-                watermark_text.chars().take(24).collect::<String>() + "…"
-            } else {
-                watermark_text.clone()
-            };
-            progress
-                .emit_status(
-                    "processing".to_string(),
-                    format!("[{}/{}] 正在处理：{}", idx + 1, total_watermarks, label),
-                )
-                .map_err(|e| format!("Progress error: {}", e))?;
+    if options.require_work {
+        let workload = images.len()
+            + json_files.len()
+            + vaj_files.len()
+            + vmi_files.len()
+            + vam_files.len()
+            + vap_files.len()
+            + svg_files.len()
+            + toml_files.len()
+            + ini_files.len();
+        if workload == 0 {
+            let enabled: Vec<&str> = [
+                (options.process_images, "图片"),
+                (options.process_json, "JSON"),
+                (options.process_vaj, "VAJ"),
+                (options.process_vmi, "VMI"),
+                (options.process_vam, "VAM"),
+                (options.process_vap, "VAP"),
+                (options.process_svg, "SVG"),
+                (options.process_config, "TOML / INI"),
+            ]
+            .into_iter()
+            .filter_map(|(on, name)| on.then_some(name))
+            .collect();
+            return Err(format!(
+                "没有可嵌入水印的文件：已启用的类型（{}）在源中均未找到",
+                if enabled.is_empty() { "无".to_string() } else { enabled.join(" / ") }
+            ));
         }
+    }
 
-        // 为当前水印创建独立的临时 processed 目录
-        let processed_dir = tempfile::tempdir()
-            .map_err(|e| format!("创建临时目录失败: {}", e))?;
-        let processed_path = processed_dir.path();
+    let mut outputs = Vec::new();
 
-        // --- 处理图片 ---
-        if process_images && !images.is_empty() {
-            if !is_batch {
+    // === 对每个水印文本处理并输出 ===
+    for (idx, watermark_text) in watermarks.iter().enumerate() {
+        // 批量模式下失败项加上序号前缀，便于区分是哪一份输出
+        let failure_item = |path: &Path| if is_batch {
+            format!("[{}/{}] {}", idx + 1, total_watermarks, path.display())
+        } else {
+            path.display().to_string()
+        };
+        let mut process_one = || -> Result<String, String> {
+            if is_batch {
+                let label: String = if watermark_text.chars().count() > 24 {
+                    watermark_text.chars().take(24).collect::<String>() + "…"
+                } else {
+                    watermark_text.clone()
+                };
                 progress
                     .emit_status(
-                        "processing_images".to_string(),
-                        format!("正在处理 {} 张图片...", images.len()),
+                        "processing".to_string(),
+                        format!("[{}/{}] 正在处理：{}", idx + 1, total_watermarks, label),
                     )
                     .map_err(|e| format!("Progress error: {}", e))?;
             }
-            let parallel_processor = ParallelProcessor::new();
-            parallel_processor
-                .process_batch_single(
-                    &images,
-                    watermark_text,
-                    config.strength,
-                    processed_path,
-                    Some(Arc::clone(&progress)),
-                    fast_mode,
-                )
-                .map_err(|e| format!("图片处理失败: {}", e))?;
-        }
 
-        // --- 处理 JSON ---
-        let json_total = json_files.len();
-        for (file_idx, (abs_path, rel_path)) in json_files.iter().enumerate() {
-            let fname = rel_path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
-            progress
-                .emit_detail_progress(idx + 1, total_watermarks, "json", file_idx + 1, json_total, fname)
-                .map_err(|e| format!("Progress error: {}", e))?;
-            let bytes = std::fs::read(abs_path)
-                .map_err(|e| format!("读取 JSON 失败 {}: {}", rel_path.display(), e))?;
-            let watermarked = if obfuscate {
-                JsonWatermarker::embed_obfuscated_bytes(&bytes, watermark_text, &watermark_mode, aes_key.as_deref())
+            // 分批发货：批量模式下在水印文本后追加序号 `[i/N]`（输出文件夹仍按原文本命名）
+            let embed_text: String = if is_batch && options.append_index {
+                WatermarkEncoder::append_index(watermark_text, idx + 1, total_watermarks)
             } else {
-                JsonWatermarker::embed_bytes(&bytes, watermark_text, &wm_key, &watermark_mode, aes_key.as_deref())
-            }.map_err(|e| format!("JSON 水印注入失败 {}: {}", rel_path.display(), e))?;
-            let dest = processed_path.join(rel_path);
-            if let Some(parent) = dest.parent() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| format!("创建目录失败: {}", e))?;
-            }
-            std::fs::write(&dest, &watermarked)
-                .map_err(|e| format!("写入 JSON 失败 {}: {}", rel_path.display(), e))?;
-        }
+                watermark_text.clone()
+            };
 
-        // --- 处理 VAJ ---
-        let vaj_total = vaj_files.len();
-        for (file_idx, (abs_path, rel_path)) in vaj_files.iter().enumerate() {
-            let fname = rel_path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
-            progress
-                .emit_detail_progress(idx + 1, total_watermarks, "vaj", file_idx + 1, vaj_total, fname)
-                .map_err(|e| format!("Progress error: {}", e))?;
-            let bytes = std::fs::read(abs_path)
-                .map_err(|e| format!("读取 VAJ 失败 {}: {}", rel_path.display(), e))?;
-            let watermarked = if obfuscate {
-                JsonWatermarker::embed_obfuscated_bytes(&bytes, watermark_text, &watermark_mode, aes_key.as_deref())
-            } else {
-                JsonWatermarker::embed_bytes(&bytes, watermark_text, &wm_key, &watermark_mode, aes_key.as_deref())
-            }.map_err(|e| format!("VAJ 水印注入失败 {}: {}", rel_path.display(), e))?;
-            let dest = processed_path.join(rel_path);
-            if let Some(parent) = dest.parent() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| format!("创建目录失败: {}", e))?;
+            // 为当前水印创建独立的临时 processed 目录
+            let processed_dir = tempfile::tempdir()
+                .map_err(|e| format!("创建临时目录失败: {}", e))?;
+            let processed_path = processed_dir.path();
+
+            // --- 处理图片 ---
+            if options.process_images && !images.is_empty() {
+                if !is_batch {
+                    progress
+                        .emit_status(
+                            "processing_images".to_string(),
+                            format!("正在处理 {} 张图片...", images.len()),
+                        )
+                        .map_err(|e| format!("Progress error: {}", e))?;
+                }
+                // 无法嵌入盲水印的图片（过小、无法解码）可选写入元数据水印兜底
+                let parallel_processor = ParallelProcessor::new()
+                    .with_metadata_fallback(options.metadata_fallback)
+                    .with_orientation_normalization(options.normalize_orientation)
+                    .with_password(options.image_seed)
+                    .with_profile(options.profile)
+                    .with_idempotent(options.idempotent);
+                // 单张图片失败时原样保留，不影响其他图片
+                let (processed, failures, warnings) = parallel_processor
+                    .process_batch_single_partial(
+                        &images,
+                        &embed_text,
+                        config.strength,
+                        processed_path,
+                        Some(Arc::clone(progress)),
+                        options.fast_mode,
+                    )
+                    .map_err(|e| format!("图片处理失败: {}", e))?;
+                summary.record_watermarked("image", processed);
+                for failure in failures {
+                    summary.record_failure(failure_item(Path::new(&failure.item)), format!("图片水印嵌入失败: {}", failure.reason));
+                }
+                for warning in warnings {
+                    summary.record_warning(failure_item(Path::new(&warning.file)), warning.reason);
+                }
             }
-            std::fs::write(&dest, &watermarked)
-                .map_err(|e| format!("写入 VAJ 失败 {}: {}", rel_path.display(), e))?;
-        }
 
-        // --- 处理 VMI ---
-        let vmi_total = vmi_files.len();
-        for (file_idx, (abs_path, rel_path)) in vmi_files.iter().enumerate() {
-            let fname = rel_path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
-            progress
-                .emit_detail_progress(idx + 1, total_watermarks, "vmi", file_idx + 1, vmi_total, fname)
-                .map_err(|e| format!("Progress error: {}", e))?;
-            let bytes = std::fs::read(abs_path)
-                .map_err(|e| format!("读取 VMI 失败 {}: {}", rel_path.display(), e))?;
-            let watermarked = if obfuscate {
-                JsonWatermarker::embed_obfuscated_bytes(&bytes, watermark_text, &watermark_mode, aes_key.as_deref())
-            } else {
-                JsonWatermarker::embed_bytes(&bytes, watermark_text, &wm_key, &watermark_mode, aes_key.as_deref())
-            }.map_err(|e| format!("VMI 水印注入失败 {}: {}", rel_path.display(), e))?;
-            let dest = processed_path.join(rel_path);
-            if let Some(parent) = dest.parent() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| format!("创建目录失败: {}", e))?;
+            // --- 处理 JSON / VAJ / VMI / VAM / VAP（均为 JSON 格式，处理流程相同）及 SVG ---
+            // MD5 模式下按盐值存储 md5(salt || 文本)；明文 / AES / HMAC 模式不加盐
+            let (json_mode, svg_mode, config_mode) = (options.mode_for("json"), options.mode_for("svg"), options.mode_for("toml"));
+            let stored_text = |mode: &str| {
+                if matches!(mode, "plaintext" | "aes" | "hmac") {
+                    std::borrow::Cow::Borrowed(embed_text.as_str())
+                } else {
+                    WatermarkEncoder::salted_text(&embed_text, options.md5_salt)
+                }
+            };
+            let (json_text, svg_text, config_text) = (stored_text(json_mode), stored_text(svg_mode), stored_text(config_mode));
+            let embed_json = |bytes: &[u8]| {
+                // 先移除易变字段，再按所选模式嵌入
+                let stripped;
+                let bytes = match options.strip_fields {
+                    Some(fields) if !fields.is_empty() => {
+                        stripped = JsonWatermarker::strip_fields_bytes(bytes, fields)?;
+                        &stripped[..]
+                    }
+                    _ => bytes,
+                };
+                if options.obfuscate && options.idempotent {
+                    JsonWatermarker::embed_obfuscated_stable_bytes(bytes, &json_text, json_mode, options.aes_key)
+                } else if options.obfuscate {
+                    JsonWatermarker::embed_obfuscated_bytes(bytes, &json_text, json_mode, options.aes_key)
+                } else if let Some(keys) = options.semi_obfuscated_keys {
+                    let embedded = if keys.is_empty() {
+                        JsonWatermarker::embed_semi_obfuscated_bytes(bytes, &json_text, SEMI_OBFUSCATED_KEYS, json_mode, options.aes_key)
+                    } else {
+                        JsonWatermarker::embed_semi_obfuscated_bytes(bytes, &json_text, keys, json_mode, options.aes_key)
+                    };
+                    embedded.map(|(bytes, _)| bytes)
+                } else {
+                    JsonWatermarker::embed_bytes(bytes, &json_text, &wm_key, json_mode, options.aes_key)
+                }
+            };
+            let embed_svg = |bytes: &[u8]| {
+                SvgWatermarker::embed_bytes(bytes, &svg_text, svg_mode, options.aes_key)
+            };
+            let embed_toml = |bytes: &[u8]| {
+                TomlWatermarker::embed_bytes(bytes, &config_text, config_mode, options.aes_key)
+            };
+            let embed_ini = |bytes: &[u8]| {
+                IniWatermarker::embed_bytes(bytes, &config_text, config_mode, options.aes_key)
+            };
+            // 幂等模式：已有水印与本次要写入的一致时原样保留该文件
+            let already_marked = |file_type: &str, bytes: &[u8]| {
+                let content = String::from_utf8_lossy(bytes);
+                let (found, text, mode): (Vec<_>, &str, &str) = match file_type {
+                    "svg" => (SvgWatermarker::scan_watermark_value(&content, options.aes_key).into_iter().collect(), svg_text.as_ref(), svg_mode),
+                    "toml" => (TomlWatermarker::scan_watermark_value(&content, options.aes_key).into_iter().collect(), config_text.as_ref(), config_mode),
+                    "ini" => (IniWatermarker::scan_watermark_value(&content, options.aes_key).into_iter().collect(), config_text.as_ref(), config_mode),
+                    _ => (JsonWatermarker::scan_watermark_values(&content, options.aes_key), json_text.as_ref(), json_mode),
+                };
+                watermarks_match(&found, text, mode)
+            };
+            type EmbedFn<'f> = &'f dyn Fn(&[u8]) -> Result<Vec<u8>, BlindMarkError>;
+            type TextFileGroup<'f> = (&'f str, &'f str, &'f Vec<(PathBuf, PathBuf)>, EmbedFn<'f>);
+            let text_file_groups: [TextFileGroup; 8] = [
+                ("json", "JSON", &json_files, &embed_json),
+                ("vaj", "VAJ", &vaj_files, &embed_json),
+                ("vmi", "VMI", &vmi_files, &embed_json),
+                ("vam", "VAM", &vam_files, &embed_json),
+                ("vap", "VAP", &vap_files, &embed_json),
+                ("svg", "SVG", &svg_files, &embed_svg),
+                ("toml", "TOML", &toml_files, &embed_toml),
+                ("ini", "INI", &ini_files, &embed_ini),
+            ];
+            for (file_type, label, files, embed_file) in text_file_groups {
+                let type_total = files.len();
+                for (file_idx, (abs_path, rel_path)) in files.iter().enumerate() {
+                    let fname = rel_path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
+                    progress
+                        .emit_detail_progress(DetailProgressEvent {
+                            batch_current: idx + 1,
+                            batch_total: total_watermarks,
+                            file_type: file_type.to_string(),
+                            type_current: file_idx + 1,
+                            type_total,
+                            filename: fname.to_string(),
+                        })
+                        .map_err(|e| format!("Progress error: {}", e))?;
+                    let bytes = match std::fs::read(abs_path) {
+                        Ok(b) => b,
+                        Err(e) => {
+                            summary.record_failure(failure_item(rel_path), format!("读取 {} 失败，已跳过: {}", label, e));
+                            continue;
+                        }
+                    };
+                    // 宽松模式：JSON 严格解析失败时尝试修复尾随逗号 / 注释后再嵌入
+                    let watermarked = if options.idempotent && already_marked(file_type, &bytes) {
+                        Ok(bytes.clone())
+                    } else {
+                        match embed_file(&bytes) {
+                            Err(e) if lenient && !matches!(file_type, "svg" | "toml" | "ini") => {
+                                let repaired = JsonWatermarker::repair_bytes(&bytes)
+                                    .and_then(|fixed| embed_file(&fixed))
+                                    .map_err(|_| e);
+                                if repaired.is_ok() {
+                                    summary.record_warning(failure_item(rel_path), format!("{} 格式不规范，已修复后嵌入", label));
+                                }
+                                repaired
+                            }
+                            result => result,
+                        }
+                    };
+                    let output_bytes = match watermarked {
+                        Ok(w) => {
+                            summary.record_watermarked(file_type, 1);
+                            // VaM 预设等严格格式：预检水印后是否仍能被 VaM 加载，可能失败时记入警告（仍写入）
+                            if matches!(file_type, "vaj" | "vmi" | "vam" | "vap") {
+                                let issues = var_package::preflight_vam_output(file_type, &bytes, &w);
+                                if !issues.is_empty() {
+                                    let detail: Vec<String> = issues.iter().map(|i| format!("{}: {}", i.kind, i.detail)).collect();
+                                    summary.record_warning(
+                                        failure_item(rel_path),
+                                        format!("{} 水印后可能无法被 VaM 加载（{}）", label, detail.join("，")),
+                                    );
+                                }
+                            }
+                            w
+                        }
+                        // 注入失败（宽松模式下修复后仍失败）：原样保留该文件并上报，不中断整个压缩包
+                        Err(e) => {
+                            progress
+                                .emit_status(
+                                    "file_skipped".to_string(),
+                                    format!("{} 解析失败，已原样保留 {}: {}", label, rel_path.display(), e),
+                                )
+                                .map_err(|e| format!("Progress error: {}", e))?;
+                            summary.record_failure(failure_item(rel_path), format!("{} 解析失败，已原样保留: {}", label, e));
+                            bytes
+                        }
+                    };
+                    let dest = processed_path.join(rel_path);
+                    if let Some(parent) = dest.parent() {
+                        std::fs::create_dir_all(parent)
+                            .map_err(|e| format!("创建目录失败: {}", e))?;
+                    }
+                    std::fs::write(&dest, &output_bytes)
+                        .map_err(|e| format!("写入 {} 失败 {}: {}", label, rel_path.display(), e))?;
+                }
             }
-            std::fs::write(&dest, &watermarked)
-                .map_err(|e| format!("写入 VMI 失败 {}: {}", rel_path.display(), e))?;
-        }
 
-        // --- 处理 VAM（JSON 格式，同 VAJ/VMI）---
-        let vam_total = vam_files.len();
-        for (file_idx, (abs_path, rel_path)) in vam_files.iter().enumerate() {
-            let fname = rel_path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
-            progress
-                .emit_detail_progress(idx + 1, total_watermarks, "vam", file_idx + 1, vam_total, fname)
-                .map_err(|e| format!("Progress error: {}", e))?;
-            let bytes = std::fs::read(abs_path)
-                .map_err(|e| format!("读取 VAM 失败 {}: {}", rel_path.display(), e))?;
-            let watermarked = if obfuscate {
-                JsonWatermarker::embed_obfuscated_bytes(&bytes, watermark_text, &watermark_mode, aes_key.as_deref())
-            } else {
-                JsonWatermarker::embed_bytes(&bytes, watermark_text, &wm_key, &watermark_mode, aes_key.as_deref())
-            }.map_err(|e| format!("VAM 水印注入失败 {}: {}", rel_path.display(), e))?;
-            let dest = processed_path.join(rel_path);
-            if let Some(parent) = dest.parent() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| format!("创建目录失败: {}", e))?;
+            // --- 复制其他文件（符号链接仅在指向包内时重建，否则跳过）---
+            let (copied, skipped_links) = copy_other_files(
+                source_dir,
+                processed_path,
+                &image_rel_strs,
+                &json_rel_paths,
+                &vaj_rel_paths,
+                &vmi_rel_paths,
+                &vam_rel_paths,
+                &vap_rel_paths,
+                &svg_rel_paths,
+                &config_rel_paths,
+            )
+            .map_err(|e| format!("复制文件失败: {}", e))?;
+            summary.copied_count += copied;
+            for link in &skipped_links {
+                progress
+                    .emit_status(
+                        "file_skipped".to_string(),
+                        format!("已跳过指向包外或无效的符号链接: {}", link.display()),
+                    )
+                    .map_err(|e| format!("Progress error: {}", e))?;
+                summary.record_failure(failure_item(link), "指向包外或无效的符号链接，已跳过");
             }
-            std::fs::write(&dest, &watermarked)
-                .map_err(|e| format!("写入 VAM 失败 {}: {}", rel_path.display(), e))?;
-        }
 
-        // --- 处理 VAP（JSON 格式，同 VAJ/VMI）---
-        let vap_total = vap_files.len();
-        for (file_idx, (abs_path, rel_path)) in vap_files.iter().enumerate() {
-            let fname = rel_path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
-            progress
-                .emit_detail_progress(idx + 1, total_watermarks, "vap", file_idx + 1, vap_total, fname)
-                .map_err(|e| format!("Progress error: {}", e))?;
-            let bytes = std::fs::read(abs_path)
-                .map_err(|e| format!("读取 VAP 失败 {}: {}", rel_path.display(), e))?;
-            let watermarked = if obfuscate {
-                JsonWatermarker::embed_obfuscated_bytes(&bytes, watermark_text, &watermark_mode, aes_key.as_deref())
-            } else {
-                JsonWatermarker::embed_bytes(&bytes, watermark_text, &wm_key, &watermark_mode, aes_key.as_deref())
-            }.map_err(|e| format!("VAP 水印注入失败 {}: {}", rel_path.display(), e))?;
-            let dest = processed_path.join(rel_path);
-            if let Some(parent) = dest.parent() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| format!("创建目录失败: {}", e))?;
+            // --- 嵌套压缩包：以相同水印递归处理，覆盖上面原样复制的副本 ---
+            for rel_path in &nested_archives {
+                let result = process_nested_archive(
+                    &source_dir.join(rel_path),
+                    &processed_path.join(rel_path),
+                    config,
+                    &embed_text,
+                    options,
+                    progress,
+                    summary,
+                );
+                if let Err(e) = result {
+                    summary.record_failure(failure_item(rel_path), format!("嵌套压缩包处理失败，已原样保留: {}", e));
+                }
             }
-            std::fs::write(&dest, &watermarked)
-                .map_err(|e| format!("写入 VAP 失败 {}: {}", rel_path.display(), e))?;
-        }
-
-        // --- 复制其他文件 ---
-        copy_other_files(
-            workspace.extracted_path(),
-            processed_path,
-            &image_rel_strs,
-            &json_rel_paths,
-            &vaj_rel_paths,
-            &vmi_rel_paths,
-            &vam_rel_paths,
-            &vap_rel_paths,
-        )
-        .map_err(|e| format!("复制文件失败: {}", e))?;
 
-        // --- 确定输出路径（始终输出到以水印文本命名的子文件夹）---
-        let folder_name = sanitize_path_component(watermark_text);
-        let subfolder = base_output_dir.join(&folder_name);
-        std::fs::create_dir_all(&subfolder)
-            .map_err(|e| format!("创建输出目录失败 {}: {}", subfolder.display(), e))?;
-        let output_path = subfolder.join(&archive_output_filename);
+            // --- 嵌入后重新写入的文件恢复源文件权限位 ---
+            if options.preserve_permissions {
+                copy_permissions(source_dir, processed_path)
+                    .map_err(|e| format!("恢复文件权限失败: {}", e))?;
+            }
 
-        // --- 打包 ---
-        progress
-            .emit_status("packaging".to_string(), format!("正在打包：{}...", &archive_output_filename))
-            .map_err(|e| format!("Progress error: {}", e))?;
+            // --- 幂等模式：输出文件沿用源文件修改时间，打包结果不随运行时间变化 ---
+            if options.idempotent {
+                copy_modified_times(source_dir, processed_path)
+                    .map_err(|e| format!("恢复修改时间失败: {}", e))?;
+            }
 
-        archive_processor
-            .create(processed_path, &output_path)
-            .map_err(|e| format!("打包失败: {}", e))?;
+            // --- 输出（打包 / 写入目标目录）---
+            finalize(watermark_text, processed_path)
+            // processed_dir 在此处 drop，自动清理
+        };
 
-        final_output = output_path.to_string_lossy().to_string();
+        match process_one() {
+            Ok(output) => outputs.push(output),
+            Err(e) => {
+                // 该水印的输出未生成，继续处理下一个水印
+                let _ = progress.emit_status("watermark_failed".to_string(), format!("{}: {}", watermark_text, e));
+                summary.record_failure(failure_item(Path::new(watermark_text)), format!("水印处理失败，未生成输出: {}", e));
+            }
+        }
 
         if is_batch {
             progress
@@ -403,21 +1407,231 @@ pub async fn process_archive(
                 )
                 .map_err(|e| format!("Progress error: {}", e))?;
         }
-        // processed_dir 在此处 drop，自动清理
     }
 
-    // 批量模式返回输出基础目录，单条模式返回输出文件路径
-    let result = if is_batch {
-        base_output_dir.to_string_lossy().to_string()
+    Ok(outputs)
+}
+
+/// `source_dir` 中可解压并按原格式重新打包的嵌套压缩包（相对路径）
+///
+/// 只能解压的格式（RAR）重新打包会改变扩展名，不在此列，仍原样复制。
+fn find_nested_archives(source_dir: &Path) -> Vec<PathBuf> {
+    use walkdir::WalkDir;
+
+    let processor = ArchiveProcessor::shared();
+    WalkDir::new(source_dir)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| processor.is_supported(e.path()) && processor.repack_path(e.path()) == e.path())
+        .filter_map(|e| e.path().strip_prefix(source_dir).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+/// 解压嵌套压缩包 `archive_path`，以 `embed_text` 嵌入水印后按原格式重新打包并写入 `output_path`
+///
+/// 内层的处理计数与失败记入同一份 `summary`；每深入一层 `nested_depth` 减 1。
+/// 打包成功后才覆盖 `output_path`，任何一步失败时保留其原有内容。
+fn process_nested_archive(
+    archive_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+    embed_text: &str,
+    options: &PipelineOptions,
+    progress: &Arc<dyn ProgressSink>,
+    summary: &mut BatchSummaryEvent,
+) -> Result<(), String> {
+    let processor = ArchiveProcessor::shared();
+    let extract_dir = tempfile::tempdir().map_err(|e| format!("创建临时目录失败: {}", e))?;
+    processor
+        .extract(archive_path, extract_dir.path())
+        .map_err(|e| format!("解压失败: {}", e))?;
+
+    let pack_dir = tempfile::tempdir().map_err(|e| format!("创建临时目录失败: {}", e))?;
+    let file_name = output_path.file_name().ok_or_else(|| "无效的压缩包路径".to_string())?;
+    let packed_path = pack_dir.path().join(file_name);
+    let inner_options = PipelineOptions {
+        selected_images: None,
+        require_work: false,
+        nested_depth: options.nested_depth - 1,
+        ..*options
+    };
+    let outputs = run_watermark_pipeline(
+        extract_dir.path(),
+        config,
+        &[embed_text.to_string()],
+        &inner_options,
+        progress,
+        summary,
+        |_, processed| {
+            processor
+                .create(processed, &packed_path)
+                .map_err(|e| format!("打包失败: {}", e))?;
+            Ok(packed_path.display().to_string())
+        },
+    )?;
+    if outputs.is_empty() {
+        return Err("未生成输出".to_string());
+    }
+    std::fs::copy(&packed_path, output_path).map_err(|e| format!("写入失败: {}", e))?;
+    Ok(())
+}
+
+/// 将 `src_root` 中各文件的权限位应用到 `dst_root` 下相同相对路径的文件（非 Unix 平台为空操作）
+///
+/// 嵌入水印的文件是重新写入的，权限位为默认值；`fs::copy` 复制的文件本身已保留权限。
+fn copy_permissions(src_root: &Path, dst_root: &Path) -> Result<(), std::io::Error> {
+    use walkdir::WalkDir;
+
+    for entry in WalkDir::new(src_root).follow_links(false).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(src_root).unwrap_or(entry.path());
+        let dst = dst_root.join(rel);
+        if dst.is_file() {
+            std::fs::set_permissions(&dst, entry.metadata()?.permissions())?;
+        }
+    }
+    Ok(())
+}
+
+/// 将 `src_root` 中各文件的修改时间应用到 `dst_root` 下相同相对路径的文件，
+/// `dst_root` 下的目录统一设为源文件中最新的修改时间
+///
+/// 目录本身的修改时间随解压 / 写入时刻变化，改用由文件内容决定的值，使打包结果可复现。
+fn copy_modified_times(src_root: &Path, dst_root: &Path) -> Result<(), std::io::Error> {
+    use walkdir::WalkDir;
+
+    let mut newest = None;
+    for entry in WalkDir::new(src_root).follow_links(false).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        newest = newest.max(Some(modified));
+        let rel = entry.path().strip_prefix(src_root).unwrap_or(entry.path());
+        let dst = dst_root.join(rel);
+        if dst.is_file() {
+            let file = std::fs::File::options().write(true).open(&dst).or_else(|_| std::fs::File::open(&dst))?;
+            file.set_modified(modified)?;
+        }
+    }
+    let Some(newest) = newest else { return Ok(()) };
+    for entry in WalkDir::new(dst_root).min_depth(1).follow_links(false).into_iter().filter_map(|e| e.ok()) {
+        if entry.file_type().is_dir() {
+            // 部分平台无法打开目录设置时间，忽略即可（仅影响目录条目的时间戳）
+            if let Ok(dir) = std::fs::File::open(entry.path()) {
+                let _ = dir.set_modified(newest);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 扫描出的已有水印 `found`（`(显示值, 模式名称, 是否已解码)`）是否全部与本次要写入的一致
+///
+/// 至少须有一个水印。`stored_text` 为按模式处理后的文本（MD5 模式已加盐）：
+/// MD5 模式比较摘要，明文 / AES / HMAC 模式比较解码后（已校验）的原文。
+fn watermarks_match(found: &[(String, String, bool)], stored_text: &str, mode: &str) -> bool {
+    let (expected, expected_mode) = if matches!(mode, "plaintext" | "aes" | "hmac") {
+        (stored_text.to_string(), mode)
     } else {
-        final_output
+        (WatermarkEncoder::encode(stored_text).md5_hash, "md5")
     };
+    !found.is_empty()
+        && found.iter().all(|(value, found_mode, decoded)| {
+            *decoded
+                && found_mode == expected_mode
+                && if expected_mode == "md5" { value.eq_ignore_ascii_case(&expected) } else { *value == expected }
+        })
+}
 
-    progress
-        .emit_complete(result.clone())
-        .map_err(|e| format!("Progress error: {}", e))?;
+/// 计算目录内容摘要（MD5），用于识别字节完全相同的处理结果
+///
+/// 按相对路径排序依次计入路径、条目类型、权限位与文件内容（符号链接计入其目标），
+/// 与文件的修改时间无关。
+fn hash_dir_contents(dir: &Path) -> Result<String, std::io::Error> {
+    use md5::{Digest, Md5};
+    use walkdir::WalkDir;
+    use crate::core::compression::common::unix_mode;
+
+    let mut hasher = Md5::new();
+    for entry in WalkDir::new(dir).follow_links(false).sort_by_file_name() {
+        let entry = entry.map_err(std::io::Error::from)?;
+        let rel = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        hasher.update(rel.to_string_lossy().as_bytes());
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            hasher.update(b"\0l");
+            hasher.update(std::fs::read_link(entry.path())?.to_string_lossy().as_bytes());
+        } else if file_type.is_file() {
+            hasher.update(b"\0f");
+            hasher.update(unix_mode(&entry.metadata()?).unwrap_or(0).to_le_bytes());
+            let mut file = std::fs::File::open(entry.path())?;
+            hasher.update(entry.metadata()?.len().to_le_bytes());
+            std::io::copy(&mut file, &mut hasher)?;
+        } else {
+            hasher.update(b"\0d");
+        }
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
 
-    Ok(result)
+/// 将已有输出硬链接到 `dst`（跨文件系统等无法硬链接时改为复制），`dst` 已存在时先删除
+fn link_or_copy(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+    match std::fs::remove_file(dst) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    std::fs::hard_link(src, dst).or_else(|_| std::fs::copy(src, dst).map(|_| ()))
+}
+
+/// 输出目录中的 SHA-256 校验清单文件名
+const CHECKSUM_FILE_NAME: &str = "SHA256SUMS";
+
+/// 流式计算文件的 SHA-256（小写十六进制），不将整个文件读入内存
+fn sha256_file(path: &Path) -> Result<String, std::io::Error> {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    let mut file = std::fs::File::open(path)?;
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 将输出文件的 SHA-256 写入 `dir/SHA256SUMS`，返回清单路径
+///
+/// 每行为 `<哈希>  <相对 dir 的路径>`（两个空格、`/` 分隔），与 `sha256sum -c` 兼容，
+/// 在 `dir` 中执行 `sha256sum -c SHA256SUMS` 即可校验。已有清单中的其他条目保留
+/// （批量处理多个压缩包共用输出目录），同一路径的条目被更新，整体按路径排序。
+fn write_checksums(dir: &Path, outputs: &[String]) -> Result<PathBuf, std::io::Error> {
+    let manifest = dir.join(CHECKSUM_FILE_NAME);
+    let mut entries = std::collections::BTreeMap::new();
+    if let Ok(existing) = std::fs::read_to_string(&manifest) {
+        for (hash, name) in existing.lines().filter_map(|line| line.split_once("  ")) {
+            entries.insert(name.to_string(), hash.to_string());
+        }
+    }
+    for output in outputs {
+        let path = Path::new(output);
+        let name = path.strip_prefix(dir).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        entries.insert(name, sha256_file(path)?);
+    }
+    let content: String = entries.iter().map(|(name, hash)| format!("{}  {}\n", hash, name)).collect();
+    std::fs::write(&manifest, content)?;
+    Ok(manifest)
+}
+
+/// 填入总耗时并发送批次汇总事件
+fn finish_batch_summary(
+    sink: &dyn ProgressSink,
+    mut summary: BatchSummaryEvent,
+    started: std::time::Instant,
+) -> Result<(), String> {
+    summary.elapsed_ms = started.elapsed().as_millis() as u64;
+    sink.emit_batch_summary(summary)
 }
 
 /// 将水印文本转换为合法的文件夹名（替换操作系统禁止的字符）
@@ -438,6 +1652,8 @@ fn sanitize_path_component(name: &str) -> String {
 }
 
 /// 将解压目录中不属于图片、JSON、VAJ、VMI、VAM、VAP 的文件原样复制到 processed 目录
+///
+/// 返回 `(复制的文件数, 被跳过的符号链接)`。
 fn copy_other_files(
     src_root: &Path,
     dst_root: &Path,
@@ -447,16 +1663,21 @@ fn copy_other_files(
     vmi_rel_paths: &[&Path],
     vam_rel_paths: &[&Path],
     vap_rel_paths: &[&Path],
-) -> Result<(), std::io::Error> {
+    svg_rel_paths: &[&Path],
+    config_rel_paths: &[&Path],
+) -> Result<(usize, Vec<std::path::PathBuf>), std::io::Error> {
     use walkdir::WalkDir;
 
+    let mut copied = 0;
+    let mut skipped_links = Vec::new();
     for entry in WalkDir::new(src_root)
         .follow_links(false)
         .into_iter()
         .filter_map(|e| e.ok())
     {
         let path = entry.path();
-        if !path.is_file() {
+        let is_symlink = entry.file_type().is_symlink();
+        if !is_symlink && !entry.file_type().is_file() {
             continue;
         }
 
@@ -470,7 +1691,9 @@ fn copy_other_files(
         let is_vmi = vmi_rel_paths.iter().any(|r| *r == rel);
         let is_vam = vam_rel_paths.iter().any(|r| *r == rel);
         let is_vap = vap_rel_paths.iter().any(|r| *r == rel);
-        if is_image || is_json || is_vaj || is_vmi || is_vam || is_vap {
+        let is_svg = svg_rel_paths.contains(&rel);
+        let is_config = config_rel_paths.contains(&rel);
+        if is_image || is_json || is_vaj || is_vmi || is_vam || is_vap || is_svg || is_config {
             continue;
         }
 
@@ -478,18 +1701,69 @@ fn copy_other_files(
         if let Some(parent) = dst.parent() {
             std::fs::create_dir_all(parent)?;
         }
+        if is_symlink {
+            if recreate_symlink(src_root, path, &dst)? {
+                copied += 1;
+            } else {
+                skipped_links.push(rel.to_path_buf());
+            }
+            continue;
+        }
         std::fs::copy(path, &dst)?;
+        // fs::copy 不保留修改时间，手动恢复以保证重新打包的结果可复现。
+        // 只读源文件复制后同样只读，写模式打开失败时退回只读句柄（同 `copy_modified_times`）；
+        // 时间戳无法恢复只影响可复现性，不视为复制失败
+        let mtime = std::fs::metadata(path)?.modified()?;
+        if let Ok(file) = std::fs::File::options().write(true).open(&dst).or_else(|_| std::fs::File::open(&dst)) {
+            let _ = file.set_modified(mtime);
+        }
+        copied += 1;
     }
 
-    Ok(())
+    Ok((copied, skipped_links))
+}
+
+/// 在输出目录中重建符号链接，返回是否成功重建
+///
+/// 仅当链接目标为相对路径且解析后仍位于 `src_root` 内时才重建（保证输出自洽、
+/// 不引用工作区外的文件）；绝对路径、越界或悬空的链接，以及不支持符号链接的平台，
+/// 均返回 `false` 交由调用方跳过并上报。
+fn recreate_symlink(src_root: &Path, link: &Path, dst: &Path) -> Result<bool, std::io::Error> {
+    let target = std::fs::read_link(link)?;
+    if target.is_absolute() {
+        return Ok(false);
+    }
+    let resolved = match link.parent().map(|p| p.join(&target)).map(std::fs::canonicalize) {
+        Some(Ok(p)) => p,
+        _ => return Ok(false),
+    };
+    let root = std::fs::canonicalize(src_root)?;
+    if !resolved.starts_with(&root) {
+        return Ok(false);
+    }
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&target, dst)?;
+        Ok(true)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = dst;
+        Ok(false)
+    }
 }
 
 /// 从压缩包中提取指定 JSON 文件的水印
+///
+/// 指定 `semi_obfuscated_keys` 时按半混淆模式的候选字段名依次查找（空列表使用默认候选），
+/// 否则读取 `watermark_key` 字段。
 #[tauri::command]
 pub async fn extract_json_watermark_from_archive(
     archive_path: String,
     json_path_in_archive: Option<String>,
     watermark_key: Option<String>,
+    semi_obfuscated_keys: Option<Vec<String>>,
 ) -> Result<String, String> {
     let archive_path_buf = std::path::PathBuf::from(&archive_path);
     let archive_name = archive_path_buf
@@ -500,18 +1774,30 @@ pub async fn extract_json_watermark_from_archive(
     let workspace = TempWorkspace::new(archive_name)
         .map_err(|e| format!("创建工作区失败: {}", e))?;
 
-    let archive_processor = ArchiveProcessor::new();
+    let archive_processor = ArchiveProcessor::shared();
     archive_processor
         .extract(&archive_path_buf, workspace.extracted_path())
         .map_err(|e| format!("解压失败: {}", e))?;
 
-    // 默认读取 meta.json；也可指定路径
+    // 默认读取 meta.json；也可指定路径。根目录下不存在时，相对包裹全部内容的单一顶层目录（如 `Package/`）查找
     let target = json_path_in_archive.unwrap_or_else(|| "meta.json".to_string());
-    let json_abs = workspace.extracted_path().join(&target);
+    let mut json_abs = workspace.extracted_path().join(&target);
+    if !json_abs.is_file() {
+        json_abs = effective_root(workspace.extracted_path()).join(&target);
+    }
 
     let content = std::fs::read_to_string(&json_abs)
         .map_err(|e| format!("读取 {} 失败: {}", target, e))?;
 
+    if let Some(keys) = semi_obfuscated_keys {
+        let found = if keys.is_empty() {
+            JsonWatermarker::extract_semi_obfuscated(&content, SEMI_OBFUSCATED_KEYS)
+        } else {
+            JsonWatermarker::extract_semi_obfuscated(&content, &keys)
+        };
+        return found.map(|(_, value)| value).map_err(|e| e.to_string());
+    }
+
     let key = watermark_key
         .as_deref()
         .filter(|k| !k.trim().is_empty())
@@ -528,12 +1814,16 @@ pub async fn extract_json_watermark_from_archive(
 /// - 遍历全部受支持格式的文件
 /// - 返回所有找到水印的文件列表（文件路径 + 水印值 + 模式）
 /// - 找不到水印字段的文件直接跳过（不报错）
+/// - 默认递归扫描嵌套对象/数组，并通过 `pointer` 报告水印所在位置；
+///   `nested=false` 时仅扫描顶层字段以提速
 #[tauri::command]
 pub async fn scan_watermarks_in_archive(
     archive_path: String,
     watermark_key: Option<String>,
     aes_key: Option<String>,
+    nested: Option<bool>,
 ) -> Result<Vec<WatermarkFinding>, String> {
+    let nested = nested.unwrap_or(true);
     let archive_path_buf = std::path::PathBuf::from(&archive_path);
     let archive_name = archive_path_buf
         .file_stem()
@@ -543,7 +1833,7 @@ pub async fn scan_watermarks_in_archive(
     let workspace = TempWorkspace::new(archive_name)
         .map_err(|e| format!("创建工作区失败: {}", e))?;
 
-    let archive_processor = ArchiveProcessor::new();
+    let archive_processor = ArchiveProcessor::shared();
     archive_processor
         .extract(&archive_path_buf, workspace.extracted_path())
         .map_err(|e| format!("解压失败: {}", e))?;
@@ -554,50 +1844,198 @@ pub async fn scan_watermarks_in_archive(
         .unwrap_or(DEFAULT_WATERMARK_KEY);
     let _ = key; // 保留参数兼容性；提取现通过值扫描实现，无需指定键名
 
-    let scanner = FileScanner::new();
+    let scanner = FileScanner::shared();
     let extracted = workspace.extracted_path();
     let aes_key_ref = aes_key.as_deref();
 
     // 收集所有 JSON / VAJ / VMI / VAM / VAP 文件（忽略各类扫描错误）
-    let mut all_files: Vec<(std::path::PathBuf, std::path::PathBuf)> = Vec::new();
-    for scan_result in [
-        scanner.scan_json_files(extracted),
-        scanner.scan_vaj_files(extracted),
-        scanner.scan_vmi_files(extracted),
-        scanner.scan_vam_files(extracted),
-        scanner.scan_vap_files(extracted),
-    ] {
-        if let Ok(files) = scan_result {
-            all_files.extend(files);
-        }
-    }
+    let all_files = collect_json_like_files(scanner, extracted);
 
-    // 逐文件扫描所有格式的水印值（兼容明文、MD5、AES 三种模式）
+    // 逐文件扫描所有格式的水印值（兼容明文、MD5、AES、HMAC 四种模式）
     let mut findings: Vec<WatermarkFinding> = Vec::new();
     for (abs_path, rel_path) in &all_files {
         if let Ok(content) = std::fs::read_to_string(abs_path) {
-            for (value, mode, decrypted) in JsonWatermarker::scan_watermark_values(&content, aes_key_ref) {
+            for loc in JsonWatermarker::scan_watermark_locations(&content, aes_key_ref, nested) {
                 findings.push(WatermarkFinding {
                     file: rel_path.to_string_lossy().to_string(),
-                    value,
-                    mode,
-                    decrypted,
+                    value: loc.value,
+                    mode: loc.mode,
+                    decrypted: loc.decrypted,
+                    pointer: loc.pointer,
+                    file_type: "json",
                 });
             }
         }
     }
+    findings.extend(scan_svg_findings(scanner, extracted, aes_key_ref).0);
+    findings.extend(scan_config_findings(scanner, extracted, aes_key_ref).0);
 
     Ok(findings)
 }
 
-/// 合并扫描结果（JSON/VAJ/VMI 水印 + 图片盲水印）
+/// 按扩展名判断是否为 JPEG（不区分大小写）
+fn is_jpeg_path(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"))
+}
+
+/// 收集目录中所有 JSON / VAJ / VMI / VAM / VAP 文件（忽略各类扫描错误）
+fn collect_json_like_files(scanner: &FileScanner, root: &Path) -> Vec<(std::path::PathBuf, std::path::PathBuf)> {
+    let mut files = Vec::new();
+    for found in [
+        scanner.scan_json_files(root),
+        scanner.scan_vaj_files(root),
+        scanner.scan_vmi_files(root),
+        scanner.scan_vam_files(root),
+        scanner.scan_vap_files(root),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        files.extend(found);
+    }
+    files
+}
+
+/// 按文件列出压缩包中 JSON / VAJ / VMI 等文件使用的水印模式
+///
+/// 与 scan_watermarks_in_archive 的扁平列表不同，结果按文件分组（每个文件一项，
+/// 包括未找到水印的文件），并标明 AES 水印能否用 `aes_key` 解密，
+/// 便于逐文件审计压缩包的加密情况。结果按文件路径排序。
+#[tauri::command]
+pub async fn classify_json_watermarks(
+    archive_path: String,
+    aes_key: Option<String>,
+) -> Result<Vec<JsonWatermarkClassification>, String> {
+    let archive_path_buf = std::path::PathBuf::from(&archive_path);
+    let archive_name = archive_path_buf
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("archive");
+
+    let workspace = TempWorkspace::new(archive_name)
+        .map_err(|e| format!("创建工作区失败: {}", e))?;
+    ArchiveProcessor::shared()
+        .extract(&archive_path_buf, workspace.extracted_path())
+        .map_err(|e| format!("解压失败: {}", e))?;
+
+    Ok(classify_json_files(workspace.extracted_path(), aes_key.as_deref()))
+}
+
+/// `classify_json_watermarks` 的同步实现：分类目录下所有 JSON 类文件（读取失败的文件跳过）
+fn classify_json_files(root: &Path, aes_key: Option<&str>) -> Vec<JsonWatermarkClassification> {
+    let mut results: Vec<JsonWatermarkClassification> = collect_json_like_files(FileScanner::shared(), root)
+        .into_iter()
+        .filter_map(|(abs_path, rel_path)| {
+            let content = std::fs::read_to_string(abs_path).ok()?;
+            let locations = JsonWatermarker::scan_watermark_locations(&content, aes_key, true);
+            let mut modes: Vec<String> = Vec::new();
+            let mut aes_decrypted: Option<bool> = None;
+            for loc in &locations {
+                if !modes.contains(&loc.mode) {
+                    modes.push(loc.mode.clone());
+                }
+                if loc.mode == "aes" {
+                    aes_decrypted = Some(aes_decrypted.unwrap_or(true) && loc.decrypted);
+                }
+            }
+            Some(JsonWatermarkClassification {
+                file: rel_path.to_string_lossy().to_string(),
+                modes,
+                watermark_count: locations.len(),
+                aes_decrypted,
+            })
+        })
+        .collect();
+    results.sort_by(|a, b| a.file.cmp(&b.file));
+    results
+}
+
+/// 扫描目录中所有 SVG 文件根元素上的水印属性（忽略读取失败的文件）
+///
+/// 返回 `(水印结果, 扫描的 SVG 文件数)`
+fn scan_svg_findings(scanner: &FileScanner, root: &Path, aes_key: Option<&str>) -> (Vec<WatermarkFinding>, usize) {
+    let files = scanner.scan_svg_files(root).unwrap_or_default();
+    let findings = files
+        .iter()
+        .filter_map(|(abs_path, rel_path)| {
+            let content = std::fs::read_to_string(abs_path).ok()?;
+            let (value, mode, decrypted) = SvgWatermarker::scan_watermark_value(content.trim_start_matches('\u{FEFF}'), aes_key)?;
+            Some(WatermarkFinding {
+                file: rel_path.to_string_lossy().to_string(),
+                value,
+                mode,
+                decrypted,
+                pointer: SVG_WATERMARK_ATTRIBUTE.to_string(),
+                file_type: "svg",
+            })
+        })
+        .collect();
+    (findings, files.len())
+}
+
+/// 扫描目录中所有 TOML / INI 文件 `[blindmark]` 节中的水印（忽略读取失败的文件）
+///
+/// 返回 `(水印结果, 扫描的 TOML / INI 文件数)`
+fn scan_config_findings(scanner: &FileScanner, root: &Path, aes_key: Option<&str>) -> (Vec<WatermarkFinding>, usize) {
+    type ScanFn = fn(&str, Option<&str>) -> Option<(String, String, bool)>;
+    let groups: [(_, ScanFn); 2] = [
+        (scanner.scan_toml_files(root).unwrap_or_default(), TomlWatermarker::scan_watermark_value),
+        (scanner.scan_ini_files(root).unwrap_or_default(), IniWatermarker::scan_watermark_value),
+    ];
+    let scanned = groups.iter().map(|(files, _)| files.len()).sum();
+    let findings = groups
+        .into_iter()
+        .flat_map(|(files, scan)| {
+            files.into_iter().filter_map(move |(abs_path, rel_path)| {
+                let content = std::fs::read_to_string(abs_path).ok()?;
+                let (value, mode, decrypted) = scan(content.trim_start_matches('\u{FEFF}'), aes_key)?;
+                Some(WatermarkFinding {
+                    file: rel_path.to_string_lossy().to_string(),
+                    value,
+                    mode,
+                    decrypted,
+                    pointer: format!("[{}].{}", CONFIG_WATERMARK_SECTION, CONFIG_WATERMARK_KEY),
+                    file_type: "config",
+                })
+            })
+        })
+        .collect();
+    (findings, scanned)
+}
+
+/// 合并扫描结果（JSON/VAJ/VMI、SVG、TOML / INI 水印 + 图片盲水印）
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CombinedScanResult {
+    /// 文本类文件的水印（含 SVG 与 TOML / INI，按 `file_type` 区分）
     pub json_findings: Vec<WatermarkFinding>,
     pub image_findings: Vec<ImageWatermarkFinding>,
-    /// 本次扫描实际处理的 PNG 图片数量（JPEG 已过滤，0 表示压缩包内无 PNG）
+    /// 本次扫描实际处理的 PNG / JPEG 图片数量（0 表示压缩包内无此类图片）
     pub scanned_png_count: usize,
+    /// 本次扫描的 JSON/VAJ/VMI/VAM/VAP 文件数量
+    pub scanned_text_file_count: usize,
+    /// 本次扫描的 SVG 文件数量
+    pub scanned_svg_count: usize,
+    /// 本次扫描的 TOML / INI 文件数量
+    pub scanned_config_file_count: usize,
+    /// 存在 AES 水印但首个无法解密（未提供密钥或密钥错误）时的提示；
+    /// 此时所有 AES 结果的 `decrypted` 均可能为 false，不代表压缩包未加水印
+    pub aes_key_warning: Option<String>,
+}
+
+/// 压缩包水印概览（按模式、按文件类型统计）
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatermarkSummary {
+    /// 各水印模式的数量："md5" / "plaintext" / "aes" / "hmac" / "unknown"（图片盲水印为原始文本，计入 "plaintext"）
+    pub by_mode: std::collections::BTreeMap<String, usize>,
+    /// 各文件类型的水印数量："json"（含 VAJ/VMI/VAM/VAP）/ "svg" / "config"（TOML / INI）/ "image"
+    pub by_file_type: std::collections::BTreeMap<String, usize>,
+    /// 未检测到任何水印的文件数量（JSON 类文件 + SVG + TOML / INI + PNG / JPEG 图片）
+    pub unwatermarked_count: usize,
 }
 
 /// 一次性扫描压缩包中所有水印（JSON/VAJ/VMI + 图片盲水印）
@@ -607,15 +2045,61 @@ pub struct CombinedScanResult {
 /// # 参数
 /// * `scan_images` - 是否扫描图片盲水印。设为 false 可跳过 DWT+DCT 提取，
 ///                   大幅缩短仅含 JSON 水印的压缩包的提取时间。
-///                   即使为 true，也只处理 PNG（JPEG 经有损压缩无法保留水印）。
+///                   即使为 true，也只处理 PNG 与 JPEG（JPEG 按 DCT 域水印提取）。
+/// * `thread_count` - 图片扫描线程数（默认使用全部 CPU 核心），与嵌入阶段的并行度相互独立。
+/// * `min_confidence` - 图片水印最低置信度，低于该值的结果视为误报被丢弃（默认保留全部）。
+/// * `tolerant` - 容错提取：对被其他工具重新保存（gamma / 色彩配置转换）的图片尝试 gamma 校正，
+///   速度较慢，默认关闭。
+/// * `image_password` - 嵌入时使用的图片盲水印密码（默认无密码）。
+/// * `top_level_only` - 仅扫描 JSON 顶层字段（更快）；默认递归扫描嵌套对象与数组。
+/// * `quick_prefilter` - 先用 `WatermarkExtractor::quick_detect` 快速排除明显无水印的图片，
+///   只对其余图片做完整提取（见 `ParallelProcessor::with_quick_prefilter`）。适合大量未加水印图片的压缩包；
+///   按块比例嵌入的图片可能被漏掉，默认关闭。
+///
+/// 文本文件扫描完成后、图片扫描开始前探测首个 AES 水印：无法解密时立即发送
+/// `aes_key_failed` 状态，并写入结果的 `aes_key_warning`。
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn scan_all_watermarks_in_archive(
+    app: AppHandle,
     archive_path: String,
     aes_key: Option<String>,
     scan_images: Option<bool>,
+    thread_count: Option<usize>,
+    min_confidence: Option<f32>,
+    tolerant: Option<bool>,
+    image_password: Option<String>,
+    top_level_only: Option<bool>,
+    quick_prefilter: Option<bool>,
 ) -> Result<CombinedScanResult, String> {
-    use rayon::prelude::*;
+    scan_all_core(
+        &archive_path,
+        aes_key.as_deref(),
+        scan_images,
+        thread_count,
+        min_confidence,
+        tolerant.unwrap_or(false),
+        quick_prefilter.unwrap_or(false),
+        password_seed(image_password.as_deref().unwrap_or("")),
+        !top_level_only.unwrap_or(false),
+        Some(&ProgressEmitter::new(app) as &dyn ProgressSink),
+    )
+}
 
+/// `scan_all_watermarks_in_archive` 的同步实现（供其他命令复用）
+#[allow(clippy::too_many_arguments)]
+fn scan_all_core(
+    archive_path: &str,
+    aes_key_ref: Option<&str>,
+    scan_images: Option<bool>,
+    thread_count: Option<usize>,
+    min_confidence: Option<f32>,
+    tolerant: bool,
+    quick_prefilter: bool,
+    image_seed: u64,
+    nested_json: bool,
+    progress: Option<&dyn ProgressSink>,
+) -> Result<CombinedScanResult, String> {
     let archive_path_buf = std::path::PathBuf::from(&archive_path);
     let archive_name = archive_path_buf
         .file_stem()
@@ -625,80 +2109,269 @@ pub async fn scan_all_watermarks_in_archive(
     let workspace = TempWorkspace::new(archive_name)
         .map_err(|e| format!("创建工作区失败: {}", e))?;
 
-    let archive_processor = ArchiveProcessor::new();
+    let archive_processor = ArchiveProcessor::shared();
     archive_processor
         .extract(&archive_path_buf, workspace.extracted_path())
         .map_err(|e| format!("解压失败: {}", e))?;
 
-    let scanner = FileScanner::new();
+    let scanner = FileScanner::shared();
     let extracted = workspace.extracted_path();
-    let aes_key_ref = aes_key.as_deref();
 
     // ── 扫描 JSON / VAJ / VMI / VAM / VAP 文件（通常数量少，顺序处理即可）──────────────
-    let mut all_text_files: Vec<(std::path::PathBuf, std::path::PathBuf)> = Vec::new();
-    for scan_result in [
-        scanner.scan_json_files(extracted),
-        scanner.scan_vaj_files(extracted),
-        scanner.scan_vmi_files(extracted),
-        scanner.scan_vam_files(extracted),
-        scanner.scan_vap_files(extracted),
-    ] {
-        if let Ok(files) = scan_result {
-            all_text_files.extend(files);
-        }
-    }
+    let all_text_files = collect_json_like_files(scanner, extracted);
 
     let mut json_findings: Vec<WatermarkFinding> = Vec::new();
     for (abs_path, rel_path) in &all_text_files {
         if let Ok(content) = std::fs::read_to_string(abs_path) {
-            for (value, mode, decrypted) in JsonWatermarker::scan_watermark_values(&content, aes_key_ref) {
+            for loc in JsonWatermarker::scan_watermark_locations(&content, aes_key_ref, nested_json) {
                 json_findings.push(WatermarkFinding {
                     file: rel_path.to_string_lossy().to_string(),
-                    value,
-                    mode,
-                    decrypted,
+                    value: loc.value,
+                    mode: loc.mode,
+                    decrypted: loc.decrypted,
+                    pointer: loc.pointer,
+                    file_type: "json",
                 });
             }
         }
     }
+    let (svg_findings, scanned_svg_count) = scan_svg_findings(scanner, extracted, aes_key_ref);
+    let (config_findings, scanned_config_file_count) = scan_config_findings(scanner, extracted, aes_key_ref);
+    json_findings.extend(svg_findings);
+    json_findings.extend(config_findings);
+
+    // 密钥探测：首个 AES 水印解密失败时尽早提示，避免用户误以为压缩包未加水印
+    let aes_key_warning = probe_aes_key(&json_findings, aes_key_ref);
+    if let (Some(warning), Some(progress)) = (&aes_key_warning, progress) {
+        progress
+            .emit_status("aes_key_failed".to_string(), warning.clone())
+            .map_err(|e| format!("Progress error: {}", e))?;
+    }
 
     // ── 并行扫描图片盲水印 ────────────────────────────────────────────────
     // 仅在 scan_images=true（默认）时执行；
-    // 只处理 PNG（无损），JPEG 经有损压缩无法保留 DWT+DCT 水印，自动过滤。
+    // 只处理 PNG 与 JPEG：JPEG 按 DCT 域水印提取（见 `WatermarkExtractor::try_extract_text_jpeg`）。
     let should_scan_images = scan_images.unwrap_or(true);
     let all_images = if should_scan_images {
         scanner.scan(extracted).unwrap_or_default()
     } else {
         vec![]
     };
-    // 过滤出 PNG：JPEG 必定提取失败，提前排除可减少无效 IO 和解码开销
+    // 过滤出 PNG / JPEG，提前排除其他格式可减少无效 IO 和解码开销
     let png_images: Vec<_> = all_images
         .into_iter()
-        .filter(|f| f.relative_path.to_lowercase().ends_with(".png"))
+        .filter(|f| f.relative_path.to_lowercase().ends_with(".png") || is_jpeg_path(&f.relative_path))
         .collect();
 
-    let mut image_findings: Vec<ImageWatermarkFinding> = if png_images.is_empty() {
-        // 无 PNG 图片（或用户关闭了图片扫描）→ 直接返回空结果，跳过 DWT+DCT 计算
+    let image_findings: Vec<ImageWatermarkFinding> = if png_images.is_empty() {
+        // 无 PNG / JPEG 图片（或用户关闭了图片扫描）→ 直接返回空结果，跳过 DWT+DCT 计算
         vec![]
     } else {
-        let extractor = WatermarkExtractor::new();
-        png_images
+        // 图片扫描使用独立线程池，可与嵌入阶段分别限速；结果已按文件路径排序
+        let scan_processor = match thread_count {
+            None => ParallelProcessor::new(),
+            Some(0) => return Err("扫描线程数必须大于 0".to_string()),
+            Some(n) => ParallelProcessor::with_threads(n),
+        };
+        scan_processor
+            .with_password(image_seed)
+            .with_quick_prefilter(quick_prefilter)
+            .scan_batch_text(&png_images, tolerant)
+            .map_err(|e| format!("扫描图片水印失败: {}", e))?
+            .into_iter()
+            .map(|(file, text, confidence)| ImageWatermarkFinding { file, text, confidence })
+            .collect()
+    };
+    let image_findings = retain_confident(image_findings, min_confidence);
+
+    Ok(CombinedScanResult {
+        json_findings,
+        image_findings,
+        scanned_png_count: png_images.len(),
+        scanned_text_file_count: all_text_files.len(),
+        scanned_svg_count,
+        scanned_config_file_count,
+        aes_key_warning,
+    })
+}
+
+/// 检查首个 AES 水印能否解密；不能时返回提示文本（无 AES 水印或解密成功时为 `None`）
+fn probe_aes_key(findings: &[WatermarkFinding], aes_key: Option<&str>) -> Option<String> {
+    let first = findings.iter().find(|f| f.mode == "aes")?;
+    if first.decrypted {
+        return None;
+    }
+    let total = findings.iter().filter(|f| f.mode == "aes").count();
+    Some(match aes_key.filter(|k| !k.is_empty()) {
+        Some(_) => format!("存在 {} 个 AES 水印，但提供的密钥无法解密（首个位于 {}），请确认密钥", total, first.file),
+        None => format!("存在 {} 个 AES 水印，但未提供密钥，无法解密（首个位于 {}）", total, first.file),
+    })
+}
+
+/// 同时扫描的压缩包数量上限（每个压缩包内部的图片扫描仍为并行）
+const MAX_CONCURRENT_ARCHIVE_SCANS: usize = 4;
+
+/// `scan_multiple_archives` 中单个压缩包的扫描结果
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ArchiveScanResult {
+    /// 扫描成功（字段与 `CombinedScanResult` 相同）
+    Scanned(CombinedScanResult),
+    /// 解压或扫描失败
+    Error { message: String },
+}
+
+/// 批量扫描多个压缩包中的水印（JSON/VAJ/VMI 水印 + 可选图片盲水印）
+///
+/// 最多同时扫描 `MAX_CONCURRENT_ARCHIVE_SCANS` 个压缩包，CPU 核心在各压缩包的图片扫描间均分。
+/// 单个压缩包失败不影响其他压缩包，记为 `Error` 项。
+///
+/// # 返回
+/// 压缩包路径 → 扫描结果（按路径排序）
+#[tauri::command]
+pub async fn scan_multiple_archives(
+    paths: Vec<String>,
+    aes_key: Option<String>,
+    scan_images: Option<bool>,
+) -> Result<std::collections::BTreeMap<String, ArchiveScanResult>, String> {
+    scan_multiple_core(&paths, aes_key.as_deref(), scan_images)
+}
+
+/// `scan_multiple_archives` 的同步实现
+fn scan_multiple_core(
+    paths: &[String],
+    aes_key: Option<&str>,
+    scan_images: Option<bool>,
+) -> Result<std::collections::BTreeMap<String, ArchiveScanResult>, String> {
+    let concurrency = paths.len().clamp(1, MAX_CONCURRENT_ARCHIVE_SCANS);
+    let threads_per_archive = (num_cpus::get() / concurrency).max(1);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(concurrency)
+        .build()
+        .map_err(|e| format!("创建线程池失败: {}", e))?;
+
+    Ok(pool.install(|| {
+        paths
             .par_iter()
-            .filter_map(|image_file| {
-                let img = image::open(&image_file.temp_path).ok()?;
-                let text = extractor.try_extract_text(&img).ok()??;
-                Some(ImageWatermarkFinding {
-                    file: image_file.relative_path.clone(),
-                    text,
-                })
+            .map(|path| {
+                let result = match scan_all_core(path, aes_key, scan_images, Some(threads_per_archive), None, false, false, DEFAULT_PASSWORD, true, None) {
+                    Ok(result) => ArchiveScanResult::Scanned(result),
+                    Err(message) => ArchiveScanResult::Error { message },
+                };
+                (path.clone(), result)
             })
             .collect()
+    }))
+}
+
+/// 压缩包水印概览：一次扫描全部文件，返回按模式 / 文件类型统计的数量及未加水印文件数
+///
+/// 内部复用 `scan_all_watermarks_in_archive`（扫描图片、默认线程数、不过滤置信度），
+/// 便于在查看详细结果前快速判断压缩包的水印情况。
+#[tauri::command]
+pub async fn summarize_archive_watermarks(
+    archive_path: String,
+    aes_key: Option<String>,
+) -> Result<WatermarkSummary, String> {
+    let result = scan_all_core(&archive_path, aes_key.as_deref(), None, None, None, false, false, DEFAULT_PASSWORD, true, None)?;
+    Ok(summarize_scan(&result))
+}
+
+/// 列出压缩包中无法在无密钥情况下解密的 AES 水印
+///
+/// 不提供 AES 密钥扫描全部 JSON/VAJ/VMI/VAM/VAP（及 SVG）文件，返回其中 `mode="aes"`、
+/// `decrypted=false` 的结果（`value` 为原始 `aes:` 密文），便于用户了解哪些文件被加密、
+/// 可能需要几把密钥。不扫描图片。
+#[tauri::command]
+pub async fn list_encrypted_watermarks(archive_path: String) -> Result<Vec<WatermarkFinding>, String> {
+    list_encrypted_core(&archive_path)
+}
+
+/// `list_encrypted_watermarks` 的同步实现
+fn list_encrypted_core(archive_path: &str) -> Result<Vec<WatermarkFinding>, String> {
+    let result = scan_all_core(archive_path, None, Some(false), None, None, false, false, DEFAULT_PASSWORD, true, None)?;
+    Ok(result
+        .json_findings
+        .into_iter()
+        .filter(|f| f.mode == "aes" && !f.decrypted)
+        .collect())
+}
+
+/// 检测压缩包中携带相同图片盲水印的图片
+///
+/// 并行扫描全部 PNG / JPEG 图片的原始文本水印，按水印文本分组，仅返回包含多张图片的组
+/// `(水印文本, 图片相对路径列表)`，按水印文本排序。多张图片共用同一水印可能意味着
+/// 复制粘贴泄露或批处理出错。
+#[tauri::command]
+pub async fn detect_duplicate_image_watermarks(archive_path: String) -> Result<Vec<(String, Vec<String>)>, String> {
+    let result = scan_all_core(&archive_path, None, Some(true), None, None, false, false, DEFAULT_PASSWORD, true, None)?;
+    Ok(group_duplicate_watermarks(&result.image_findings))
+}
+
+/// 按水印文本分组图片结果，保留包含多张图片的组
+fn group_duplicate_watermarks(findings: &[ImageWatermarkFinding]) -> Vec<(String, Vec<String>)> {
+    let mut groups: std::collections::BTreeMap<&str, Vec<String>> = std::collections::BTreeMap::new();
+    for finding in findings {
+        groups.entry(finding.text.as_str()).or_default().push(finding.file.clone());
+    }
+    groups
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(text, files)| (text.to_string(), files))
+        .collect()
+}
+
+/// 由合并扫描结果计算水印概览
+fn summarize_scan(result: &CombinedScanResult) -> WatermarkSummary {
+    let mut summary = WatermarkSummary::default();
+
+    for finding in &result.json_findings {
+        *summary.by_mode.entry(finding.mode.clone()).or_default() += 1;
+        *summary.by_file_type.entry(finding.file_type.to_string()).or_default() += 1;
+    }
+    for _ in &result.image_findings {
+        *summary.by_mode.entry("plaintext".to_string()).or_default() += 1;
+        *summary.by_file_type.entry("image".to_string()).or_default() += 1;
+    }
+
+    // 同一文件可能含多处水印，按文件类型分别去重后计算未加水印数量
+    let marked_files = |file_type: &str| {
+        result.json_findings.iter()
+            .filter(|f| f.file_type == file_type)
+            .map(|f| f.file.as_str())
+            .collect::<std::collections::HashSet<_>>()
+            .len()
     };
+    let marked_images: std::collections::HashSet<&str> =
+        result.image_findings.iter().map(|f| f.file.as_str()).collect();
+    summary.unwatermarked_count = result.scanned_text_file_count - marked_files("json")
+        + result.scanned_svg_count - marked_files("svg")
+        + result.scanned_config_file_count - marked_files("config")
+        + result.scanned_png_count - marked_images.len();
+
+    summary
+}
 
-    // 按文件路径排序，保证结果顺序稳定
-    image_findings.sort_by(|a, b| a.file.cmp(&b.file));
+/// 丢弃置信度低于 `min_confidence` 的图片水印结果（None 表示保留全部）
+fn retain_confident(
+    findings: Vec<ImageWatermarkFinding>,
+    min_confidence: Option<f32>,
+) -> Vec<ImageWatermarkFinding> {
+    match min_confidence {
+        Some(min) => findings.into_iter().filter(|f| f.confidence >= min).collect(),
+        None => findings,
+    }
+}
 
-    Ok(CombinedScanResult { json_findings, image_findings, scanned_png_count: png_images.len() })
+/// 识别压缩包格式（不解压）
+///
+/// 优先读取文件头魔数（可识别扩展名错误的文件，如实为 7z 的 `.zip`），无法识别时按扩展名判断。
+/// 返回 `"zip"` / `"7z"` / `"rar"` / `"var"` 等格式名；均无法识别时返回错误。
+#[tauri::command]
+pub async fn detect_archive_type(path: String) -> Result<String, String> {
+    ArchiveProcessor::shared()
+        .detect_format(Path::new(&path))
+        .ok_or_else(|| format!("无法识别压缩包格式: {}", path))
 }
 
 /// 列出压缩包中所有图片文件的相对路径
@@ -717,12 +2390,12 @@ pub async fn list_images_in_archive(
     let workspace = TempWorkspace::new(archive_name)
         .map_err(|e| format!("创建工作区失败: {}", e))?;
 
-    let archive_processor = ArchiveProcessor::new();
+    let archive_processor = ArchiveProcessor::shared();
     archive_processor
         .extract(&archive_path_buf, workspace.extracted_path())
         .map_err(|e| format!("解压失败: {}", e))?;
 
-    let scanner = FileScanner::new();
+    let scanner = FileScanner::shared();
     let images = scanner
         .scan(workspace.extracted_path())
         .map_err(|e| format!("扫描图片失败: {}", e))?;
@@ -744,12 +2417,12 @@ pub async fn scan_image_watermarks_in_archive(
     let workspace = TempWorkspace::new(archive_name)
         .map_err(|e| format!("创建工作区失败: {}", e))?;
 
-    let archive_processor = ArchiveProcessor::new();
+    let archive_processor = ArchiveProcessor::shared();
     archive_processor
         .extract(&archive_path_buf, workspace.extracted_path())
         .map_err(|e| format!("解压失败: {}", e))?;
 
-    let scanner = FileScanner::new();
+    let scanner = FileScanner::shared();
     let images = scanner
         .scan(workspace.extracted_path())
         .map_err(|e| format!("扫描图片失败: {}", e))?;
@@ -758,17 +2431,1358 @@ pub async fn scan_image_watermarks_in_archive(
     let mut findings: Vec<ImageWatermarkFinding> = Vec::new();
 
     for image_file in &images {
-        let img = match image::open(&image_file.temp_path) {
+        let img = match open_limited(&image_file.temp_path, &DecodeLimits::default()) {
             Ok(img) => img,
             Err(_) => continue,
         };
-        if let Ok(Some(text)) = extractor.try_extract_text(&img) {
+        if let Ok(Some((text, confidence))) = extractor.try_extract_text_with_confidence(&img) {
             findings.push(ImageWatermarkFinding {
                 file: image_file.relative_path.clone(),
                 text,
+                confidence,
             });
         }
     }
 
     Ok(findings)
 }
+
+/// 批量校验目录或压缩包中每张图片的盲水印是否属于期望列表（发货后 QA）
+///
+/// 并行提取每张图片的水印（文本优先，其次 MD5，见 `WatermarkExtractor::extract_any`；
+/// JPEG 只读取 DCT 域文本水印）：
+/// 文本水印须与期望列表中的某项完全一致；MD5 水印按 `salt` 反查（同 `resolve_watermark`）。
+/// 逐个返回 match / mismatch / notFound / error，按相对路径排序。
+#[tauri::command]
+pub async fn batch_verify(
+    dir_or_archive: String,
+    expected: Vec<String>,
+    salt: Option<String>,
+    image_password: Option<String>,
+) -> Result<Vec<ImageVerifyResult>, String> {
+    batch_verify_core(
+        Path::new(&dir_or_archive),
+        &expected,
+        salt.as_deref(),
+        password_seed(image_password.as_deref().unwrap_or("")),
+    )
+}
+
+/// `batch_verify` 的同步实现
+fn batch_verify_core(
+    source: &Path,
+    expected: &[String],
+    salt: Option<&str>,
+    image_seed: u64,
+) -> Result<Vec<ImageVerifyResult>, String> {
+    // 压缩包先解压到临时工作区，目录直接扫描
+    let workspace = if source.is_dir() {
+        None
+    } else {
+        let archive_name = source.file_stem().and_then(|s| s.to_str()).unwrap_or("archive");
+        let workspace = TempWorkspace::new(archive_name)
+            .map_err(|e| format!("创建工作区失败: {}", e))?;
+        ArchiveProcessor::shared()
+            .extract(source, workspace.extracted_path())
+            .map_err(|e| format!("解压失败: {}", e))?;
+        Some(workspace)
+    };
+    let root = workspace.as_ref().map_or(source, |w| w.extracted_path());
+
+    let images = FileScanner::shared()
+        .scan(root)
+        .map_err(|e| format!("扫描图片失败: {}", e))?;
+    let extracted = ParallelProcessor::new()
+        .with_password(image_seed)
+        .scan_batch_any(&images)
+        .map_err(|e| e.to_string())?;
+
+    Ok(extracted
+        .into_iter()
+        .map(|(file, result)| ImageVerifyResult { file, outcome: image_verify_outcome(result, expected, salt) })
+        .collect())
+}
+
+/// 按期望列表判定单张图片的提取结果：文本须完全一致，MD5 按 `salt` 反查（同 `resolve_watermark`）
+fn image_verify_outcome(
+    result: Result<ExtractedWatermark, BlindMarkError>,
+    expected: &[String],
+    salt: Option<&str>,
+) -> VerifyOutcome {
+    match result {
+        Ok(ExtractedWatermark::Text(text)) if expected.contains(&text) => VerifyOutcome::Match { expected: text },
+        Ok(ExtractedWatermark::Text(found)) => VerifyOutcome::Mismatch { found },
+        Ok(ExtractedWatermark::Md5(hash)) => md5_verify_outcome(hash, expected, salt),
+        Ok(ExtractedWatermark::None) | Err(BlindMarkError::WatermarkNotFound(_)) => VerifyOutcome::NotFound,
+        Err(e) => VerifyOutcome::Error { message: e.to_string() },
+    }
+}
+
+fn md5_verify_outcome(hash: String, expected: &[String], salt: Option<&str>) -> VerifyOutcome {
+    match WatermarkEncoder::resolve_md5(&hash, expected, salt) {
+        Some(text) => VerifyOutcome::Match { expected: text.to_string() },
+        None => VerifyOutcome::Mismatch { found: hash },
+    }
+}
+
+/// `verify_archive_consistency` 的结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyReport {
+    /// 所有检查的文件都带有期望水印
+    pub consistent: bool,
+    /// 检查的文件数（JSON / VAJ / VMI / VAM / VAP 文件 + PNG / JPEG 图片）
+    pub checked_count: usize,
+    /// 与期望水印不一致的文件（含不同水印、未找到水印、AES 无法解密），按路径排序
+    pub divergent: Vec<ConsistencyIssue>,
+}
+
+/// `verify_archive_consistency` 中一个不一致的文件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyIssue {
+    /// 文件在压缩包中的相对路径
+    pub file: String,
+    /// 不一致的原因（`Mismatch` / `NotFound` / `Error`）
+    #[serde(flatten)]
+    pub outcome: VerifyOutcome,
+}
+
+/// 校验压缩包中所有可加水印的文件是否带有同一个水印 `expected_text`，列出不一致的文件
+///
+/// 用于发现中断或混用水印的处理结果。JSON / VAJ / VMI / VAM / VAP 文件的每处水印都须与期望一致
+/// （AES 水印用 `aes_key` 解密、HMAC 水印用其校验签名，MD5 水印按 `salt` 反查）；PNG / JPEG 图片的盲水印按
+/// `batch_verify` 的规则判定（文本完全一致，MD5 反查；JPEG 读取 DCT 域文本水印）。
+#[tauri::command]
+pub async fn verify_archive_consistency(
+    archive_path: String,
+    expected_text: String,
+    aes_key: Option<String>,
+    salt: Option<String>,
+    image_password: Option<String>,
+) -> Result<ConsistencyReport, String> {
+    verify_consistency_core(
+        Path::new(&archive_path),
+        &expected_text,
+        aes_key.as_deref(),
+        salt.as_deref(),
+        password_seed(image_password.as_deref().unwrap_or("")),
+    )
+}
+
+/// `verify_archive_consistency` 的同步实现
+fn verify_consistency_core(
+    archive_path: &Path,
+    expected_text: &str,
+    aes_key: Option<&str>,
+    salt: Option<&str>,
+    image_seed: u64,
+) -> Result<ConsistencyReport, String> {
+    let archive_name = archive_path.file_stem().and_then(|s| s.to_str()).unwrap_or("archive");
+    let workspace = TempWorkspace::new(archive_name)
+        .map_err(|e| format!("创建工作区失败: {}", e))?;
+    ArchiveProcessor::shared()
+        .extract(archive_path, workspace.extracted_path())
+        .map_err(|e| format!("解压失败: {}", e))?;
+    let root = workspace.extracted_path();
+    let scanner = FileScanner::shared();
+    let expected = [expected_text.to_string()];
+
+    let mut checked_count = 0;
+    let mut divergent = Vec::new();
+
+    // ── 文本文件：每处水印都须与期望一致 ──
+    for (abs_path, rel_path) in collect_json_like_files(scanner, root) {
+        checked_count += 1;
+        let file = rel_path.to_string_lossy().to_string();
+        let content = match std::fs::read_to_string(&abs_path) {
+            Ok(content) => content,
+            Err(e) => {
+                divergent.push(ConsistencyIssue { file, outcome: VerifyOutcome::Error { message: e.to_string() } });
+                continue;
+            }
+        };
+        let outcomes: Vec<VerifyOutcome> = JsonWatermarker::scan_watermark_locations(&content, aes_key, true)
+            .into_iter()
+            .map(|loc| match loc.mode.as_str() {
+                "md5" => md5_verify_outcome(loc.value, &expected, salt),
+                "aes" if !loc.decrypted => VerifyOutcome::Error { message: "AES 水印无法解密（密钥缺失或错误）".to_string() },
+                "hmac" if !loc.decrypted => VerifyOutcome::Error { message: "HMAC 签名校验失败（密钥缺失、错误或水印被篡改）".to_string() },
+                _ if loc.value == expected_text => VerifyOutcome::Match { expected: loc.value },
+                _ => VerifyOutcome::Mismatch { found: loc.value },
+            })
+            .collect();
+        let outcome = if outcomes.is_empty() {
+            Some(VerifyOutcome::NotFound)
+        } else {
+            outcomes.into_iter().find(|o| !matches!(o, VerifyOutcome::Match { .. }))
+        };
+        if let Some(outcome) = outcome {
+            divergent.push(ConsistencyIssue { file, outcome });
+        }
+    }
+
+    // ── PNG / JPEG 图片：盲水印须与期望一致 ──
+    let images: Vec<_> = scanner
+        .scan(root)
+        .map_err(|e| format!("扫描图片失败: {}", e))?
+        .into_iter()
+        .filter(|f| f.relative_path.to_lowercase().ends_with(".png") || is_jpeg_path(&f.relative_path))
+        .collect();
+    checked_count += images.len();
+    let extracted = ParallelProcessor::new()
+        .with_password(image_seed)
+        .scan_batch_any(&images)
+        .map_err(|e| e.to_string())?;
+    for (file, result) in extracted {
+        let outcome = image_verify_outcome(result, &expected, salt);
+        if !matches!(outcome, VerifyOutcome::Match { .. }) {
+            divergent.push(ConsistencyIssue { file, outcome });
+        }
+    }
+
+    divergent.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(ConsistencyReport { consistent: divergent.is_empty(), checked_count, divergent })
+}
+
+/// `remove_watermarks_from_archive` 的结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatermarkRemovalResult {
+    /// 重新打包后的压缩包路径
+    pub output: String,
+    /// 移除了水印字段的文件（相对路径，按路径排序）
+    pub cleaned_files: Vec<String>,
+}
+
+/// 移除压缩包中所有 JSON / VAJ / VMI / VAM / VAP 文件根对象里的水印字段，并重新打包
+///
+/// 按值识别水印（见 `JsonWatermarker::remove`），默认、半混淆与混淆字段名均会移除；
+/// 不含水印或无法解析的文件原样保留。图片盲水印不受影响。
+/// 输出写入 `output_dir`（未指定时与源文件同目录），文件名为 `<原名>_clean.<扩展名>`
+/// （只能解压的格式如 RAR 改为 .zip）。
+#[tauri::command]
+pub async fn remove_watermarks_from_archive(
+    archive_path: String,
+    output_dir: Option<String>,
+) -> Result<WatermarkRemovalResult, String> {
+    remove_watermarks_core(Path::new(&archive_path), output_dir.as_deref().map(Path::new))
+}
+
+/// `remove_watermarks_from_archive` 的同步实现
+fn remove_watermarks_core(
+    archive_path: &Path,
+    output_dir: Option<&Path>,
+) -> Result<WatermarkRemovalResult, String> {
+    let archive_name = archive_path.file_stem().and_then(|s| s.to_str()).unwrap_or("archive");
+    let processor = ArchiveProcessor::shared();
+    let repacked = processor.repack_path(archive_path);
+    let output_filename = match repacked.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_clean.{}", archive_name, ext),
+        None => format!("{}_clean", archive_name),
+    };
+    let base_output_dir = match output_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => archive_path.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(".")),
+    };
+    ensure_output_not_recursive(&base_output_dir, archive_path)?;
+    std::fs::create_dir_all(&base_output_dir)
+        .map_err(|e| format!("创建输出目录失败 {}: {}", base_output_dir.display(), e))?;
+
+    let workspace = TempWorkspace::new(archive_name)
+        .map_err(|e| format!("创建工作区失败: {}", e))?;
+    processor
+        .extract(archive_path, workspace.extracted_path())
+        .map_err(|e| format!("解压失败: {}", e))?;
+    let root = workspace.extracted_path();
+
+    let mut cleaned_files = Vec::new();
+    for (abs_path, rel_path) in collect_json_like_files(FileScanner::shared(), root) {
+        let Ok(bytes) = std::fs::read(&abs_path) else { continue };
+        // 仅改写确实含水印的文件，其余文件保持原有格式与编码
+        let has_watermark = json_marker::decode_text_bytes(&bytes)
+            .is_ok_and(|content| !JsonWatermarker::scan_watermark_locations(&content, None, false).is_empty());
+        if !has_watermark {
+            continue;
+        }
+        let Ok(clean) = JsonWatermarker::remove_bytes(&bytes) else { continue };
+        std::fs::write(&abs_path, clean)
+            .map_err(|e| format!("写入 {} 失败: {}", rel_path.display(), e))?;
+        cleaned_files.push(rel_path.to_string_lossy().to_string());
+    }
+    cleaned_files.sort();
+
+    let output_path = base_output_dir.join(output_filename);
+    processor
+        .create(root, &output_path)
+        .map_err(|e| format!("打包失败: {}", e))?;
+    Ok(WatermarkRemovalResult { output: output_path.to_string_lossy().to_string(), cleaned_files })
+}
+
+/// 列出解压目录中仅大小写不同的文件路径对（`/` 分隔的相对路径，见 `find_case_collisions`）
+fn case_collisions(root: &Path) -> Vec<(String, String)> {
+    let mut names: Vec<String> = walkdir::WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.path().strip_prefix(root).ok().map(|p| p.to_string_lossy().replace('\\', "/")))
+        .collect();
+    names.sort();
+    find_case_collisions(names.iter().map(String::as_str))
+}
+
+/// 校验压缩包是否为合法的 VaM .var 包（meta.json 存在且字段完整，contentList 均存在）
+///
+/// 用于在添加水印前发现结构异常的包，仅读取条目列表与 meta.json，不解压整个压缩包。
+#[tauri::command]
+pub async fn validate_var_package(archive_path: String) -> Result<VarValidationReport, String> {
+    var_package::validate_var_package(Path::new(&archive_path)).map_err(|e| e.to_string())
+}
+
+/// 读取压缩包中单个文件的内容（供界面预览某个资源）
+///
+/// ZIP 按名称直接定位条目；7z 需顺序扫描条目，找到后即停止。均不解压整个压缩包到磁盘。
+#[tauri::command]
+pub async fn read_file_from_archive(archive_path: String, relative_path: String) -> Result<Vec<u8>, String> {
+    ArchiveProcessor::shared()
+        .read_file(Path::new(&archive_path), &relative_path)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(file: &str, confidence: f32) -> ImageWatermarkFinding {
+        ImageWatermarkFinding { file: file.to_string(), text: "wm".to_string(), confidence }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_other_files_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), b"outside").unwrap();
+
+        let src = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(src.path().join("data")).unwrap();
+        std::fs::write(src.path().join("data/real.txt"), b"inside").unwrap();
+        symlink("real.txt", src.path().join("data/inner_link.txt")).unwrap();
+        symlink(outside.path().join("secret.txt"), src.path().join("escape.txt")).unwrap();
+        symlink("missing.txt", src.path().join("dangling.txt")).unwrap();
+
+        let dst = tempfile::tempdir().unwrap();
+        let (copied, mut skipped) = copy_other_files(src.path(), dst.path(), &[], &[], &[], &[], &[], &[], &[], &[]).unwrap();
+        skipped.sort();
+
+        assert_eq!(copied, 2, "real.txt + 包内链接");
+        assert_eq!(skipped, vec![std::path::PathBuf::from("dangling.txt"), std::path::PathBuf::from("escape.txt")]);
+        assert!(!dst.path().join("escape.txt").exists(), "包外链接不应被复制");
+
+        let inner = dst.path().join("data/inner_link.txt");
+        assert!(std::fs::symlink_metadata(&inner).unwrap().file_type().is_symlink(), "包内链接应重建为链接");
+        assert_eq!(std::fs::read(&inner).unwrap(), b"inside");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_other_files_read_only_source() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let src = tempfile::tempdir().unwrap();
+        let file = src.path().join("readme.txt");
+        std::fs::write(&file, b"read only").unwrap();
+        let mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        std::fs::File::options().write(true).open(&file).unwrap().set_modified(mtime).unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o444)).unwrap();
+
+        // 非 root 时复制结果无法以写模式打开，不应导致复制失败
+        let dst = tempfile::tempdir().unwrap();
+        let (copied, _) = copy_other_files(src.path(), dst.path(), &[], &[], &[], &[], &[], &[], &[], &[]).unwrap();
+        assert_eq!(copied, 1);
+        let copied_file = dst.path().join("readme.txt");
+        assert_eq!(std::fs::read(&copied_file).unwrap(), b"read only");
+        let metadata = std::fs::metadata(&copied_file).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o444);
+        assert_eq!(metadata.modified().unwrap(), mtime);
+    }
+
+    #[test]
+    fn test_process_request_defaults() {
+        let request: ProcessRequest = serde_json::from_value(serde_json::json!({
+            "processImages": false, "processJson": true, "processVaj": true, "processVmi": true,
+            "processVam": true, "processVap": true, "obfuscate": false, "watermarkMode": "md5",
+            "fastMode": false, "lenientJson": true, "nestedArchiveDepth": 99,
+        }))
+        .unwrap();
+        let folders = HashMap::new();
+        let options = request.pipeline_options(&folders);
+        assert!(options.lenient && !options.process_svg && !options.idempotent);
+        assert_eq!(options.mode_policy, ModePolicy::Uniform);
+        assert_eq!(options.profile, Profile::default());
+        assert_eq!(options.nested_depth, MAX_NESTED_ARCHIVE_DEPTH);
+        assert_eq!(options.image_seed, DEFAULT_PASSWORD);
+        assert!(request.validate(&single_text_config("alice")).is_ok());
+    }
+
+    #[test]
+    fn test_summarize_mixed_archive() {
+        use crate::core::watermark::embedder::WatermarkEmbedder;
+
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        let base = gradient_image();
+        WatermarkEmbedder::new().embed_raw_text(&base, "buyer", 0.5, false).unwrap().save(src.join("marked.png")).unwrap();
+        base.save(src.join("clean.png")).unwrap();
+
+        let md5 = JsonWatermarker::embed(r#"{"a": 1}"#, "buyer", DEFAULT_WATERMARK_KEY, "md5", None).unwrap();
+        let txt = JsonWatermarker::embed(r#"{"b": 2}"#, "buyer", DEFAULT_WATERMARK_KEY, "plaintext", None).unwrap();
+        let zip_path = zip_fixture(root.path(), &[("md5.json", &md5), ("txt.vaj", &txt), ("plain.json", r#"{"c": 3}"#)]);
+
+        let result = scan_all_core(zip_path.to_str().unwrap(), None, None, None, None, false, false, DEFAULT_PASSWORD, true, None).unwrap();
+        let summary = summarize_scan(&result);
+
+        assert_eq!(summary.by_mode.get("md5"), Some(&1));
+        assert_eq!(summary.by_mode.get("plaintext"), Some(&2), "JSON 明文 + 图片盲水印");
+        assert_eq!(summary.by_file_type.get("json"), Some(&2));
+        assert_eq!(summary.by_file_type.get("image"), Some(&1));
+        assert_eq!(summary.unwatermarked_count, 2, "plain.json + clean.png");
+    }
+
+    #[test]
+    fn test_summarize_counts_svg_and_config_per_type() {
+        let clean = r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#;
+        let svg = SvgWatermarker::embed(clean, "buyer", "plaintext", None).unwrap();
+        let toml = TomlWatermarker::embed("name = \"pkg\"\n", "buyer", "md5", None).unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let zip_path = zip_fixture(
+            root.path(),
+            &[("plain.json", r#"{"c": 3}"#), ("icon.svg", &svg), ("clean.svg", clean), ("mod.toml", &toml)],
+        );
+
+        let result = scan_all_core(zip_path.to_str().unwrap(), None, Some(false), None, None, false, false, DEFAULT_PASSWORD, true, None).unwrap();
+        assert_eq!((result.scanned_text_file_count, result.scanned_svg_count, result.scanned_config_file_count), (1, 2, 1));
+        let summary = summarize_scan(&result);
+
+        assert_eq!(summary.by_file_type.get("json"), None);
+        assert_eq!(summary.by_file_type.get("svg"), Some(&1));
+        assert_eq!(summary.by_file_type.get("config"), Some(&1));
+        assert_eq!(summary.unwatermarked_count, 2, "plain.json + clean.svg");
+    }
+
+    #[test]
+    fn test_list_encrypted_watermarks() {
+        let aes_a = JsonWatermarker::embed(r#"{"a": 1}"#, "alice", DEFAULT_WATERMARK_KEY, "aes", Some("key-1")).unwrap();
+        let aes_b = JsonWatermarker::embed(r#"{"b": 2}"#, "bob", DEFAULT_WATERMARK_KEY, "aes", Some("key-2")).unwrap();
+        let md5 = JsonWatermarker::embed(r#"{"c": 3}"#, "carol", DEFAULT_WATERMARK_KEY, "md5", None).unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let zip_path = zip_fixture(root.path(), &[("a.json", &aes_a), ("sub/b.vaj", &aes_b), ("c.vmi", &md5)]);
+
+        let mut encrypted = list_encrypted_core(zip_path.to_str().unwrap()).unwrap();
+        encrypted.sort_by(|a, b| a.file.cmp(&b.file));
+
+        let files: Vec<&str> = encrypted.iter().map(|f| f.file.as_str()).collect();
+        assert_eq!(files, vec!["a.json", "sub/b.vaj"], "MD5 水印不应列出");
+        assert!(encrypted.iter().all(|f| f.mode == "aes" && !f.decrypted && f.value.starts_with("aes:")));
+        assert_ne!(encrypted[0].value, encrypted[1].value);
+    }
+
+    #[test]
+    fn test_detect_duplicate_image_watermarks() {
+        use crate::core::watermark::embedder::WatermarkEmbedder;
+
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        std::fs::create_dir_all(src.join("textures")).unwrap();
+        let base = gradient_image();
+        let embedder = WatermarkEmbedder::new();
+        for (file, text) in [("a.png", "buyer-1"), ("textures/b.png", "buyer-1"), ("c.png", "buyer-2")] {
+            embedder.embed_raw_text(&base, text, 0.5, false).unwrap().save(src.join(file)).unwrap();
+        }
+        base.save(src.join("clean.png")).unwrap();
+        let zip_path = zip_fixture(root.path(), &[]);
+
+        let result = scan_all_core(zip_path.to_str().unwrap(), None, Some(true), None, None, false, false, DEFAULT_PASSWORD, true, None).unwrap();
+        let groups = group_duplicate_watermarks(&result.image_findings);
+        assert_eq!(groups, vec![("buyer-1".to_string(), vec!["a.png".to_string(), "textures/b.png".to_string()])]);
+    }
+
+    #[test]
+    fn test_nested_archive_is_watermarked() {
+        let inner_root = tempfile::tempdir().unwrap();
+        let inner_zip = zip_fixture(inner_root.path(), &[("meta.json", r#"{"name": "inner"}"#)]);
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("src/nested")).unwrap();
+        std::fs::copy(inner_zip, root.path().join("src/nested/inner.zip")).unwrap();
+        let archive = zip_fixture(root.path(), &[]);
+
+        let config = single_text_config("alice");
+        let inner_json = |options: &PipelineOptions, name: &str| {
+            let out = root.path().join(name);
+            let ProcessOutcome::Success { output } = process_archive_core(
+                &archive, Some(&out), &config, &["alice".to_string()], options, None, Arc::new(SummarySink::default()),
+            ).unwrap() else { panic!("应成功") };
+            let extracted = root.path().join(format!("{}_outer", name));
+            ArchiveProcessor::new().extract(Path::new(&output), &extracted).unwrap();
+            let inner = root.path().join(format!("{}_inner", name));
+            ArchiveProcessor::new().extract(&extracted.join("nested/inner.zip"), &inner).unwrap();
+            std::fs::read_to_string(inner.join("meta.json")).unwrap()
+        };
+
+        // 默认不处理嵌套压缩包，原样复制
+        assert!(!inner_json(&text_only_options(), "copied").contains("alice"));
+        let nested = PipelineOptions { nested_depth: 1, ..text_only_options() };
+        let json = inner_json(&nested, "nested");
+        assert!(json.contains("_watermark") && json.contains("alice"), "{}", json);
+    }
+
+    #[test]
+    fn test_idempotent_reruns_produce_identical_archives() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(
+            root.path(),
+            &[
+                ("meta.json", r#"{"licenseType": "CC BY", "creatorName": "Dnaddr", "version": 3}"#),
+                ("Custom/Atom/look.vap", r#"{"id": "look", "storables": []}"#),
+                ("Custom/readme.txt", "hello"),
+            ],
+        );
+
+        let config = single_text_config("alice");
+        let options = PipelineOptions { obfuscate: true, flat_output: true, idempotent: true, ..text_only_options() };
+        let run = |input: &Path, name: &str| {
+            let out = root.path().join(name);
+            let ProcessOutcome::Success { output } = process_archive_core(
+                input, Some(&out), &config, &["alice".to_string()], &options, None, Arc::new(SummarySink::default()),
+            ).unwrap() else { panic!("应成功") };
+            std::fs::read(output).unwrap()
+        };
+
+        // 对同一源包连续运行两次：混淆字段名与时间戳均稳定
+        let first = run(&archive, "first");
+        assert_eq!(run(&archive, "second"), first);
+        // 对已处理过的输出再运行：水印一致的文件原样保留
+        let watermarked = root.path().join("first/pkg.zip");
+        assert_eq!(run(&watermarked, "rerun"), first);
+    }
+
+    #[test]
+    fn test_flat_output_single_mode() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#)]);
+
+        let config = single_text_config("alice");
+        let options = PipelineOptions { flat_output: true, ..text_only_options() };
+        let run = |out: &Path, watermarks: &[String]| {
+            process_archive_core(&archive, Some(out), &config, watermarks, &options, None, Arc::new(SummarySink::default()))
+        };
+
+        // 单水印：直接写入输出目录，不建子文件夹
+        let out = root.path().join("flat");
+        let ProcessOutcome::Success { output } = run(&out, &["alice".to_string()]).unwrap() else { panic!("应成功") };
+        assert_eq!(Path::new(&output), out.join("pkg.zip"));
+        let entries: Vec<_> = std::fs::read_dir(&out).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(entries, vec![std::ffi::OsString::from("pkg.zip")], "不应创建水印子文件夹");
+
+        // 批量模式仍按水印文本分子文件夹
+        let out = root.path().join("batch");
+        run(&out, &["alice".to_string(), "bob".to_string()]).unwrap();
+        assert!(out.join("alice/pkg.zip").exists() && out.join("bob/pkg.zip").exists());
+
+        // 输出到源文件目录会覆盖原压缩包，直接拒绝
+        let err = run(root.path(), &["alice".to_string()]).unwrap_err();
+        assert!(err.contains("覆盖原压缩包"), "{}", err);
+    }
+
+    #[test]
+    fn test_batch_verify_mixed_expected_list() {
+        use crate::core::watermark::embedder::WatermarkEmbedder;
+
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        let base = gradient_image();
+        let embedder = WatermarkEmbedder::new();
+        embedder.embed_raw_text(&base, "alice", 0.5, false).unwrap().save(src.join("a.png")).unwrap();
+        embedder.embed_raw_text(&base, "mallory", 0.5, false).unwrap().save(src.join("b.png")).unwrap();
+        embedder.with_md5_salt(Some("pepper")).embed(&base, "bob", 0.5).unwrap().save(src.join("c.png")).unwrap();
+        base.save(src.join("d.png")).unwrap();
+        std::fs::write(src.join("f.jpg"), WatermarkEmbedder::new().embed_jpeg_to_bytes(&base, "carol", 0.5).unwrap()).unwrap();
+
+        let expected = vec!["alice".to_string(), "bob".to_string(), "carol".to_string()];
+        let check = |source: &Path| {
+            let results = batch_verify_core(source, &expected, Some("pepper"), DEFAULT_PASSWORD).unwrap();
+            results.into_iter().map(|r| (r.file, r.outcome)).collect::<Vec<_>>()
+        };
+
+        let archive = zip_fixture(root.path(), &[("e.png", "not an image")]);
+        let results = check(&src);
+        assert_eq!(results[0], ("a.png".to_string(), VerifyOutcome::Match { expected: "alice".to_string() }));
+        assert_eq!(results[1], ("b.png".to_string(), VerifyOutcome::Mismatch { found: "mallory".to_string() }));
+        assert_eq!(results[2], ("c.png".to_string(), VerifyOutcome::Match { expected: "bob".to_string() }));
+        assert_eq!(results[3], ("d.png".to_string(), VerifyOutcome::NotFound));
+        assert!(matches!(results[4], (_, VerifyOutcome::Error { .. })));
+        // JPEG 读取 DCT 域水印
+        assert_eq!(results[5], ("f.jpg".to_string(), VerifyOutcome::Match { expected: "carol".to_string() }));
+
+        // 压缩包输入结果相同
+        assert_eq!(check(&archive), results);
+    }
+
+    #[test]
+    fn test_verify_archive_consistency_flags_divergent_image() {
+        use crate::core::watermark::embedder::WatermarkEmbedder;
+
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        let base = gradient_image();
+        let embedder = WatermarkEmbedder::new();
+        embedder.embed_raw_text(&base, "alice", 0.5, false).unwrap().save(src.join("a.png")).unwrap();
+        WatermarkEmbedder::new().with_md5_salt(Some("pepper")).embed(&base, "alice", 0.5).unwrap().save(src.join("b.png")).unwrap();
+        std::fs::write(src.join("photo.jpg"), embedder.embed_jpeg_to_bytes(&base, "alice", 0.5).unwrap()).unwrap();
+        let meta = JsonWatermarker::embed(r#"{"name": "pkg"}"#, "alice", DEFAULT_WATERMARK_KEY, "aes", Some("secret")).unwrap();
+        let preset = JsonWatermarker::embed(r#"{"id": "look"}"#, &WatermarkEncoder::salted_text("alice", Some("pepper")), DEFAULT_WATERMARK_KEY, "md5", None).unwrap();
+        let files = [("meta.json", meta.as_str()), ("look.vap", preset.as_str())];
+
+        // 每次校验前重新打包 src，纳入后续新增的文件
+        let verify = |aes_key: Option<&str>| {
+            let archive = zip_fixture(root.path(), &files);
+            verify_consistency_core(&archive, "alice", aes_key, Some("pepper"), DEFAULT_PASSWORD).unwrap()
+        };
+
+        let report = verify(Some("secret"));
+        assert!(report.consistent, "{:?}", report.divergent);
+        assert_eq!(report.checked_count, 5);
+
+        // PNG 与 JPEG 各一张带有其他买家的水印，另有未解密的 AES 水印
+        embedder.embed_raw_text(&base, "mallory", 0.5, false).unwrap().save(src.join("c.png")).unwrap();
+        std::fs::write(src.join("d.jpg"), embedder.embed_jpeg_to_bytes(&base, "mallory", 0.5).unwrap()).unwrap();
+        let report = verify(None);
+        assert!(!report.consistent);
+        let files: Vec<_> = report.divergent.iter().map(|i| i.file.as_str()).collect();
+        assert_eq!(files, ["c.png", "d.jpg", "meta.json"]);
+        assert_eq!(report.divergent[0].outcome, VerifyOutcome::Mismatch { found: "mallory".to_string() });
+        assert_eq!(report.divergent[1].outcome, VerifyOutcome::Mismatch { found: "mallory".to_string() });
+        assert!(matches!(report.divergent[2].outcome, VerifyOutcome::Error { .. }));
+    }
+
+    #[test]
+    fn test_remove_watermarks_from_archive_repackages_clean_files() {
+        let meta = JsonWatermarker::embed_obfuscated(r#"{"creatorName": "me", "packageName": "pkg"}"#, "alice", "aes", Some("secret")).unwrap();
+        let scene = JsonWatermarker::embed(r#"{"id": "scene"}"#, "alice", DEFAULT_WATERMARK_KEY, "plaintext", None).unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(
+            root.path(),
+            &[("meta.json", &meta), ("scene.vaj", &scene), ("plain.vmi", r#"{"id": "morph"}"#), ("list.json", r#"["txt:alice"]"#)],
+        );
+        let out = tempfile::tempdir().unwrap();
+        let result = remove_watermarks_core(&archive, Some(out.path())).unwrap();
+        assert_eq!(result.cleaned_files, ["meta.json", "scene.vaj"]);
+        assert_eq!(Path::new(&result.output), out.path().join("pkg_clean.zip"));
+
+        let extracted = tempfile::tempdir().unwrap();
+        ArchiveProcessor::new().extract(Path::new(&result.output), extracted.path()).unwrap();
+        for file in ["meta.json", "scene.vaj"] {
+            let content = std::fs::read_to_string(extracted.path().join(file)).unwrap();
+            assert!(JsonWatermarker::scan_watermark_values(&content, None).is_empty(), "{}", file);
+        }
+        // 不含水印与非 Object 根节点的文件原样保留
+        assert_eq!(std::fs::read_to_string(extracted.path().join("plain.vmi")).unwrap(), r#"{"id": "morph"}"#);
+        assert_eq!(std::fs::read_to_string(extracted.path().join("list.json")).unwrap(), r#"["txt:alice"]"#);
+    }
+
+    #[test]
+    fn test_write_checksums_manifest() {
+        use sha2::{Digest, Sha256};
+
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#)]);
+        let other = root.path().join("other.zip");
+        std::fs::copy(&archive, &other).unwrap();
+
+        let config = single_text_config("alice");
+        let options = PipelineOptions { write_checksums: true, ..text_only_options() };
+        let out = root.path().join("out");
+        let watermarks = ["alice".to_string(), "bob".to_string()];
+        let archives = [archive, other];
+        process_archives_batch_core(&archives, Some(&out), &config, &watermarks, &options, None, false, Arc::new(SummarySink::default())).unwrap();
+
+        // 两个压缩包共用输出目录，清单合并了全部 4 个输出
+        let manifest = std::fs::read_to_string(out.join(CHECKSUM_FILE_NAME)).unwrap();
+        let lines: Vec<&str> = manifest.lines().collect();
+        assert_eq!(lines.len(), 4, "{}", manifest);
+        for line in lines {
+            let (hash, name) = line.split_once("  ").expect("sha256sum 格式：哈希 + 两个空格 + 路径");
+            let recomputed = format!("{:x}", Sha256::digest(std::fs::read(out.join(name)).unwrap()));
+            assert_eq!(hash, recomputed, "{}", name);
+        }
+        assert!(manifest.contains("  alice/pkg.zip\n") && manifest.contains("  bob/other.zip\n"));
+    }
+
+    #[test]
+    fn test_md5_salt_changes_stored_json_value() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#)]);
+
+        let config = single_text_config("alice");
+        let stored = |salt: Option<&str>, out: &str| {
+            let options = PipelineOptions { watermark_mode: "md5", md5_salt: salt, ..text_only_options() };
+            let out = root.path().join(out);
+            process_archive_core(&archive, Some(&out), &config, &["alice".to_string()], &options, None, Arc::new(SummarySink::default())).unwrap();
+            let meta = ArchiveProcessor::new().read_file(&out.join("alice/pkg.zip"), "meta.json").unwrap();
+            JsonWatermarker::extract_bytes(&meta, DEFAULT_WATERMARK_KEY).unwrap()
+        };
+
+        let plain = stored(None, "plain");
+        let salted = stored(Some("pepper"), "salted");
+        assert_eq!(plain, WatermarkEncoder::encode("alice").md5_hash);
+        assert_eq!(salted, WatermarkEncoder::encode_salted("alice", Some("pepper")).md5_hash);
+        assert_ne!(plain, salted);
+        let buyers = ["bob", "alice"];
+        assert_eq!(WatermarkEncoder::resolve_md5(&salted, &buyers, Some("pepper")), Some("alice"));
+    }
+
+    #[test]
+    fn test_content_aware_mode_policy() {
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        gradient_image().save(src.join("cover.png")).unwrap();
+        let archive = zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#), ("icon.svg", r#"<svg width="1"/>"#)]);
+
+        let config = single_text_config("alice");
+        let options = PipelineOptions {
+            process_images: true,
+            mode_policy: ModePolicy::ContentAware,
+            aes_key: Some("secret"),
+            ..text_only_options()
+        };
+        let out = root.path().join("out");
+        process_archive_core(&archive, Some(&out), &config, &["alice".to_string()], &options, None, Arc::new(SummarySink::default())).unwrap();
+
+        let output = out.join("alice/pkg.zip");
+        let meta = String::from_utf8(ArchiveProcessor::new().read_file(&output, "meta.json").unwrap()).unwrap();
+        let json = &JsonWatermarker::scan_watermark_locations(&meta, Some("secret"), false)[0];
+        assert_eq!((json.value.as_str(), json.mode.as_str()), ("alice", "aes"));
+        let svg = String::from_utf8(ArchiveProcessor::new().read_file(&output, "icon.svg").unwrap()).unwrap();
+        let (value, mode, _) = SvgWatermarker::scan_watermark_value(&svg, Some("secret")).unwrap();
+        assert_eq!((value, mode), (WatermarkEncoder::encode("alice").md5_hash, "md5".to_string()));
+        let png = ArchiveProcessor::new().read_file(&output, "cover.png").unwrap();
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!(WatermarkExtractor::new().extract_text(&img).unwrap(), "alice");
+    }
+
+    #[test]
+    fn test_json_file_source_with_folders() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#)]);
+        let list = root.path().join("wm.json");
+        std::fs::write(&list, r#"[{"text": "alice", "folder": "buyer_001"}, "bob"]"#).unwrap();
+
+        let config = WatermarkConfig::new(0.5, WatermarkSource::JsonFile { path: list.to_string_lossy().to_string() });
+        let (watermarks, folders) = read_watermark_texts(&config, &SummarySink::default()).unwrap();
+        assert_eq!(watermarks, ["alice", "bob"]);
+
+        let options = PipelineOptions { output_folders: Some(&folders), ..text_only_options() };
+        let out = root.path().join("out");
+        process_archive_core(&archive, Some(&out), &config, &watermarks, &options, None, Arc::new(SummarySink::default())).unwrap();
+        assert!(out.join("buyer_001/pkg.zip").exists());
+        assert!(out.join("bob/pkg.zip").exists());
+        assert!(!out.join("alice").exists());
+    }
+
+    #[test]
+    fn test_render_two_level_dir_template() {
+        let vars = [("watermark", "alice/bob"), ("archive_stem", "pkg"), ("date", "2024-02-29")];
+        assert_eq!(render_dir_template("{date}/{watermark}", &vars).unwrap(), Path::new("2024-02-29").join("alice_bob"));
+        assert_eq!(render_dir_template("{archive_stem}\\buyer-{watermark}/", &vars).unwrap(), Path::new("pkg").join("buyer-alice_bob"));
+        assert_eq!(render_dir_template(DEFAULT_DIR_TEMPLATE, &vars).unwrap(), Path::new("alice_bob"));
+        assert!(render_dir_template("{buyer}/{watermark}", &vars).is_err());
+        assert!(render_dir_template("{watermark", &vars).is_err());
+        assert!(render_dir_template("//", &vars).is_err());
+        assert_eq!(utc_date_string(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_709_164_800)), "2024-02-29");
+
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#)]);
+        let config = single_text_config("alice");
+        let watermarks = ["alice".to_string(), "bob".to_string()];
+        let options = PipelineOptions { dir_template: Some("{archive_stem}/{watermark}"), ..text_only_options() };
+        let out = root.path().join("out");
+        process_archive_core(&archive, Some(&out), &config, &watermarks, &options, None, Arc::new(SummarySink::default())).unwrap();
+        assert!(out.join("pkg/alice/pkg.zip").exists());
+        assert!(out.join("pkg/bob/pkg.zip").exists());
+    }
+
+    #[test]
+    fn test_rejects_recursive_output_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#)]);
+
+        let config = single_text_config("alice");
+        let options = text_only_options();
+        let run = |out: &Path| {
+            process_archive_core(&archive, Some(out), &config, &["alice".to_string()], &options, None, Arc::new(SummarySink::default()))
+        };
+
+        // 源文件所在目录 + 以源压缩包命名的嵌套路径
+        let nested = root.path().join("pkg.zip").join("alice");
+        let err = run(&nested).unwrap_err();
+        assert!(err.contains("源压缩包路径内"), "{}", err);
+
+        // 临时工作区内
+        let workspace = TempWorkspace::new("guard").unwrap();
+        let err = run(&workspace.extracted_path().join("out")).unwrap_err();
+        assert!(err.contains("临时工作区内"), "{}", err);
+
+        // 源文件所在目录本身仍可作为输出目录
+        assert!(matches!(run(root.path()).unwrap(), ProcessOutcome::Success { .. }));
+    }
+
+    #[test]
+    fn test_preflight_watermark_texts() {
+        let long = "x".repeat(70);
+        let sink = SummarySink::default();
+        let with_images = PipelineOptions { process_images: true, watermark_mode: "md5", ..text_only_options() };
+
+        // 图片水印超长时报错；不处理图片时 MD5 长文本只警告
+        let err = preflight_watermark_texts(std::slice::from_ref(&long), &with_images, &sink).unwrap_err();
+        assert!(err.contains("超出图片水印最大长度"), "{}", err);
+        let text_only = PipelineOptions { watermark_mode: "md5", ..text_only_options() };
+        preflight_watermark_texts(std::slice::from_ref(&long), &text_only, &sink).unwrap();
+        let statuses = sink.statuses.lock().unwrap().clone();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].0, "text_warning");
+
+        // 批量追加序号时按截断后的实际文本校验
+        let batch = [long.clone(), "bob".to_string()];
+        assert!(preflight_watermark_texts(&batch, &with_images, &sink).is_err());
+        preflight_watermark_texts(&batch, &PipelineOptions { append_index: true, ..with_images }, &sink).unwrap();
+    }
+
+    #[test]
+    fn test_require_work_fails_without_watermarkable_files() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#)]);
+
+        let config = single_text_config("alice");
+        let images_only = PipelineOptions {
+            process_images: true,
+            process_json: false,
+            process_vaj: false,
+            process_vmi: false,
+            process_vam: false,
+            process_vap: false,
+            process_svg: false,
+            process_config: false,
+            ..text_only_options()
+        };
+        let run = |options: &PipelineOptions| {
+            let out = root.path().join("out");
+            process_archive_core(&archive, Some(&out), &config, &["alice".to_string()], options, None, Arc::new(SummarySink::default()))
+        };
+
+        // 默认行为不变：无可处理文件时仍输出副本
+        assert!(matches!(run(&images_only), Ok(ProcessOutcome::Success { .. })));
+
+        let strict = PipelineOptions { require_work: true, ..images_only };
+        let err = run(&strict).unwrap_err();
+        assert!(err.contains("没有可嵌入水印的文件") && err.contains("图片"), "{}", err);
+
+        // JSON 开启时有可处理文件；JPEG 可嵌入 DCT 域盲水印，同样算作可处理
+        assert!(run(&PipelineOptions { process_json: true, ..strict }).is_ok());
+        std::fs::write(root.path().join("src/photo.jpg"), b"\xFF\xD8\xFF\xD9").unwrap();
+        zip_fixture(root.path(), &[]);
+        assert!(run(&strict).is_ok());
+    }
+
+    #[test]
+    fn test_dedup_identical_outputs() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#), ("readme.txt", "keep me")]);
+
+        let config = single_text_config("alice");
+        let run = |options: &PipelineOptions, out: &Path, watermarks: &[String]| {
+            let sink = Arc::new(SummarySink::default());
+            let outcome = process_archive_core(&archive, Some(out), &config, watermarks, options, None, sink.clone()).unwrap();
+            let summary = sink.summaries.lock().unwrap().pop().unwrap();
+            (outcome, summary)
+        };
+
+        // MD5 模式下相同文本的摘要相同，第二份输出复用第一份，两个水印仍都计入结果
+        let md5 = PipelineOptions { watermark_mode: "md5", dedup_outputs: true, ..text_only_options() };
+        let watermarks = ["alice".to_string(), "bob".to_string(), "alice".to_string()];
+        let (outcome, summary) = run(&md5, &root.path().join("md5"), &watermarks);
+        assert!(matches!(outcome, ProcessOutcome::Success { .. }));
+        assert_eq!(summary.watermark_count, 3);
+        assert_eq!(summary.warnings.len(), 1, "{:?}", summary.warnings);
+        assert_eq!(summary.warnings[0].file, "alice");
+        assert!(summary.warnings[0].reason.contains("内容相同"));
+
+        // 处理结果与水印文本无关时（不处理 JSON），不同子文件夹的输出硬链接到同一份
+        let copy_only = PipelineOptions { process_json: false, dedup_outputs: true, ..text_only_options() };
+        let out = root.path().join("copy");
+        let (_, summary) = run(&copy_only, &out, &["alice".to_string(), "bob".to_string()]);
+        assert_eq!(summary.warnings.len(), 1);
+        let (first, second) = (out.join("alice/pkg.zip"), out.join("bob/pkg.zip"));
+        assert_eq!(std::fs::read(&first).unwrap(), std::fs::read(&second).unwrap());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(std::fs::metadata(&first).unwrap().ino(), std::fs::metadata(&second).unwrap().ino());
+        }
+
+        // 未开启时每个水印都单独打包
+        let (_, summary) = run(&text_only_options(), &root.path().join("plain"), &watermarks);
+        assert!(summary.warnings.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_preserve_permissions_through_pipeline() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        let files = [("meta.json", r#"{"name": "pkg"}"#, 0o600), ("readme.txt", "keep me", 0o644), ("run.sh", "#!/bin/sh", 0o755)];
+        for (name, content, mode) in files {
+            std::fs::write(src.join(name), content).unwrap();
+            std::fs::set_permissions(src.join(name), std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let archive = root.path().join("pkg.zip");
+        ArchiveProcessor::with_options(Default::default(), true).create(&src, &archive).unwrap();
+
+        let config = single_text_config("alice");
+        let options = PipelineOptions { preserve_permissions: true, ..text_only_options() };
+        let out = root.path().join("out");
+        let result = process_archive_core(&archive, Some(&out), &config, &["alice".to_string()], &options, None, Arc::new(SummarySink::default()));
+        let ProcessOutcome::Success { output } = result.unwrap() else { panic!("应成功") };
+
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&output).unwrap()).unwrap();
+        for (name, _, mode) in files {
+            let entry_mode = zip.by_name(name).unwrap().unix_mode().map(|m| m & 0o7777);
+            assert_eq!(entry_mode, Some(mode), "{} 的权限位应保留", name);
+        }
+        // meta.json 已被重新写入（注入水印）
+        let mut meta = String::new();
+        std::io::Read::read_to_string(&mut zip.by_name("meta.json").unwrap(), &mut meta).unwrap();
+        assert_eq!(JsonWatermarker::scan_watermark_values(&meta, None)[0].0, "alice");
+    }
+
+    #[test]
+    fn test_archives_batch_continue_on_error() {
+        let root = tempfile::tempdir().unwrap();
+        let valid = root.path().join("valid.zip");
+        std::fs::rename(zip_fixture(root.path(), &[("meta.json", r#"{"name": "pkg"}"#)]), &valid).unwrap();
+        let corrupt = root.path().join("corrupt.zip");
+        std::fs::write(&corrupt, b"not a zip archive").unwrap();
+
+        let out = root.path().join("out");
+        let config = single_text_config("alice");
+        let watermarks = ["alice".to_string()];
+        let archives = [corrupt.clone(), valid];
+        let run = |continue_on_error: bool| {
+            process_archives_batch_core(
+                &archives,
+                Some(&out),
+                &config,
+                &watermarks,
+                &text_only_options(),
+                None,
+                continue_on_error,
+                Arc::new(SummarySink::default()),
+            )
+        };
+
+        // 默认遇到损坏的压缩包立即中止
+        let err = run(false).unwrap_err();
+        assert!(err.contains("corrupt.zip"), "{}", err);
+        assert!(!out.join("alice/valid.zip").exists());
+
+        let results = run(true).unwrap();
+        assert_eq!(results.len(), 2);
+        let ProcessOutcome::Failed { failures } = &results[0].outcome else { panic!("损坏的压缩包应失败，得 {:?}", results[0]) };
+        assert_eq!(failures[0].item, corrupt.to_string_lossy());
+        assert!(failures[0].reason.contains("解压失败"), "{}", failures[0].reason);
+        assert!(matches!(results[1].outcome, ProcessOutcome::Success { .. }), "有效压缩包应继续处理，得 {:?}", results[1]);
+
+        let extracted = tempfile::tempdir().unwrap();
+        ArchiveProcessor::new().extract(&out.join("alice/valid.zip"), extracted.path()).unwrap();
+        let meta = std::fs::read_to_string(extracted.path().join("meta.json")).unwrap();
+        assert_eq!(JsonWatermarker::scan_watermark_values(&meta, None)[0].0, "alice");
+    }
+
+    #[test]
+    fn test_classify_json_watermarks_by_file() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path();
+        let embed = |mode: &str, aes: Option<&str>| {
+            JsonWatermarker::embed(r#"{"n": 1}"#, "alice", DEFAULT_WATERMARK_KEY, mode, aes).unwrap()
+        };
+        std::fs::write(dir.join("a_plain.json"), embed("plaintext", None)).unwrap();
+        std::fs::write(dir.join("b_md5.vaj"), embed("md5", None)).unwrap();
+        std::fs::write(dir.join("c_aes.vmi"), embed("aes", Some("right-key"))).unwrap();
+        std::fs::write(dir.join("d_none.json"), r#"{"n": 1}"#).unwrap();
+        // 同一文件混用两种模式
+        let mixed = JsonWatermarker::embed(&embed("plaintext", None), "bob", "xOther", "aes", Some("right-key")).unwrap();
+        std::fs::write(dir.join("e_mixed.json"), mixed).unwrap();
+
+        let by_file = |results: &[JsonWatermarkClassification], name: &str| {
+            results.iter().find(|r| r.file == name).cloned().unwrap()
+        };
+
+        let results = classify_json_files(dir, Some("right-key"));
+        assert_eq!(results.len(), 5);
+        assert_eq!(by_file(&results, "a_plain.json").modes, ["plaintext"]);
+        assert_eq!(by_file(&results, "a_plain.json").aes_decrypted, None);
+        assert_eq!(by_file(&results, "b_md5.vaj").modes, ["md5"]);
+        assert_eq!(by_file(&results, "c_aes.vmi").modes, ["aes"]);
+        assert_eq!(by_file(&results, "c_aes.vmi").aes_decrypted, Some(true));
+        let none = by_file(&results, "d_none.json");
+        assert!(none.modes.is_empty() && none.watermark_count == 0);
+        let mixed = by_file(&results, "e_mixed.json");
+        assert_eq!(mixed.watermark_count, 2);
+        assert!(mixed.modes.contains(&"plaintext".to_string()) && mixed.modes.contains(&"aes".to_string()));
+
+        // 错误密钥：AES 水印仍被识别，但标记为未解密
+        let results = classify_json_files(dir, Some("wrong-key"));
+        assert_eq!(by_file(&results, "c_aes.vmi").aes_decrypted, Some(false));
+        assert_eq!(by_file(&results, "e_mixed.json").aes_decrypted, Some(false));
+    }
+
+    #[test]
+    fn test_scan_warns_when_aes_key_fails() {
+        let aes = JsonWatermarker::embed(r#"{"n": 1}"#, "alice", DEFAULT_WATERMARK_KEY, "aes", Some("right-key")).unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let zip_path = zip_fixture(root.path(), &[("a.json", &aes), ("b.json", &aes)]);
+        let zip_path = zip_path.to_str().unwrap();
+
+        let scan = |key: Option<&str>| {
+            let sink = SummarySink::default();
+            let result = scan_all_core(zip_path, key, Some(false), None, None, false, false, DEFAULT_PASSWORD, true, Some(&sink)).unwrap();
+            let statuses = sink.statuses.into_inner().unwrap();
+            (result, statuses)
+        };
+
+        let (result, statuses) = scan(Some("wrong-key"));
+        assert!(result.json_findings.iter().all(|f| f.mode == "aes" && !f.decrypted));
+        let warning = result.aes_key_warning.expect("密钥错误时应提示");
+        assert!(warning.contains("2 个 AES 水印") && warning.contains("密钥无法解密"), "{}", warning);
+        assert_eq!(statuses, vec![("aes_key_failed".to_string(), warning)]);
+
+        let (result, _) = scan(None);
+        assert!(result.aes_key_warning.unwrap().contains("未提供密钥"));
+
+        let (result, statuses) = scan(Some("right-key"));
+        assert!(result.aes_key_warning.is_none() && statuses.is_empty());
+        assert!(result.json_findings.iter().all(|f| f.decrypted && f.value == "alice"));
+    }
+
+    #[test]
+    fn test_scan_multiple_archives() {
+        let root = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        for (name, buyer) in [("a", "alice"), ("b", "bob")] {
+            let json = JsonWatermarker::embed(r#"{"n": 1}"#, buyer, DEFAULT_WATERMARK_KEY, "plaintext", None).unwrap();
+            let archive = zip_fixture(&root.path().join(name), &[("meta.json", &json)]);
+            paths.push(archive.to_string_lossy().to_string());
+        }
+        let missing = root.path().join("missing.zip").to_string_lossy().to_string();
+        paths.push(missing.clone());
+
+        let results = scan_multiple_core(&paths, None, Some(false)).unwrap();
+        assert_eq!(results.len(), 3);
+        for (path, buyer) in paths.iter().zip(["alice", "bob"]) {
+            let ArchiveScanResult::Scanned(result) = &results[path] else { panic!("{} 应扫描成功，得 {:?}", path, results[path]) };
+            assert_eq!(result.json_findings.len(), 1);
+            assert_eq!(result.json_findings[0].value, buyer);
+            assert_eq!(result.scanned_png_count, 0);
+        }
+        assert!(matches!(&results[&missing], ArchiveScanResult::Error { message } if message.contains("解压失败")));
+    }
+
+    /// 记录批次汇总事件的 ProgressSink
+    #[derive(Default)]
+    struct SummarySink {
+        summaries: std::sync::Mutex<Vec<BatchSummaryEvent>>,
+        statuses: std::sync::Mutex<Vec<(String, String)>>,
+    }
+
+    impl ProgressSink for SummarySink {
+        fn emit_progress(&self, _: usize, _: usize, _: String, _: f32, _: String) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn emit_status(&self, status: String, message: String) -> Result<(), String> {
+            self.statuses.lock().unwrap().push((status, message));
+            Ok(())
+        }
+
+        fn emit_batch_summary(&self, summary: BatchSummaryEvent) -> Result<(), String> {
+            self.summaries.lock().unwrap().push(summary);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_batch_summary_event() {
+        let mut summary = BatchSummaryEvent { watermark_count: 2, copied_count: 4, ..Default::default() };
+        summary.record_watermarked("image", 3);
+        summary.record_watermarked("image", 3);
+        summary.record_watermarked("json", 1);
+        summary.record_failure("[1/2] broken.json", "JSON 解析失败，已原样保留");
+
+        let sink = SummarySink::default();
+        let started = std::time::Instant::now() - std::time::Duration::from_millis(20);
+        finish_batch_summary(&sink, summary, started).unwrap();
+
+        let summaries = sink.summaries.lock().unwrap();
+        assert_eq!(summaries.len(), 1, "应只发送一次汇总");
+        let sent = &summaries[0];
+        assert_eq!(sent.watermark_count, 2);
+        assert_eq!(sent.watermarked_by_type.get("image"), Some(&6));
+        assert_eq!(sent.watermarked_by_type.get("json"), Some(&1));
+        assert_eq!(sent.copied_count, 4);
+        assert!(sent.elapsed_ms >= 20, "耗时应被填入，得 {}", sent.elapsed_ms);
+        assert_eq!(sent.failures.len(), 1);
+        assert_eq!(sent.failures[0].item, "[1/2] broken.json");
+    }
+
+    #[test]
+    fn test_retain_confident_threshold() {
+        let borderline = || vec![finding("clean.png", 1.0), finding("borderline.png", 0.4)];
+
+        let high: Vec<String> = retain_confident(borderline(), Some(0.9)).into_iter().map(|f| f.file).collect();
+        assert_eq!(high, vec!["clean.png"], "高阈值应过滤边缘结果");
+
+        let low: Vec<String> = retain_confident(borderline(), Some(0.2)).into_iter().map(|f| f.file).collect();
+        assert_eq!(low, vec!["clean.png", "borderline.png"], "低阈值应保留边缘结果");
+
+        assert_eq!(retain_confident(borderline(), None).len(), 2, "默认保留全部");
+    }
+
+    fn text_only_options() -> PipelineOptions<'static> {
+        PipelineOptions {
+            process_images: false,
+            process_json: true,
+            process_vaj: true,
+            process_vmi: true,
+            process_vam: true,
+            process_vap: true,
+            process_svg: true,
+            process_config: true,
+            obfuscate: false,
+            watermark_mode: "plaintext",
+            mode_policy: ModePolicy::Uniform,
+            aes_key: None,
+            selected_images: None,
+            fast_mode: false,
+            append_index: false,
+            lenient: false,
+            metadata_fallback: false,
+            normalize_orientation: false,
+            flat_output: false,
+            preserve_permissions: false,
+            dedup_outputs: false,
+            semi_obfuscated_keys: None,
+            output_folders: None,
+            dir_template: None,
+            md5_salt: None,
+            write_checksums: false,
+            strip_fields: None,
+            require_work: false,
+            image_seed: DEFAULT_PASSWORD,
+            profile: Profile::Balanced,
+            nested_depth: 0,
+            idempotent: false,
+        }
+    }
+
+    fn single_text_config(text: &str) -> WatermarkConfig {
+        WatermarkConfig::new(0.5, WatermarkSource::SingleText { content: text.to_string() })
+    }
+
+    /// 256×256 渐变图，纹理足以容纳 PNG 与 JPEG 盲水印
+    fn gradient_image() -> image::DynamicImage {
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(256, 256, |x, y| {
+            image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+        }))
+    }
+
+    /// 将 `files` 写入 `root/src`（自动创建父目录），再打包为 `root/pkg.zip`
+    ///
+    /// `root/src` 中已有的文件一并打包，图片等二进制文件可先行写入。
+    fn zip_fixture(root: &Path, files: &[(&str, &str)]) -> PathBuf {
+        let src = root.join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        for (name, content) in files {
+            let path = src.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let archive = root.join("pkg.zip");
+        ArchiveProcessor::new().create(&src, &archive).unwrap();
+        archive
+    }
+
+    #[test]
+    fn test_process_directory_tree() {
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("unpacked");
+        std::fs::create_dir_all(src.join("Custom/Atom")).unwrap();
+        std::fs::write(src.join("meta.json"), r#"{"name": "pkg"}"#).unwrap();
+        std::fs::write(src.join("Custom/Atom/look.vaj"), r#"{"id": "look"}"#).unwrap();
+        std::fs::write(src.join("Custom/readme.txt"), b"keep me").unwrap();
+        std::fs::write(src.join("Custom/icon.svg"), r#"<?xml version="1.0"?><svg width="1"/>"#).unwrap();
+        std::fs::write(src.join("pack.toml"), "# 配置\nname = \"pkg\"\n").unwrap();
+        std::fs::write(src.join("Custom/settings.ini"), "[General]\nname=pkg\n").unwrap();
+
+        let out = root.path().join("out");
+        let config = single_text_config("alice");
+        let sink = Arc::new(SummarySink::default());
+        let result = process_directory_core(
+            &src,
+            Some(&out),
+            &config,
+            &["alice".to_string()],
+            &text_only_options(),
+            Arc::clone(&sink) as Arc<dyn ProgressSink>,
+        )
+        .unwrap();
+
+        let target = std::fs::canonicalize(&out).unwrap().join("alice").join("unpacked");
+        let ProcessOutcome::Success { output } = result else { panic!("应全部成功，得 {:?}", result) };
+        assert_eq!(Path::new(&output), target, "单条模式应返回输出目录");
+
+        let meta = std::fs::read_to_string(target.join("meta.json")).unwrap();
+        assert_eq!(JsonWatermarker::scan_watermark_values(&meta, None)[0].0, "alice");
+        let vaj = std::fs::read_to_string(target.join("Custom/Atom/look.vaj")).unwrap();
+        assert_eq!(JsonWatermarker::scan_watermark_values(&vaj, None)[0].0, "alice");
+        assert_eq!(std::fs::read(target.join("Custom/readme.txt")).unwrap(), b"keep me");
+        let svg = std::fs::read_to_string(target.join("Custom/icon.svg")).unwrap();
+        assert!(svg.starts_with(r#"<?xml version="1.0"?><svg "#), "XML 声明应保留: {}", svg);
+        assert_eq!(SvgWatermarker::scan_watermark_value(&svg, None).unwrap().0, "alice");
+        let toml = std::fs::read_to_string(target.join("pack.toml")).unwrap();
+        assert!(toml.starts_with("# 配置\nname = \"pkg\"\n"), "注释应保留: {}", toml);
+        assert_eq!(TomlWatermarker::scan_watermark_value(&toml, None).unwrap().0, "alice");
+        let ini = std::fs::read_to_string(target.join("Custom/settings.ini")).unwrap();
+        assert_eq!(IniWatermarker::scan_watermark_value(&ini, None).unwrap().0, "alice");
+
+        // 源目录保持不变
+        assert_eq!(std::fs::read_to_string(src.join("meta.json")).unwrap(), r#"{"name": "pkg"}"#);
+
+        let summaries = sink.summaries.lock().unwrap();
+        assert_eq!(summaries[0].watermarked_by_type.get("json"), Some(&1));
+        assert_eq!(summaries[0].watermarked_by_type.get("vaj"), Some(&1));
+        assert_eq!(summaries[0].watermarked_by_type.get("svg"), Some(&1));
+        assert_eq!(summaries[0].watermarked_by_type.get("toml"), Some(&1));
+        assert_eq!(summaries[0].watermarked_by_type.get("ini"), Some(&1));
+        assert_eq!(summaries[0].copied_count, 1);
+        drop(summaries);
+
+        // 目标已存在：拒绝覆盖，该水印不生成输出
+        let again = process_directory_core(
+            &src, Some(&out), &config, &["alice".to_string()], &text_only_options(), sink,
+        )
+        .unwrap();
+        let ProcessOutcome::Failed { failures } = again else { panic!("输出目录非空时应失败，得 {:?}", again) };
+        assert!(failures[0].reason.contains("输出目录已存在且非空"), "{}", failures[0].reason);
+    }
+
+    #[test]
+    fn test_too_small_jpeg_copied_with_warning() {
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("pkg");
+        std::fs::create_dir_all(&src).unwrap();
+        // 64×64 只有 64 个 8×8 块，容纳不下 544 位的 JPEG 盲水印，只能原样复制
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(64, 64)).save(src.join("photo.jpg")).unwrap();
+
+        let out = root.path().join("out");
+        let config = single_text_config("dave");
+        let sink = Arc::new(SummarySink::default());
+        let options = PipelineOptions { process_images: true, ..text_only_options() };
+        let result = process_directory_core(
+            &src, Some(&out), &config, &["dave".to_string()], &options, Arc::clone(&sink) as Arc<dyn ProgressSink>,
+        )
+        .unwrap();
+        assert!(matches!(result, ProcessOutcome::Success { .. }), "过小的 JPEG 原样复制不算失败，得 {:?}", result);
+
+        let summaries = sink.summaries.lock().unwrap();
+        assert_eq!(summaries[0].warnings.len(), 1);
+        assert_eq!(summaries[0].warnings[0].file, "photo.jpg");
+        assert!(summaries[0].warnings[0].reason.contains("已原样复制"), "{}", summaries[0].warnings[0].reason);
+    }
+
+    #[test]
+    fn test_archive_jpeg_gets_dct_watermark() {
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        gradient_image().save(src.join("photo.jpg")).unwrap();
+        let archive = zip_fixture(root.path(), &[]);
+
+        let out = root.path().join("out");
+        let config = single_text_config("dave");
+        let sink = Arc::new(SummarySink::default());
+        let options = PipelineOptions { process_images: true, ..text_only_options() };
+        process_archive_core(&archive, Some(&out), &config, &["dave".to_string()], &options, None, Arc::clone(&sink) as Arc<dyn ProgressSink>).unwrap();
+
+        // 足够大的 JPEG 嵌入 DCT 域盲水印，而不是原样复制
+        assert!(sink.summaries.lock().unwrap()[0].warnings.is_empty());
+        let photo = ArchiveProcessor::new().read_file(&out.join("dave/pkg.zip"), "photo.jpg").unwrap();
+        assert_ne!(photo, std::fs::read(src.join("photo.jpg")).unwrap());
+        let img = image::load_from_memory(&photo).unwrap();
+        assert_eq!(WatermarkExtractor::new().try_extract_text_jpeg(&img).unwrap().as_deref(), Some("dave"));
+    }
+
+    #[test]
+    fn test_process_directory_partial_success() {
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("pkg");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("good.json"), r#"{"name": "pkg"}"#).unwrap();
+        std::fs::write(src.join("broken.json"), "{ not json").unwrap();
+
+        let out = root.path().join("out");
+        let config = single_text_config("carol");
+        let result = process_directory_core(
+            &src,
+            Some(&out),
+            &config,
+            &["carol".to_string()],
+            &text_only_options(),
+            Arc::new(SummarySink::default()),
+        )
+        .unwrap();
+
+        let ProcessOutcome::Partial { output, failures } = result else { panic!("应部分成功，得 {:?}", result) };
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].item, "broken.json");
+
+        // 成功的文件已注入水印，失败的文件原样保留
+        let target = Path::new(&output);
+        let good = std::fs::read_to_string(target.join("good.json")).unwrap();
+        assert_eq!(JsonWatermarker::scan_watermark_values(&good, None)[0].0, "carol");
+        assert_eq!(std::fs::read_to_string(target.join("broken.json")).unwrap(), "{ not json");
+    }
+
+    #[test]
+    fn test_process_directory_rejects_output_inside_source() {
+        let src = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join("a.json"), "{}").unwrap();
+        let config = single_text_config("bob");
+        let result = process_directory_core(
+            src.path(),
+            Some(&src.path().join("out")),
+            &config,
+            &["bob".to_string()],
+            &text_only_options(),
+            Arc::new(SummarySink::default()),
+        );
+        assert!(result.is_err(), "输出目录位于源目录内时应报错");
+    }
+}
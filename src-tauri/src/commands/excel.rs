@@ -1,13 +1,25 @@
-use calamine::{Reader, open_workbook, Xlsx};
+use calamine::{Reader, open_workbook, Data, Xlsx};
+use tauri::AppHandle;
+use crate::utils::progress::ProgressEmitter;
+
+/// 每读取多少行上报一次进度
+const EXCEL_PROGRESS_INTERVAL: usize = 1000;
 
 /// Read watermark texts from Excel file (first column), synchronous core implementation.
 ///
 /// # Behavior
-/// - Reads first worksheet
+/// - Reads first worksheet cell by cell (streaming, no full range in memory)
 /// - Extracts first column values
-/// - Skips row 0 (treated as header)
+/// - Skips row 0 when `has_header` (the default for callers); headerless sheets read from row 0
 /// - Stops at first empty cell
-pub(crate) fn read_excel_core(excel_path: &str) -> Result<Vec<String>, String> {
+/// - Stops after `max_rows` watermarks when set (default unlimited)
+/// - Calls `on_progress(rows_read)` every `EXCEL_PROGRESS_INTERVAL` rows
+pub(crate) fn read_excel_core(
+    excel_path: &str,
+    max_rows: Option<usize>,
+    has_header: bool,
+    mut on_progress: impl FnMut(usize),
+) -> Result<Vec<String>, String> {
     let mut workbook: Xlsx<_> = open_workbook(excel_path)
         .map_err(|e| format!("打开 Excel 失败: {}", e))?;
 
@@ -17,34 +29,154 @@ pub(crate) fn read_excel_core(excel_path: &str) -> Result<Vec<String>, String> {
     }
 
     let first_sheet_name = worksheet_names[0].clone();
-    let range = workbook
-        .worksheet_range(&first_sheet_name)
+    let mut cells = workbook
+        .worksheet_cells_reader(&first_sheet_name)
         .map_err(|e| format!("读取工作表失败: {}", e))?;
 
     let mut watermarks = Vec::new();
+    let limit = max_rows.unwrap_or(usize::MAX);
 
-    // 从第 1 行开始（跳过第 0 行表头）
-    for row_idx in 1..range.height() {
-        if let Some(cell) = range.get((row_idx, 0)) {
-            let text = cell.to_string();
-            if text.trim().is_empty() {
-                break;
-            }
-            watermarks.push(text);
-        } else {
+    // 有表头时从第 1 行开始（跳过第 0 行）；单元格按行序输出，第一列出现空缺即视为结束
+    let first_row = has_header as u32;
+    let mut expected_row = first_row;
+    while watermarks.len() < limit {
+        let Some(cell) = cells
+            .next_cell()
+            .map_err(|e| format!("读取工作表失败: {}", e))?
+        else {
+            break;
+        };
+        let (row, col) = cell.get_position();
+        if col != 0 || row < first_row {
+            continue;
+        }
+        if row != expected_row {
             break;
         }
+        let text = Data::from(cell.get_value().clone()).to_string();
+        if text.trim().is_empty() {
+            break;
+        }
+        watermarks.push(text);
+        expected_row += 1;
+
+        if watermarks.len() % EXCEL_PROGRESS_INTERVAL == 0 {
+            on_progress(watermarks.len());
+        }
     }
 
     if watermarks.is_empty() {
-        return Err("Excel 第一列未找到水印文本（第 0 行视为表头，从第 1 行读取）".to_string());
+        return Err(if has_header {
+            "Excel 第一列未找到水印文本（第 0 行视为表头，从第 1 行读取）".to_string()
+        } else {
+            "Excel 第一列未找到水印文本".to_string()
+        });
     }
 
     Ok(watermarks)
 }
 
 /// Read watermark texts from Excel file (Tauri command, wraps `read_excel_core`)
+///
+/// `max_rows` 限制最多读取的水印条数（None 表示不限），
+/// `has_header` 为 false 时第 0 行也作为水印读取（默认 true，第 0 行视为表头），
+/// 读取过程中每 `EXCEL_PROGRESS_INTERVAL` 行发送一次 `reading_excel` 状态。
 #[tauri::command]
-pub async fn read_excel_watermarks(excel_path: String) -> Result<Vec<String>, String> {
-    read_excel_core(&excel_path)
+pub async fn read_excel_watermarks(
+    app: AppHandle,
+    excel_path: String,
+    max_rows: Option<usize>,
+    has_header: Option<bool>,
+) -> Result<Vec<String>, String> {
+    let progress = ProgressEmitter::new(app);
+    read_excel_core(&excel_path, max_rows, has_header.unwrap_or(true), |rows| {
+        let _ = progress.emit_status("reading_excel".to_string(), format!("已读取 {} 行...", rows));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    /// 生成最小化的 xlsx（第一列为内联字符串），首行为表头
+    fn write_test_xlsx(path: &std::path::Path, header: &str, rows: &[String]) {
+        let mut sheet_rows = String::new();
+        for (i, text) in std::iter::once(&header.to_string()).chain(rows).enumerate() {
+            sheet_rows.push_str(&format!(
+                r#"<row r="{r}"><c r="A{r}" t="inlineStr"><is><t>{t}</t></is></c></row>"#,
+                r = i + 1,
+                t = text
+            ));
+        }
+        let files = [
+            ("[Content_Types].xml", r#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#.to_string()),
+            ("_rels/.rels", r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#.to_string()),
+            ("xl/workbook.xml", r#"<?xml version="1.0" encoding="UTF-8"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#.to_string()),
+            ("xl/_rels/workbook.xml.rels", r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#.to_string()),
+            ("xl/worksheets/sheet1.xml", format!(r#"<?xml version="1.0" encoding="UTF-8"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{}</sheetData></worksheet>"#, sheet_rows)),
+        ];
+
+        let mut zip = ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, content) in files {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_read_excel_skips_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wm.xlsx");
+        let rows: Vec<String> = vec!["张三".into(), "李四".into()];
+        write_test_xlsx(&path, "买家", &rows);
+
+        let result = read_excel_core(path.to_str().unwrap(), None, true, |_| {}).unwrap();
+        assert_eq!(result, rows);
+    }
+
+    #[test]
+    fn test_read_excel_headerless_keeps_first_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wm.xlsx");
+        let rows: Vec<String> = vec!["李四".into(), "王五".into()];
+        write_test_xlsx(&path, "张三", &rows);
+
+        let result = read_excel_core(path.to_str().unwrap(), None, false, |_| {}).unwrap();
+        assert_eq!(result, vec!["张三", "李四", "王五"]);
+        let result = read_excel_core(path.to_str().unwrap(), Some(1), false, |_| {}).unwrap();
+        assert_eq!(result, vec!["张三"]);
+
+        // 仅有一行时：有表头读不到水印，无表头读到该行
+        let single = dir.path().join("single.xlsx");
+        write_test_xlsx(&single, "赵六", &[]);
+        assert!(read_excel_core(single.to_str().unwrap(), None, true, |_| {}).is_err());
+        assert_eq!(read_excel_core(single.to_str().unwrap(), None, false, |_| {}).unwrap(), vec!["赵六"]);
+    }
+
+    #[test]
+    fn test_read_excel_max_rows_truncates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wm.xlsx");
+        let rows: Vec<String> = (1..=10).map(|i| format!("buyer{}", i)).collect();
+        write_test_xlsx(&path, "header", &rows);
+
+        let result = read_excel_core(path.to_str().unwrap(), Some(3), true, |_| {}).unwrap();
+        assert_eq!(result, vec!["buyer1", "buyer2", "buyer3"]);
+    }
+
+    #[test]
+    fn test_read_excel_reports_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wm.xlsx");
+        let rows: Vec<String> = (0..2500).map(|i| format!("b{}", i)).collect();
+        write_test_xlsx(&path, "header", &rows);
+
+        let mut reported = Vec::new();
+        let result = read_excel_core(path.to_str().unwrap(), None, true, |n| reported.push(n)).unwrap();
+        assert_eq!(result.len(), 2500);
+        assert_eq!(reported, vec![1000, 2000]);
+    }
 }
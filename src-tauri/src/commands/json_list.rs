@@ -0,0 +1,97 @@
+use serde_json::Value;
+
+/// One watermark read from a JSON list file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JsonWatermarkEntry {
+    /// Watermark text
+    pub text: String,
+    /// Output subfolder name (defaults to the watermark text when absent)
+    pub folder: Option<String>,
+}
+
+/// Read the batch watermark list from a JSON file, synchronous core implementation.
+///
+/// # Accepted formats
+/// - Array of strings: `["张三", "李四"]`
+/// - Array of objects: `[{"text": "张三", "folder": "buyer_001"}, {"text": "李四"}]`
+///
+/// Both forms may be mixed. The root must be an array; blank texts, non-string
+/// `text` / `folder` values and any other element type are rejected with the element index.
+pub(crate) fn read_json_watermarks_core(path: &str) -> Result<Vec<JsonWatermarkEntry>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("读取 JSON 水印列表失败: {}", e))?;
+    let root: Value = serde_json::from_str(content.trim_start_matches('\u{FEFF}'))
+        .map_err(|e| format!("JSON 水印列表解析失败: {}", e))?;
+    let Value::Array(items) = root else {
+        return Err("JSON 水印列表的根节点必须是数组".to_string());
+    };
+
+    let mut entries = Vec::with_capacity(items.len());
+    for (index, item) in items.into_iter().enumerate() {
+        let entry = match item {
+            Value::String(text) => JsonWatermarkEntry { text, folder: None },
+            Value::Object(mut obj) => {
+                let text = match obj.remove("text") {
+                    Some(Value::String(text)) => text,
+                    _ => return Err(format!("JSON 水印列表第 {} 项缺少字符串字段 text", index)),
+                };
+                let folder = match obj.remove("folder") {
+                    None | Some(Value::Null) => None,
+                    Some(Value::String(folder)) if !folder.trim().is_empty() => Some(folder),
+                    Some(_) => return Err(format!("JSON 水印列表第 {} 项的 folder 必须是非空字符串", index)),
+                };
+                JsonWatermarkEntry { text, folder }
+            }
+            _ => return Err(format!("JSON 水印列表第 {} 项必须是字符串或对象", index)),
+        };
+        if entry.text.trim().is_empty() {
+            return Err(format!("JSON 水印列表第 {} 项的水印文本为空", index));
+        }
+        entries.push(entry);
+    }
+
+    if entries.is_empty() {
+        return Err("JSON 水印列表为空".to_string());
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(content: &str) -> Result<Vec<JsonWatermarkEntry>, String> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wm.json");
+        std::fs::write(&path, content).unwrap();
+        read_json_watermarks_core(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn test_read_json_string_array() {
+        let entries = read("\u{FEFF}[\"张三\", \"李四\"]").unwrap();
+        let texts: Vec<_> = entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, ["张三", "李四"]);
+        assert!(entries.iter().all(|e| e.folder.is_none()));
+    }
+
+    #[test]
+    fn test_read_json_object_array() {
+        let entries = read(r#"[{"text": "张三", "folder": "buyer_001"}, {"text": "李四"}, "王五"]"#).unwrap();
+        assert_eq!(entries, vec![
+            JsonWatermarkEntry { text: "张三".into(), folder: Some("buyer_001".into()) },
+            JsonWatermarkEntry { text: "李四".into(), folder: None },
+            JsonWatermarkEntry { text: "王五".into(), folder: None },
+        ]);
+    }
+
+    #[test]
+    fn test_read_json_rejects_invalid_shapes() {
+        assert!(read(r#"{"text": "张三"}"#).unwrap_err().contains("数组"));
+        assert!(read("[]").is_err());
+        assert!(read("[1]").unwrap_err().contains("第 0 项"));
+        assert!(read(r#"["ok", {"folder": "x"}]"#).unwrap_err().contains("第 1 项"));
+        assert!(read(r#"[{"text": "a", "folder": 3}]"#).is_err());
+        assert!(read(r#"["  "]"#).is_err());
+    }
+}
@@ -2,3 +2,4 @@
 pub mod watermark;
 pub mod archive;
 pub mod excel;
+pub mod json_list;
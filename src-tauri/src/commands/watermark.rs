@@ -1,5 +1,19 @@
-use image::open;
-use crate::core::watermark::{embedder::WatermarkEmbedder, extractor::WatermarkExtractor};
+use std::path::Path;
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage, RgbaImage};
+use serde::Serialize;
+use crate::core::watermark::{
+    embedder::WatermarkEmbedder,
+    encoder::{WatermarkEncoder, TEXT_WATERMARK_TOTAL_BITS},
+    extractor::{ExtractedWatermark, ExtractionDiagnosis, WatermarkExtractor},
+};
+use crate::models::BlindMarkError;
+use crate::utils::decode_limits::{open_limited, DecodeLimits};
+use crate::utils::degrade::{stress_test, Degradation, StressTestReport};
+
+/// Load an image, rejecting oversized headers before decoding
+fn open(path: impl AsRef<Path>) -> Result<DynamicImage, BlindMarkError> {
+    open_limited(path.as_ref(), &DecodeLimits::default())
+}
 
 /// Embed watermark into a single image (for preview)
 ///
@@ -7,6 +21,7 @@ use crate::core::watermark::{embedder::WatermarkEmbedder, extractor::WatermarkEx
 /// * `image_path` - Path to input image
 /// * `watermark_text` - Text to embed
 /// * `strength` - Embedding strength (0.1 - 1.0)
+/// * `salt` - Optional MD5 salt; embeds `md5(salt || text)` (see `WatermarkEncoder::salted_text`)
 ///
 /// # Returns
 /// * PNG encoded bytes of watermarked image
@@ -15,6 +30,7 @@ pub async fn embed_watermark_single(
     image_path: String,
     watermark_text: String,
     strength: f32,
+    salt: Option<String>,
 ) -> Result<Vec<u8>, String> {
     // Validate strength
     if !(0.1..=1.0).contains(&strength) {
@@ -26,7 +42,7 @@ pub async fn embed_watermark_single(
         .map_err(|e| format!("Failed to load image {}: {}", image_path, e))?;
 
     // Create embedder
-    let embedder = WatermarkEmbedder::new();
+    let embedder = WatermarkEmbedder::new().with_md5_salt(salt.as_deref());
 
     // Embed watermark and return as PNG bytes
     let watermarked_bytes = embedder.embed_to_bytes(&image, &watermark_text, strength)
@@ -35,27 +51,296 @@ pub async fn embed_watermark_single(
     Ok(watermarked_bytes)
 }
 
+/// Embed a watermark and return an image of where it changed the pixels (for preview)
+///
+/// The per-pixel difference is stretched so the largest change maps to 255, since
+/// raw changes are only a few levels. By default the output is a grayscale
+/// magnitude (largest change over the three channels); with `colored`, red /
+/// green / blue intensity shows how much each channel changed.
+///
+/// # Arguments
+/// * `image_path` - Path to input image
+/// * `watermark_text` - Text to embed
+/// * `strength` - Embedding strength (0.1 - 1.0)
+/// * `salt` - Optional MD5 salt (see `embed_watermark_single`)
+/// * `colored` - Output false-color per-channel changes instead of grayscale
+///
+/// # Returns
+/// * PNG encoded bytes of the difference image
+#[tauri::command]
+pub async fn embed_preview_diff(
+    image_path: String,
+    watermark_text: String,
+    strength: f32,
+    salt: Option<String>,
+    colored: Option<bool>,
+) -> Result<Vec<u8>, String> {
+    if !(0.1..=1.0).contains(&strength) {
+        return Err(format!("Strength must be between 0.1 and 1.0, got {}", strength));
+    }
+    let image = open(&image_path)
+        .map_err(|e| format!("Failed to load image {}: {}", image_path, e))?;
+    let watermarked = WatermarkEmbedder::new()
+        .with_md5_salt(salt.as_deref())
+        .embed(&image, &watermark_text, strength)
+        .map_err(|e| format!("Failed to embed watermark: {}", e))?;
+
+    let diff = preview_diff_image(&image, &watermarked, colored.unwrap_or(false));
+    let mut buffer = Vec::new();
+    diff.write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(buffer)
+}
+
+/// Difference image between `original` and `watermarked` (same size), stretched to 0 - 255
+fn preview_diff_image(original: &DynamicImage, watermarked: &DynamicImage, colored: bool) -> RgbImage {
+    let (original, watermarked) = (original.to_rgb8(), watermarked.to_rgb8());
+    let deltas: Vec<[u8; 3]> = original
+        .pixels()
+        .zip(watermarked.pixels())
+        .map(|(a, b)| std::array::from_fn(|c| a.0[c].abs_diff(b.0[c])))
+        .collect();
+    // A single scale for all channels keeps their intensities comparable
+    let max = deltas.iter().flatten().copied().max().unwrap_or(0).max(1) as u32;
+    let stretch = |d: u8| (d as u32 * 255 / max) as u8;
+
+    let mut out = RgbImage::new(original.width(), original.height());
+    for (pixel, delta) in out.pixels_mut().zip(&deltas) {
+        *pixel = if colored {
+            Rgb(delta.map(stretch))
+        } else {
+            let magnitude = stretch(delta.iter().copied().max().unwrap_or(0));
+            Rgb([magnitude; 3])
+        };
+    }
+    out
+}
+
+/// Result of `extract_watermark`, so the frontend can tell "no watermark" from "unreadable file"
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ExtractionResult {
+    /// Text watermark, or MD5 hash (32 hex chars) for MD5-mode images
+    Found { watermark: String },
+    /// The image was read fine but carries no trustworthy watermark
+    NotFound,
+    /// The file could not be loaded or processed
+    Error { message: String },
+}
+
 /// Extract watermark from an image
 ///
+/// Tries the raw text watermark first, then falls back to MD5 (see `WatermarkExtractor::extract_any`).
+///
 /// # Arguments
 /// * `image_path` - Path to watermarked image
 ///
 /// # Returns
-/// * Extracted MD5 hash string
+/// * `ExtractionResult` (never an `Err`: failures are reported as `ExtractionResult::Error`)
 #[tauri::command]
-pub async fn extract_watermark(image_path: String) -> Result<String, String> {
-    // Load image
+pub async fn extract_watermark(image_path: String) -> ExtractionResult {
+    extract_watermark_from_path(&image_path)
+}
+
+fn extract_watermark_from_path(image_path: &str) -> ExtractionResult {
+    let image = match open(image_path) {
+        Ok(image) => image,
+        Err(e) => return ExtractionResult::Error {
+            message: format!("Failed to load image {}: {}", image_path, e),
+        },
+    };
+
+    match WatermarkExtractor::new().extract_any(&image) {
+        Ok(ExtractedWatermark::Text(watermark)) | Ok(ExtractedWatermark::Md5(watermark)) => {
+            ExtractionResult::Found { watermark }
+        }
+        Ok(ExtractedWatermark::None) | Err(BlindMarkError::WatermarkNotFound(_)) => ExtractionResult::NotFound,
+        Err(e) => ExtractionResult::Error {
+            message: format!("Failed to extract watermark: {}", e),
+        },
+    }
+}
+
+/// Extract a text watermark from raw RGBA pixels (e.g. a frontend canvas's `ImageData`)
+///
+/// Skips the file / encode round-trip, so the UI can extract from a live canvas.
+///
+/// # Arguments
+/// * `width` / `height` - Buffer dimensions in pixels
+/// * `rgba_bytes` - Row-major RGBA8 pixels, exactly `width * height * 4` bytes
+///
+/// # Returns
+/// * `ExtractionResult` (never an `Err`: failures are reported as `ExtractionResult::Error`)
+#[tauri::command]
+pub async fn extract_watermark_from_rgba(width: u32, height: u32, rgba_bytes: Vec<u8>) -> ExtractionResult {
+    extract_watermark_from_rgba_buffer(width, height, rgba_bytes)
+}
+
+fn extract_watermark_from_rgba_buffer(width: u32, height: u32, rgba_bytes: Vec<u8>) -> ExtractionResult {
+    if let Err(e) = DecodeLimits::default().check(width, height, "(RGBA buffer)") {
+        return ExtractionResult::Error { message: e.to_string() };
+    }
+    let expected = width as u64 * height as u64 * 4;
+    if rgba_bytes.len() as u64 != expected {
+        return ExtractionResult::Error {
+            message: format!(
+                "RGBA buffer has {} bytes, expected {} ({}×{}×4)",
+                rgba_bytes.len(), expected, width, height
+            ),
+        };
+    }
+    // Length was checked above, so construction cannot fail
+    let Some(buffer) = RgbaImage::from_raw(width, height, rgba_bytes) else {
+        return ExtractionResult::Error { message: "Invalid RGBA buffer".to_string() };
+    };
+
+    match WatermarkExtractor::new().try_extract_text(&DynamicImage::ImageRgba8(buffer)) {
+        Ok(Some(watermark)) => ExtractionResult::Found { watermark },
+        Ok(None) | Err(BlindMarkError::WatermarkNotFound(_)) => ExtractionResult::NotFound,
+        Err(e) => ExtractionResult::Error {
+            message: format!("Failed to extract watermark: {}", e),
+        },
+    }
+}
+
+/// Check that an image carries the MD5 watermark of `expected_text`
+///
+/// Useful for QA: confirms the right buyer ID was embedded.
+///
+/// # Arguments
+/// * `image_path` - Path to watermarked image
+/// * `expected_text` - Text that should have been embedded (MD5 mode)
+/// * `salt` - MD5 salt used at embedding time, if any
+///
+/// # Returns
+/// * `Ok(true)` if the extracted MD5 equals `md5(salt || expected_text)`
+/// * `Ok(false)` if a watermark was found but belongs to different text (or a different salt)
+/// * `Err` if the image can't be loaded or carries no trustworthy watermark
+#[tauri::command]
+pub async fn verify_image_watermark(
+    image_path: String,
+    expected_text: String,
+    salt: Option<String>,
+) -> Result<bool, String> {
+    verify_image_watermark_at(&image_path, &expected_text, salt.as_deref())
+}
+
+fn verify_image_watermark_at(image_path: &str, expected_text: &str, salt: Option<&str>) -> Result<bool, String> {
+    let image = open(image_path)
+        .map_err(|e| format!("Failed to load image {}: {}", image_path, e))?;
+
+    let expected = WatermarkEncoder::encode_salted(expected_text, salt).md5_hash;
+    match WatermarkExtractor::new().extract_any(&image) {
+        Ok(ExtractedWatermark::Md5(md5_hash)) => Ok(md5_hash == expected),
+        // A text watermark is a different buyer ID format, so it can't match
+        Ok(ExtractedWatermark::Text(_)) => Ok(false),
+        Ok(ExtractedWatermark::None) => Err(format!("No MD5 watermark found in {}", image_path)),
+        Err(e) => Err(format!("Failed to extract watermark: {}", e)),
+    }
+}
+
+/// Bits per row of the grid rendered by `render_watermark_bits`
+const BIT_GRID_COLUMNS: usize = 32;
+/// Side length in pixels of one bit cell
+const BIT_GRID_CELL: u32 = 8;
+
+/// Render the extracted watermark bits as a black/white grid (for debugging and demos)
+///
+/// Extracts the 544-bit text payload, or the 128-bit MD5 payload when `md5` is set,
+/// thresholding the three-channel soft sum (see `WatermarkExtractor::extract_bits`).
+/// Bits are laid out row-major, 32 per row, as 8×8 px cells: 1 is black, 0 is white.
+///
+/// # Arguments
+/// * `image_path` - Path to watermarked image
+/// * `md5` - Render the 128-bit MD5 payload instead of the text payload
+///
+/// # Returns
+/// * PNG encoded bytes of the grid
+#[tauri::command]
+pub async fn render_watermark_bits(image_path: String, md5: Option<bool>) -> Result<Vec<u8>, String> {
+    render_watermark_bits_at(&image_path, md5.unwrap_or(false))
+}
+
+fn render_watermark_bits_at(image_path: &str, md5: bool) -> Result<Vec<u8>, String> {
+    let image = open(image_path)
+        .map_err(|e| format!("Failed to load image {}: {}", image_path, e))?;
+    let bit_count = if md5 { 128 } else { TEXT_WATERMARK_TOTAL_BITS };
+    let bits = WatermarkExtractor::new()
+        .extract_bits(&image, bit_count)
+        .map_err(|e| format!("Failed to extract watermark bits: {}", e))?;
+
+    let mut buffer = Vec::new();
+    DynamicImage::ImageLuma8(bit_grid_image(&bits))
+        .write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(buffer)
+}
+
+/// Grid of `BIT_GRID_COLUMNS` cells per row; cells past the last bit stay mid-gray
+fn bit_grid_image(bits: &[u8]) -> GrayImage {
+    let rows = bits.len().div_ceil(BIT_GRID_COLUMNS) as u32;
+    let mut out = GrayImage::from_pixel(BIT_GRID_COLUMNS as u32 * BIT_GRID_CELL, rows * BIT_GRID_CELL, Luma([128]));
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let index = (y / BIT_GRID_CELL) as usize * BIT_GRID_COLUMNS + (x / BIT_GRID_CELL) as usize;
+        if let Some(&bit) = bits.get(index) {
+            *pixel = Luma([if bit == 1 { 0 } else { 255 }]);
+        }
+    }
+    out
+}
+
+/// Explain why the text watermark of an image does or does not extract
+///
+/// Reports the first failing step: odd dimensions, too small for the payload,
+/// no magic found, or magic found but the payload is not valid UTF-8; otherwise
+/// the extracted text (see `WatermarkExtractor::diagnose`).
+///
+/// # Arguments
+/// * `image_path` - Path to the image
+#[tauri::command]
+pub async fn diagnose_extraction(image_path: String) -> Result<ExtractionDiagnosis, String> {
     let image = open(&image_path)
         .map_err(|e| format!("Failed to load image {}: {}", image_path, e))?;
+    WatermarkExtractor::new()
+        .diagnose(&image)
+        .map_err(|e| format!("Failed to extract watermark: {}", e))
+}
 
-    // Create extractor
-    let extractor = WatermarkExtractor::new();
+/// Look up which buyer an extracted MD5 watermark belongs to
+///
+/// # Arguments
+/// * `md5_hash` - MD5 hash read from an image or JSON file
+/// * `candidates` - Candidate watermark texts (e.g. the Excel buyer list)
+/// * `salt` - MD5 salt used at embedding time, if any
+///
+/// # Returns
+/// * The first candidate whose (salted) MD5 equals `md5_hash`, or `None`
+#[tauri::command]
+pub async fn resolve_watermark(md5_hash: String, candidates: Vec<String>, salt: Option<String>) -> Option<String> {
+    WatermarkEncoder::resolve_md5(&md5_hash, &candidates, salt.as_deref()).map(str::to_string)
+}
 
-    // Extract watermark
-    let md5_hash = extractor.extract(&image)
-        .map_err(|e| format!("Failed to extract watermark: {}", e))?;
+/// Run a robustness report card for one image
+///
+/// Embeds `watermark_text` as a text watermark, then applies each degradation of
+/// `Degradation::standard_matrix` (identity, JPEG q50/q75/q90, resize 0.8/1.2,
+/// gaussian noise) and reports whether the watermark still extracts.
+///
+/// # Arguments
+/// * `image_path` - Path to input image
+/// * `watermark_text` - Text to embed
+/// * `strength` - Embedding strength (0.1 - 1.0)
+#[tauri::command]
+pub async fn stress_test_watermark(
+    image_path: String,
+    watermark_text: String,
+    strength: f32,
+) -> Result<StressTestReport, String> {
+    let image = open(&image_path)
+        .map_err(|e| format!("Failed to load image {}: {}", image_path, e))?;
 
-    Ok(md5_hash)
+    stress_test(&image, &watermark_text, strength, &Degradation::standard_matrix())
+        .map_err(|e| format!("Failed to embed watermark: {}", e))
 }
 
 /// Get image dimensions
@@ -74,6 +359,20 @@ pub async fn get_image_dimensions(image_path: String) -> Result<(u32, u32), Stri
     Ok((width, height))
 }
 
+/// Smallest square image that can hold `watermark_text` as a text watermark
+///
+/// # Arguments
+/// * `watermark_text` - Text to embed
+/// * `redundancy` - Payload copies required per image (default 1)
+///
+/// # Returns
+/// * (width, height) tuple, e.g. (256, 256) for short texts
+#[tauri::command]
+pub async fn min_dimensions_for_text(watermark_text: String, redundancy: Option<usize>) -> Result<(u32, u32), String> {
+    WatermarkEncoder::min_dimensions_for_text(&watermark_text, redundancy.unwrap_or(1))
+        .map_err(|e| e.to_string())
+}
+
 /// Get number of logical CPU cores available for parallel processing
 ///
 /// # Returns
@@ -82,3 +381,162 @@ pub async fn get_image_dimensions(image_path: String) -> Result<(u32, u32), Stri
 pub fn get_cpu_count() -> usize {
     num_cpus::get()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageBuffer;
+
+    fn save_test_image(dir: &std::path::Path, name: &str, width: u32, height: u32) -> (String, DynamicImage) {
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| {
+            Rgb([((x * 255) / width) as u8, ((y * 255) / height) as u8, 128])
+        }));
+        let path = dir.join(name);
+        image.save(&path).unwrap();
+        (path.to_string_lossy().to_string(), image)
+    }
+
+    #[test]
+    fn test_extract_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, image) = save_test_image(dir.path(), "plain.png", 256, 256);
+        let watermarked = WatermarkEmbedder::new().embed_raw_text(&image, "alice", 0.5, false).unwrap();
+        let path = dir.path().join("marked.png");
+        watermarked.save(&path).unwrap();
+
+        assert_eq!(
+            extract_watermark_from_path(&path.to_string_lossy()),
+            ExtractionResult::Found { watermark: "alice".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_preview_diff_colors_every_channel() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, image) = save_test_image(dir.path(), "plain.png", 256, 256);
+        let watermarked = WatermarkEmbedder::new().embed(&image, "alice", 0.5).unwrap();
+
+        let colored = preview_diff_image(&image, &watermarked, true);
+        let channel_max: [u8; 3] = std::array::from_fn(|c| colored.pixels().map(|p| p.0[c]).max().unwrap());
+        assert!(channel_max.iter().all(|&m| m > 0), "all three channels should change: {:?}", channel_max);
+        assert_eq!(channel_max.iter().max(), Some(&255), "largest change is stretched to 255");
+
+        let gray = preview_diff_image(&image, &watermarked, false);
+        assert!(gray.pixels().all(|p| p.0[0] == p.0[1] && p.0[1] == p.0[2]));
+        assert!(gray.pixels().any(|p| p.0[0] > 0));
+
+        // Identical images give an all-black diff
+        assert!(preview_diff_image(&image, &image, true).pixels().all(|p| p.0 == [0, 0, 0]));
+    }
+
+    #[test]
+    fn test_render_watermark_bits_grid() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, image) = save_test_image(dir.path(), "plain.png", 256, 256);
+        let watermarked = WatermarkEmbedder::new().embed_raw_text(&image, "alice", 0.5, false).unwrap();
+        let path = dir.path().join("marked.png");
+        watermarked.save(&path).unwrap();
+        let path = path.to_string_lossy();
+
+        let png = render_watermark_bits_at(&path, false).unwrap();
+        let grid = image::load_from_memory(&png).unwrap().to_luma8();
+        let cells = (grid.width() / BIT_GRID_CELL) * (grid.height() / BIT_GRID_CELL);
+        assert_eq!(cells as usize, TEXT_WATERMARK_TOTAL_BITS);
+        let expected = WatermarkEncoder::text_to_bits("alice").unwrap();
+        let rendered: Vec<u8> = (0..TEXT_WATERMARK_TOTAL_BITS)
+            .map(|i| {
+                let (col, row) = ((i % BIT_GRID_COLUMNS) as u32, (i / BIT_GRID_COLUMNS) as u32);
+                (grid.get_pixel(col * BIT_GRID_CELL, row * BIT_GRID_CELL).0[0] == 0) as u8
+            })
+            .collect();
+        assert_eq!(rendered, expected);
+
+        let md5 = image::load_from_memory(&render_watermark_bits_at(&path, true).unwrap()).unwrap();
+        assert_eq!((md5.width() / BIT_GRID_CELL) * (md5.height() / BIT_GRID_CELL), 128);
+    }
+
+    #[test]
+    fn test_extract_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, _) = save_test_image(dir.path(), "plain.png", 256, 256);
+        assert_eq!(extract_watermark_from_path(&path), ExtractionResult::NotFound);
+    }
+
+    #[test]
+    fn test_extract_from_rgba_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, image) = save_test_image(dir.path(), "plain.png", 256, 256);
+        let watermarked = WatermarkEmbedder::new().embed_raw_text(&image, "alice", 0.5, false).unwrap();
+        let rgba = watermarked.to_rgba8().into_raw();
+
+        assert_eq!(
+            extract_watermark_from_rgba_buffer(256, 256, rgba.clone()),
+            ExtractionResult::Found { watermark: "alice".to_string() }
+        );
+        assert_eq!(
+            extract_watermark_from_rgba_buffer(256, 256, image.to_rgba8().into_raw()),
+            ExtractionResult::NotFound
+        );
+
+        // Buffer length must match the declared dimensions
+        let ExtractionResult::Error { message } = extract_watermark_from_rgba_buffer(256, 255, rgba) else {
+            panic!("length mismatch should be an error")
+        };
+        assert!(message.contains("expected 261120"), "{}", message);
+    }
+
+    #[test]
+    fn test_verify_image_watermark() {
+        let dir = tempfile::tempdir().unwrap();
+        let (plain_path, image) = save_test_image(dir.path(), "plain.png", 256, 256);
+        let watermarked = WatermarkEmbedder::new().embed(&image, "buyer-42", 0.5).unwrap();
+        let path = dir.path().join("marked.png");
+        watermarked.save(&path).unwrap();
+        let path = path.to_string_lossy();
+
+        assert_eq!(verify_image_watermark_at(&path, "buyer-42", None), Ok(true));
+        assert_eq!(verify_image_watermark_at(&path, "buyer-43", None), Ok(false));
+
+        // Extraction failures are errors, not mismatches
+        assert!(verify_image_watermark_at(&plain_path, "buyer-42", None).is_err());
+        let missing = dir.path().join("missing.png");
+        assert!(verify_image_watermark_at(&missing.to_string_lossy(), "buyer-42", None).is_err());
+    }
+
+    #[test]
+    fn test_verify_salted_watermark() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, image) = save_test_image(dir.path(), "plain.png", 256, 256);
+        let watermarked = WatermarkEmbedder::new()
+            .with_md5_salt(Some("pepper"))
+            .embed(&image, "buyer-42", 0.5)
+            .unwrap();
+        let path = dir.path().join("marked.png");
+        watermarked.save(&path).unwrap();
+        let path = path.to_string_lossy();
+
+        assert_eq!(verify_image_watermark_at(&path, "buyer-42", Some("pepper")), Ok(true));
+        assert_eq!(verify_image_watermark_at(&path, "buyer-42", None), Ok(false));
+
+        let ExtractionResult::Found { watermark } = extract_watermark_from_path(&path) else { panic!("应找到水印") };
+        let candidates = vec!["buyer-41".to_string(), "buyer-42".to_string()];
+        assert_eq!(WatermarkEncoder::resolve_md5(&watermark, &candidates, Some("pepper")), Some("buyer-42"));
+    }
+
+    #[test]
+    fn test_extract_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.png");
+        assert!(matches!(
+            extract_watermark_from_path(&missing.to_string_lossy()),
+            ExtractionResult::Error { .. }
+        ));
+
+        let corrupt = dir.path().join("corrupt.png");
+        std::fs::write(&corrupt, b"not an image").unwrap();
+        assert!(matches!(
+            extract_watermark_from_path(&corrupt.to_string_lossy()),
+            ExtractionResult::Error { .. }
+        ));
+    }
+}
@@ -2,17 +2,49 @@ use std::path::Path;
 use std::fs::{self, File};
 use sevenz_rust::{SevenZReader, SevenZWriter, Password};
 use walkdir::WalkDir;
-use crate::core::compression::common::ArchiveHandler;
+use crate::core::compression::common::{unix_mode, ArchiveHandler, ByteProgress, CaseCollisionGuard, ExtractionBudget, ExtractionLimits, ProgressReader};
 use crate::models::BlindMarkError;
 
 /// 7z archive handler
 ///
 /// Handles extraction and creation of 7z archives using sevenz-rust.
-pub struct SevenZHandler;
+pub struct SevenZHandler {
+    limits: ExtractionLimits,
+    preserve_permissions: bool,
+}
+
+/// 7z attribute flag marking a Unix mode stored in the high 16 bits (p7zip convention)
+const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+const FILE_ATTRIBUTE_ARCHIVE: u32 = 0x20;
 
 impl SevenZHandler {
     pub fn new() -> Self {
-        Self
+        Self::with_limits(ExtractionLimits::default())
+    }
+
+    /// Create a handler that enforces custom extraction limits
+    pub fn with_limits(limits: ExtractionLimits) -> Self {
+        Self { limits, preserve_permissions: false }
+    }
+
+    /// Write each source file's Unix permission bits into the created entries (off by default)
+    pub fn with_preserved_permissions(mut self, enabled: bool) -> Self {
+        self.preserve_permissions = enabled;
+        self
+    }
+
+    /// Build an entry for `path`, storing its Unix mode when permissions are preserved
+    fn entry_for(&self, path: &Path, name: String) -> sevenz_rust::SevenZArchiveEntry {
+        let mut entry = sevenz_rust::SevenZArchiveEntry::from_path(path, name);
+        if self.preserve_permissions {
+            if let Some(mode) = fs::metadata(path).ok().as_ref().and_then(unix_mode) {
+                let kind = if entry.is_directory() { FILE_ATTRIBUTE_DIRECTORY } else { FILE_ATTRIBUTE_ARCHIVE };
+                entry.has_windows_attributes = true;
+                entry.windows_attributes = kind | FILE_ATTRIBUTE_UNIX_EXTENSION | (mode << 16);
+            }
+        }
+        entry
     }
 }
 
@@ -26,7 +58,11 @@ impl ArchiveHandler for SevenZHandler {
     /// # Behavior
     /// - Preserves directory hierarchy
     /// - Creates parent directories as needed
+    /// - Restores each file's modification time from the entry timestamp
+    /// - Sets file permissions on Unix systems when the entry carries a Unix mode
     /// - Does not support password-protected archives
+    /// - Aborts with `CorruptedArchive` once the handler's `ExtractionLimits` are exceeded
+    /// - On case-insensitive filesystems, refuses files whose paths differ only in case
     fn extract(&self, archive_path: &Path, dest_dir: &Path) -> Result<(), BlindMarkError> {
         let file = File::open(archive_path)
             .map_err(|e| BlindMarkError::Archive(
@@ -51,16 +87,29 @@ impl ArchiveHandler for SevenZHandler {
                 format!("Failed to create destination directory: {}", e)
             ))?;
 
-        // Extract all entries
-        reader.for_each_entries(|entry, reader| {
+        // Extract all entries; limit violations and case collisions are stashed so the
+        // caller sees the original error instead of a wrapped sevenz error
+        let mut budget = ExtractionBudget::new(self.limits);
+        let mut case_guard = CaseCollisionGuard::for_dir(dest_dir);
+        let mut limit_error: Option<BlindMarkError> = None;
+        let result = reader.for_each_entries(|entry, reader| {
             let entry_path = entry.name();
             let output_path = dest_dir.join(entry_path);
+            if let Err(e) = budget.add_entry() {
+                limit_error = Some(e);
+                return Err(sevenz_rust::Error::other("extraction limit exceeded"));
+            }
 
             if entry.is_directory() {
                 // Create directory
                 fs::create_dir_all(&output_path)
                     .map_err(|e| sevenz_rust::Error::io(e))?;
             } else {
+                if let Err(e) = case_guard.check(&entry_path.replace('\\', "/")) {
+                    limit_error = Some(e);
+                    return Err(sevenz_rust::Error::other("case-colliding entry"));
+                }
+
                 // Create parent directories
                 if let Some(parent) = output_path.parent() {
                     fs::create_dir_all(parent)
@@ -71,13 +120,38 @@ impl ArchiveHandler for SevenZHandler {
                 let mut output_file = File::create(&output_path)
                     .map_err(|e| sevenz_rust::Error::io(e))?;
 
-                std::io::copy(reader, &mut output_file)
-                    .map_err(|e| sevenz_rust::Error::io(e))?;
+                match budget.copy(reader, &mut output_file, entry_path) {
+                    Ok(_) => {}
+                    Err(e @ BlindMarkError::CorruptedArchive(_)) => {
+                        limit_error = Some(e);
+                        return Err(sevenz_rust::Error::other("extraction limit exceeded"));
+                    }
+                    Err(e) => return Err(sevenz_rust::Error::other(e.to_string())),
+                }
+
+                if entry.has_last_modified_date {
+                    output_file.set_modified(entry.last_modified_date().into())
+                        .map_err(sevenz_rust::Error::io)?;
+                }
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    let attributes = entry.windows_attributes();
+                    if entry.has_windows_attributes && attributes & FILE_ATTRIBUTE_UNIX_EXTENSION != 0 {
+                        let mode = (attributes >> 16) & 0o7777;
+                        fs::set_permissions(&output_path, fs::Permissions::from_mode(mode))
+                            .map_err(sevenz_rust::Error::io)?;
+                    }
+                }
             }
 
             Ok(true) // Continue processing
-        })
-        .map_err(|e| BlindMarkError::Archive(
+        });
+        if let Some(e) = limit_error {
+            return Err(e);
+        }
+        result.map_err(|e| BlindMarkError::Archive(
             format!("Failed to extract 7z archive: {}", e)
         ))?;
 
@@ -93,7 +167,19 @@ impl ArchiveHandler for SevenZHandler {
     /// # Behavior
     /// - Preserves directory hierarchy
     /// - Uses LZMA2 compression
+    /// - Entries carry source modification times (via `SevenZArchiveEntry::from_path`)
+    /// - With `with_preserved_permissions`, entries also carry the source Unix mode
     fn create(&self, source_dir: &Path, output_path: &Path) -> Result<(), BlindMarkError> {
+        self.create_with_progress(source_dir, output_path, &|_, _| {})
+    }
+
+    /// Create 7z archive like `create`, reporting file bytes as they are written
+    fn create_with_progress(
+        &self,
+        source_dir: &Path,
+        output_path: &Path,
+        on_progress: ByteProgress<'_>,
+    ) -> Result<(), BlindMarkError> {
         let file = File::create(output_path)
             .map_err(|e| BlindMarkError::Archive(
                 format!("Failed to create 7z file {}: {}", output_path.display(), e)
@@ -104,13 +190,21 @@ impl ArchiveHandler for SevenZHandler {
                 format!("Failed to create 7z writer: {}", e)
             ))?;
 
-        // Walk source directory
-        let walker = WalkDir::new(source_dir)
+        // Walk source directory (collected first so the total size is known up front)
+        let entries: Vec<_> = WalkDir::new(source_dir)
             .follow_links(false)
             .into_iter()
-            .filter_map(|e| e.ok());
-
-        for entry in walker {
+            .filter_map(|e| e.ok())
+            .collect();
+        let total: u64 = entries
+            .iter()
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| e.metadata().ok())
+            .map(|m| m.len())
+            .sum();
+        let mut written = 0u64;
+
+        for entry in entries {
             let path = entry.path();
             let relative_path = path.strip_prefix(source_dir)
                 .map_err(|e| BlindMarkError::Archive(
@@ -127,14 +221,14 @@ impl ArchiveHandler for SevenZHandler {
 
             if path.is_file() {
                 // Add file to archive (stream directly from disk, no intermediate buffer)
-                let mut file = File::open(path)
+                let file = File::open(path)
                     .map_err(|e| BlindMarkError::Archive(
                         format!("Failed to open file {}: {}", path.display(), e)
                     ))?;
 
                 writer.push_archive_entry(
-                    sevenz_rust::SevenZArchiveEntry::from_path(&path, name),
-                    Some(&mut file),
+                    self.entry_for(path, name),
+                    Some(ProgressReader::new(file, &mut written, total, on_progress)),
                 )
                 .map_err(|e| BlindMarkError::Archive(
                     format!("Failed to add file to archive: {}", e)
@@ -142,7 +236,7 @@ impl ArchiveHandler for SevenZHandler {
             } else if path.is_dir() {
                 // Add directory entry
                 writer.push_archive_entry::<&[u8]>(
-                    sevenz_rust::SevenZArchiveEntry::from_path(&path, name),
+                    self.entry_for(path, name),
                     None,
                 )
                 .map_err(|e| BlindMarkError::Archive(
@@ -159,6 +253,38 @@ impl ArchiveHandler for SevenZHandler {
         Ok(())
     }
 
+    /// Read a single file entry's bytes by name
+    ///
+    /// 7z has no central index to seek by, so entries are scanned in order (solid
+    /// blocks must be decoded up to the entry anyway); scanning stops at the match
+    /// and nothing is written to disk.
+    fn read_entry(&self, archive_path: &Path, name: &str) -> Result<Option<Vec<u8>>, BlindMarkError> {
+        let mut reader = SevenZReader::open(archive_path, Password::empty())
+            .map_err(|e| BlindMarkError::Archive(
+                format!("Failed to read 7z archive {}: {}", archive_path.display(), e)
+            ))?;
+
+        let mut budget = ExtractionBudget::new(self.limits);
+        let mut found: Option<Result<Vec<u8>, BlindMarkError>> = None;
+        let result = reader.for_each_entries(|entry, reader| {
+            if entry.is_directory() || entry.name() != name {
+                return Ok(true);
+            }
+            let mut data = Vec::new();
+            found = Some(budget.copy(reader, &mut data, name).map(|_| data));
+            Ok(false)
+        });
+        match found {
+            Some(data) => data.map(Some),
+            None => {
+                result.map_err(|e| BlindMarkError::Archive(
+                    format!("Failed to read 7z archive: {}", e)
+                ))?;
+                Ok(None)
+            }
+        }
+    }
+
     /// Check if this handler supports the given archive
     ///
     /// Returns true for files with .7z extension (case-insensitive)
@@ -230,6 +356,26 @@ mod tests {
         assert_eq!(content3, "content3");
     }
 
+    #[test]
+    fn test_extract_preserves_mtime() {
+        let temp_source = TempDir::new().unwrap();
+        let temp_dest = TempDir::new().unwrap();
+        let temp_archive = TempDir::new().unwrap();
+
+        let source_file = temp_source.path().join("dated.txt");
+        fs::write(&source_file, b"dated").unwrap();
+        let mtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_589_718_896);
+        File::options().write(true).open(&source_file).unwrap().set_modified(mtime).unwrap();
+
+        let handler = SevenZHandler::new();
+        let archive_path = temp_archive.path().join("dated.7z");
+        handler.create(temp_source.path(), &archive_path).unwrap();
+        handler.extract(&archive_path, temp_dest.path()).unwrap();
+
+        let extracted = fs::metadata(temp_dest.path().join("dated.txt")).unwrap().modified().unwrap();
+        assert_eq!(extracted, mtime, "解压后的文件时间应与条目时间一致");
+    }
+
     #[test]
     fn test_extract_preserves_hierarchy() {
         let temp_source = TempDir::new().unwrap();
@@ -274,4 +420,26 @@ mod tests {
         assert!(result.is_ok());
         assert!(archive_path.exists());
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_preserved_permissions_roundtrip() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_source = TempDir::new().unwrap();
+        let temp_dest = TempDir::new().unwrap();
+        let temp_archive = TempDir::new().unwrap();
+
+        let script = temp_source.path().join("run.sh");
+        fs::write(&script, b"#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+
+        let handler = SevenZHandler::new().with_preserved_permissions(true);
+        let archive_path = temp_archive.path().join("perms.7z");
+        handler.create(temp_source.path(), &archive_path).unwrap();
+        handler.extract(&archive_path, temp_dest.path()).unwrap();
+
+        let mode = fs::metadata(temp_dest.path().join("run.sh")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+    }
 }
@@ -1,5 +1,7 @@
 // Archive handler trait for different compression formats
 
+use std::collections::HashMap;
+use std::io::{Read, Write};
 use std::path::Path;
 use crate::models::BlindMarkError;
 
@@ -11,6 +13,342 @@ pub trait ArchiveHandler: Send + Sync {
     /// Create archive from directory preserving hierarchy
     fn create(&self, source_dir: &Path, output_path: &Path) -> Result<(), BlindMarkError>;
 
+    /// Create archive like `create`, reporting source bytes written as `(written, total)`
+    ///
+    /// `total` is the summed size of the files being packed. The default forwards to
+    /// `create` without reporting; handlers that can write override it.
+    fn create_with_progress(
+        &self,
+        source_dir: &Path,
+        output_path: &Path,
+        on_progress: ByteProgress<'_>,
+    ) -> Result<(), BlindMarkError> {
+        let _ = on_progress;
+        self.create(source_dir, output_path)
+    }
+
+    /// Whether `create` can write this format (extract-only handlers return `false`)
+    fn can_create(&self) -> bool {
+        true
+    }
+
     /// Check if this handler supports the given file
     fn supports(&self, archive_path: &Path) -> bool;
+
+    /// Read a single file entry's bytes by its relative path (`/` separators)
+    ///
+    /// Returns `Ok(None)` if no file entry with that name exists. The default
+    /// implementation extracts the whole archive into a temporary directory;
+    /// handlers whose format allows it override this to read only that entry.
+    fn read_entry(&self, archive_path: &Path, name: &str) -> Result<Option<Vec<u8>>, BlindMarkError> {
+        let relative = Path::new(name);
+        if !relative.components().all(|c| matches!(c, std::path::Component::Normal(_))) {
+            return Ok(None);
+        }
+        let temp = tempfile::TempDir::new()?;
+        self.extract(archive_path, temp.path())?;
+        let path = temp.path().join(relative);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(std::fs::read(path)?))
+    }
+}
+
+/// Callback receiving `(bytes_written, total_bytes)` while an archive is created
+pub type ByteProgress<'a> = &'a dyn Fn(u64, u64);
+
+/// Reader wrapper that adds every byte read to a running total and reports it
+///
+/// Wraps each file's data as it is fed to the archive writer, so progress follows
+/// the bytes actually written (one callback per read, i.e. per `io::copy` chunk).
+pub struct ProgressReader<'a, R> {
+    inner: R,
+    written: &'a mut u64,
+    total: u64,
+    on_progress: ByteProgress<'a>,
+}
+
+impl<'a, R: Read> ProgressReader<'a, R> {
+    pub fn new(inner: R, written: &'a mut u64, total: u64, on_progress: ByteProgress<'a>) -> Self {
+        Self { inner, written, total, on_progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            *self.written += n as u64;
+            (self.on_progress)(*self.written, self.total);
+        }
+        Ok(n)
+    }
+}
+
+/// Permission bits (`mode & 0o7777`) of a file, or `None` on platforms without Unix modes
+pub fn unix_mode(metadata: &std::fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() & 0o7777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Pairs of file paths that differ only in letter case, e.g. `Meta.json` / `meta.json`
+///
+/// Such entries overwrite each other when extracted on a case-insensitive filesystem
+/// (Windows, default macOS). Each pair is `(first seen, later)` in input order.
+pub fn find_case_collisions<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Vec<(String, String)> {
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    let mut collisions = Vec::new();
+    for name in names {
+        match seen.get(&name.to_lowercase()) {
+            Some(&first) if first != name => collisions.push((first.to_string(), name.to_string())),
+            Some(_) => {}
+            None => {
+                seen.insert(name.to_lowercase(), name);
+            }
+        }
+    }
+    collisions
+}
+
+/// The single top-level directory that wraps every entry, e.g. `Package` for `Package/meta.json`
+///
+/// Returns `None` if any file sits at the archive root or the entries span several
+/// top-level directories. Entry names use `/` separators.
+pub fn single_wrapper_dir<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Option<String> {
+    let mut wrapper: Option<&str> = None;
+    for name in names {
+        let (top, _) = name.split_once('/')?;
+        match wrapper {
+            None => wrapper = Some(top),
+            Some(w) if w == top => {}
+            Some(_) => return None,
+        }
+    }
+    wrapper.map(str::to_string)
+}
+
+/// Effective content root of an extracted archive
+///
+/// If `dir` contains exactly one entry and it is a directory (a wrapping `Package/` folder),
+/// that directory is returned; otherwise `dir` itself.
+pub fn effective_root(dir: &Path) -> std::path::PathBuf {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return dir.to_path_buf();
+    };
+    let mut entries = entries.filter_map(Result::ok);
+    match (entries.next(), entries.next()) {
+        (Some(only), None) if only.file_type().is_ok_and(|t| t.is_dir()) => only.path(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+/// Whether `dir` lives on a case-insensitive filesystem
+///
+/// Probes by creating a lowercase temporary file and looking it up in upper case;
+/// returns `false` if the probe cannot be created.
+pub fn is_case_insensitive_dir(dir: &Path) -> bool {
+    let Ok(probe) = tempfile::Builder::new().prefix("blindmark_case_probe_").tempfile_in(dir) else {
+        return false;
+    };
+    let Some(name) = probe.path().file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    dir.join(name.to_uppercase()).exists()
+}
+
+/// Refuses extracting a file whose path differs only in case from an earlier one
+/// when the destination is case-insensitive (the later file would silently overwrite it)
+///
+/// On case-sensitive destinations both files are kept; the collision is reported by
+/// the caller (see `find_case_collisions`).
+#[derive(Debug)]
+pub struct CaseCollisionGuard {
+    case_insensitive: bool,
+    seen: HashMap<String, String>,
+}
+
+impl CaseCollisionGuard {
+    pub fn new(case_insensitive: bool) -> Self {
+        Self { case_insensitive, seen: HashMap::new() }
+    }
+
+    /// Guard for extracting into `dest_dir`, probing its filesystem's case sensitivity
+    pub fn for_dir(dest_dir: &Path) -> Self {
+        Self::new(is_case_insensitive_dir(dest_dir))
+    }
+
+    /// Record one file path (`/` separators), failing if it would overwrite an earlier one
+    pub fn check(&mut self, name: &str) -> Result<(), BlindMarkError> {
+        if !self.case_insensitive {
+            return Ok(());
+        }
+        match self.seen.get(&name.to_lowercase()) {
+            Some(first) if first != name => Err(BlindMarkError::Archive(format!(
+                "Entries {} and {} differ only in letter case and would overwrite each other on this filesystem",
+                first, name
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                self.seen.insert(name.to_lowercase(), name.to_string());
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Upper bounds enforced while extracting an archive (zip-bomb protection)
+///
+/// Sizes are measured on the bytes actually written, not on the sizes declared
+/// in the archive headers, which a malicious archive can forge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractionLimits {
+    /// Maximum number of entries (files and directories)
+    pub max_entries: usize,
+    /// Maximum total uncompressed size in bytes
+    pub max_total_size: u64,
+    /// Maximum uncompressed size of a single file in bytes
+    pub max_file_size: u64,
+}
+
+impl ExtractionLimits {
+    /// No limits (trusted archives only)
+    pub fn unlimited() -> Self {
+        Self { max_entries: usize::MAX, max_total_size: u64::MAX, max_file_size: u64::MAX }
+    }
+}
+
+impl Default for ExtractionLimits {
+    /// 100 000 entries, 16 GiB in total, 4 GiB per file — far above real VaM packages
+    fn default() -> Self {
+        Self {
+            max_entries: 100_000,
+            max_total_size: 16 * 1024 * 1024 * 1024,
+            max_file_size: 4 * 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// Running totals for one extraction, checked against `ExtractionLimits`
+#[derive(Debug)]
+pub struct ExtractionBudget {
+    limits: ExtractionLimits,
+    entries: usize,
+    total_size: u64,
+}
+
+impl ExtractionBudget {
+    pub fn new(limits: ExtractionLimits) -> Self {
+        Self { limits, entries: 0, total_size: 0 }
+    }
+
+    /// Count one more entry, failing once `max_entries` is exceeded
+    pub fn add_entry(&mut self) -> Result<(), BlindMarkError> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            return Err(BlindMarkError::CorruptedArchive(format!(
+                "Archive has more than {} entries", self.limits.max_entries
+            )));
+        }
+        Ok(())
+    }
+
+    /// Copy one entry's data, aborting as soon as a size limit is exceeded
+    ///
+    /// At most one byte beyond the allowance is read, so an oversized entry never
+    /// lands on disk in full.
+    pub fn copy<R: Read + ?Sized, W: Write>(
+        &mut self,
+        reader: &mut R,
+        writer: &mut W,
+        name: &str,
+    ) -> Result<u64, BlindMarkError> {
+        let remaining_total = self.limits.max_total_size.saturating_sub(self.total_size);
+        let allowance = self.limits.max_file_size.min(remaining_total);
+        let written = std::io::copy(&mut reader.take(allowance.saturating_add(1)), writer)
+            .map_err(|e| BlindMarkError::Archive(format!("Failed to extract file {}: {}", name, e)))?;
+        if written > allowance {
+            return Err(if written > self.limits.max_file_size {
+                BlindMarkError::CorruptedArchive(format!(
+                    "Entry {} exceeds the {} byte size limit", name, self.limits.max_file_size
+                ))
+            } else {
+                BlindMarkError::CorruptedArchive(format!(
+                    "Archive exceeds the {} byte total size limit", self.limits.max_total_size
+                ))
+            });
+        }
+        self.total_size += written;
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_collisions_detected_logically() {
+        let names = ["Meta.json", "a/b.png", "meta.json", "A/B.png", "meta.json", "other.json"];
+        assert_eq!(find_case_collisions(names), vec![
+            ("Meta.json".to_string(), "meta.json".to_string()),
+            ("a/b.png".to_string(), "A/B.png".to_string()),
+            ("Meta.json".to_string(), "meta.json".to_string()),
+        ]);
+        assert!(find_case_collisions(["a.json", "b.json"]).is_empty());
+
+        let mut guard = CaseCollisionGuard::new(true);
+        guard.check("Meta.json").unwrap();
+        guard.check("Meta.json").unwrap();
+        let err = guard.check("meta.json").unwrap_err().to_string();
+        assert!(err.contains("Meta.json") && err.contains("meta.json"), "{}", err);
+
+        let mut guard = CaseCollisionGuard::new(false);
+        guard.check("Meta.json").unwrap();
+        guard.check("meta.json").unwrap();
+    }
+
+    #[test]
+    fn test_single_wrapper_dir() {
+        assert_eq!(single_wrapper_dir(["Package/meta.json", "Package/Saves/a.json"]), Some("Package".to_string()));
+        assert_eq!(single_wrapper_dir(["meta.json", "Saves/a.json"]), None, "root file");
+        assert_eq!(single_wrapper_dir(["Custom/a.vmi", "Saves/a.json"]), None, "several top-level dirs");
+        assert_eq!(single_wrapper_dir([]), None);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("Package/Saves")).unwrap();
+        assert_eq!(effective_root(dir.path()), dir.path().join("Package"));
+        std::fs::write(dir.path().join("readme.txt"), b"x").unwrap();
+        assert_eq!(effective_root(dir.path()), dir.path());
+    }
+
+    #[test]
+    fn test_budget_limits() {
+        let limits = ExtractionLimits { max_entries: 2, max_total_size: 10, max_file_size: 6 };
+        let mut budget = ExtractionBudget::new(limits);
+        let mut sink = Vec::new();
+
+        budget.add_entry().unwrap();
+        assert_eq!(budget.copy(&mut &b"123456"[..], &mut sink, "a").unwrap(), 6);
+        budget.add_entry().unwrap();
+        assert!(matches!(budget.add_entry(), Err(BlindMarkError::CorruptedArchive(_))));
+
+        // Single file over the limit
+        let mut budget = ExtractionBudget::new(limits);
+        assert!(matches!(budget.copy(&mut &b"1234567"[..], &mut sink, "big"), Err(BlindMarkError::CorruptedArchive(_))));
+
+        // Running total over the limit
+        let mut budget = ExtractionBudget::new(limits);
+        budget.copy(&mut &b"123456"[..], &mut sink, "a").unwrap();
+        let err = budget.copy(&mut &b"12345"[..], &mut sink, "b").unwrap_err();
+        assert!(err.to_string().contains("total size"), "{}", err);
+    }
 }
@@ -1,17 +1,43 @@
 // Archive compression modules
 pub mod common;
 pub mod zip_handler;
+pub mod var_package;
 
 #[path = "7z_handler.rs"]
 pub mod sevenz_handler;
 
+#[cfg(feature = "rar")]
+pub mod rar_handler;
+
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use crate::models::BlindMarkError;
-use common::ArchiveHandler;
+use common::{ArchiveHandler, ByteProgress, ExtractionLimits};
 use zip_handler::ZipHandler;
 use sevenz_handler::SevenZHandler;
 
+/// Leading magic bytes of each archive format, checked in order
+const ARCHIVE_SIGNATURES: [(&[u8], &str); 4] = [
+    (b"PK\x03\x04", "zip"),
+    // Empty ZIP archive (end of central directory only)
+    (b"PK\x05\x06", "zip"),
+    (b"7z\xBC\xAF\x27\x1C", "7z"),
+    (b"Rar!\x1A\x07", "rar"),
+];
+
+/// Detect the archive format from the file's leading bytes
+///
+/// Reads at most 8 bytes. Returns `None` for unreadable files and unknown content.
+pub fn sniff_format(path: &Path) -> Option<&'static str> {
+    let mut header = Vec::with_capacity(8);
+    std::fs::File::open(path).ok()?.take(8).read_to_end(&mut header).ok()?;
+    ARCHIVE_SIGNATURES
+        .iter()
+        .find(|(magic, _)| header.starts_with(magic))
+        .map(|&(_, format)| format)
+}
+
 /// Archive processor that orchestrates the complete workflow
 ///
 /// Workflow:
@@ -19,22 +45,79 @@ use sevenz_handler::SevenZHandler;
 /// 2. Extract archive to temporary workspace
 /// 3. Return extraction path for further processing
 /// 4. Create new archive from processed files
+///
+/// Cloning is cheap (the handlers are shared `Arc`s); commands use `ArchiveProcessor::shared()`.
+#[derive(Clone)]
 pub struct ArchiveProcessor {
     handlers: Vec<Arc<dyn ArchiveHandler>>,
 }
 
 impl ArchiveProcessor {
     /// Create a new archive processor with all supported handlers
+    ///
+    /// Extraction uses the default `ExtractionLimits`.
     pub fn new() -> Self {
-        let handlers: Vec<Arc<dyn ArchiveHandler>> = vec![
-            Arc::new(ZipHandler::new()),
-            Arc::new(SevenZHandler::new()),
+        Self::with_limits(ExtractionLimits::default())
+    }
+
+    /// Process-wide processor with the default limits, built on first use
+    ///
+    /// Handlers are stateless `Send + Sync`, so one instance can serve concurrent commands.
+    pub fn shared() -> &'static ArchiveProcessor {
+        static SHARED: OnceLock<ArchiveProcessor> = OnceLock::new();
+        SHARED.get_or_init(ArchiveProcessor::new)
+    }
+
+    /// Process-wide processor like `shared()`, but whose created archives keep
+    /// each file's Unix permission bits (see `with_options`)
+    pub fn shared_preserving_permissions() -> &'static ArchiveProcessor {
+        static SHARED: OnceLock<ArchiveProcessor> = OnceLock::new();
+        SHARED.get_or_init(|| ArchiveProcessor::with_options(ExtractionLimits::default(), true))
+    }
+
+    /// Create an archive processor whose handlers enforce custom extraction limits
+    pub fn with_limits(limits: ExtractionLimits) -> Self {
+        Self::with_options(limits, false)
+    }
+
+    /// Create an archive processor with custom extraction limits
+    ///
+    /// With `preserve_permissions`, `create` stores each file's Unix mode in the
+    /// ZIP / 7z entries so executable scripts keep their mode; extraction always
+    /// restores stored modes on Unix.
+    pub fn with_options(limits: ExtractionLimits, preserve_permissions: bool) -> Self {
+        #[allow(unused_mut)]
+        let mut handlers: Vec<Arc<dyn ArchiveHandler>> = vec![
+            Arc::new(ZipHandler::with_limits(limits).with_preserved_permissions(preserve_permissions)),
+            Arc::new(SevenZHandler::with_limits(limits).with_preserved_permissions(preserve_permissions)),
         ];
+        #[cfg(feature = "rar")]
+        handlers.push(Arc::new(rar_handler::RarHandler::with_limits(limits)));
 
+        Self::with_handlers(handlers)
+    }
+
+    /// Create an archive processor from an explicit handler list
+    ///
+    /// Handlers are consulted in order; the first whose `supports` accepts a path
+    /// handles it. Use `new()` for the default set.
+    pub fn with_handlers(handlers: Vec<Arc<dyn ArchiveHandler>>) -> Self {
         Self { handlers }
     }
 
-    /// Auto-detect and get appropriate handler for an archive
+    /// Add a handler, consulted before the existing ones
+    ///
+    /// A registered handler can add a new format or take over one of the
+    /// built-in extensions.
+    pub fn register_handler(&mut self, handler: Arc<dyn ArchiveHandler>) {
+        self.handlers.insert(0, handler);
+    }
+
+    /// Auto-detect and get appropriate handler for an existing archive
+    ///
+    /// The file's magic bytes take precedence, so a mislabeled archive (e.g. a 7z
+    /// saved as `.zip`) still opens; the extension is used when the content is not
+    /// recognized or the file cannot be read.
     ///
     /// # Arguments
     /// * `archive_path` - Path to the archive file
@@ -42,6 +125,16 @@ impl ArchiveProcessor {
     /// # Returns
     /// * Handler that supports this archive type
     fn get_handler(&self, archive_path: &Path) -> Result<Arc<dyn ArchiveHandler>, BlindMarkError> {
+        let sniffed = sniff_format(archive_path)
+            .and_then(|format| self.handler_by_extension(&Path::new("archive").with_extension(format)).ok());
+        match sniffed {
+            Some(handler) => Ok(handler),
+            None => self.handler_by_extension(archive_path),
+        }
+    }
+
+    /// Get the handler for a path by its extension only (used for output paths)
+    fn handler_by_extension(&self, archive_path: &Path) -> Result<Arc<dyn ArchiveHandler>, BlindMarkError> {
         for handler in &self.handlers {
             if handler.supports(archive_path) {
                 return Ok(Arc::clone(handler));
@@ -72,6 +165,23 @@ impl ArchiveProcessor {
         Ok(dest_dir.to_path_buf())
     }
 
+    /// Read one file's bytes from an archive without extracting the rest
+    ///
+    /// # Arguments
+    /// * `archive_path` - Path to archive file
+    /// * `relative_path` - Entry path inside the archive (`/` or `\` separators)
+    ///
+    /// # Returns
+    /// * The entry's bytes, or `Archive` error if the archive has no such file
+    pub fn read_file(&self, archive_path: &Path, relative_path: &str) -> Result<Vec<u8>, BlindMarkError> {
+        let name = relative_path.replace('\\', "/");
+        let name = name.trim_start_matches('/');
+        let handler = self.get_handler(archive_path)?;
+        handler.read_entry(archive_path, name)?.ok_or_else(|| BlindMarkError::Archive(
+            format!("File not found in archive: {}", relative_path)
+        ))
+    }
+
     /// Create archive from source directory
     ///
     /// # Arguments
@@ -86,11 +196,55 @@ impl ArchiveProcessor {
         source_dir: &Path,
         output_path: &Path,
     ) -> Result<PathBuf, BlindMarkError> {
-        let handler = self.get_handler(output_path)?;
+        let handler = self.handler_by_extension(output_path)?;
         handler.create(source_dir, output_path)?;
         Ok(output_path.to_path_buf())
     }
 
+    /// Create archive like `create`, reporting packaging progress
+    ///
+    /// `on_progress` receives `(bytes_written, total_bytes)` of source file data as
+    /// it is fed to the archive writer, once per copied chunk; callers throttle.
+    pub fn create_with_progress(
+        &self,
+        source_dir: &Path,
+        output_path: &Path,
+        on_progress: ByteProgress<'_>,
+    ) -> Result<PathBuf, BlindMarkError> {
+        let handler = self.handler_by_extension(output_path)?;
+        handler.create_with_progress(source_dir, output_path, on_progress)?;
+        Ok(output_path.to_path_buf())
+    }
+
+    /// Output path used when repacking `archive_path`
+    ///
+    /// Formats that can only be extracted (RAR) are repacked as ZIP:
+    /// `"pkg.rar" -> "pkg.zip"`. Other paths are returned unchanged.
+    pub fn repack_path(&self, archive_path: &Path) -> PathBuf {
+        match self.handler_by_extension(archive_path) {
+            Ok(handler) if !handler.can_create() => archive_path.with_extension("zip"),
+            _ => archive_path.to_path_buf(),
+        }
+    }
+
+    /// Output path used when repacking `archive_path` as `format` (e.g. `"7z"`)
+    ///
+    /// `None` keeps the input format (see `repack_path`). Otherwise the extension
+    /// is replaced by `format` (case-insensitive, leading `.` optional), which must
+    /// be a format this processor can create.
+    pub fn repack_path_as(&self, archive_path: &Path, format: Option<&str>) -> Result<PathBuf, BlindMarkError> {
+        let Some(format) = format.map(|f| f.trim().trim_start_matches('.').to_lowercase()) else {
+            return Ok(self.repack_path(archive_path));
+        };
+        let output_path = archive_path.with_extension(&format);
+        match self.handler_by_extension(&output_path) {
+            Ok(handler) if handler.can_create() => Ok(output_path),
+            _ => Err(BlindMarkError::UnsupportedArchive(
+                format!("Unsupported output format: {}", format)
+            )),
+        }
+    }
+
     /// Generate output filename with "_watermarked" suffix
     ///
     /// # Example
@@ -124,12 +278,31 @@ impl ArchiveProcessor {
         self.handlers.iter().any(|h| h.supports(path))
     }
 
+    /// Detect the format of an existing archive: magic bytes first, then a supported extension
+    ///
+    /// # Returns
+    /// * Format name such as `"zip"`, `"7z"` or `"rar"` (`.var` reports `"var"` only when
+    ///   the content is not recognized), or `None` if neither identifies the file
+    pub fn detect_format(&self, path: &Path) -> Option<String> {
+        if let Some(format) = sniff_format(path) {
+            return Some(format.to_string());
+        }
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .filter(|_| self.is_supported(path))
+    }
+
     /// Get list of supported archive extensions
     ///
-    /// 当前支持 ZIP（含 .var 别名）和 7z。RAR 因 `unrar` 需要系统库（许可证限制），
-    /// 暂未实现，传入 .rar 文件会返回错误。
+    /// 当前支持 ZIP（含 .var 别名）和 7z。启用 `rar` feature 后额外支持解压 RAR
+    /// （受 UnRAR 许可证限制只能解压，输出时重新打包为 ZIP，见 `repack_path`）。
+    #[allow(unused_mut)]
     pub fn supported_extensions() -> Vec<&'static str> {
-        vec!["zip", "var", "7z"]
+        let mut extensions = vec!["zip", "var", "7z"];
+        #[cfg(feature = "rar")]
+        extensions.push("rar");
+        extensions
     }
 }
 
@@ -166,16 +339,88 @@ mod tests {
         assert_eq!(output3, Path::new("noext_watermarked"));
     }
 
+    /// Handler for `.dummy` files that "extracts" by copying the file as `payload.bin`
+    struct DummyHandler;
+
+    impl ArchiveHandler for DummyHandler {
+        fn extract(&self, archive_path: &Path, dest_dir: &Path) -> Result<(), BlindMarkError> {
+            fs::create_dir_all(dest_dir)?;
+            fs::copy(archive_path, dest_dir.join("payload.bin"))?;
+            Ok(())
+        }
+
+        fn create(&self, source_dir: &Path, output_path: &Path) -> Result<(), BlindMarkError> {
+            fs::copy(source_dir.join("payload.bin"), output_path)?;
+            Ok(())
+        }
+
+        fn supports(&self, archive_path: &Path) -> bool {
+            archive_path.extension().is_some_and(|e| e.eq_ignore_ascii_case("dummy"))
+        }
+    }
+
+    #[test]
+    fn test_register_custom_handler() {
+        let temp = TempDir::new().unwrap();
+        let archive = temp.path().join("pack.dummy");
+        fs::write(&archive, b"custom format").unwrap();
+
+        let mut processor = ArchiveProcessor::new();
+        assert!(!processor.is_supported(&archive));
+        processor.register_handler(Arc::new(DummyHandler));
+        assert!(processor.is_supported(&archive));
+        assert!(processor.is_supported(Path::new("still.zip")), "built-in handlers remain");
+
+        let extracted = temp.path().join("out");
+        processor.extract(&archive, &extracted).unwrap();
+        assert_eq!(fs::read(extracted.join("payload.bin")).unwrap(), b"custom format");
+        assert_eq!(processor.read_file(&archive, "payload.bin").unwrap(), b"custom format");
+
+        let repacked = temp.path().join("repacked.dummy");
+        processor.create(&extracted, &repacked).unwrap();
+        assert_eq!(fs::read(&repacked).unwrap(), b"custom format");
+
+        // An explicit handler list contains only what was given
+        let only_dummy = ArchiveProcessor::with_handlers(vec![Arc::new(DummyHandler)]);
+        assert!(only_dummy.is_supported(&archive));
+        assert!(!only_dummy.is_supported(Path::new("test.zip")));
+    }
+
     #[test]
     fn test_is_supported() {
         let processor = ArchiveProcessor::new();
         assert!(processor.is_supported(Path::new("test.zip")));
         assert!(processor.is_supported(Path::new("test.7z")));
         assert!(processor.is_supported(Path::new("TEST.ZIP")));
-        assert!(!processor.is_supported(Path::new("test.rar")));
+        assert_eq!(processor.is_supported(Path::new("test.rar")), cfg!(feature = "rar"));
         assert!(!processor.is_supported(Path::new("test.tar.gz")));
     }
 
+    #[test]
+    fn test_shared_processor() {
+        let shared = ArchiveProcessor::shared();
+        assert!(std::ptr::eq(shared, ArchiveProcessor::shared()), "should return the same instance");
+
+        // Same behaviour as a freshly built processor, also from several threads at once
+        let temp_source = TempDir::new().unwrap();
+        create_test_files(temp_source.path());
+        std::thread::scope(|scope| {
+            for i in 0..4 {
+                let source = temp_source.path();
+                scope.spawn(move || {
+                    let temp = TempDir::new().unwrap();
+                    let zip_path = temp.path().join(format!("test{}.zip", i));
+                    ArchiveProcessor::shared().create(source, &zip_path).unwrap();
+                    let extract_dir = temp.path().join("out");
+                    fs::create_dir_all(&extract_dir).unwrap();
+                    ArchiveProcessor::new().extract(&zip_path, &extract_dir).unwrap();
+                    assert_eq!(fs::read(extract_dir.join("subdir/file2.txt")).unwrap(), b"test content 2");
+                });
+            }
+        });
+        assert_eq!(shared.is_supported(Path::new("a.7z")), ArchiveProcessor::new().is_supported(Path::new("a.7z")));
+    }
+
     #[test]
     fn test_supported_extensions() {
         let extensions = ArchiveProcessor::supported_extensions();
@@ -212,6 +457,53 @@ mod tests {
         assert_eq!(content, "test content 1");
     }
 
+    #[test]
+    fn test_read_file_from_archive() {
+        let temp_source = TempDir::new().unwrap();
+        let temp_output = TempDir::new().unwrap();
+        create_test_files(temp_source.path());
+
+        let processor = ArchiveProcessor::new();
+        for ext in ["zip", "7z"] {
+            let archive_path = temp_output.path().join(format!("test.{}", ext));
+            processor.create(temp_source.path(), &archive_path).unwrap();
+
+            let data = processor.read_file(&archive_path, "subdir/file2.txt").unwrap();
+            assert_eq!(data, b"test content 2", "{}", ext);
+            assert_eq!(processor.read_file(&archive_path, "subdir\\file2.txt").unwrap(), data, "{}", ext);
+            assert!(processor.read_file(&archive_path, "missing.txt").is_err(), "{}", ext);
+            assert!(processor.read_file(&archive_path, "subdir").is_err(), "{}", ext);
+        }
+    }
+
+    #[test]
+    fn test_create_reports_byte_progress() {
+        let temp_source = TempDir::new().unwrap();
+        let temp_output = TempDir::new().unwrap();
+        create_test_files(temp_source.path());
+        // Larger than one io::copy chunk so a file is reported in several steps
+        fs::write(temp_source.path().join("big.bin"), vec![7u8; 100_000]).unwrap();
+        let expected_total = 100_000 + "test content 1".len() as u64 + "test content 2".len() as u64;
+
+        let processor = ArchiveProcessor::new();
+        for ext in ["zip", "7z"] {
+            let calls = std::cell::RefCell::new(Vec::new());
+            let archive_path = temp_output.path().join(format!("test.{}", ext));
+            processor
+                .create_with_progress(temp_source.path(), &archive_path, &|written, total| {
+                    calls.borrow_mut().push((written, total))
+                })
+                .unwrap();
+
+            let calls = calls.into_inner();
+            assert!(calls.len() > 3, "{}: expected several updates, got {}", ext, calls.len());
+            assert!(calls.windows(2).all(|w| w[0].0 < w[1].0), "{}: progress must increase", ext);
+            assert!(calls.iter().all(|&(_, total)| total == expected_total), "{}", ext);
+            assert_eq!(calls.last(), Some(&(expected_total, expected_total)), "{}", ext);
+            assert_eq!(processor.read_file(&archive_path, "big.bin").unwrap().len(), 100_000, "{}", ext);
+        }
+    }
+
     #[test]
     fn test_extract_and_create_7z() {
         let temp_source = TempDir::new().unwrap();
@@ -237,6 +529,118 @@ mod tests {
         assert!(extract_path.join("subdir/file2.txt").exists());
     }
 
+    #[test]
+    fn test_extraction_limits_abort_oversized_archive() {
+        let temp_source = TempDir::new().unwrap();
+        let temp_output = TempDir::new().unwrap();
+        fs::write(temp_source.path().join("big.txt"), vec![b'a'; 4096]).unwrap();
+        fs::write(temp_source.path().join("small.txt"), b"ok").unwrap();
+
+        let tight = ExtractionLimits { max_entries: 10, max_total_size: 1024, max_file_size: 1024 };
+        let few = ExtractionLimits { max_entries: 1, ..ExtractionLimits::default() };
+
+        for ext in ["zip", "7z"] {
+            let archive_path = temp_output.path().join(format!("bomb.{}", ext));
+            ArchiveProcessor::new().create(temp_source.path(), &archive_path).unwrap();
+
+            for limits in [tight, few] {
+                let temp_extract = TempDir::new().unwrap();
+                let result = ArchiveProcessor::with_limits(limits).extract(&archive_path, temp_extract.path());
+                assert!(
+                    matches!(result, Err(BlindMarkError::CorruptedArchive(_))),
+                    "{} with {:?} should be rejected, got {:?}", ext, limits, result
+                );
+            }
+
+            // Default limits accept the same archive
+            let temp_extract = TempDir::new().unwrap();
+            ArchiveProcessor::new().extract(&archive_path, temp_extract.path()).unwrap();
+            assert_eq!(fs::read(temp_extract.path().join("big.txt")).unwrap().len(), 4096);
+        }
+    }
+
+    #[test]
+    fn test_repack_path() {
+        let processor = ArchiveProcessor::new();
+        assert_eq!(processor.repack_path(Path::new("out/pkg.zip")), Path::new("out/pkg.zip"));
+        assert_eq!(processor.repack_path(Path::new("pkg.7z")), Path::new("pkg.7z"));
+        assert_eq!(processor.repack_path(Path::new("pkg.tar.gz")), Path::new("pkg.tar.gz"));
+    }
+
+    #[test]
+    fn test_mislabeled_archive_sniffed() {
+        let temp_source = TempDir::new().unwrap();
+        let temp_output = TempDir::new().unwrap();
+        create_test_files(temp_source.path());
+
+        let processor = ArchiveProcessor::new();
+        let real_7z = temp_output.path().join("real.7z");
+        processor.create(temp_source.path(), &real_7z).unwrap();
+        let real_zip = temp_output.path().join("real.zip");
+        processor.create(temp_source.path(), &real_zip).unwrap();
+
+        // 7z content saved as .zip, ZIP content without a known extension
+        let mislabeled = temp_output.path().join("mislabeled.zip");
+        fs::copy(&real_7z, &mislabeled).unwrap();
+        let no_ext = temp_output.path().join("download.bin");
+        fs::copy(&real_zip, &no_ext).unwrap();
+
+        assert_eq!(sniff_format(&mislabeled), Some("7z"));
+        assert_eq!(processor.detect_format(&mislabeled).as_deref(), Some("7z"));
+        assert_eq!(processor.detect_format(&no_ext).as_deref(), Some("zip"));
+        assert_eq!(processor.detect_format(&temp_source.path().join("file1.txt")), None);
+        assert_eq!(processor.detect_format(Path::new("missing.var")).as_deref(), Some("var"));
+
+        for archive in [&mislabeled, &no_ext] {
+            let extract_dir = TempDir::new().unwrap();
+            processor.extract(archive, extract_dir.path()).unwrap();
+            assert_eq!(fs::read(extract_dir.path().join("subdir/file2.txt")).unwrap(), b"test content 2");
+        }
+    }
+
+    #[test]
+    fn test_repack_zip_as_7z() {
+        let temp_source = TempDir::new().unwrap();
+        let temp_output = TempDir::new().unwrap();
+        create_test_files(temp_source.path());
+
+        let processor = ArchiveProcessor::new();
+        let zip_path = temp_output.path().join("pkg.zip");
+        processor.create(temp_source.path(), &zip_path).unwrap();
+
+        assert_eq!(processor.repack_path_as(&zip_path, None).unwrap(), zip_path);
+        assert_eq!(processor.repack_path_as(&zip_path, Some(".ZIP")).unwrap(), zip_path);
+        assert!(matches!(
+            processor.repack_path_as(&zip_path, Some("tar")),
+            Err(BlindMarkError::UnsupportedArchive(_))
+        ));
+
+        let extracted = TempDir::new().unwrap();
+        processor.extract(&zip_path, extracted.path()).unwrap();
+        let output_path = processor.repack_path_as(&zip_path, Some("7z")).unwrap();
+        assert_eq!(output_path, temp_output.path().join("pkg.7z"));
+        processor.create(extracted.path(), &output_path).unwrap();
+
+        // The repacked archive really is 7z
+        let roundtrip = TempDir::new().unwrap();
+        SevenZHandler::new().extract(&output_path, roundtrip.path()).unwrap();
+        assert_eq!(fs::read(roundtrip.path().join("subdir/file2.txt")).unwrap(), b"test content 2");
+    }
+
+    #[cfg(feature = "rar")]
+    #[test]
+    fn test_rar_routing() {
+        let processor = ArchiveProcessor::new();
+        assert!(ArchiveProcessor::supported_extensions().contains(&"rar"));
+        assert_eq!(processor.repack_path(Path::new("out/pkg.RAR")), Path::new("out/pkg.zip"));
+        assert!(processor.repack_path_as(Path::new("pkg.zip"), Some("rar")).is_err());
+
+        let temp = TempDir::new().unwrap();
+        let result = processor.create(temp.path(), &temp.path().join("pkg.rar"));
+        assert!(matches!(result, Err(BlindMarkError::UnsupportedArchive(_))));
+    }
+
+    #[cfg(not(feature = "rar"))]
     #[test]
     fn test_unsupported_format() {
         let processor = ArchiveProcessor::new();
@@ -261,7 +665,7 @@ mod tests {
         assert!(processor.get_handler(Path::new("test.7z")).is_ok());
 
         // Should fail for unsupported formats
-        assert!(processor.get_handler(Path::new("test.rar")).is_err());
+        assert_eq!(processor.get_handler(Path::new("test.rar")).is_ok(), cfg!(feature = "rar"));
         assert!(processor.get_handler(Path::new("test.tar.gz")).is_err());
     }
 }
@@ -0,0 +1,150 @@
+use std::path::Path;
+use std::fs::{self, File};
+use unrar::Archive;
+use crate::core::compression::common::{ArchiveHandler, ExtractionBudget, ExtractionLimits};
+use crate::core::compression::zip_handler::sanitize_zip_path;
+use crate::models::BlindMarkError;
+
+/// RAR archive handler (extraction only)
+///
+/// Built only with the `rar` feature: the `unrar` crate bundles the UnRAR source,
+/// whose license forbids using it to create RAR archives. `create` therefore always
+/// fails; callers repack RAR input as ZIP (see `ArchiveProcessor::repack_path`).
+pub struct RarHandler {
+    limits: ExtractionLimits,
+}
+
+impl RarHandler {
+    pub fn new() -> Self {
+        Self { limits: ExtractionLimits::default() }
+    }
+
+    /// Create a handler that enforces custom extraction limits
+    pub fn with_limits(limits: ExtractionLimits) -> Self {
+        Self { limits }
+    }
+}
+
+impl ArchiveHandler for RarHandler {
+    /// Extract RAR archive to destination directory
+    ///
+    /// # Behavior
+    /// - Preserves directory hierarchy; entries escaping `dest_dir` are skipped
+    /// - Entries are read into memory one at a time, so the declared size is checked
+    ///   against `max_file_size` before reading
+    /// - Does not support password-protected archives
+    /// - Aborts with `CorruptedArchive` once the handler's `ExtractionLimits` are exceeded
+    fn extract(&self, archive_path: &Path, dest_dir: &Path) -> Result<(), BlindMarkError> {
+        let mut archive = Archive::new(archive_path)
+            .open_for_processing()
+            .map_err(|e| BlindMarkError::Archive(
+                format!("Failed to open RAR archive {}: {}", archive_path.display(), e)
+            ))?;
+
+        fs::create_dir_all(dest_dir)
+            .map_err(|e| BlindMarkError::Archive(
+                format!("Failed to create destination directory: {}", e)
+            ))?;
+
+        let mut budget = ExtractionBudget::new(self.limits);
+        while let Some(header) = archive
+            .read_header()
+            .map_err(|e| BlindMarkError::Archive(format!("Failed to read RAR header: {}", e)))?
+        {
+            budget.add_entry()?;
+            let entry = header.entry();
+            let name = entry.filename.to_string_lossy().replace('\\', "/");
+            let relative = sanitize_zip_path(&name);
+
+            archive = match relative {
+                Some(relative) if entry.is_directory() => {
+                    fs::create_dir_all(dest_dir.join(relative))
+                        .map_err(|e| BlindMarkError::Archive(
+                            format!("Failed to create directory {}: {}", name, e)
+                        ))?;
+                    header.skip()
+                }
+                Some(relative) if entry.is_file() => {
+                    if entry.unpacked_size > self.limits.max_file_size {
+                        return Err(BlindMarkError::CorruptedArchive(format!(
+                            "Entry {} exceeds the {} byte size limit", name, self.limits.max_file_size
+                        )));
+                    }
+                    let output_path = dest_dir.join(relative);
+                    if let Some(parent) = output_path.parent() {
+                        fs::create_dir_all(parent)
+                            .map_err(|e| BlindMarkError::Archive(
+                                format!("Failed to create directory for {}: {}", name, e)
+                            ))?;
+                    }
+                    let (data, next) = header
+                        .read()
+                        .map_err(|e| BlindMarkError::Archive(format!("Failed to extract file {}: {}", name, e)))?;
+                    let mut output = File::create(&output_path)
+                        .map_err(|e| BlindMarkError::Archive(
+                            format!("Failed to create file {}: {}", output_path.display(), e)
+                        ))?;
+                    budget.copy(&mut data.as_slice(), &mut output, &name)?;
+                    Ok(next)
+                }
+                // Unsafe path (absolute / `..`) or special entry: skip it
+                _ => header.skip(),
+            }
+            .map_err(|e| BlindMarkError::Archive(format!("Failed to read RAR archive: {}", e)))?;
+        }
+
+        Ok(())
+    }
+
+    /// RAR creation is not available: always returns an error
+    fn create(&self, _source_dir: &Path, output_path: &Path) -> Result<(), BlindMarkError> {
+        Err(BlindMarkError::UnsupportedArchive(format!(
+            "Cannot create {}: RAR archives can only be extracted (UnRAR license), repack as .zip or .7z instead",
+            output_path.display()
+        )))
+    }
+
+    fn can_create(&self) -> bool {
+        false
+    }
+
+    fn supports(&self, archive_path: &Path) -> bool {
+        archive_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("rar"))
+            .unwrap_or(false)
+    }
+}
+
+impl Default for RarHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rar_handler_is_extract_only() {
+        let handler = RarHandler::new();
+        assert!(handler.supports(Path::new("pkg.rar")));
+        assert!(handler.supports(Path::new("PKG.RAR")));
+        assert!(!handler.supports(Path::new("pkg.zip")));
+        assert!(!handler.can_create());
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let result = handler.create(temp.path(), &temp.path().join("out.rar"));
+        assert!(matches!(result, Err(BlindMarkError::UnsupportedArchive(_))));
+    }
+
+    #[test]
+    fn test_rar_extract_rejects_invalid_archive() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let fake = temp.path().join("fake.rar");
+        fs::write(&fake, b"not a rar archive").unwrap();
+        assert!(RarHandler::new().extract(&fake, &temp.path().join("out")).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+// VaM .var package structure validation
+
+use std::path::Path;
+use serde::Serialize;
+use serde_json::Value;
+use crate::core::compression::zip_handler::ZipHandler;
+use crate::core::compression::common::{ArchiveHandler, single_wrapper_dir};
+use crate::core::watermark::json_marker::{decode_text_bytes, is_watermark_value};
+use crate::models::BlindMarkError;
+
+/// meta.json 中必须存在的字段
+pub const REQUIRED_META_KEYS: [&str; 3] = ["licenseType", "creatorName", "packageName"];
+
+/// VaM 加载时依赖的顶层结构字段（按文件扩展名）
+///
+/// 预设（.vap）缺少 `setUnlistedParamsToDefault` 时 VaM 会改为叠加模式加载，`storables` 缺失则无内容可加载。
+pub const VAM_STRUCTURAL_MARKERS: [(&str, &[&str]); 1] = [("vap", &["setUnlistedParamsToDefault", "storables"])];
+
+/// 单条校验问题
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VarIssue {
+    /// 问题类型："missing_meta" / "invalid_meta" / "missing_key" / "missing_content"；
+    /// 水印预检另有 "invalid_output" / "missing_marker" / "changed_field"
+    pub kind: String,
+    /// 问题详情（字段名或缺失的路径等）
+    pub detail: String,
+}
+
+/// .var 包校验报告
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VarValidationReport {
+    /// 无任何问题时为 true
+    pub valid: bool,
+    /// 压缩包内文件条目数
+    pub entry_count: usize,
+    /// 包裹全部内容的单一顶层目录（如 `Package`）；存在时 meta.json 与 contentList 相对该目录查找
+    pub wrapper_dir: Option<String>,
+    /// 发现的全部问题
+    pub issues: Vec<VarIssue>,
+}
+
+impl VarIssue {
+    fn new(kind: &str, detail: impl Into<String>) -> Self {
+        Self { kind: kind.to_string(), detail: detail.into() }
+    }
+}
+
+/// 校验压缩包是否为合法的 VaM .var 包
+///
+/// # 检查项
+/// 1. 根目录存在 `meta.json`
+/// 2. `meta.json` 可解析为 JSON 对象，且包含 `licenseType` / `creatorName` / `packageName`
+/// 3. `contentList` 中的每一项都存在于压缩包中（文件或目录前缀，大小写不敏感，与 VaM 一致）
+///
+/// 全部内容包裹在单一顶层目录（如 `Package/meta.json`）中时，以该目录为包根目录检查。
+///
+/// 仅读取条目列表与 meta.json，不解压整个压缩包。
+pub fn validate_var_package(archive_path: &Path) -> Result<VarValidationReport, BlindMarkError> {
+    let handler = ZipHandler::new();
+    if !handler.supports(archive_path) {
+        return Err(BlindMarkError::UnsupportedArchive(format!(
+            "VaM 包必须为 .var / .zip 格式: {}",
+            archive_path.display()
+        )));
+    }
+
+    let entries = handler.list_entries(archive_path)?;
+    let mut issues = Vec::new();
+    let wrapper_dir = single_wrapper_dir(entries.iter().map(String::as_str));
+    let prefix = wrapper_dir.as_ref().map(|dir| format!("{}/", dir)).unwrap_or_default();
+
+    match handler.read_entry(archive_path, &format!("{}meta.json", prefix))? {
+        None => issues.push(VarIssue::new("missing_meta", "meta.json")),
+        Some(bytes) => {
+            let meta = decode_text_bytes(&bytes)
+                .ok()
+                .and_then(|s| serde_json::from_str::<Value>(&s).ok());
+            match meta {
+                Some(Value::Object(obj)) => {
+                    for key in REQUIRED_META_KEYS {
+                        if !obj.contains_key(key) {
+                            issues.push(VarIssue::new("missing_key", key));
+                        }
+                    }
+                    let lower_entries: Vec<String> =
+                        entries.iter().map(|e| e.to_lowercase()).collect();
+                    let content_list = obj
+                        .get("contentList")
+                        .and_then(|v| v.as_array())
+                        .map(|a| a.as_slice())
+                        .unwrap_or(&[]);
+                    for item in content_list.iter().filter_map(|v| v.as_str()) {
+                        if !content_exists(&lower_entries, &format!("{}{}", prefix, item)) {
+                            issues.push(VarIssue::new("missing_content", item));
+                        }
+                    }
+                }
+                _ => issues.push(VarIssue::new("invalid_meta", "meta.json 不是有效的 JSON 对象")),
+            }
+        }
+    }
+
+    Ok(VarValidationReport {
+        valid: issues.is_empty(),
+        entry_count: entries.len(),
+        wrapper_dir,
+        issues,
+    })
+}
+
+/// 水印预检：检查 VaM 文件（.vap / .vaj / .vmi / .vam）嵌入水印后是否仍能被 VaM 正常加载
+///
+/// # 检查项
+/// 1. 输出可解析为 JSON 对象（原文件为对象时）
+/// 2. 原文件中存在的 `VAM_STRUCTURAL_MARKERS` 字段在输出中仍存在且值不变
+/// 3. 原文件的其余顶层字段在输出中仍存在且值不变（值为旧水印格式的字段除外，嵌入时会被替换）
+///
+/// 原文件本身无法解析时不做检查（嵌入会失败并原样保留）。返回发现的全部问题，为空表示安全。
+pub fn preflight_vam_output(file_type: &str, original: &[u8], watermarked: &[u8]) -> Vec<VarIssue> {
+    let parse = |bytes: &[u8]| {
+        decode_text_bytes(bytes)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+    };
+    let Some(Value::Object(before)) = parse(original) else {
+        return Vec::new();
+    };
+    let Some(Value::Object(after)) = parse(watermarked) else {
+        return vec![VarIssue::new("invalid_output", "水印后的内容不是有效的 JSON 对象")];
+    };
+
+    let markers = VAM_STRUCTURAL_MARKERS
+        .iter()
+        .find(|(ext, _)| ext.eq_ignore_ascii_case(file_type))
+        .map(|(_, markers)| *markers)
+        .unwrap_or(&[]);
+    let mut issues = Vec::new();
+    for (key, value) in &before {
+        let is_marker = markers.contains(&key.as_str());
+        if !is_marker && value.as_str().is_some_and(is_watermark_value) {
+            continue;
+        }
+        match after.get(key) {
+            None if is_marker => issues.push(VarIssue::new("missing_marker", key.as_str())),
+            Some(v) if v != value => issues.push(VarIssue::new("changed_field", key.as_str())),
+            None => issues.push(VarIssue::new("changed_field", key.as_str())),
+            Some(_) => {}
+        }
+    }
+    issues
+}
+
+/// 判断 contentList 中的一项是否存在（精确匹配文件，或作为目录前缀）
+fn content_exists(lower_entries: &[String], item: &str) -> bool {
+    let wanted = item.replace('\\', "/").trim_matches('/').to_lowercase();
+    if wanted.is_empty() {
+        return true;
+    }
+    let dir_prefix = format!("{}/", wanted);
+    lower_entries
+        .iter()
+        .any(|e| *e == wanted || e.starts_with(&dir_prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn build_var(meta: &str, files: &[&str]) -> (TempDir, std::path::PathBuf) {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("meta.json"), meta).unwrap();
+        for f in files {
+            let p = source.path().join(f);
+            fs::create_dir_all(p.parent().unwrap()).unwrap();
+            fs::write(p, b"{}").unwrap();
+        }
+        let out = TempDir::new().unwrap();
+        let var_path = out.path().join("Creator.Package.1.var");
+        ZipHandler::new().create(source.path(), &var_path).unwrap();
+        drop(source);
+        (out, var_path)
+    }
+
+    #[test]
+    fn test_valid_package() {
+        let meta = r#"{
+  "licenseType": "CC BY",
+  "creatorName": "Creator",
+  "packageName": "Package",
+  "contentList": ["Saves/scene/scene.json", "Custom/Atom/Person/Morphs"]
+}"#;
+        let (_dir, path) = build_var(meta, &["Saves/scene/scene.json", "Custom/Atom/Person/Morphs/a.vmi"]);
+        let report = validate_var_package(&path).unwrap();
+        assert!(report.valid, "合法包不应有问题: {:?}", report.issues);
+        assert_eq!(report.entry_count, 3);
+    }
+
+    #[test]
+    fn test_invalid_package() {
+        let meta = r#"{"licenseType": "CC BY", "contentList": ["Saves/scene/missing.json"]}"#;
+        let (_dir, path) = build_var(meta, &["Saves/scene/scene.json"]);
+        let report = validate_var_package(&path).unwrap();
+        assert!(!report.valid);
+        assert!(report.issues.contains(&VarIssue::new("missing_key", "creatorName")));
+        assert!(report.issues.contains(&VarIssue::new("missing_key", "packageName")));
+        assert!(report.issues.contains(&VarIssue::new("missing_content", "Saves/scene/missing.json")));
+    }
+
+    #[test]
+    fn test_single_wrapper_package() {
+        let source = TempDir::new().unwrap();
+        let root = source.path().join("Package");
+        fs::create_dir_all(root.join("Saves/scene")).unwrap();
+        let meta = r#"{"licenseType": "CC BY", "creatorName": "Creator", "packageName": "Package", "contentList": ["Saves/scene/scene.json"]}"#;
+        fs::write(root.join("meta.json"), meta).unwrap();
+        fs::write(root.join("Saves/scene/scene.json"), b"{}").unwrap();
+        let out = TempDir::new().unwrap();
+        let var_path = out.path().join("wrapped.var");
+        ZipHandler::new().create(source.path(), &var_path).unwrap();
+
+        let report = validate_var_package(&var_path).unwrap();
+        assert!(report.valid, "包裹目录中的 meta.json 应被找到: {:?}", report.issues);
+        assert_eq!(report.wrapper_dir.as_deref(), Some("Package"));
+    }
+
+    #[test]
+    fn test_missing_meta() {
+        let source = TempDir::new().unwrap();
+        fs::write(source.path().join("scene.json"), b"{}").unwrap();
+        let out = TempDir::new().unwrap();
+        let var_path = out.path().join("bad.var");
+        ZipHandler::new().create(source.path(), &var_path).unwrap();
+
+        let report = validate_var_package(&var_path).unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.issues, vec![VarIssue::new("missing_meta", "meta.json")]);
+    }
+
+    #[test]
+    fn test_preflight_vap_preset() {
+        use crate::core::watermark::JsonWatermarker;
+        use crate::core::watermark::json_marker::DEFAULT_WATERMARK_KEY;
+
+        let preset = r#"{
+   "setUnlistedParamsToDefault" : "true",
+   "storables" : [
+      {
+         "id" : "geometry",
+         "morphs" : [
+            { "uid" : "Breast size", "name" : "Breast size", "value" : "0.35" },
+            { "uid" : "Custom/Atom/Person/Morphs/female/Creator/Nose.vmi", "value" : "0.6" }
+         ]
+      },
+      { "id" : "skin", "Skin Color" : { "h" : "0.05", "s" : "0.2", "v" : "0.9" } }
+   ]
+}"#;
+        let bytes = preset.as_bytes();
+
+        // 普通模式与混淆模式嵌入均不破坏预设结构
+        let plain = JsonWatermarker::embed_bytes(bytes, "buyer", DEFAULT_WATERMARK_KEY, "md5", None).unwrap();
+        assert_eq!(preflight_vam_output("vap", bytes, &plain), vec![]);
+        let obfuscated = JsonWatermarker::embed_obfuscated_bytes(bytes, "buyer", "plaintext", None).unwrap();
+        assert_eq!(preflight_vam_output("vap", bytes, &obfuscated), vec![]);
+
+        // 丢失结构字段 / 改动 storables / 输出无法解析
+        let mut value: Value = serde_json::from_str(preset).unwrap();
+        let obj = value.as_object_mut().unwrap();
+        obj.shift_remove("setUnlistedParamsToDefault");
+        obj["storables"].as_array_mut().unwrap().pop();
+        let broken = serde_json::to_vec(&value).unwrap();
+        assert_eq!(
+            preflight_vam_output("vap", bytes, &broken),
+            vec![
+                VarIssue::new("missing_marker", "setUnlistedParamsToDefault"),
+                VarIssue::new("changed_field", "storables"),
+            ]
+        );
+        assert_eq!(
+            preflight_vam_output("VAP", bytes, b"{\"storables\": [")[0].kind,
+            "invalid_output"
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_zip() {
+        assert!(validate_var_package(Path::new("package.7z")).is_err());
+    }
+}
@@ -2,11 +2,12 @@ use std::path::Path;
 use std::fs::{self, File};
 use std::io;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use encoding_rs::GBK;
-use zip::{ZipArchive, ZipWriter, write::FullFileOptions, CompressionMethod, HasZipMetadata};
+use zip::{ZipArchive, ZipWriter, write::FullFileOptions, CompressionMethod, DateTime, HasZipMetadata};
 use rayon::prelude::*;
 use walkdir::WalkDir;
-use crate::core::compression::common::ArchiveHandler;
+use crate::core::compression::common::{unix_mode, ArchiveHandler, ByteProgress, CaseCollisionGuard, ExtractionBudget, ExtractionLimits, ProgressReader};
 use crate::models::BlindMarkError;
 
 /// Detect and decode a ZIP entry filename from its raw bytes.
@@ -49,7 +50,7 @@ fn decode_zip_filename(raw: &[u8], is_utf8: bool) -> String {
 ///
 /// Replicates the logic of `ZipFile::enclosed_name()` but works on an
 /// arbitrary `&str` (needed after we re-decode the filename ourselves).
-fn sanitize_zip_path(name: &str) -> Option<PathBuf> {
+pub(crate) fn sanitize_zip_path(name: &str) -> Option<PathBuf> {
     if name.contains('\0') {
         return None;
     }
@@ -75,7 +76,7 @@ fn sanitize_zip_path(name: &str) -> Option<PathBuf> {
 /// 等特殊情况容易产生不匹配而报错。
 fn file_opts(method: CompressionMethod, level: Option<i64>, _name: &str) -> Result<FullFileOptions<'static>, BlindMarkError> {
     // EFS 标志由 zip 库自动处理，此处不再手动添加 0x7075 字段。
-    // 不写入 Unix 权限位（unix_permissions）：
+    // 默认不写入 Unix 权限位（unix_permissions，见 `ZipHandler::with_preserved_permissions`）：
     //   - .var 是跨平台包，接收方（Windows / VaM）不需要 Unix 属性
     //   - 写入权限位会导致部分 Windows 工具将文件标记为只读或产生警告
     let mut opts = FullFileOptions::default().compression_method(method);
@@ -85,17 +86,126 @@ fn file_opts(method: CompressionMethod, level: Option<i64>, _name: &str) -> Resu
     Ok(opts)
 }
 
+/// Convert a ZIP (MS-DOS) entry timestamp to `SystemTime`.
+///
+/// DOS timestamps carry no timezone; they are interpreted as UTC, the same
+/// convention `system_time_to_zip` uses, so extract → create round-trips to
+/// the identical stored value.
+fn zip_time_to_system(dt: DateTime) -> Option<SystemTime> {
+    // days_from_civil (proleptic Gregorian calendar)
+    let (y, m, d) = (dt.year() as i64, dt.month() as i64, dt.day() as i64);
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let secs = days * 86400 + dt.hour() as i64 * 3600 + dt.minute() as i64 * 60 + dt.second() as i64;
+    u64::try_from(secs).ok().map(|s| UNIX_EPOCH + Duration::from_secs(s))
+}
+
+/// Convert a file's `SystemTime` to a ZIP (MS-DOS) timestamp, as UTC.
+///
+/// Returns `None` outside the DOS range (1980–2107); odd seconds are rounded
+/// down by the format's 2-second resolution.
+fn system_time_to_zip(t: SystemTime) -> Option<DateTime> {
+    let secs = t.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    DateTime::from_date_and_time(
+        u16::try_from(year).ok()?,
+        month as u8,
+        day as u8,
+        (rem / 3600) as u8,
+        (rem % 3600 / 60) as u8,
+        (rem % 60) as u8,
+    )
+    .ok()
+}
+
 /// ZIP archive handler
 ///
 /// Handles extraction and creation of ZIP archives while preserving directory hierarchy.
-pub struct ZipHandler;
+pub struct ZipHandler {
+    limits: ExtractionLimits,
+    preserve_permissions: bool,
+}
 
 impl ZipHandler {
     pub fn new() -> Self {
-        Self
+        Self::with_limits(ExtractionLimits::default())
+    }
+
+    /// Create a handler that enforces custom extraction limits
+    pub fn with_limits(limits: ExtractionLimits) -> Self {
+        Self { limits, preserve_permissions: false }
+    }
+
+    /// Write each source file's Unix permission bits into the created entries
+    ///
+    /// Off by default: see `file_opts` for why entries normally carry no Unix
+    /// attributes. Enable it for packages with executable scripts.
+    pub fn with_preserved_permissions(mut self, enabled: bool) -> Self {
+        self.preserve_permissions = enabled;
+        self
+    }
+
+    /// Unix mode to store for `path`, if permissions are preserved
+    fn mode_of(&self, path: &Path) -> Option<u32> {
+        if !self.preserve_permissions {
+            return None;
+        }
+        fs::metadata(path).ok().as_ref().and_then(unix_mode)
+    }
+
+    /// List the (decoded, sanitized) names of all file entries without extracting
+    ///
+    /// Directory entries and unsafe paths are skipped; names use `/` separators.
+    pub fn list_entries(&self, archive_path: &Path) -> Result<Vec<String>, BlindMarkError> {
+        let mut archive = open_zip(archive_path)?;
+        let mut names = Vec::with_capacity(archive.len());
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i)
+                .map_err(|e| BlindMarkError::Archive(
+                    format!("Failed to read file at index {}: {}", i, e)
+                ))?;
+            if file.is_dir() {
+                continue;
+            }
+            let meta = file.get_metadata();
+            let decoded = decode_zip_filename(&meta.file_name_raw, meta.is_utf8);
+            if let Some(path) = sanitize_zip_path(&decoded) {
+                names.push(path.to_string_lossy().replace('\\', "/"));
+            }
+        }
+        Ok(names)
     }
 }
 
+/// Open a ZIP archive for reading
+fn open_zip(archive_path: &Path) -> Result<ZipArchive<File>, BlindMarkError> {
+    let file = File::open(archive_path)
+        .map_err(|e| BlindMarkError::Archive(
+            format!("Failed to open ZIP archive {}: {}", archive_path.display(), e)
+        ))?;
+    ZipArchive::new(file)
+        .map_err(|e| BlindMarkError::Archive(
+            format!("Failed to read ZIP archive: {}", e)
+        ))
+}
+
 impl ArchiveHandler for ZipHandler {
     /// Extract ZIP archive to destination directory
     ///
@@ -107,6 +217,9 @@ impl ArchiveHandler for ZipHandler {
     /// - Preserves directory hierarchy
     /// - Creates parent directories as needed
     /// - Sets file permissions on Unix systems
+    /// - Restores each file's modification time from the entry timestamp
+    /// - Aborts with `CorruptedArchive` once the handler's `ExtractionLimits` are exceeded
+    /// - On case-insensitive filesystems, refuses files whose paths differ only in case
     fn extract(&self, archive_path: &Path, dest_dir: &Path) -> Result<(), BlindMarkError> {
         let file = File::open(archive_path)
             .map_err(|e| BlindMarkError::Archive(
@@ -125,7 +238,10 @@ impl ArchiveHandler for ZipHandler {
             ))?;
 
         // Extract each file
+        let mut budget = ExtractionBudget::new(self.limits);
+        let mut case_guard = CaseCollisionGuard::for_dir(dest_dir);
         for i in 0..archive.len() {
+            budget.add_entry()?;
             let mut file = archive.by_index(i)
                 .map_err(|e| BlindMarkError::Archive(
                     format!("Failed to read file at index {}: {}", i, e)
@@ -156,6 +272,8 @@ impl ArchiveHandler for ZipHandler {
                         format!("Failed to create directory {}: {}", output_path.display(), e)
                     ))?;
             } else {
+                case_guard.check(&file_path.to_string_lossy().replace('\\', "/"))?;
+
                 // Create parent directories
                 if let Some(parent) = output_path.parent() {
                     fs::create_dir_all(parent)
@@ -170,10 +288,14 @@ impl ArchiveHandler for ZipHandler {
                         format!("Failed to create output file {}: {}", output_path.display(), e)
                     ))?;
 
-                std::io::copy(&mut file, &mut output_file)
-                    .map_err(|e| BlindMarkError::Archive(
-                        format!("Failed to extract file {}: {}", file_path.display(), e)
-                    ))?;
+                budget.copy(&mut file, &mut output_file, &file_path.display().to_string())?;
+
+                if let Some(mtime) = file.last_modified().and_then(zip_time_to_system) {
+                    output_file.set_modified(mtime)
+                        .map_err(|e| BlindMarkError::Archive(
+                            format!("Failed to set modification time on {}: {}", output_path.display(), e)
+                        ))?;
+                }
 
                 // Set permissions on Unix systems
                 #[cfg(unix)]
@@ -202,12 +324,25 @@ impl ArchiveHandler for ZipHandler {
     /// - Enumerates entries in a single pass, then reads all files in parallel with Rayon
     /// - Already-compressed formats (PNG, JPG, MP3…) are stored without re-compression
     /// - Text/data files use Deflate level 1 (fastest) for quick compression
+    /// - Each entry carries its source file's modification time
+    /// - With `with_preserved_permissions`, each entry also carries its source file's Unix mode
     fn create(&self, source_dir: &Path, output_path: &Path) -> Result<(), BlindMarkError> {
+        self.create_with_progress(source_dir, output_path, &|_, _| {})
+    }
+
+    /// Create ZIP archive like `create`, reporting file bytes as they are written
+    fn create_with_progress(
+        &self,
+        source_dir: &Path,
+        output_path: &Path,
+        on_progress: ByteProgress<'_>,
+    ) -> Result<(), BlindMarkError> {
         // === Step 1: Enumerate entries (single-threaded walk) ===
-        let mut dir_names: Vec<String> = Vec::new();
+        let mut dir_names: Vec<(String, Option<DateTime>, Option<u32>)> = Vec::new();
         let mut file_infos: Vec<(std::path::PathBuf, String)> = Vec::new();
 
-        for entry in WalkDir::new(source_dir).follow_links(false).into_iter().filter_map(|e| e.ok()) {
+        // 按文件名排序遍历，相同内容与修改时间的目录总是得到相同的压缩包
+        for entry in WalkDir::new(source_dir).follow_links(false).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
             let path = entry.path();
             let relative = path.strip_prefix(source_dir)
                 .map_err(|e| BlindMarkError::Archive(
@@ -227,21 +362,27 @@ impl ArchiveHandler for ZipHandler {
             }
             let name = relative.to_string_lossy().replace('\\', "/");
             if path.is_dir() {
-                dir_names.push(name);
+                let mtime = entry.metadata().ok().and_then(|m| m.modified().ok()).and_then(system_time_to_zip);
+                dir_names.push((name, mtime, self.mode_of(path)));
             } else if path.is_file() {
                 file_infos.push((path.to_path_buf(), name));
             }
         }
 
         // === Step 2: Read all files in parallel ===
-        let file_data: Vec<(String, Vec<u8>)> = file_infos
+        type FileData = (String, Vec<u8>, Option<DateTime>, Option<u32>);
+        let file_data: Vec<FileData> = file_infos
             .into_par_iter()
             .map(|(path, name)| {
                 let data = fs::read(&path)
                     .map_err(|e| BlindMarkError::Archive(
                         format!("Failed to read file {}: {}", path.display(), e)
                     ))?;
-                Ok((name, data))
+                let mtime = fs::metadata(&path)
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(system_time_to_zip);
+                Ok((name, data, mtime, self.mode_of(&path)))
             })
             .collect::<Result<Vec<_>, BlindMarkError>>()?;
 
@@ -251,36 +392,50 @@ impl ArchiveHandler for ZipHandler {
                 format!("Failed to create ZIP file {}: {}", output_path.display(), e)
             ))?;
         let mut zip = ZipWriter::new(file);
+        let total: u64 = file_data.iter().map(|(_, data, _, _)| data.len() as u64).sum();
+        let mut written = 0u64;
 
-        for name in dir_names {
+        for (name, mtime, mode) in dir_names {
             let stored_name = if name.ends_with('/') {
                 name.clone()
             } else {
                 format!("{}/", name)
             };
-            let opts = file_opts(CompressionMethod::Stored, None, &stored_name)?;
+            let mut opts = file_opts(CompressionMethod::Stored, None, &stored_name)?;
+            if let Some(mtime) = mtime {
+                opts = opts.last_modified_time(mtime);
+            }
+            if let Some(mode) = mode {
+                opts = opts.unix_permissions(mode);
+            }
             zip.add_directory(&stored_name, opts)
                 .map_err(|e| BlindMarkError::Archive(
                     format!("Failed to add directory {} to archive: {}", stored_name, e)
                 ))?;
         }
 
-        for (name, data) in file_data {
+        for (name, data, mtime, mode) in file_data {
             // Already-compressed formats: store as-is (zero CPU cost)
             // Text/binary formats: fast Deflate level 1
-            let opts = if is_already_compressed(&name) {
+            let mut opts = if is_already_compressed(&name) {
                 file_opts(CompressionMethod::Stored, None, &name)?
             } else {
                 file_opts(CompressionMethod::Deflated, Some(1), &name)?
             };
+            if let Some(mtime) = mtime {
+                opts = opts.last_modified_time(mtime);
+            }
+            if let Some(mode) = mode {
+                opts = opts.unix_permissions(mode);
+            }
 
             zip.start_file(&name, opts)
                 .map_err(|e| BlindMarkError::Archive(
                     format!("Failed to start file {} in archive: {}", name, e)
                 ))?;
 
-            let mut cursor = io::Cursor::new(&data);
-            io::copy(&mut cursor, &mut zip)
+            let mut reader = ProgressReader::new(io::Cursor::new(&data), &mut written, total, on_progress);
+            io::copy(&mut reader, &mut zip)
                 .map_err(|e| BlindMarkError::Archive(
                     format!("Failed to write file {} to archive: {}", name, e)
                 ))?;
@@ -300,6 +455,36 @@ impl ArchiveHandler for ZipHandler {
         Ok(())
     }
 
+    /// Read a single file entry's bytes by its decoded name, without extracting the archive
+    ///
+    /// UTF-8 names are looked up directly via `by_name`; otherwise entries are scanned so
+    /// GBK-encoded names still match. The entry is subject to the handler's size limits.
+    fn read_entry(&self, archive_path: &Path, name: &str) -> Result<Option<Vec<u8>>, BlindMarkError> {
+        let mut archive = open_zip(archive_path)?;
+        let index = match archive.index_for_name(name) {
+            Some(i) => Some(i),
+            None => (0..archive.len()).find(|&i| {
+                archive.by_index_raw(i).is_ok_and(|file| {
+                    let meta = file.get_metadata();
+                    decode_zip_filename(&meta.file_name_raw, meta.is_utf8) == name
+                })
+            }),
+        };
+        let Some(index) = index else {
+            return Ok(None);
+        };
+        let mut file = archive.by_index(index)
+            .map_err(|e| BlindMarkError::Archive(
+                format!("Failed to read file at index {}: {}", index, e)
+            ))?;
+        if file.is_dir() {
+            return Ok(None);
+        }
+        let mut data = Vec::new();
+        ExtractionBudget::new(self.limits).copy(&mut file, &mut data, name)?;
+        Ok(Some(data))
+    }
+
     /// Check if this handler supports the given archive
     ///
     /// Returns true for ZIP-compatible formats: .zip, .var (VaM package)
@@ -469,6 +654,63 @@ mod tests {
         assert!(!dest.join("desktop.ini").exists(), "desktop.ini 应被过滤");
     }
 
+    #[test]
+    fn test_list_and_read_entries() {
+        let temp_source = TempDir::new().unwrap();
+        let temp_archive = TempDir::new().unwrap();
+        create_test_files(temp_source.path());
+
+        let handler = ZipHandler::new();
+        let zip_path = temp_archive.path().join("test.zip");
+        handler.create(temp_source.path(), &zip_path).unwrap();
+
+        let mut names = handler.list_entries(&zip_path).unwrap();
+        names.sort();
+        assert_eq!(names, vec!["file1.txt", "file2.txt", "subdir/file3.txt"]);
+
+        let data = handler.read_entry(&zip_path, "subdir/file3.txt").unwrap();
+        assert_eq!(data.as_deref(), Some(&b"content3"[..]));
+        assert!(handler.read_entry(&zip_path, "missing.txt").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_zip_time_roundtrip() {
+        let dt = DateTime::from_date_and_time(2021, 2, 28, 23, 59, 58).unwrap();
+        let t = zip_time_to_system(dt).unwrap();
+        // 2021-02-28T23:59:58Z
+        assert_eq!(t, UNIX_EPOCH + Duration::from_secs(1614556798));
+        assert_eq!(system_time_to_zip(t), Some(dt));
+    }
+
+    #[test]
+    fn test_extract_and_create_preserve_mtime() {
+        let temp = TempDir::new().unwrap();
+        let archive_path = temp.path().join("dated.zip");
+
+        // 构造一个条目时间为 2020-05-17 12:34:56 的压缩包
+        let entry_time = DateTime::from_date_and_time(2020, 5, 17, 12, 34, 56).unwrap();
+        {
+            let mut zip = ZipWriter::new(File::create(&archive_path).unwrap());
+            let opts = FullFileOptions::default().last_modified_time(entry_time);
+            zip.start_file("dated.txt", opts).unwrap();
+            io::Write::write_all(&mut zip, b"dated").unwrap();
+            zip.finish().unwrap();
+        }
+
+        let handler = ZipHandler::new();
+        let extract_dir = temp.path().join("extracted");
+        handler.extract(&archive_path, &extract_dir).unwrap();
+
+        let mtime = fs::metadata(extract_dir.join("dated.txt")).unwrap().modified().unwrap();
+        assert_eq!(Some(mtime), zip_time_to_system(entry_time), "解压后的文件时间应与条目时间一致");
+
+        // 重新打包后条目时间保持不变
+        let repacked = temp.path().join("repacked.zip");
+        handler.create(&extract_dir, &repacked).unwrap();
+        let mut archive = ZipArchive::new(File::open(&repacked).unwrap()).unwrap();
+        assert_eq!(archive.by_name("dated.txt").unwrap().last_modified(), Some(entry_time));
+    }
+
     #[test]
     fn test_create_and_extract() {
         let temp_source = TempDir::new().unwrap();
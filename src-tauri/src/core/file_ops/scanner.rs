@@ -1,23 +1,35 @@
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use walkdir::WalkDir;
 use crate::models::ImageFile;
 
 /// Recursive file scanner for finding supported images
 ///
-/// Scans directories recursively and filters for PNG/JPEG/JPG files.
+/// Scans directories recursively and filters for PNG/JPEG/JPG files and the
+/// lossless raster formats BMP/TGA/PPM.
 /// Maintains relative paths for preserving directory hierarchy.
+#[derive(Clone)]
 pub struct FileScanner {
     supported_extensions: Vec<&'static str>,
 }
 
 impl FileScanner {
-    /// Create a new file scanner with default supported formats (PNG, JPEG, JPG)
+    /// Create a new file scanner with default supported formats (PNG, JPEG, JPG, BMP, TGA, PPM)
+    ///
+    /// JPEG is lossy and only copied (or given a metadata watermark) by the batch
+    /// processor; every other default format carries the blind watermark.
     pub fn new() -> Self {
         Self {
-            supported_extensions: vec!["png", "jpg", "jpeg"],
+            supported_extensions: vec!["png", "jpg", "jpeg", "bmp", "tga", "ppm"],
         }
     }
 
+    /// Process-wide scanner with the default formats, built on first use
+    pub fn shared() -> &'static FileScanner {
+        static SHARED: OnceLock<FileScanner> = OnceLock::new();
+        SHARED.get_or_init(FileScanner::new)
+    }
+
     /// Create a file scanner with custom supported extensions
     pub fn with_extensions(extensions: Vec<&'static str>) -> Self {
         Self {
@@ -182,6 +194,21 @@ impl FileScanner {
         self.scan_files_by_extension(root_path, "vap")
     }
 
+    /// 扫描目录中的所有 SVG 文件（.svg 扩展名，XML 矢量图）
+    pub fn scan_svg_files(&self, root_path: &Path) -> Result<Vec<(PathBuf, PathBuf)>, std::io::Error> {
+        self.scan_files_by_extension(root_path, "svg")
+    }
+
+    /// 扫描目录中的所有 TOML 配置文件（.toml 扩展名）
+    pub fn scan_toml_files(&self, root_path: &Path) -> Result<Vec<(PathBuf, PathBuf)>, std::io::Error> {
+        self.scan_files_by_extension(root_path, "toml")
+    }
+
+    /// 扫描目录中的所有 INI 配置文件（.ini 扩展名）
+    pub fn scan_ini_files(&self, root_path: &Path) -> Result<Vec<(PathBuf, PathBuf)>, std::io::Error> {
+        self.scan_files_by_extension(root_path, "ini")
+    }
+
     /// 扫描目录中的所有 CSLIST 文件（.cslist 扩展名，VaM 布料模拟列表，纯文本）
     pub fn scan_cslist_files(&self, root_path: &Path) -> Result<Vec<(PathBuf, PathBuf)>, std::io::Error> {
         self.scan_files_by_extension(root_path, "cslist")
@@ -216,6 +243,9 @@ mod tests {
         fs::write(base.join("images/photo.png"), b"photo").unwrap();
         fs::write(base.join("images/photos/vacation.jpg"), b"vacation").unwrap();
         fs::write(base.join("images/screenshots/screen.PNG"), b"screen").unwrap();
+        fs::write(base.join("images/skin.bmp"), b"bmp").unwrap();
+        fs::write(base.join("images/hair.TGA"), b"tga").unwrap();
+        fs::write(base.join("images/normal.ppm"), b"ppm").unwrap();
 
         // Create non-image files (should be ignored)
         fs::write(base.join("readme.txt"), b"text file").unwrap();
@@ -232,8 +262,8 @@ mod tests {
 
         let images = scanner.scan(temp_dir.path()).unwrap();
 
-        // Should find 6 image files
-        assert_eq!(images.len(), 6);
+        // Should find 9 image files (including BMP / TGA / PPM)
+        assert_eq!(images.len(), 9);
     }
 
     #[test]
@@ -303,7 +333,7 @@ mod tests {
         let scanner = FileScanner::new();
 
         let count = scanner.count_images(temp_dir.path()).unwrap();
-        assert_eq!(count, 6);
+        assert_eq!(count, 9);
     }
 
     #[test]
@@ -361,9 +391,12 @@ mod tests {
         let scanner = FileScanner::new();
         let extensions = scanner.supported_extensions();
 
-        assert_eq!(extensions.len(), 3);
+        assert_eq!(extensions.len(), 6);
         assert!(extensions.contains(&"png"));
         assert!(extensions.contains(&"jpg"));
         assert!(extensions.contains(&"jpeg"));
+        assert!(extensions.contains(&"bmp"));
+        assert!(extensions.contains(&"tga"));
+        assert!(extensions.contains(&"ppm"));
     }
 }
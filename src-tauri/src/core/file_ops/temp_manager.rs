@@ -1,8 +1,88 @@
 use tempfile::TempDir;
 use std::path::{Path, PathBuf};
 use std::fs;
+use walkdir::WalkDir;
 use crate::models::BlindMarkError;
 
+/// Per-entry overhead of an archive (local header + central directory record), in bytes
+const ARCHIVE_ENTRY_OVERHEAD: u64 = 128;
+
+/// Extensions whose content is already compressed and stores at ~100%
+const COMPRESSED_EXTENSIONS: [&str; 14] = [
+    "png", "jpg", "jpeg", "webp", "gif", "mp3", "ogg", "wav", "mp4", "webm", "zip", "7z", "rar", "var",
+];
+
+/// Extensions of text formats, which compress well
+const TEXT_EXTENSIONS: [&str; 13] = [
+    "json", "vaj", "vmi", "vam", "vap", "svg", "txt", "cs", "xml", "vab", "md", "toml", "ini",
+];
+
+/// Expected compressed/original size ratio for a file, by extension
+fn compression_ratio(path: &Path) -> f64 {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    if COMPRESSED_EXTENSIONS.contains(&ext.as_str()) {
+        1.0
+    } else if TEXT_EXTENSIONS.contains(&ext.as_str()) {
+        0.3
+    } else {
+        0.8
+    }
+}
+
+/// Estimate the size of the archive that packaging `processed_dir` will produce
+///
+/// Sums every file's size scaled by a per-type compression ratio (already
+/// compressed media ~1.0, text ~0.3, other ~0.8) plus a fixed per-entry
+/// overhead. Deliberately errs on the high side: it is meant for a disk space
+/// check before packaging, not for display.
+pub fn estimate_output_size(processed_dir: &Path) -> Result<u64, BlindMarkError> {
+    let mut estimate = 0u64;
+    for entry in WalkDir::new(processed_dir).min_depth(1) {
+        let entry = entry.map_err(|e| BlindMarkError::Archive(
+            format!("Failed to read directory entry: {}", e)
+        ))?;
+        let metadata = entry.metadata().map_err(|e| BlindMarkError::Archive(
+            format!("Failed to get metadata: {}", e)
+        ))?;
+        estimate += ARCHIVE_ENTRY_OVERHEAD;
+        if metadata.is_file() {
+            estimate += (metadata.len() as f64 * compression_ratio(entry.path())).ceil() as u64;
+        }
+    }
+    Ok(estimate)
+}
+
+/// Check that `dir` has room for `required` bytes
+///
+/// # Returns
+/// * `Some(warning)` if the free space is smaller than `required`, `None` otherwise
+///   (including when the free space cannot be determined)
+pub fn check_disk_space(dir: &Path, required: u64) -> Option<String> {
+    let available = fs2::available_space(dir).ok()?;
+    (available < required).then(|| format!(
+        "insufficient disk space in {}: about {} bytes needed, {} bytes available",
+        dir.display(), required, available
+    ))
+}
+
+/// Probe whether a directory is writable by creating and deleting a temp file in it
+///
+/// Used before any heavy work (extraction, embedding) so that a read-only output
+/// location fails fast with a clear message instead of deep inside a copy.
+pub fn ensure_writable(dir: &Path) -> Result<(), BlindMarkError> {
+    tempfile::Builder::new()
+        .prefix(".blindmark_probe_")
+        .tempfile_in(dir)
+        .map(drop)
+        .map_err(|e| BlindMarkError::InvalidConfig(
+            format!("output directory not writable: {}: {}", dir.display(), e)
+        ))
+}
+
 /// Temporary workspace manager for archive processing
 ///
 /// Creates a disk-based temporary workspace with subdirectories:
@@ -25,6 +105,9 @@ impl TempWorkspace {
     /// # Returns
     /// * `TempWorkspace` with extracted/ and processed/ subdirectories
     pub fn new(archive_name: &str) -> Result<Self, BlindMarkError> {
+        // Fail early with a clear message if the system temp directory is read-only
+        ensure_writable(&std::env::temp_dir())?;
+
         // Create temporary directory with prefix
         let temp_dir = tempfile::Builder::new()
             .prefix(&format!("blindmark_{}_", archive_name))
@@ -136,6 +219,11 @@ impl TempWorkspace {
         self.dir_size(&self.processed_path)
     }
 
+    /// Estimated size of the archive packaged from the processed directory (see `estimate_output_size`)
+    pub fn estimated_output_size(&self) -> Result<u64, BlindMarkError> {
+        estimate_output_size(&self.processed_path)
+    }
+
     /// Calculate total size of all files in a directory recursively
     fn dir_size(&self, path: &Path) -> Result<u64, BlindMarkError> {
         let mut total_size = 0u64;
@@ -275,6 +363,68 @@ mod tests {
         assert_eq!(total_size, 15);
     }
 
+    #[test]
+    fn test_estimate_output_size() {
+        let workspace = TempWorkspace::new("test_estimate").unwrap();
+        workspace.write_processed(Path::new("textures/skin.png"), &[0u8; 1000]).unwrap();
+        workspace.write_processed(Path::new("meta.json"), &[b' '; 1000]).unwrap();
+        workspace.write_processed(Path::new("data.bin"), &[0u8; 1000]).unwrap();
+        assert_eq!(workspace.processed_size().unwrap(), 3000);
+
+        // png 1000 + json 300 + bin 800，加上 3 个文件与 1 个目录的条目开销
+        let expected = 1000 + 300 + 800 + 4 * ARCHIVE_ENTRY_OVERHEAD;
+        assert_eq!(workspace.estimated_output_size().unwrap(), expected);
+        assert_eq!(estimate_output_size(workspace.processed_path()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_check_disk_space() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(check_disk_space(dir.path(), 1).is_none());
+        let warning = check_disk_space(dir.path(), u64::MAX).expect("u64::MAX 字节不可能有足够空间");
+        assert!(warning.contains("insufficient disk space"), "{}", warning);
+    }
+
+    #[test]
+    fn test_ensure_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(ensure_writable(dir.path()).is_ok());
+        // 探测文件应被删除
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_ensure_writable_not_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        fs::write(&file, b"x").unwrap();
+
+        let result = ensure_writable(&file);
+        match result {
+            Err(BlindMarkError::InvalidConfig(msg)) => assert!(msg.contains("not writable")),
+            other => panic!("Expected InvalidConfig, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ensure_writable_read_only_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ro = dir.path().join("readonly");
+        fs::create_dir(&ro).unwrap();
+        fs::set_permissions(&ro, fs::Permissions::from_mode(0o555)).unwrap();
+
+        // root 不受权限位限制，此时无法模拟只读目录
+        let bypasses_permissions = fs::write(ro.join("probe"), b"x").is_ok();
+        if !bypasses_permissions {
+            assert!(matches!(ensure_writable(&ro), Err(BlindMarkError::InvalidConfig(_))));
+        }
+
+        fs::set_permissions(&ro, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
     #[test]
     fn test_cleanup_on_drop() {
         let base_path;
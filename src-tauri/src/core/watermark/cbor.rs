@@ -0,0 +1,227 @@
+use serde_json::{Map, Number, Value};
+use crate::models::BlindMarkError;
+
+// ─── 结构化水印 payload 的最小 CBOR 编解码（RFC 8949 子集）────────────────────
+//
+// 仅覆盖 JSON 数据模型：无符号 / 负整数、f64、文本串、数组、以文本为键的 map、
+// true / false / null。不支持字节串、标签、半精度浮点与不定长编码。
+// payload 上限仅 64 字节，不值得为此引入完整的 CBOR 依赖。
+
+/// CBOR 主类型（高 3 位）
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_SIMPLE: u8 = 7;
+
+/// 主类型 7 下的简单值与浮点数标记
+const SIMPLE_FALSE: u8 = 20;
+const SIMPLE_TRUE: u8 = 21;
+const SIMPLE_NULL: u8 = 22;
+const FLOAT_32: u8 = 26;
+const FLOAT_64: u8 = 27;
+
+/// 解码时允许的最大嵌套深度
+const MAX_DEPTH: usize = 16;
+
+/// 将 JSON 值编码为 CBOR 字节（整数与长度均取最短编码，map 保持键的原有顺序）
+pub fn to_cbor(value: &Value) -> Result<Vec<u8>, BlindMarkError> {
+    let mut out = Vec::new();
+    write_value(&mut out, value)?;
+    Ok(out)
+}
+
+/// 将 CBOR 字节解码为 JSON 值；不支持的类型、截断或多余的尾部字节均报错
+pub fn from_cbor(bytes: &[u8]) -> Result<Value, BlindMarkError> {
+    let mut decoder = Decoder { bytes, pos: 0 };
+    let value = decoder.read_value(0)?;
+    if decoder.pos != bytes.len() {
+        return Err(invalid("CBOR 数据末尾有多余字节"));
+    }
+    Ok(value)
+}
+
+fn invalid(message: &str) -> BlindMarkError {
+    BlindMarkError::ExtractionFailed(format!("结构化水印解析失败: {}", message))
+}
+
+fn write_head(out: &mut Vec<u8>, major: u8, n: u64) {
+    let major = major << 5;
+    match n {
+        0..=23 => out.push(major | n as u8),
+        24..=0xFF => out.extend([major | 24, n as u8]),
+        0x100..=0xFFFF => {
+            out.push(major | 25);
+            out.extend((n as u16).to_be_bytes());
+        }
+        0x1_0000..=0xFFFF_FFFF => {
+            out.push(major | 26);
+            out.extend((n as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend(n.to_be_bytes());
+        }
+    }
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) -> Result<(), BlindMarkError> {
+    match value {
+        Value::Null => out.push((MAJOR_SIMPLE << 5) | SIMPLE_NULL),
+        Value::Bool(b) => out.push((MAJOR_SIMPLE << 5) | if *b { SIMPLE_TRUE } else { SIMPLE_FALSE }),
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                write_head(out, MAJOR_UNSIGNED, u);
+            } else if let Some(i) = n.as_i64() {
+                // 负整数 -1 - n 编码为 n
+                write_head(out, MAJOR_NEGATIVE, !(i as u64));
+            } else {
+                let f = n.as_f64().ok_or_else(|| BlindMarkError::InvalidConfig(format!("无法编码的数值: {}", n)))?;
+                out.push((MAJOR_SIMPLE << 5) | FLOAT_64);
+                out.extend(f.to_be_bytes());
+            }
+        }
+        Value::String(s) => {
+            write_head(out, MAJOR_TEXT, s.len() as u64);
+            out.extend(s.as_bytes());
+        }
+        Value::Array(items) => {
+            write_head(out, MAJOR_ARRAY, items.len() as u64);
+            for item in items {
+                write_value(out, item)?;
+            }
+        }
+        Value::Object(map) => {
+            write_head(out, MAJOR_MAP, map.len() as u64);
+            for (key, item) in map {
+                write_head(out, MAJOR_TEXT, key.len() as u64);
+                out.extend(key.as_bytes());
+                write_value(out, item)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], BlindMarkError> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| invalid("数据被截断"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    /// 读取头部，返回 `(主类型, 附加信息, 参数值)`；浮点数的参数值为原始位模式
+    fn read_head(&mut self) -> Result<(u8, u8, u64), BlindMarkError> {
+        let first = self.take(1)?[0];
+        let (major, info) = (first >> 5, first & 0x1F);
+        let n = match info {
+            0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64,
+            27 => u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            _ => return Err(invalid("不支持不定长或保留的编码")),
+        };
+        Ok((major, info, n))
+    }
+
+    /// 读取长度参数，长度不可能超过剩余字节数（每项至少 1 字节）
+    fn read_len(&mut self, n: u64) -> Result<usize, BlindMarkError> {
+        usize::try_from(n)
+            .ok()
+            .filter(|&len| len <= self.bytes.len() - self.pos)
+            .ok_or_else(|| invalid("长度超出数据范围"))
+    }
+
+    fn read_text(&mut self, n: u64) -> Result<String, BlindMarkError> {
+        let len = self.read_len(n)?;
+        let raw = self.take(len)?;
+        String::from_utf8(raw.to_vec()).map_err(|_| invalid("文本不是合法的 UTF-8"))
+    }
+
+    fn read_value(&mut self, depth: usize) -> Result<Value, BlindMarkError> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("嵌套层数过深"));
+        }
+        let (major, info, n) = self.read_head()?;
+        Ok(match major {
+            MAJOR_UNSIGNED => Value::from(n),
+            MAJOR_NEGATIVE => {
+                let i = i64::try_from(n).map_err(|_| invalid("负整数超出范围"))?;
+                Value::from(-1 - i)
+            }
+            MAJOR_TEXT => Value::String(self.read_text(n)?),
+            MAJOR_ARRAY => {
+                let len = self.read_len(n)?;
+                let mut items = Vec::with_capacity(len);
+                for _ in 0..len {
+                    items.push(self.read_value(depth + 1)?);
+                }
+                Value::Array(items)
+            }
+            MAJOR_MAP => {
+                let len = self.read_len(n)?;
+                let mut map = Map::new();
+                for _ in 0..len {
+                    let (key_major, _, key_n) = self.read_head()?;
+                    if key_major != MAJOR_TEXT {
+                        return Err(invalid("map 的键必须是文本"));
+                    }
+                    let key = self.read_text(key_n)?;
+                    map.insert(key, self.read_value(depth + 1)?);
+                }
+                Value::Object(map)
+            }
+            MAJOR_SIMPLE => match info {
+                SIMPLE_FALSE => Value::Bool(false),
+                SIMPLE_TRUE => Value::Bool(true),
+                SIMPLE_NULL => Value::Null,
+                FLOAT_32 => float(f32::from_bits(n as u32) as f64)?,
+                FLOAT_64 => float(f64::from_bits(n))?,
+                _ => return Err(invalid("不支持的简单值")),
+            },
+            _ => return Err(invalid("不支持字节串或标签")),
+        })
+    }
+}
+
+fn float(f: f64) -> Result<Value, BlindMarkError> {
+    Number::from_f64(f).map(Value::Number).ok_or_else(|| invalid("浮点数不是有限值"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cbor_roundtrip_and_known_encoding() {
+        // RFC 8949 附录 A 的示例
+        assert_eq!(to_cbor(&json!(1000)).unwrap(), [0x19, 0x03, 0xE8]);
+        assert_eq!(to_cbor(&json!(-1000)).unwrap(), [0x39, 0x03, 0xE7]);
+        assert_eq!(to_cbor(&json!({"a": 1, "b": [2, 3]})).unwrap(),
+            [0xA2, 0x61, 0x61, 0x01, 0x61, 0x62, 0x82, 0x02, 0x03]);
+
+        let value = json!({"buyer": "张三", "order": 1234567890123u64, "neg": -5, "ok": true, "x": null, "f": 1.5});
+        assert_eq!(from_cbor(&to_cbor(&value).unwrap()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_cbor_rejects_malformed_input() {
+        assert!(from_cbor(&[]).is_err());
+        assert!(from_cbor(&[0x62, 0x61]).is_err(), "truncated text");
+        assert!(from_cbor(&[0x01, 0x02]).is_err(), "trailing bytes");
+        assert!(from_cbor(&[0x9F]).is_err(), "indefinite length");
+        assert!(from_cbor(&[0xA1, 0x01, 0x02]).is_err(), "non-text key");
+        assert!(from_cbor(&[0x9B, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]).is_err(), "huge length");
+        assert!(from_cbor(&[0x81; 40]).is_err(), "too deep");
+    }
+}
@@ -0,0 +1,331 @@
+use crate::models::BlindMarkError;
+use crate::core::watermark::json_marker::JsonWatermarker;
+
+/// UTF-8 BOM 字节序列（0xEF 0xBB 0xBF）
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// 水印所在的节名（`[blindmark]`）
+pub const CONFIG_WATERMARK_SECTION: &str = "blindmark";
+
+/// 水印键名（`[blindmark]` 节下的 `watermark = "<编码值>"`）
+pub const CONFIG_WATERMARK_KEY: &str = "watermark";
+
+/// TOML 水印注入器
+///
+/// 在 `[blindmark]` 表中写入 `watermark = "<编码值>"`，编码方式与 JSON 水印一致
+/// （md5 / plaintext / aes / hmac，见 `JsonWatermarker::encode_watermark`）。
+/// 基于 `toml_edit` 改写，其余表、注释与格式原样保留。
+pub struct TomlWatermarker;
+
+/// INI 水印注入器
+///
+/// 在 `[blindmark]` 节中写入 `watermark = "<编码值>"`（编码同 `TomlWatermarker`）。
+/// INI 没有统一规范，按行改写：只改动水印节，其余行（含注释、空行与换行风格）逐字节保留。
+pub struct IniWatermarker;
+
+impl TomlWatermarker {
+    /// 向 TOML 内容中注入水印（已有水印时替换）
+    ///
+    /// # 参数
+    /// * `content`        - 原始 TOML 文本
+    /// * `watermark_text` - 要嵌入的明文
+    /// * `mode`           - 编码模式（"md5" / "plaintext" / "aes" / "hmac"）
+    /// * `aes_key`        - AES 模式下的用户密钥
+    pub fn embed(
+        content: &str,
+        watermark_text: &str,
+        mode: &str,
+        aes_key: Option<&str>,
+    ) -> Result<String, BlindMarkError> {
+        let mut doc = parse_toml(content)?;
+        let encoded = JsonWatermarker::encode_watermark(watermark_text, mode, aes_key)?;
+        let section = doc
+            .entry(CONFIG_WATERMARK_SECTION)
+            .or_insert(toml_edit::table())
+            .as_table_like_mut()
+            .ok_or_else(|| BlindMarkError::ImageProcessing(
+                format!("TOML 中的 `{}` 不是表，无法写入水印", CONFIG_WATERMARK_SECTION)
+            ))?;
+        section.insert(CONFIG_WATERMARK_KEY, toml_edit::value(encoded));
+        Ok(doc.to_string())
+    }
+
+    /// 从 TOML 内容中提取水印的存储值（未解码，可交给 `JsonWatermarker::decode_watermark`）
+    pub fn extract(content: &str) -> Result<String, BlindMarkError> {
+        let doc = parse_toml(content)?;
+        doc.get(CONFIG_WATERMARK_SECTION)
+            .and_then(|item| item.as_table_like())
+            .and_then(|table| table.get(CONFIG_WATERMARK_KEY))
+            .and_then(|item| item.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| BlindMarkError::ExtractionFailed("未在 TOML 中找到水印".to_string()))
+    }
+
+    /// 检查 TOML 内容是否已包含水印
+    pub fn has_watermark(content: &str) -> bool {
+        Self::extract(content).is_ok()
+    }
+
+    /// 嵌入水印到 TOML 字节（仅支持 UTF-8，输入带 BOM 时输出同样带 BOM）
+    pub fn embed_bytes(
+        bytes: &[u8],
+        watermark_text: &str,
+        mode: &str,
+        aes_key: Option<&str>,
+    ) -> Result<Vec<u8>, BlindMarkError> {
+        map_utf8(bytes, "TOML", |content| Self::embed(content, watermark_text, mode, aes_key))
+    }
+
+    /// 从 TOML 字节中提取水印存储值
+    pub fn extract_bytes(bytes: &[u8]) -> Result<String, BlindMarkError> {
+        let (_, content) = split_utf8(bytes, "TOML")?;
+        Self::extract(content)
+    }
+
+    /// 扫描 TOML 内容中的水印并解码
+    ///
+    /// 返回 `Some((显示值, 模式名称, 是否已成功解密))`，无水印时返回 `None`。
+    pub fn scan_watermark_value(content: &str, aes_key: Option<&str>) -> Option<(String, String, bool)> {
+        let raw = Self::extract(content).ok()?;
+        Some(JsonWatermarker::decode_watermark(&raw, aes_key))
+    }
+}
+
+impl IniWatermarker {
+    /// 向 INI 内容中注入水印（已有水印时替换）
+    ///
+    /// 已有 `[blindmark]` 节时水印写在节标题之后，节内旧的水印键被删除；
+    /// 否则在文件末尾追加该节。参数同 `TomlWatermarker::embed`。
+    pub fn embed(
+        content: &str,
+        watermark_text: &str,
+        mode: &str,
+        aes_key: Option<&str>,
+    ) -> Result<String, BlindMarkError> {
+        let encoded = JsonWatermarker::encode_watermark(watermark_text, mode, aes_key)?;
+        let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+        let entry = format!("{} = {}{}", CONFIG_WATERMARK_KEY, quote_ini(&encoded), newline);
+
+        let mut out = String::with_capacity(content.len() + entry.len() + 16);
+        let mut in_section = false;
+        let mut written = false;
+        for line in content.split_inclusive('\n') {
+            if let Some(name) = ini_section_name(line) {
+                in_section = name.eq_ignore_ascii_case(CONFIG_WATERMARK_SECTION);
+                out.push_str(line);
+                if in_section && !written {
+                    if !line.ends_with('\n') {
+                        out.push_str(newline);
+                    }
+                    out.push_str(&entry);
+                    written = true;
+                }
+                continue;
+            }
+            if in_section && ini_entry(line).is_some_and(|(key, _)| key.eq_ignore_ascii_case(CONFIG_WATERMARK_KEY)) {
+                continue;
+            }
+            out.push_str(line);
+        }
+
+        if !written {
+            if !out.is_empty() {
+                if !out.ends_with('\n') {
+                    out.push_str(newline);
+                }
+                out.push_str(newline);
+            }
+            out.push_str(&format!("[{}]{}", CONFIG_WATERMARK_SECTION, newline));
+            out.push_str(&entry);
+        }
+        Ok(out)
+    }
+
+    /// 从 INI 内容中提取水印的存储值（未解码，可交给 `JsonWatermarker::decode_watermark`）
+    pub fn extract(content: &str) -> Result<String, BlindMarkError> {
+        let mut in_section = false;
+        for line in content.lines() {
+            if let Some(name) = ini_section_name(line) {
+                in_section = name.eq_ignore_ascii_case(CONFIG_WATERMARK_SECTION);
+                continue;
+            }
+            if !in_section {
+                continue;
+            }
+            if let Some((key, value)) = ini_entry(line) {
+                if key.eq_ignore_ascii_case(CONFIG_WATERMARK_KEY) {
+                    return Ok(unquote_ini(value));
+                }
+            }
+        }
+        Err(BlindMarkError::ExtractionFailed("未在 INI 中找到水印".to_string()))
+    }
+
+    /// 检查 INI 内容是否已包含水印
+    pub fn has_watermark(content: &str) -> bool {
+        Self::extract(content).is_ok()
+    }
+
+    /// 嵌入水印到 INI 字节（仅支持 UTF-8，输入带 BOM 时输出同样带 BOM）
+    pub fn embed_bytes(
+        bytes: &[u8],
+        watermark_text: &str,
+        mode: &str,
+        aes_key: Option<&str>,
+    ) -> Result<Vec<u8>, BlindMarkError> {
+        map_utf8(bytes, "INI", |content| Self::embed(content, watermark_text, mode, aes_key))
+    }
+
+    /// 从 INI 字节中提取水印存储值
+    pub fn extract_bytes(bytes: &[u8]) -> Result<String, BlindMarkError> {
+        let (_, content) = split_utf8(bytes, "INI")?;
+        Self::extract(content)
+    }
+
+    /// 扫描 INI 内容中的水印并解码，返回值同 `TomlWatermarker::scan_watermark_value`
+    pub fn scan_watermark_value(content: &str, aes_key: Option<&str>) -> Option<(String, String, bool)> {
+        let raw = Self::extract(content).ok()?;
+        Some(JsonWatermarker::decode_watermark(&raw, aes_key))
+    }
+}
+
+// ─── 私有工具函数 ──────────────────────────────────────────────────────────────
+
+fn parse_toml(content: &str) -> Result<toml_edit::Document, BlindMarkError> {
+    content
+        .parse::<toml_edit::Document>()
+        .map_err(|e| BlindMarkError::ImageProcessing(format!("TOML 解析失败: {}", e)))
+}
+
+/// 拆分 UTF-8 BOM 与正文；非 UTF-8 内容返回错误
+fn split_utf8<'a>(bytes: &'a [u8], label: &str) -> Result<(&'a [u8], &'a str), BlindMarkError> {
+    let (bom, body) = match bytes.strip_prefix(UTF8_BOM) {
+        Some(body) => (UTF8_BOM, body),
+        None => (&bytes[..0], bytes),
+    };
+    let content = std::str::from_utf8(body).map_err(|_| {
+        BlindMarkError::ImageProcessing(format!("{} 文件须为 UTF-8 编码", label))
+    })?;
+    Ok((bom, content))
+}
+
+/// 对 UTF-8 正文应用 `f`，保留原有的 BOM
+fn map_utf8(
+    bytes: &[u8],
+    label: &str,
+    f: impl FnOnce(&str) -> Result<String, BlindMarkError>,
+) -> Result<Vec<u8>, BlindMarkError> {
+    let (bom, content) = split_utf8(bytes, label)?;
+    let result = f(content)?;
+    let mut out = Vec::with_capacity(bom.len() + result.len());
+    out.extend_from_slice(bom);
+    out.extend_from_slice(result.as_bytes());
+    Ok(out)
+}
+
+/// INI 节标题 `[name]` 的节名；其他行返回 `None`
+fn ini_section_name(line: &str) -> Option<&str> {
+    line.trim().strip_prefix('[')?.strip_suffix(']').map(str::trim)
+}
+
+/// INI 键值行 `key = value`（或 `key: value`）；注释行与其他行返回 `None`
+fn ini_entry(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.starts_with(';') || line.starts_with('#') {
+        return None;
+    }
+    let split = line.find(['=', ':'])?;
+    Some((line[..split].trim(), line[split + 1..].trim()))
+}
+
+/// 将值写成带反斜杠转义的双引号字符串，避免 `;` / `#` / 换行被解析器截断
+fn quote_ini(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `quote_ini` 的逆操作；未加引号的值原样返回
+fn unquote_ini(value: &str) -> String {
+    let Some(inner) = value.strip_prefix('"') else {
+        return value.to_string();
+    };
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some(other) => out.push(other),
+                None => break,
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = "# 资源包配置\ntitle = \"pack\" # 行尾注释\n\n[author]\nname = \"someone\"\n";
+
+    #[test]
+    fn test_toml_roundtrip_preserves_content() {
+        for mode in ["plaintext", "md5", "aes"] {
+            let marked = TomlWatermarker::embed(TOML, "alice", mode, Some("secret")).unwrap();
+            assert!(marked.starts_with(TOML), "原有内容与注释应保留: {}", marked);
+            assert!(marked.contains("[blindmark]"));
+
+            let (value, found_mode, decrypted) = TomlWatermarker::scan_watermark_value(&marked, Some("secret")).unwrap();
+            assert_eq!(found_mode, mode);
+            assert!(decrypted);
+            if mode != "md5" {
+                assert_eq!(value, "alice");
+            }
+        }
+
+        // 再次嵌入替换旧水印，不重复写入
+        let once = TomlWatermarker::embed(TOML, "alice", "plaintext", None).unwrap();
+        let twice = TomlWatermarker::embed(&once, "bob", "plaintext", None).unwrap();
+        assert_eq!(twice.matches("watermark =").count(), 1);
+        assert_eq!(TomlWatermarker::scan_watermark_value(&twice, None).unwrap().0, "bob");
+
+        // BOM 保留；无水印与非法 TOML 报错
+        let bom = [UTF8_BOM, TOML.as_bytes()].concat();
+        let marked = TomlWatermarker::embed_bytes(&bom, "alice", "plaintext", None).unwrap();
+        assert!(marked.starts_with(UTF8_BOM));
+        assert_eq!(TomlWatermarker::extract_bytes(&marked).unwrap(), "txt:alice");
+        assert!(!TomlWatermarker::has_watermark(TOML));
+        assert!(TomlWatermarker::embed("blindmark = 1", "alice", "plaintext", None).is_err());
+        assert!(TomlWatermarker::embed("[broken", "alice", "plaintext", None).is_err());
+    }
+
+    #[test]
+    fn test_ini_roundtrip_preserves_content() {
+        let ini = "; settings\r\n[General]\r\nname=pack\r\n";
+        let marked = IniWatermarker::embed(ini, "a;b \"c\"", "plaintext", None).unwrap();
+        assert!(marked.starts_with(ini));
+        assert!(marked.ends_with("[blindmark]\r\nwatermark = \"txt:a;b \\\"c\\\"\"\r\n"), "{:?}", marked);
+        assert_eq!(IniWatermarker::scan_watermark_value(&marked, None).unwrap().0, "a;b \"c\"");
+
+        // 已有节：水印写在节标题后，旧水印键被删除，节内其他键保留
+        let existing = "[BlindMark]\nwatermark = old\nother = 1\n[next]\nwatermark = keep\n";
+        let marked = IniWatermarker::embed(existing, "alice", "plaintext", None).unwrap();
+        assert_eq!(marked, "[BlindMark]\nwatermark = \"txt:alice\"\nother = 1\n[next]\nwatermark = keep\n");
+        assert!(!IniWatermarker::has_watermark("[next]\nwatermark = keep\n"));
+    }
+}
@@ -11,11 +11,31 @@ pub const D1: f64 = 36.0;
 pub const D2: f64 = 20.0;
 
 /// 4×4 分块大小（与 Python blind_watermark 一致）
-const BLOCK_H: usize = 4;
-const BLOCK_W: usize = 4;
-
-/// 默认嵌入密码（种子）
-const PASSWORD: u64 = 1;
+pub const BLOCK_H: usize = 4;
+pub const BLOCK_W: usize = 4;
+
+/// 4×4 SVD 分解结果 (U, S, Vt)，矩阵按行展平
+type Svd4x4 = ([f64; 16], [f64; 4], [f64; 16]);
+
+/// SVD 最大迭代次数；4×4 块通常几十次内收敛，超出视为不收敛
+const SVD_MAX_ITERATIONS: usize = 1000;
+
+/// 默认嵌入密码（种子），与 Python blind_watermark 默认值一致
+pub const DEFAULT_PASSWORD: u64 = 1;
+
+/// 块扫描顺序：水印比特按此顺序依次（循环）分配到各 4×4 块
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanOrder {
+    /// 行优先（默认，与 Python blind_watermark 一致）
+    #[default]
+    RowMajor,
+    /// Hilbert 空间填充曲线顺序
+    ///
+    /// 行优先顺序下块网格宽度与水印长度对齐时，同一位的各副本会落在同一列，竖条状的
+    /// 局部修改会同时破坏其多数副本；Hilbert 顺序打散了这种对齐。对方块状修改没有改善。
+    Hilbert,
+}
 
 /// DCT + SVD + QIM 水印处理器
 ///
@@ -35,11 +55,91 @@ const PASSWORD: u64 = 1;
 /// 相同预处理后，读取 S[0] 和 S[1]：
 /// `bit = (s % d > d/2) ? 1.0 : 0.0`
 /// 再对所有块的循环副本取平均，三通道求和后阈值判决。
-pub struct DCTProcessor;
+///
+/// ### 密码
+/// 打乱顺序由密码决定，图片中不存储任何密码信息；
+/// 提取时必须使用与嵌入相同的密码，否则无法还原水印。
+/// 注意：大面积平滑区域的 4×4 块近似秩 1，打乱后奇异值几乎不变，
+/// 此类图片上密码的隔离作用有限。
+///
+/// ### 静默边框
+/// 设置 `margin` 后，与 LL 子带边缘距离不足 `margin` 像素的块既不嵌入也不提取，
+/// 水印比特按剩余块的顺序循环分配。图片中不记录该值，提取时须使用相同配置。
+///
+/// ### 选块间隔
+/// 设置 `block_stride` 后，在上述块中每隔 `block_stride` 个取一个参与嵌入/提取，
+/// 其余块保持原样；选中的块仍均匀分布在整张图片上。
+///
+/// ### 扫描顺序
+/// 设置 `scan_order` 后，参与嵌入/提取的块按该顺序排列（见 `ScanOrder`）；
+/// 块集合与各块的打乱顺序不变。图片中不记录该值，由文本水印头部区分（见 `WatermarkEncoder::text_to_bits_for_hilbert`）。
+///
+/// ### QIM 步长
+/// 默认 `[D1, D2]`。步长越大，奇异值可容忍的扰动越大（更鲁棒），对像素的修改也越明显；
+/// 图片中不记录该值，提取时须使用相同步长。文本水印的嵌入强度按倍数缩放步长（见 `scale_qim_steps`），
+/// 倍数档位记录在文本水印头部（见 `WatermarkEncoder::strength_level`）。
+#[derive(Clone, Copy)]
+pub struct DCTProcessor {
+    password: u64,
+    margin: usize,
+    block_stride: usize,
+    scan_order: ScanOrder,
+    qim_steps: [f64; 2],
+}
 
 impl DCTProcessor {
     pub fn new() -> Self {
-        Self
+        Self::with_password(DEFAULT_PASSWORD)
+    }
+
+    /// 使用自定义密码（打乱种子）创建处理器
+    pub fn with_password(password: u64) -> Self {
+        Self { password, margin: 0, block_stride: 1, scan_order: ScanOrder::RowMajor, qim_steps: [D1, D2] }
+    }
+
+    /// 设置 LL 子带上的静默边框宽度（像素），边框内的块不参与嵌入/提取
+    pub fn with_margin(mut self, margin: usize) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// 设置选块间隔：每隔 `stride` 个块取一个参与嵌入/提取（0 视为 1）
+    pub fn with_block_stride(mut self, stride: usize) -> Self {
+        self.block_stride = stride.max(1);
+        self
+    }
+
+    /// 设置块扫描顺序（默认行优先）
+    pub fn with_scan_order(mut self, order: ScanOrder) -> Self {
+        self.scan_order = order;
+        self
+    }
+
+    /// 设置主 / 次奇异值的 QIM 量化步长（默认 `[D1, D2]`）
+    pub fn with_qim_steps(mut self, d1: f64, d2: f64) -> Self {
+        self.qim_steps = [d1, d2];
+        self
+    }
+
+    /// 将主 / 次奇异值的 QIM 量化步长同时乘以 `factor`（嵌入强度对应的步长倍数）
+    pub fn scale_qim_steps(mut self, factor: f64) -> Self {
+        self.qim_steps = self.qim_steps.map(|d| d * factor);
+        self
+    }
+
+    /// 主 / 次奇异值的 QIM 量化步长
+    pub fn qim_steps(&self) -> [f64; 2] {
+        self.qim_steps
+    }
+
+    /// 打乱种子（密码）
+    pub fn password(&self) -> u64 {
+        self.password
+    }
+
+    /// LL 子带上的静默边框宽度（像素）
+    pub fn margin(&self) -> usize {
+        self.margin
     }
 
     // ─── 公开接口 ────────────────────────────────────────────────────────────
@@ -57,10 +157,8 @@ impl DCTProcessor {
         ll: &mut Array2<f64>,
         wm_bits: &[u8],
     ) -> Result<(), BlindMarkError> {
-        let (h, w) = ll.dim();
-        let blocks_h = h / BLOCK_H;
-        let blocks_w = w / BLOCK_W;
-        let block_num = blocks_h * blocks_w;
+        let blocks = self.active_blocks(ll.dim());
+        let block_num = blocks.len();
 
         if block_num < wm_bits.len() {
             return Err(BlindMarkError::ExtractionFailed(format!(
@@ -70,10 +168,8 @@ impl DCTProcessor {
             )));
         }
 
-        for block_idx in 0..block_num {
-            let bi = block_idx / blocks_w;
-            let bj = block_idx % blocks_w;
-            let bit = wm_bits[block_idx % wm_bits.len()];
+        for (k, &(block_idx, bi, bj)) in blocks.iter().enumerate() {
+            let bit = wm_bits[k % wm_bits.len()];
 
             // 读取块
             let block = Self::read_block(ll, bi, bj);
@@ -82,15 +178,17 @@ impl DCTProcessor {
             let dct_block = dct2d_block(block);
 
             // 打乱
-            let perm = generate_shuffler(PASSWORD, block_idx);
+            let perm = generate_shuffler(self.password, block_idx);
             let shuffled: [f64; 16] = std::array::from_fn(|i| dct_block[perm[i]]);
 
-            // SVD
-            let (u, mut s, vt) = svd_4x4(shuffled);
+            // SVD（不收敛的病态块原样保留，不嵌入）
+            let Ok((u, mut s, vt)) = svd_4x4(shuffled) else {
+                continue;
+            };
 
             // QIM 嵌入
-            s[0] = qim_encode(s[0], bit, D1);
-            s[1] = qim_encode(s[1], bit, D2);
+            s[0] = qim_encode(s[0], bit, self.qim_steps[0]);
+            s[1] = qim_encode(s[1], bit, self.qim_steps[1]);
 
             // 重建
             let modified = reconstruct_svd(&u, &s, &vt);
@@ -123,52 +221,131 @@ impl DCTProcessor {
         ll: &Array2<f64>,
         wm_size: usize,
     ) -> Result<Vec<f64>, BlindMarkError> {
-        let (h, w) = ll.dim();
-        let blocks_h = h / BLOCK_H;
-        let blocks_w = w / BLOCK_W;
-        let block_num = blocks_h * blocks_w;
+        Self::cyclic_average(&self.extract_block_softs(ll), wm_size)
+    }
 
-        if block_num < wm_size {
-            return Err(BlindMarkError::ExtractionFailed(format!(
-                "图片太小：{} 块 < {} 位水印",
-                block_num,
-                wm_size
-            )));
-        }
+    /// LL 子带可用于嵌入的 4×4 块数（即可嵌入的最大位数）
+    pub fn capacity(&self, ll_dim: (usize, usize)) -> usize {
+        self.active_blocks(ll_dim).len()
+    }
 
-        // 每块提取一个软判决值（与 Python extract_raw 对应）
-        let mut wm_block_bits = vec![0.0f64; block_num];
+    /// 逐块提取软判决值（按嵌入顺序），SVD 不收敛的块为 `None`
+    ///
+    /// 与 `cyclic_average` 组合即 `extract_watermark_blocks_soft`；分开调用时
+    /// 可对同一组块值按不同水印长度求平均，而无需重复 DCT / SVD。
+    pub fn extract_block_softs(&self, ll: &Array2<f64>) -> Vec<Option<f64>> {
+        self.block_softs(&self.extract_block_singulars(ll))
+    }
 
-        for block_idx in 0..block_num {
-            let bi = block_idx / blocks_w;
-            let bj = block_idx % blocks_w;
+    /// 逐块提取主 / 次奇异值 `[s0, s1]`（按嵌入顺序），SVD 不收敛的块为 `None`
+    ///
+    /// 与 `block_softs` 组合即 `extract_block_softs`；分开调用时可按不同 QIM 步长解码同一组块，
+    /// 而无需重复 DCT / SVD。
+    pub fn extract_block_singulars(&self, ll: &Array2<f64>) -> Vec<Option<[f64; 2]>> {
+        self.active_blocks(ll.dim())
+            .iter()
+            .map(|&(block_idx, bi, bj)| {
+                let dct_block = dct2d_block(Self::read_block(ll, bi, bj));
+                let perm = generate_shuffler(self.password, block_idx);
+                let shuffled: [f64; 16] = std::array::from_fn(|i| dct_block[perm[i]]);
+                svd_4x4(shuffled).ok().map(|(_, s, _)| [s[0], s[1]])
+            })
+            .collect()
+    }
 
-            let block = Self::read_block(ll, bi, bj);
-            let dct_block = dct2d_block(block);
+    /// 按当前 QIM 步长将逐块奇异值（见 `extract_block_singulars`）解码为逐块软判决值
+    pub fn block_softs(&self, singulars: &[Option<[f64; 2]>]) -> Vec<Option<f64>> {
+        singulars.iter().map(|s| s.map(|s| self.block_soft(s))).collect()
+    }
 
-            let perm = generate_shuffler(PASSWORD, block_idx);
-            let shuffled: [f64; 16] = std::array::from_fn(|i| dct_block[perm[i]]);
+    /// 按当前 QIM 步长解码单个块的奇异值 `[s0, s1]`
+    pub fn block_soft(&self, [s0, s1]: [f64; 2]) -> f64 {
+        // 与 Python 一致：3:1 加权平均两个奇异值的解码结果
+        let bit0 = qim_decode_soft(s0, self.qim_steps[0]);
+        let bit1 = qim_decode_soft(s1, self.qim_steps[1]);
+        (bit0 * 3.0 + bit1) / 4.0
+    }
+
+    /// 抽样估计 LL 子带主奇异值的 QIM 余数聚集度（快速判断是否嵌入过水印，见 `WatermarkExtractor::quick_detect`）
+    ///
+    /// 从参与嵌入的块中均匀抽取至多 `sample_blocks` 个，把 `s[0]` 相对 `d1` 的余数映射到相位
+    /// `4π · (s[0] mod d1) / d1`（两个 QIM 格点 1/4、3/4 重合），返回平均向量的模长，值域 [0, 1]。
+    /// 嵌入过水印的块聚集在同一相位附近（像素取整带来的整体偏移不影响模长），接近 1；
+    /// 未嵌入的块近似均匀分布，接近 0。无可用块时返回 `None`。
+    pub fn qim_clustering(&self, ll: &Array2<f64>, sample_blocks: usize) -> Option<f64> {
+        self.clustering_of(&self.sample_principal_singulars(ll, sample_blocks))
+    }
+
+    /// 从参与嵌入的块中均匀抽取至多 `sample_blocks` 个，返回其主奇异值（供 `clustering_of` 按不同步长复用）
+    pub fn sample_principal_singulars(&self, ll: &Array2<f64>, sample_blocks: usize) -> Vec<f64> {
+        let blocks = self.active_blocks(ll.dim());
+        let step = blocks.len().div_ceil(sample_blocks.max(1)).max(1);
+        blocks
+            .iter()
+            .step_by(step)
+            .filter_map(|&(block_idx, bi, bj)| {
+                let dct_block = dct2d_block(Self::read_block(ll, bi, bj));
+                let perm = generate_shuffler(self.password, block_idx);
+                let shuffled: [f64; 16] = std::array::from_fn(|i| dct_block[perm[i]]);
+                svd_4x4(shuffled).ok().map(|(_, s, _)| s[0])
+            })
+            .collect()
+    }
 
-            let (_, s, _) = svd_4x4(shuffled);
+    /// 按当前 `d1` 计算抽样主奇异值的 QIM 余数聚集度（见 `qim_clustering`）；无样本时返回 `None`
+    pub fn clustering_of(&self, principal_singulars: &[f64]) -> Option<f64> {
+        let (cos_sum, sin_sum) = principal_singulars.iter().fold((0.0f64, 0.0f64), |(c, s), &s0| {
+            let phase = 4.0 * std::f64::consts::PI * s0 / self.qim_steps[0];
+            (c + phase.cos(), s + phase.sin())
+        });
+        (!principal_singulars.is_empty()).then(|| cos_sum.hypot(sin_sum) / principal_singulars.len() as f64)
+    }
+
+    /// 从全部块的逐块软判决值（或奇异值）中取出选块间隔为 `stride` 时参与嵌入的块
+    ///
+    /// 与 `with_block_stride(stride)` 后调用 `extract_block_softs` 的结果相同，但无需重复 DCT / SVD。
+    pub fn subsample_blocks<T: Copy>(block_softs: &[T], stride: usize) -> Vec<T> {
+        block_softs.iter().step_by(stride.max(1)).copied().collect()
+    }
 
-            // 与 Python 一致：3:1 加权平均两个奇异值的解码结果
-            let bit0 = qim_decode_soft(s[0], D1);
-            let bit1 = qim_decode_soft(s[1], D2);
-            wm_block_bits[block_idx] = (bit0 * 3.0 + bit1) / 4.0;
+    /// 将按行优先顺序提取的逐块软判决值（或奇异值）重排为 `order` 顺序
+    ///
+    /// `block_softs` 须来自相同边框、选块间隔为 1 的行优先处理器（`ll_dim` 为对应 LL 子带尺寸），
+    /// 结果与 `with_scan_order(order)` 后调用 `extract_block_softs` 相同，但无需重复 DCT / SVD。
+    pub fn reorder_blocks<T: Copy>(&self, ll_dim: (usize, usize), block_softs: &[Option<T>], order: ScanOrder) -> Vec<Option<T>> {
+        let row_major = Self { block_stride: 1, scan_order: ScanOrder::RowMajor, ..*self }.active_blocks(ll_dim);
+        let position: std::collections::HashMap<usize, usize> =
+            row_major.iter().enumerate().map(|(k, &(idx, _, _))| (idx, k)).collect();
+        Self { block_stride: 1, scan_order: order, ..*self }
+            .active_blocks(ll_dim)
+            .iter()
+            .map(|(idx, _, _)| position.get(idx).and_then(|&k| block_softs.get(k).copied().flatten()))
+            .collect()
+    }
+
+    /// 以 `wm_size` 为周期对逐块软判决值取平均（与 Python extract_avg 一致），`None` 不参与平均
+    ///
+    /// 块数少于 `wm_size` 时返回错误。
+    pub fn cyclic_average(block_softs: &[Option<f64>], wm_size: usize) -> Result<Vec<f64>, BlindMarkError> {
+        let block_num = block_softs.len();
+
+        if block_num < wm_size {
+            return Err(BlindMarkError::ExtractionFailed(format!(
+                "图片太小：{} 块 < {} 位水印",
+                block_num,
+                wm_size
+            )));
         }
 
-        // 循环平均（与 Python extract_avg 一致）
         let mut wm_avg = vec![0.0f64; wm_size];
-        for i in 0..wm_size {
+        for (i, avg) in wm_avg.iter_mut().enumerate() {
             let mut sum = 0.0;
             let mut count = 0usize;
-            let mut j = i;
-            while j < block_num {
-                sum += wm_block_bits[j];
+            for bit in block_softs[i..].iter().step_by(wm_size).flatten() {
+                sum += bit;
                 count += 1;
-                j += wm_size;
             }
-            wm_avg[i] = if count > 0 { sum / count as f64 } else { 0.5 };
+            *avg = if count > 0 { sum / count as f64 } else { 0.5 };
         }
 
         Ok(wm_avg)
@@ -176,6 +353,27 @@ impl DCTProcessor {
 
     // ─── 私有辅助方法 ─────────────────────────────────────────────────────────
 
+    /// 参与嵌入/提取的块，按扫描顺序返回 `(网格序号, 块行, 块列)`
+    ///
+    /// 网格序号用于生成打乱顺序；`margin = 0` 且 `block_stride = 1` 时即全部块。
+    /// 选块间隔在排序之后生效。
+    fn active_blocks(&self, (h, w): (usize, usize)) -> Vec<(usize, usize, usize)> {
+        let blocks_h = h / BLOCK_H;
+        let blocks_w = w / BLOCK_W;
+        let first_row = self.margin.div_ceil(BLOCK_H);
+        let first_col = self.margin.div_ceil(BLOCK_W);
+        let end_row = h.saturating_sub(self.margin) / BLOCK_H;
+        let end_col = w.saturating_sub(self.margin) / BLOCK_W;
+        let mut blocks: Vec<(usize, usize, usize)> = (first_row..end_row.min(blocks_h))
+            .flat_map(|bi| (first_col..end_col.min(blocks_w)).map(move |bj| (bi * blocks_w + bj, bi, bj)))
+            .collect();
+        if self.scan_order == ScanOrder::Hilbert {
+            let side = blocks_h.max(blocks_w).next_power_of_two();
+            blocks.sort_by_key(|&(_, bi, bj)| hilbert_index(side, bj, bi));
+        }
+        blocks.into_iter().step_by(self.block_stride).collect()
+    }
+
     /// 从 LL 子带读取一个 4×4 块（行优先展平）
     fn read_block(ll: &Array2<f64>, bi: usize, bj: usize) -> [f64; 16] {
         let mut block = [0.0f64; 16];
@@ -197,6 +395,12 @@ impl DCTProcessor {
     }
 }
 
+impl Default for DCTProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // ─── 内部纯函数（不依赖 self）────────────────────────────────────────────────
 
 /// 1D 正交 DCT-II，N=4（与 OpenCV cv2.dct 一致）
@@ -285,12 +489,22 @@ fn idct2d_block(block: [f64; 16]) -> [f64; 16] {
 /// 4×4 矩阵的 SVD，返回 (U, S, Vt)，奇异值降序排列
 ///
 /// 使用 nalgebra 的 Matrix4<f64>，与 numpy.linalg.svd 约定一致。
-fn svd_4x4(data: [f64; 16]) -> ([f64; 16], [f64; 4], [f64; 16]) {
+///
+/// # 错误
+/// 块含 NaN / 无穷值，或迭代 `SVD_MAX_ITERATIONS` 次仍未收敛时返回错误（而非 panic）。
+fn svd_4x4(data: [f64; 16]) -> Result<Svd4x4, BlindMarkError> {
+    if !data.iter().all(|v| v.is_finite()) {
+        return Err(BlindMarkError::ImageProcessing("SVD 输入块含非有限值".to_string()));
+    }
     let m = Matrix4::<f64>::from_row_slice(&data);
-    let svd = m.svd(true, true);
-    let u = svd.u.unwrap();
+    let svd = m
+        .try_svd(true, true, f64::EPSILON * 5.0, SVD_MAX_ITERATIONS)
+        .ok_or_else(|| BlindMarkError::ImageProcessing("4×4 块 SVD 未收敛".to_string()))?;
+    let (u, vt) = match (svd.u, svd.v_t) {
+        (Some(u), Some(vt)) => (u, vt),
+        _ => return Err(BlindMarkError::ImageProcessing("SVD 未返回奇异向量".to_string())),
+    };
     let s = svd.singular_values;
-    let vt = svd.v_t.unwrap();
 
     let mut u_arr = [0.0f64; 16];
     let mut s_arr = [0.0f64; 4];
@@ -302,7 +516,7 @@ fn svd_4x4(data: [f64; 16]) -> ([f64; 16], [f64; 4], [f64; 16]) {
         }
         s_arr[i] = s[i];
     }
-    (u_arr, s_arr, vt_arr)
+    Ok((u_arr, s_arr, vt_arr))
 }
 
 /// 从 U、S、Vt 重建矩阵（展平为 16 元素数组）
@@ -325,14 +539,14 @@ fn reconstruct_svd(u: &[f64; 16], s: &[f64; 4], vt: &[f64; 16]) -> [f64; 16] {
 /// QIM 嵌入（与 Python `(s//d + 0.25 + 0.5*bit)*d` 完全一致）
 ///
 /// bit=0 → 量化到 0.25*d 处，bit=1 → 量化到 0.75*d 处
-fn qim_encode(s: f64, bit: u8, d: f64) -> f64 {
+pub(crate) fn qim_encode(s: f64, bit: u8, d: f64) -> f64 {
     (s / d).floor() * d + (0.25 + 0.5 * bit as f64) * d
 }
 
 /// QIM 软判决提取
 ///
 /// 若 `s % d > d/2` → 1.0（bit=1），否则 → 0.0（bit=0）
-fn qim_decode_soft(s: f64, d: f64) -> f64 {
+pub(crate) fn qim_decode_soft(s: f64, d: f64) -> f64 {
     if d <= 0.0 {
         return 0.5;
     }
@@ -341,6 +555,62 @@ fn qim_decode_soft(s: f64, d: f64) -> f64 {
     if remainder > d / 2.0 { 1.0 } else { 0.0 }
 }
 
+/// 由用户输入的密码文本派生打乱种子（SHA-256 前 8 字节，大端序）
+///
+/// 空字符串（或仅空白）返回 `DEFAULT_PASSWORD`，与未设置密码时的结果一致。
+pub fn password_seed(password: &str) -> u64 {
+    use sha2::{Digest, Sha256};
+
+    let password = password.trim();
+    if password.is_empty() {
+        return DEFAULT_PASSWORD;
+    }
+    let digest = Sha256::digest(password.as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().expect("SHA-256 输出至少 8 字节"))
+}
+
+/// Hilbert 曲线：边长为 `side`（2 的幂）的网格中坐标 `(x, y)` 在曲线上的序号
+pub fn hilbert_index(side: usize, mut x: usize, mut y: usize) -> usize {
+    let mut d = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as usize;
+        let ry = (y & s > 0) as usize;
+        d += s * s * ((3 * rx) ^ ry);
+        hilbert_rotate(side, &mut x, &mut y, rx, ry);
+        s /= 2;
+    }
+    d
+}
+
+/// `hilbert_index` 的逆映射：曲线序号 `d` 对应的坐标 `(x, y)`
+pub fn hilbert_point(side: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < side {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        hilbert_rotate(s, &mut x, &mut y, rx, ry);
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+/// Hilbert 曲线象限旋转 / 翻转
+fn hilbert_rotate(n: usize, x: &mut usize, y: &mut usize, rx: usize, ry: usize) {
+    if ry == 0 {
+        if rx == 1 {
+            *x = n - 1 - *x;
+            *y = n - 1 - *y;
+        }
+        std::mem::swap(x, y);
+    }
+}
+
 /// 为指定块生成确定性随机置换（嵌入/提取使用相同置换保证一致性）
 fn generate_shuffler(password: u64, block_idx: usize) -> [usize; 16] {
     let seed = password.wrapping_mul(1_000_003).wrapping_add(block_idx as u64);
@@ -403,7 +673,7 @@ mod tests {
             5.0, 3.0, 30.0, 0.5,
             2.0, 1.0, 0.5, 10.0,
         ];
-        let (u, s, vt) = svd_4x4(data);
+        let (u, s, vt) = svd_4x4(data).unwrap();
         let reconstructed = reconstruct_svd(&u, &s, &vt);
         for i in 0..16 {
             assert!((data[i] - reconstructed[i]).abs() < 1e-6,
@@ -411,6 +681,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_svd_degenerate_block() {
+        // 全零 / 秩 1 块仍可分解
+        assert!(svd_4x4([0.0; 16]).is_ok());
+        assert!(svd_4x4([7.0; 16]).is_ok());
+
+        // 非有限值返回错误而非 panic
+        let mut nan_block = [1.0f64; 16];
+        nan_block[5] = f64::NAN;
+        assert!(svd_4x4(nan_block).is_err());
+        assert!(svd_4x4([f64::INFINITY; 16]).is_err());
+
+        // 嵌入时病态块原样保留，其余块照常处理，提取不受影响
+        let processor = DCTProcessor::new();
+        let mut ll = Array2::from_shape_fn((64, 64), |(i, j)| 100.0 + ((i * 7 + j * 3) % 50) as f64);
+        ll[[0, 0]] = f64::NAN;
+        let bits: Vec<u8> = (0..32).map(|i| (i % 3 == 0) as u8).collect();
+        processor.embed_watermark_blocks(&mut ll, &bits).unwrap();
+        assert!(ll[[0, 0]].is_nan());
+        assert_eq!(ll[[0, 1]], 100.0 + 3.0, "病态块内其余像素不应被修改");
+
+        let soft = processor.extract_watermark_blocks_soft(&ll, bits.len()).unwrap();
+        let extracted: Vec<u8> = soft.iter().map(|&v| (v > 0.5) as u8).collect();
+        assert_eq!(extracted, bits);
+    }
+
     #[test]
     fn test_qim_encode_decode() {
         for &original_s in &[100.0f64, 250.5, 500.0, 999.9, 36.1, 36.9] {
@@ -423,6 +719,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_password_seed() {
+        assert_eq!(password_seed(""), DEFAULT_PASSWORD);
+        assert_eq!(password_seed("  "), DEFAULT_PASSWORD);
+        assert_eq!(password_seed("abc"), password_seed("abc"));
+        assert_ne!(password_seed("abc"), password_seed("abd"));
+    }
+
     #[test]
     fn test_embed_extract_no_quantization() {
         let processor = DCTProcessor::new();
@@ -461,6 +765,70 @@ mod tests {
         assert!(result.is_err(), "图片太小应返回错误");
     }
 
+    #[test]
+    fn test_hilbert_mapping_roundtrip() {
+        for side in [1usize, 2, 4, 8, 16] {
+            let mut seen = vec![false; side * side];
+            for d in 0..side * side {
+                let (x, y) = hilbert_point(side, d);
+                assert!(x < side && y < side);
+                assert_eq!(hilbert_index(side, x, y), d);
+                seen[y * side + x] = true;
+                // 相邻序号在网格上相邻
+                if d > 0 {
+                    let (px, py) = hilbert_point(side, d - 1);
+                    assert_eq!(px.abs_diff(x) + py.abs_diff(y), 1);
+                }
+            }
+            assert!(seen.iter().all(|&v| v));
+        }
+    }
+
+    #[test]
+    fn test_hilbert_order_spreads_column_aligned_copies() {
+        // LL 256×256 → 64×64 块；544 = 8.5 × 64，行优先时同一位的各副本只落在两列上
+        let dim = (256, 256);
+        let wm_size = 544;
+        // 竖条局部修改：第 20 ~ 23 列块被破坏
+        let damaged = |order: ScanOrder| -> usize {
+            let blocks = DCTProcessor::new().with_scan_order(order).active_blocks(dim);
+            let mut hit = vec![0usize; wm_size];
+            let mut copies = vec![0usize; wm_size];
+            for (k, &(_, _, bj)) in blocks.iter().enumerate() {
+                copies[k % wm_size] += 1;
+                hit[k % wm_size] += (20..24).contains(&bj) as usize;
+            }
+            // 多数副本被破坏（循环平均后易翻转）的最长连续比特段
+            let lost: Vec<bool> = (0..wm_size).map(|i| hit[i] * 2 >= copies[i]).collect();
+            let (mut run, mut longest) = (0, 0);
+            for &l in lost.iter().chain(&lost) {
+                run = if l { run + 1 } else { 0 };
+                longest = longest.max(run.min(wm_size));
+            }
+            longest
+        };
+        let row_major = damaged(ScanOrder::RowMajor);
+        let hilbert = damaged(ScanOrder::Hilbert);
+        assert!(row_major >= 4, "行优先下竖条应破坏一段连续比特: {}", row_major);
+        assert!(hilbert < row_major, "Hilbert 顺序应减少连续受损比特: {} vs {}", hilbert, row_major);
+    }
+
+    #[test]
+    fn test_hilbert_reorder_matches_direct_extraction() {
+        let mut ll = Array2::from_shape_fn((96, 128), |(y, x)| ((x * 7 + y * 3) % 200) as f64 + 20.0);
+        let hilbert = DCTProcessor::new().with_scan_order(ScanOrder::Hilbert);
+        let bits: Vec<u8> = (0..544).map(|i| (i % 5 == 0) as u8).collect();
+        hilbert.embed_watermark_blocks(&mut ll, &bits).unwrap();
+
+        let row_major = DCTProcessor::new().extract_block_softs(&ll);
+        let reordered = DCTProcessor::new().reorder_blocks(ll.dim(), &row_major, ScanOrder::Hilbert);
+        assert_eq!(reordered, hilbert.extract_block_softs(&ll));
+
+        let soft = DCTProcessor::cyclic_average(&reordered, 544).unwrap();
+        let extracted: Vec<u8> = soft.iter().map(|&v| (v > 0.5) as u8).collect();
+        assert_eq!(extracted, bits);
+    }
+
     #[test]
     fn test_shuffler_deterministic() {
         let p1 = generate_shuffler(1, 42);
@@ -30,6 +30,32 @@ pub struct DWTComponents {
     pub hh1: Array2<f64>,
 }
 
+/// 1-level DWT subband that carries the watermark
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Subband {
+    /// Low-frequency approximation (default, compatible with Python blind_watermark)
+    #[default]
+    #[serde(rename = "LL", alias = "ll")]
+    Ll,
+    /// Vertical details: changes hide in edges and texture but are weaker against
+    /// blurring and lossy compression
+    #[serde(rename = "HL", alias = "hl")]
+    Hl,
+}
+
+impl Subband {
+    /// Pick this subband out of `decompose_1level`'s (LL, LH, HL, HH) tuple
+    pub fn select(
+        self,
+        bands: &mut (Array2<f64>, Array2<f64>, Array2<f64>, Array2<f64>),
+    ) -> &mut Array2<f64> {
+        match self {
+            Subband::Ll => &mut bands.0,
+            Subband::Hl => &mut bands.2,
+        }
+    }
+}
+
 impl DWTProcessor {
     /// Create a new DWT processor with 2-level decomposition
     pub fn new() -> Self {
@@ -296,6 +322,12 @@ impl DWTProcessor {
     }
 }
 
+impl Default for DWTProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,10 +1,14 @@
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb};
+use image::{codecs::jpeg::JpegEncoder, imageops::{self, FilterType}, DynamicImage, GenericImageView, ImageBuffer, Rgb, Rgb32FImage, RgbImage, Rgba};
 use ndarray::Array2;
 use crate::models::BlindMarkError;
 use crate::core::watermark::{
-    dwt::DWTProcessor,
-    dct::DCTProcessor,
-    encoder::WatermarkEncoder,
+    cbor,
+    dwt::{DWTProcessor, Subband},
+    dct::{DCTProcessor, ScanOrder},
+    jpeg::{self, JPEG_QIM_STEP},
+    encoder::{WatermarkEncoder, TEXT_BLOCK_STRIDES, TEXT_WATERMARK_MAX_BYTES, TEXT_WATERMARK_TOTAL_BITS},
+    profile::Profile,
+    recipe::WatermarkRecipe,
 };
 
 /// 完整的水印嵌入流水线
@@ -18,9 +22,40 @@ use crate::core::watermark::{
 ///    - QIM 公式：`s_new = (floor(s/d) + 0.25 + 0.5*bit) * d`，d1=36，d2=20
 /// 5. 重组 LL 子带，做 1 级 IDWT 重建通道
 /// 6. 三通道合并，像素值钳制到 [0, 255]
+///
+/// 启用 `with_channel_selection` 后，文本水印只嵌入方差最大的两个通道，见 `select_channels`。
+/// 预设档位（`with_profile`）可改用其他 QIM 步长与 HL 子带，见 `Profile`；
+/// 文本水印的步长再按嵌入强度缩放，见 `embed_raw_text`。
 pub struct WatermarkEmbedder {
     dwt: DWTProcessor,
     dct: DCTProcessor,
+    channel_selection: bool,
+    redundancy: usize,
+    downscale_embed: Option<u32>,
+    chained: bool,
+    block_stride: usize,
+    scan_order: ScanOrder,
+    subband: Subband,
+    md5_salt: Option<String>,
+}
+
+/// `embed_jpeg_to_bytes` 输出 JPEG 的编码质量
+pub const JPEG_OUTPUT_QUALITY: u8 = 95;
+
+/// 降采样嵌入/提取使用的缩放滤波器，两端必须一致
+pub const DOWNSCALE_FILTER: FilterType = FilterType::Triangle;
+
+/// 计算降采样嵌入的目标尺寸：长边缩放到 `max_dim`，按比例缩放短边并取偶数（DWT 要求）
+///
+/// 长边不超过 `max_dim` 时返回 `None`，即无需降采样。
+pub fn downscale_dimensions(width: u32, height: u32, max_dim: u32) -> Option<(u32, u32)> {
+    let max_dim = max_dim.max(2) & !1;
+    if width.max(height) <= max_dim {
+        return None;
+    }
+    let scale = max_dim as f64 / width.max(height) as f64;
+    let even = |v: u32| (((v as f64 * scale).round() as u32) & !1).max(2);
+    Some((even(width), even(height)))
 }
 
 impl WatermarkEmbedder {
@@ -28,18 +63,204 @@ impl WatermarkEmbedder {
         Self {
             dwt: DWTProcessor::new(),
             dct: DCTProcessor::new(),
+            channel_selection: false,
+            redundancy: 1,
+            downscale_embed: None,
+            chained: false,
+            block_stride: 1,
+            scan_order: ScanOrder::RowMajor,
+            subband: Subband::Ll,
+            md5_salt: None,
+        }
+    }
+
+    /// 使用自定义密码（打乱种子，见 `dct::password_seed`）创建，嵌入与提取须使用相同密码
+    pub fn with_password(password: u64) -> Self {
+        Self {
+            dwt: DWTProcessor::new(),
+            dct: DCTProcessor::with_password(password),
+            channel_selection: false,
+            redundancy: 1,
+            downscale_embed: None,
+            chained: false,
+            block_stride: 1,
+            scan_order: ScanOrder::RowMajor,
+            subband: Subband::Ll,
+            md5_salt: None,
         }
     }
 
+    /// 按配方创建（见 `WatermarkRecipe`），配方中的固定参数须与当前实现一致
+    pub fn with_recipe(recipe: &WatermarkRecipe) -> Result<Self, BlindMarkError> {
+        recipe.validate()?;
+        let mut embedder = Self::with_password(recipe.password)
+            .with_qim_steps(recipe.qim_steps)
+            .with_subband(recipe.subband)
+            .with_quiet_zone(recipe.quiet_zone)
+            .with_redundancy(recipe.redundancy)
+            .with_scan_order(recipe.scan_order)
+            .with_md5_salt(recipe.md5_salt.as_deref());
+        embedder.channel_selection = recipe.channel_selection;
+        embedder.block_stride = recipe.block_stride.max(1);
+        embedder.chained = recipe.chained;
+        embedder.downscale_embed = recipe.downscale_embed;
+        Ok(embedder)
+    }
+
+    /// 导出当前生效的嵌入配置，可保存为 JSON 供日后复现（见 `with_recipe`）
+    pub fn recipe(&self) -> WatermarkRecipe {
+        WatermarkRecipe {
+            qim_steps: self.dct.qim_steps(),
+            subband: self.subband,
+            password: self.dct.password(),
+            // 静默边框按 LL 子带像素存储，换算回原图像素
+            quiet_zone: (self.dct.margin() * 2) as u32,
+            channel_selection: self.channel_selection,
+            scan_order: self.scan_order,
+            redundancy: self.redundancy,
+            block_stride: self.block_stride,
+            chained: self.chained,
+            downscale_embed: self.downscale_embed,
+            md5_salt: self.md5_salt.clone(),
+            ..WatermarkRecipe::default()
+        }
+    }
+
+    /// 应用预设档位（见 `Profile`），一次设置 QIM 步长、嵌入子带、冗余份数与通道选择
+    ///
+    /// 提取端须用 `WatermarkExtractor::with_profile` 设置相同档位。
+    pub fn with_profile(self, profile: Profile) -> Self {
+        let [d1, d2] = profile.qim_steps();
+        let mut embedder = self
+            .with_qim_steps([d1, d2])
+            .with_subband(profile.subband())
+            .with_redundancy(profile.redundancy());
+        embedder.channel_selection = profile.channel_selection();
+        embedder
+    }
+
+    /// 设置主 / 次奇异值的 QIM 量化步长（默认 `[D1, D2]`），提取端须使用相同步长
+    fn with_qim_steps(mut self, [d1, d2]: [f64; 2]) -> Self {
+        self.dct = self.dct.with_qim_steps(d1, d2);
+        self
+    }
+
+    /// 设置嵌入子带（默认 LL），提取端须使用相同子带
+    fn with_subband(mut self, subband: Subband) -> Self {
+        self.subband = subband;
+        self
+    }
+
+    /// 设置静默边框：距图片边缘 `margin` 像素内的区域不嵌入水印，
+    /// 使裁掉少量边框后仍保留完整的水印副本。图片中不记录该值，嵌入与提取须使用相同边框
+    pub fn with_quiet_zone(mut self, margin: u32) -> Self {
+        // 1 级 DWT 后 LL 子带尺寸减半
+        self.dct = self.dct.with_margin((margin as usize).div_ceil(2));
+        self
+    }
+
+    /// 启用按图选择通道：文本水印仅嵌入方差最大的两个通道，并在头部记录所选通道
+    ///
+    /// 方差大的通道纹理丰富，修改更不易察觉；单一色调主导的图片（如大面积蓝天）
+    /// 可避开几乎恒定的通道。提取端自动识别，无需额外配置。MD5 水印不受影响。
+    pub fn with_channel_selection(mut self) -> Self {
+        self.channel_selection = true;
+        self
+    }
+
+    /// 设置文本水印的显式冗余份数（默认 1，0 视为 1）
+    ///
+    /// 544 位载荷重复 `redundancy` 份后嵌入，图片可用块数不足 `544 × redundancy` 时报错，
+    /// 使每位至少有 `redundancy` 个副本，鲁棒性不再仅取决于图片尺寸。
+    /// 提取端用 `WatermarkExtractor::with_redundancy` 设置相同份数以多数表决解码；
+    /// 副本与块的对应关系不变，未设置冗余的提取端仍可读取。MD5 水印不受影响。
+    pub fn with_redundancy(mut self, redundancy: usize) -> Self {
+        self.redundancy = redundancy.max(1);
+        self
+    }
+
+    /// 启用降采样嵌入：长边超过 `max_dim` 的大图先缩小到长边 `max_dim` 再嵌入文本水印，
+    /// 水印造成的像素差值放大回原尺寸后叠加到原图
+    ///
+    /// 与高速模式（仅处理左上角 ROI）不同，水印覆盖整张图片，但有效块数按缩小后的尺寸计算，
+    /// 鲁棒性低于全尺寸嵌入。提取端须用 `WatermarkExtractor::with_downscale` 设置相同的 `max_dim`。
+    /// MD5 水印与 alpha 平面不受影响。
+    pub fn with_downscale_embed(mut self, max_dim: u32) -> Self {
+        self.downscale_embed = Some(max_dim);
+        self
+    }
+
+    /// 启用链式水印：超过 64 字节的文本拆分到多个 544 位槽位嵌入（见 `WatermarkEncoder::text_to_chained_bits`）
+    ///
+    /// 各槽位落在不同的块上，图片可用块数须不少于 `槽位数 × 544`，否则报错。
+    /// 提取端自动识别，无需额外配置。不超过 64 字节的文本仍按普通格式嵌入；
+    /// 链式水印使用全部三个通道，且不重复冗余份数（忽略 `with_channel_selection` / `with_redundancy`）。
+    pub fn with_chained_payload(mut self) -> Self {
+        self.chained = true;
+        self
+    }
+
+    /// 设置按比例选块嵌入：文本水印只嵌入约 `fraction` 比例的块（每隔若干块取一个），其余块保持原样
+    ///
+    /// 比例按 `WatermarkEncoder::block_stride_for_fraction` 取 1/2 ~ 1/16 中不低于 `fraction` 的档位，
+    /// 用于超大图片减少 DCT / SVD 计算量。与高速模式（仅处理左上角 ROI）不同，选中的块均匀分布在
+    /// 整张图片上。所选块数不足 `544 × redundancy` 时自动增大比例直至足够（必要时使用全部块）。
+    /// 头部记录实际比例，提取端自动识别；使用全部三个通道（忽略 `with_channel_selection`）。
+    /// MD5 水印、链式水印与 alpha 平面不受影响。
+    pub fn with_block_fraction(mut self, fraction: f32) -> Self {
+        self.block_stride = WatermarkEncoder::block_stride_for_fraction(fraction);
+        self
+    }
+
+    /// 设置文本水印的块扫描顺序（见 `ScanOrder`，默认行优先）
+    ///
+    /// Hilbert 顺序打散行优先下同列对齐的比特副本，头部使用 `TEXT_HILBERT_MAGIC` 记录，提取端自动识别。
+    /// 使用全部三个通道，优先于 `with_block_fraction` 与 `with_channel_selection`；
+    /// MD5 水印、链式水印与 alpha 平面仍按行优先顺序。
+    pub fn with_scan_order(mut self, order: ScanOrder) -> Self {
+        self.scan_order = order;
+        self
+    }
+
+    /// 设置 MD5 盐值：`embed` 嵌入 `md5(salt || 文本)` 而非 `md5(文本)`（见 `WatermarkEncoder::salted_text`）
+    ///
+    /// 加盐后嵌入的哈希值随之改变，校验 / 反查时须使用相同盐值。原始文本水印不受影响。
+    pub fn with_md5_salt(mut self, salt: Option<&str>) -> Self {
+        self.md5_salt = salt.filter(|s| !s.is_empty()).map(str::to_string);
+        self
+    }
+
+    /// 按像素值方差选出最大的两个通道，按通道序号升序返回
+    pub fn select_channels(image: &RgbImage) -> [usize; 2] {
+        let n = (image.width() as f64 * image.height() as f64).max(1.0);
+        let mut sum = [0.0f64; 3];
+        let mut sum_sq = [0.0f64; 3];
+        for p in image.pixels() {
+            for c in 0..3 {
+                let v = p[c] as f64;
+                sum[c] += v;
+                sum_sq[c] += v * v;
+            }
+        }
+        let variance: Vec<f64> = (0..3).map(|c| sum_sq[c] / n - (sum[c] / n).powi(2)).collect();
+
+        // 去掉方差最小的通道（相同时去掉序号较大的，保证结果稳定）
+        let lowest = (0..3)
+            .rev()
+            .min_by(|&a, &b| variance[a].total_cmp(&variance[b]))
+            .unwrap_or(2);
+        let mut kept = (0..3).filter(|&c| c != lowest);
+        [kept.next().unwrap_or(0), kept.next().unwrap_or(1)]
+    }
+
     /// 将 MD5 水印嵌入图片
     ///
     /// # 参数
     /// * `image`          - 输入图片
     /// * `watermark_text` - 要嵌入的文本（将被 MD5 哈希为 128 位）
-    /// * `strength`       - 当前为保留参数，不影响嵌入效果。
-    ///                      算法使用固定 QIM 步长（d1=36，d2=20），
-    ///                      提取时需相同步长，因此强度不可变。
-    ///                      传入值须在 [0.1, 1.0] 范围内以通过校验。
+    /// * `strength`       - 须在 [0.1, 1.0] 范围内，但不影响嵌入效果：
+    ///   128 位 MD5 序列没有头部，无法记录强度档位，因此固定使用默认 QIM 步长（d1=36，d2=20）。
+    ///   原始文本水印的强度见 `embed_raw_text()`。
     pub fn embed(
         &self,
         image: &DynamicImage,
@@ -52,7 +273,7 @@ impl WatermarkEmbedder {
             ));
         }
 
-        let watermark_data = WatermarkEncoder::encode(watermark_text);
+        let watermark_data = WatermarkEncoder::encode_salted(watermark_text, self.md5_salt.as_deref());
         self.embed_bits(image, &watermark_data.binary_sequence)
     }
 
@@ -61,7 +282,9 @@ impl WatermarkEmbedder {
     /// # 参数
     /// * `image`     - 输入图片
     /// * `text`      - 要嵌入的原始文本（直接存储，不做哈希处理）
-    /// * `strength`  - 保留参数，同 `embed()`，当前不影响嵌入效果
+    /// * `strength`  - 嵌入强度 [0.1, 1.0]：按 0.1 取整后将 QIM 步长乘以 `STRENGTH_STEP_FACTORS`
+    ///   中的倍数（0.6 ~ 2.0，强度 0.5 为默认步长）。强度越高越能抵抗噪声与压缩，
+    ///   对像素的修改也越明显；档位记录在头部，提取端自动识别
     /// * `fast_mode` - 高速模式：对两维均超过 512px 的大图，仅处理左上角
     ///                 512×512 区域再贴回原图
     pub fn embed_raw_text(
@@ -94,8 +317,189 @@ impl WatermarkEmbedder {
             return Ok(DynamicImage::ImageRgb8(result));
         }
 
+        // ── 降采样嵌入：在缩小图上嵌入，差值放大后叠加回原图 ─────────────────
+        if let Some((small_w, small_h)) = self
+            .downscale_embed
+            .and_then(|max_dim| downscale_dimensions(width, height, max_dim))
+        {
+            return self.embed_downscaled(image, text, strength, small_w, small_h);
+        }
+
+        // 嵌入强度：按档位缩放 QIM 步长，并在每个 544 位单元的头部记录档位，提取端据此自动还原步长
+        let level = WatermarkEncoder::strength_level(strength);
+        let dct = self.dct.scale_qim_steps(WatermarkEncoder::step_factor(level).unwrap_or(1.0));
+        let with_level = |mut bits: Vec<u8>| {
+            WatermarkEncoder::mark_strength_level(&mut bits, level);
+            bits
+        };
+
+        if self.chained && text.len() > TEXT_WATERMARK_MAX_BYTES {
+            let bits = WatermarkEncoder::text_to_chained_bits(text)?;
+            // 1 级 DWT 后 LL 子带尺寸减半
+            let capacity = self.dct.capacity(((height / 2) as usize, (width / 2) as usize));
+            if capacity < bits.len() {
+                return Err(BlindMarkError::ImageProcessing(format!(
+                    "图片太小：{}×{} 仅能容纳 {} 位，链式水印需 {} 个槽位共 {} 位",
+                    width, height, capacity, bits.len() / TEXT_WATERMARK_TOTAL_BITS, bits.len()
+                )));
+            }
+            return self.embed_bits_in_channels(image, &with_level(bits), &[0, 1, 2], &dct);
+        }
+
+        if self.scan_order == ScanOrder::Hilbert {
+            let bits = WatermarkEncoder::text_to_bits_for_hilbert(text)?;
+            let bits = WatermarkEncoder::repeat_bits(&bits, self.redundancy);
+            return self.embed_bits_in_channels(image, &with_level(bits), &[0, 1, 2], &dct.with_scan_order(ScanOrder::Hilbert));
+        }
+
+        if self.block_stride > 1 {
+            // 选块后容量不足时逐级减小间隔，保证每位至少有 redundancy 个副本
+            let ll_dim = ((height / 2) as usize, (width / 2) as usize);
+            let needed = TEXT_WATERMARK_TOTAL_BITS * self.redundancy;
+            let stride = TEXT_BLOCK_STRIDES
+                .iter()
+                .rev()
+                .copied()
+                .filter(|&stride| stride <= self.block_stride)
+                .find(|&stride| self.dct.with_block_stride(stride).capacity(ll_dim) >= needed);
+            if let Some(stride) = stride {
+                let bits = WatermarkEncoder::text_to_bits_for_stride(text, stride)?;
+                let bits = WatermarkEncoder::repeat_bits(&bits, self.redundancy);
+                return self.embed_bits_in_channels(image, &with_level(bits), &[0, 1, 2], &dct.with_block_stride(stride));
+            }
+        }
+
+        if self.channel_selection {
+            let channels = Self::select_channels(&image.to_rgb8());
+            let bits = WatermarkEncoder::text_to_bits_for_channels(text, channels)?;
+            let bits = WatermarkEncoder::repeat_bits(&bits, self.redundancy);
+            return self.embed_bits_in_channels(image, &with_level(bits), &channels, &dct);
+        }
+
         let bits = WatermarkEncoder::text_to_bits(text)?;
-        self.embed_bits(image, &bits)
+        let bits = WatermarkEncoder::repeat_bits(&bits, self.redundancy);
+        self.embed_bits_in_channels(image, &with_level(bits), &[0, 1, 2], &dct)
+    }
+
+    /// 将结构化数据（如 `{buyer, order_id, ts}`）以 CBOR 编码后作为盲水印嵌入图片
+    ///
+    /// 占用与原始文本水印相同的 64 字节 payload（魔数为 `TEXT_STRUCTURED_MAGIC`），
+    /// CBOR 编码后超过 64 字节时报错。遵循 `with_redundancy` 设置，其余文本选项不适用；
+    /// 提取端使用 `WatermarkExtractor::extract_structured`。
+    pub fn embed_structured(
+        &self,
+        image: &DynamicImage,
+        value: &serde_json::Value,
+    ) -> Result<DynamicImage, BlindMarkError> {
+        let payload = cbor::to_cbor(value)?;
+        let bits = WatermarkEncoder::structured_to_bits(&payload)?;
+        self.embed_bits(image, &WatermarkEncoder::repeat_bits(&bits, self.redundancy))
+    }
+
+    /// 将原始文本嵌入图片的 8×8 亮度 DCT 块（见 `jpeg` 模块），保存为 JPEG 后仍可提取
+    ///
+    /// 与 `embed_raw_text` 的空间域算法不同，直接修改与 JPEG 编码网格对齐的 8×8 块的中频系数，
+    /// 以质量 ≥ 85 重新编码后水印仍在；提取端使用 `WatermarkExtractor::try_extract_text_jpeg`。
+    /// 544 位载荷循环铺满全部块，图片至少需 544 个完整的 8×8 块（如 200×200），否则报错。
+    /// `strength` 同 `embed_raw_text`，按档位缩放 `JPEG_QIM_STEP` 并记录在头部；密码决定块顺序，
+    /// 其余文本选项（通道选择、冗余、选块、链式等）不适用。输出为 RGB（JPEG 没有 alpha）。
+    pub fn embed_raw_text_jpeg(
+        &self,
+        image: &DynamicImage,
+        text: &str,
+        strength: f32,
+    ) -> Result<DynamicImage, BlindMarkError> {
+        if !(0.1..=1.0).contains(&strength) {
+            return Err(BlindMarkError::InvalidConfig(
+                format!("Strength must be between 0.1 and 1.0, got {}", strength)
+            ));
+        }
+        let level = WatermarkEncoder::strength_level(strength);
+        let mut bits = WatermarkEncoder::text_to_bits(text)?;
+        WatermarkEncoder::mark_strength_level(&mut bits, level);
+        let step = JPEG_QIM_STEP * WatermarkEncoder::step_factor(level).unwrap_or(1.0);
+        let marked = jpeg::embed_bits(&image.to_rgb8(), &bits, step, self.dct.password())?;
+        Ok(DynamicImage::ImageRgb8(marked))
+    }
+
+    /// `embed_raw_text_jpeg` 后按 `JPEG_OUTPUT_QUALITY` 编码，返回 JPEG 字节
+    pub fn embed_jpeg_to_bytes(
+        &self,
+        image: &DynamicImage,
+        text: &str,
+        strength: f32,
+    ) -> Result<Vec<u8>, BlindMarkError> {
+        let watermarked = self.embed_raw_text_jpeg(image, text, strength)?;
+        let mut buffer = Vec::new();
+        JpegEncoder::new_with_quality(&mut buffer, JPEG_OUTPUT_QUALITY)
+            .encode_image(&watermarked)
+            .map_err(|e| BlindMarkError::ImageProcessing(
+                format!("Failed to encode JPEG: {}", e)
+            ))?;
+        Ok(buffer)
+    }
+
+    /// 降采样嵌入（见 `with_downscale_embed`），输出为 RGB 原尺寸图片
+    fn embed_downscaled(
+        &self,
+        image: &DynamicImage,
+        text: &str,
+        strength: f32,
+        small_w: u32,
+        small_h: u32,
+    ) -> Result<DynamicImage, BlindMarkError> {
+        let (width, height) = image.dimensions();
+        let small = image.resize_exact(small_w, small_h, DOWNSCALE_FILTER).to_rgb8();
+        let marked = self
+            .embed_raw_text(&DynamicImage::ImageRgb8(small.clone()), text, strength, false)?
+            .to_rgb8();
+
+        // 浮点图片缩放时像素值被钳制到 [0, 1]，差值 [-255, 255] 先平移缩放到该区间
+        let delta: Rgb32FImage = ImageBuffer::from_fn(small_w, small_h, |x, y| {
+            let (m, s) = (marked.get_pixel(x, y), small.get_pixel(x, y));
+            Rgb([0, 1, 2].map(|c| (m[c] as f32 - s[c] as f32 + 255.0) / 510.0))
+        });
+        let delta = imageops::resize(&delta, width, height, DOWNSCALE_FILTER);
+
+        let mut result = image.to_rgb8();
+        for (x, y, p) in result.enumerate_pixels_mut() {
+            let d = delta.get_pixel(x, y);
+            for c in 0..3 {
+                p[c] = (p[c] as f32 + d[c] * 510.0 - 255.0).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        Ok(DynamicImage::ImageRgb8(result))
+    }
+
+    /// 将任意比特序列作为盲水印嵌入图片（通用水印原语）
+    ///
+    /// 不做 MD5 或文本编码，调用方可在此之上实现自定义的 payload 格式；
+    /// 提取端使用 `WatermarkExtractor::extract_bits` 按相同长度取回。
+    ///
+    /// # 参数
+    /// * `image`    - 输入图片
+    /// * `bits`     - 待嵌入的比特序列，每个元素须为 0 或 1
+    /// * `strength` - 保留参数，同 `embed()`，当前不影响嵌入效果
+    pub fn embed_bits_public(
+        &self,
+        image: &DynamicImage,
+        bits: &[u8],
+        strength: f32,
+    ) -> Result<DynamicImage, BlindMarkError> {
+        if !(0.1..=1.0).contains(&strength) {
+            return Err(BlindMarkError::InvalidConfig(
+                format!("Strength must be between 0.1 and 1.0, got {}", strength)
+            ));
+        }
+        if bits.is_empty() {
+            return Err(BlindMarkError::InvalidConfig("比特序列不能为空".to_string()));
+        }
+        if let Some(i) = bits.iter().position(|&b| b > 1) {
+            return Err(BlindMarkError::InvalidConfig(
+                format!("比特值只能为 0 或 1：第 {} 位为 {}", i, bits[i])
+            ));
+        }
+        self.embed_bits(image, bits)
     }
 
     /// 嵌入并返回 PNG 字节（用于预览/API）
@@ -125,6 +529,17 @@ impl WatermarkEmbedder {
         &self,
         image: &DynamicImage,
         bits: &[u8],
+    ) -> Result<DynamicImage, BlindMarkError> {
+        self.embed_bits_in_channels(image, bits, &[0, 1, 2], &self.dct)
+    }
+
+    /// 用 `dct` 仅在 `selected` 所列通道中嵌入比特序列，其余通道保持原样
+    fn embed_bits_in_channels(
+        &self,
+        image: &DynamicImage,
+        bits: &[u8],
+        selected: &[usize],
+        dct: &DCTProcessor,
     ) -> Result<DynamicImage, BlindMarkError> {
         let rgb_image = image.to_rgb8();
         let (width, height) = rgb_image.dimensions();
@@ -136,8 +551,9 @@ impl WatermarkEmbedder {
                 format!("图片尺寸必须为偶数（DWT 要求）：{}×{}", width, height)
             ));
         }
+        Self::check_short_side(dct, width, height, bits.len())?;
 
-        // ── 三通道分别处理 ───────────────────────────────────────────────────
+        // ── 各通道分别处理 ───────────────────────────────────────────────────
         let mut channels: [Array2<f64>; 3] = [
             Array2::zeros((h, w)),
             Array2::zeros((h, w)),
@@ -152,15 +568,8 @@ impl WatermarkEmbedder {
             }
         }
 
-        for ch_data in &mut channels {
-            // 1 级 DWT → (LL, LH, HL, HH)
-            let (mut ll, lh, hl, hh) = self.dwt.decompose_1level(ch_data.view())?;
-
-            // QIM 嵌入到 LL 子带
-            self.dct.embed_watermark_blocks(&mut ll, bits)?;
-
-            // 1 级 IDWT 重建
-            *ch_data = self.dwt.reconstruct_1level(&ll, &lh, &hl, &hh)?;
+        for &ch in selected {
+            self.embed_plane(&mut channels[ch], bits, dct)?;
         }
 
         // ── 合并三通道为 RGB 图片（像素值钳制到 [0, 255]）───────────────────
@@ -176,6 +585,95 @@ impl WatermarkEmbedder {
 
         Ok(DynamicImage::ImageRgb8(result))
     }
+
+    /// 容量不足且原因在于短边过窄（如 4096×4 的横幅）时，报告按当前长边所需的最小短边
+    ///
+    /// 每个 4×4 块对应原图 8×8 像素，短边不足 8 px 时一个块也划分不出。
+    /// 即使短边加到与长边相同也容纳不下时，交由 `DCTProcessor` 报告通用的"图片太小"错误。
+    fn check_short_side(dct: &DCTProcessor, width: u32, height: u32, bit_count: usize) -> Result<(), BlindMarkError> {
+        let capacity = |w: u32, h: u32| dct.capacity(((h / 2) as usize, (w / 2) as usize));
+        if capacity(width, height) >= bit_count {
+            return Ok(());
+        }
+        let wide = width >= height;
+        let long = width.max(height);
+        let min_short = (8..=long)
+            .step_by(8)
+            .find(|&short| if wide { capacity(long, short) } else { capacity(short, long) } >= bit_count);
+        match min_short {
+            Some(min_short) => {
+                let (long_axis, short_axis) = if wide { ("宽度", "高度") } else { ("高度", "宽度") };
+                Err(BlindMarkError::ImageProcessing(format!(
+                    "图片 {}×{} 的{}过窄：{}为 {} px 时{}至少需要 {} px 才能容纳 {} 位水印",
+                    width, height, short_axis, long_axis, long, short_axis, min_short, bit_count
+                )))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 对单个平面（颜色通道或 alpha）做 DWT → 子带（默认 LL，见 `with_profile`）QIM 嵌入 → IDWT
+    fn embed_plane(&self, plane: &mut Array2<f64>, bits: &[u8], dct: &DCTProcessor) -> Result<(), BlindMarkError> {
+        // 1 级 DWT → (LL, LH, HL, HH)
+        let mut bands = self.dwt.decompose_1level(plane.view())?;
+
+        // QIM 嵌入到所选子带
+        dct.embed_watermark_blocks(self.subband.select(&mut bands), bits)?;
+
+        // 1 级 IDWT 重建
+        let (ll, lh, hl, hh) = &bands;
+        *plane = self.dwt.reconstruct_1level(ll, lh, hl, hh)?;
+        Ok(())
+    }
+
+    /// 双平面嵌入：RGB 通道嵌入 `text`，alpha 通道嵌入第二个独立水印 `alpha_text`
+    ///
+    /// 仅对带 alpha 且 alpha 并非全不透明（全为 255）的图片写入 alpha 平面；
+    /// 全不透明 alpha 没有可用的变化范围，嵌入后会被钳制破坏，因此跳过。
+    /// 输入带 alpha 时输出为 RGBA（原 alpha 或嵌入后的 alpha），否则为 RGB。
+    ///
+    /// # 返回
+    /// `(水印图片, alpha 平面是否已嵌入)`
+    pub fn embed_raw_text_dual(
+        &self,
+        image: &DynamicImage,
+        text: &str,
+        alpha_text: &str,
+        strength: f32,
+    ) -> Result<(DynamicImage, bool), BlindMarkError> {
+        let rgb_watermarked = self.embed_raw_text(image, text, strength, false)?;
+        if !image.color().has_alpha() {
+            return Ok((rgb_watermarked, false));
+        }
+
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        let (w, h) = (width as usize, height as usize);
+        let mut alpha: Array2<f64> = Array2::zeros((h, w));
+        for (x, y, p) in rgba.enumerate_pixels() {
+            alpha[[y as usize, x as usize]] = p[3] as f64;
+        }
+
+        let embedded = alpha.iter().any(|&a| a < 255.0);
+        if embedded {
+            let bits = WatermarkEncoder::text_to_bits(alpha_text)?;
+            self.embed_plane(&mut alpha, &bits, &self.dct)?;
+        }
+
+        let rgb = rgb_watermarked.to_rgb8();
+        let mut result = ImageBuffer::new(width, height);
+        for (x, y, p) in rgb.enumerate_pixels() {
+            let a = alpha[[y as usize, x as usize]].clamp(0.0, 255.0) as u8;
+            result.put_pixel(x, y, Rgba([p[0], p[1], p[2], a]));
+        }
+        Ok((DynamicImage::ImageRgba8(result), embedded))
+    }
+}
+
+impl Default for WatermarkEmbedder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
@@ -206,6 +704,26 @@ mod tests {
         assert!(result.is_ok(), "嵌入应成功: {:?}", result.err());
     }
 
+    #[test]
+    fn test_extreme_aspect_ratio_reports_short_side() {
+        let embedder = WatermarkEmbedder::new();
+        // 4096×4：LL 仅 2 行，划分不出 4×4 块；一行块只有 512 个，需两行（16 px）
+        let err = embedder.embed_raw_text(&create_test_image(4096, 4), "banner", 0.5, false).unwrap_err().to_string();
+        assert!(err.contains("高度过窄") && err.contains("至少需要 16 px"), "{}", err);
+        let err = embedder.embed_raw_text(&create_test_image(4, 4096), "banner", 0.5, false).unwrap_err().to_string();
+        assert!(err.contains("宽度过窄") && err.contains("至少需要 16 px"), "{}", err);
+        // 1024×8：一行 128 块，544 位需 5 行块
+        let err = embedder.embed_raw_text(&create_test_image(1024, 8), "banner", 0.5, false).unwrap_err().to_string();
+        assert!(err.contains("至少需要 40 px"), "{}", err);
+
+        // 4096×16 的横幅有 1024 块，足够嵌入并提取
+        let banner = embedder.embed_raw_text(&create_test_image(4096, 16), "banner", 0.5, false).unwrap();
+        assert_eq!(banner.dimensions(), (4096, 16));
+        // 正方形图片仍为通用的容量不足错误
+        let err = embedder.embed_raw_text(&create_test_image(64, 64), "banner", 0.5, false).unwrap_err().to_string();
+        assert!(err.contains("图片太小"), "{}", err);
+    }
+
     #[test]
     fn test_embed_raw_text_basic() {
         let embedder = WatermarkEmbedder::new();
@@ -277,6 +795,80 @@ mod tests {
         assert_eq!(watermarked.height(), orig_h, "高度应保持不变");
     }
 
+    #[test]
+    fn test_downscale_embed_roundtrip() {
+        use crate::core::watermark::extractor::WatermarkExtractor;
+
+        let image = create_test_image(2048, 2048);
+        let embedder = WatermarkEmbedder::new().with_downscale_embed(512);
+        let watermarked = embedder.embed_raw_text(&image, "Downscale", 0.5, false).unwrap();
+        assert_eq!(watermarked.dimensions(), (2048, 2048), "应输出原尺寸");
+
+        let extractor = WatermarkExtractor::new().with_downscale(512);
+        assert_eq!(extractor.try_extract_text(&watermarked).unwrap().as_deref(), Some("Downscale"));
+    }
+
+    #[test]
+    fn test_block_fraction_roundtrip() {
+        use crate::core::watermark::extractor::WatermarkExtractor;
+
+        let image = create_test_image(512, 512);
+        let full = WatermarkEmbedder::new().embed_raw_text(&image, "Fraction", 0.5, false).unwrap();
+        let partial = WatermarkEmbedder::new()
+            .with_block_fraction(0.25)
+            .embed_raw_text(&image, "Fraction", 0.5, false)
+            .unwrap();
+
+        // 默认提取端自动识别比例；指定相同比例时只读取这部分块
+        let auto = WatermarkExtractor::new();
+        assert_eq!(auto.try_extract_text(&partial).unwrap().as_deref(), Some("Fraction"));
+        let known = WatermarkExtractor::new().with_block_fraction(0.25);
+        assert_eq!(known.try_extract_text(&partial).unwrap().as_deref(), Some("Fraction"));
+        assert_eq!(known.try_extract_text(&full).unwrap(), None, "指定比例时不识别全图格式");
+
+        // 块数不足时自动增大比例：256×256 仅 1024 块，1/4 不足 544，退回 1/2
+        let small = WatermarkEmbedder::new()
+            .with_block_fraction(0.25)
+            .embed_raw_text(&create_test_image(256, 256), "Small", 0.5, false)
+            .unwrap();
+        assert_eq!(auto.try_extract_text(&small).unwrap().as_deref(), Some("Small"));
+        assert_eq!(WatermarkEncoder::block_stride_for_fraction(0.25), 4);
+        assert_eq!(WatermarkEncoder::block_stride_for_fraction(0.3), 2);
+        assert_eq!(WatermarkEncoder::block_stride_for_fraction(1.0), 1);
+    }
+
+    #[test]
+    fn test_chained_payload_roundtrip() {
+        use crate::core::watermark::extractor::WatermarkExtractor;
+
+        let text: String = (0..200).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+        assert_eq!(text.len(), 200);
+        let image = create_test_image(512, 512);
+
+        // 未启用链式水印时超长文本报错
+        assert!(WatermarkEmbedder::new().embed_raw_text(&image, &text, 0.5, false).is_err());
+
+        let embedder = WatermarkEmbedder::new().with_chained_payload();
+        let watermarked = embedder.embed_raw_text(&image, &text, 0.5, false).unwrap();
+        let (extracted, confidence) = WatermarkExtractor::new()
+            .try_extract_text_with_confidence(&watermarked)
+            .unwrap()
+            .expect("应识别链式水印");
+        assert_eq!(extracted, text);
+        assert!(confidence > 0.5, "置信度过低: {}", confidence);
+
+        // 容量不足：128×128 → 1024 块 < 4 × 544 位
+        let small = create_test_image(128, 128);
+        assert!(embedder.embed_raw_text(&small, &text, 0.5, false).is_err());
+    }
+
+    #[test]
+    fn test_downscale_dimensions() {
+        assert_eq!(downscale_dimensions(512, 300, 512), None);
+        assert_eq!(downscale_dimensions(2048, 2048, 512), Some((512, 512)));
+        assert_eq!(downscale_dimensions(3000, 1001, 512), Some((512, 170)));
+    }
+
     #[test]
     fn test_embed_raw_text_fast_mode_small_image() {
         let embedder = WatermarkEmbedder::new();
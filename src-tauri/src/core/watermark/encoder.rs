@@ -1,5 +1,6 @@
 use md5::{Md5, Digest};
 use crate::models::{WatermarkData, BlindMarkError};
+use super::reed_solomon;
 
 // ─── 原始文本水印编码常量 ────────────────────────────────────────────────────────
 
@@ -11,6 +12,51 @@ pub const TEXT_WATERMARK_HEADER_BITS: usize = 32;
 pub const TEXT_WATERMARK_TOTAL_BITS: usize = 544;
 /// 文本 payload 最大字节数（UTF-8 编码后）
 pub const TEXT_WATERMARK_MAX_BYTES: usize = 64;
+/// MD5 十六进制摘要长度（md5 模式下 JSON / SVG 中存储的水印长度）
+pub const MD5_HEX_LEN: usize = 32;
+/// 链式水印槽位的魔数："WS"（slot）
+///
+/// 第二字节 0x53 的高 5 位与 `CHANNEL_MAGIC_TAG` 不同，不会与选择通道格式混淆。
+pub const TEXT_CHAIN_MAGIC: [u8; 2] = [0x57, 0x53];
+/// 链式水印的最大槽位数
+pub const TEXT_CHAIN_MAX_SLOTS: usize = 16;
+/// 每个槽位 payload 中记录 `[槽位序号, 槽位总数]` 的字节数
+const CHAIN_SLOT_HEADER_BYTES: usize = 2;
+/// 每个槽位可携带的文本字节数
+pub const TEXT_CHAIN_SLOT_BYTES: usize = TEXT_WATERMARK_MAX_BYTES - CHAIN_SLOT_HEADER_BYTES;
+/// 选择通道嵌入时魔数第二字节的高位标记，低 3 位为通道掩码（bit c = 通道 c 携带水印）
+///
+/// 标准魔数 0x4D 的 bit 3 为 1，而 0x40 | 掩码 的 bit 3 恒为 0，二者不会混淆。
+const CHANNEL_MAGIC_TAG: u8 = 0x40;
+/// 按比例选块嵌入时魔数第二字节的高位标记，低 3 位为选块间隔的 log2（1 ~ 4）
+///
+/// 0x60 的高 5 位与 `CHANNEL_MAGIC_TAG`、标准魔数 0x4D、链式魔数 0x53 均不同。
+const STRIDE_MAGIC_TAG: u8 = 0x60;
+/// Hilbert 扫描顺序文本水印的魔数："WH"
+///
+/// 第二字节 0x48 与标准魔数 0x4D、链式魔数 0x53 不同，且按 `& !0x07` 比较时
+/// 也不会被识别为 `CHANNEL_MAGIC_TAG` 或 `STRIDE_MAGIC_TAG` 格式。
+pub const TEXT_HILBERT_MAGIC: [u8; 2] = [0x57, 0x48];
+/// 结构化（CBOR）水印的魔数："WP"（payload）
+///
+/// 第二字节 0x50 与其他魔数均不同，按 `& !0x07` 比较时也不属于通道或选块格式。
+pub const TEXT_STRUCTURED_MAGIC: [u8; 2] = [0x57, 0x50];
+/// 带 Reed–Solomon 纠错的文本水印魔数："WR"
+///
+/// 第二字节 0x52 与其他魔数均不同，按 `& !0x07` 比较时也不属于通道或选块格式。
+pub const TEXT_ECC_MAGIC: [u8; 2] = [0x57, 0x52];
+/// 纠错格式的校验字节数，最多纠正其一半（8 个）字节错误
+pub const TEXT_ECC_PARITY_BYTES: usize = 16;
+/// 纠错格式的文本 payload 最大字节数：544 位减去头部与校验字节
+pub const TEXT_ECC_MAX_BYTES: usize = (TEXT_WATERMARK_TOTAL_BITS - TEXT_WATERMARK_HEADER_BITS) / 8 - TEXT_ECC_PARITY_BYTES;
+/// 按比例选块嵌入支持的选块间隔（每隔多少个块取一个），对应比例 1/2 ~ 1/16
+pub const TEXT_BLOCK_STRIDES: [usize; 4] = [2, 4, 8, 16];
+/// 各嵌入强度档位的 QIM 步长倍数：档位 n（1 ~ 10）对应强度 n / 10，强度 0.5 即默认步长
+///
+/// 档位记录在文本水印长度字段的高字节（见 `strength_level`）；默认步长记为 0，与未记录档位的旧图片一致。
+pub const STRENGTH_STEP_FACTORS: [f64; 10] = [0.6, 0.7, 0.8, 0.9, 1.0, 1.2, 1.4, 1.6, 1.8, 2.0];
+/// 每个 4×4 块在原图上对应的边长（1 级 DWT 后 LL 子带尺寸减半）
+const PIXELS_PER_BLOCK_SIDE: usize = 8;
 
 /// Watermark encoder for converting text to MD5 hash and binary sequence
 pub struct WatermarkEncoder;
@@ -43,6 +89,34 @@ impl WatermarkEncoder {
         WatermarkData::new(md5_hash, binary_sequence)
     }
 
+    /// Prepend `salt` to `text` for salted MD5 mode; a missing or empty salt leaves `text` unchanged
+    ///
+    /// Hashing the result stores `md5(salt || text)` instead of `md5(text)`, so reversing a
+    /// stored hash with a dictionary of buyer names requires knowing the salt. Note that a salt
+    /// changes every stored MD5 value: embedding, verification and lookup must all use the same one.
+    pub fn salted_text<'a>(text: &'a str, salt: Option<&str>) -> std::borrow::Cow<'a, str> {
+        match salt.filter(|s| !s.is_empty()) {
+            Some(salt) => std::borrow::Cow::Owned(format!("{}{}", salt, text)),
+            None => std::borrow::Cow::Borrowed(text),
+        }
+    }
+
+    /// Salted variant of `encode`: MD5 of `salt || text` (see `salted_text`)
+    pub fn encode_salted(text: &str, salt: Option<&str>) -> WatermarkData {
+        Self::encode(&Self::salted_text(text, salt))
+    }
+
+    /// Find which candidate text produced `md5_hash` under the given salt
+    ///
+    /// Comparison is case-insensitive on the hex digest. Returns the first matching candidate.
+    pub fn resolve_md5<'a, S: AsRef<str>>(md5_hash: &str, candidates: &'a [S], salt: Option<&str>) -> Option<&'a str> {
+        let target = md5_hash.trim().to_ascii_lowercase();
+        candidates
+            .iter()
+            .map(AsRef::as_ref)
+            .find(|text| Self::encode_salted(text, salt).md5_hash == target)
+    }
+
     /// Decode binary sequence back to MD5 hash string
     ///
     /// Takes a 128-bit binary sequence and converts it back to hex string format.
@@ -82,10 +156,148 @@ impl WatermarkEncoder {
 
     /// 将原始文本编码为固定 544 位比特序列（用于图片盲水印）
     ///
-    /// 格式：[魔数 2B: 0x57 0x4D][长度 2B u16 大端序][UTF-8文本][零填充]
+    /// 格式：[魔数 2B: 0x57 0x4D][长度 2B u16 大端序][UTF-8文本][零填充]；
+    /// 长度字段的高字节为嵌入强度档位，此处为 0（默认步长），嵌入时按强度写入（见 `mark_strength_level`）
     ///
-    /// 最大文本长度：64 字节（UTF-8 编码后），约 64 个 ASCII 字符或 21 个汉字
+    /// 最大文本长度：64 字节（UTF-8 编码后），约 64 个 ASCII 字符或 21 个汉字；
+    /// 更长的文本使用多槽位的链式格式（见 `text_to_chained_bits`）
     pub fn text_to_bits(text: &str) -> Result<Vec<u8>, BlindMarkError> {
+        Self::text_to_bits_with_magic(text, TEXT_WATERMARK_MAGIC)
+    }
+
+    /// 编码仅嵌入部分通道的文本水印，头部记录所用通道
+    ///
+    /// 格式同 `text_to_bits`，但魔数第二字节为 `0x40 | 通道掩码`，
+    /// 提取端据此得知应合并哪两个通道的软判决值。
+    pub fn text_to_bits_for_channels(text: &str, channels: [usize; 2]) -> Result<Vec<u8>, BlindMarkError> {
+        if channels[0] == channels[1] || channels.iter().any(|&c| c > 2) {
+            return Err(BlindMarkError::InvalidConfig(format!("无效的通道组合: {:?}", channels)));
+        }
+        let mask = channels.iter().fold(0u8, |m, &c| m | (1 << c));
+        Self::text_to_bits_with_magic(text, [TEXT_WATERMARK_MAGIC[0], CHANNEL_MAGIC_TAG | mask])
+    }
+
+    /// 编码按比例选块嵌入的文本水印，头部记录选块间隔 `stride`（须为 `TEXT_BLOCK_STRIDES` 之一）
+    ///
+    /// 格式同 `text_to_bits`，但魔数第二字节为 `0x60 | log2(stride)`，
+    /// 提取端据此得知水印只嵌入了每隔 `stride` 个块中的一个。
+    pub fn text_to_bits_for_stride(text: &str, stride: usize) -> Result<Vec<u8>, BlindMarkError> {
+        if !TEXT_BLOCK_STRIDES.contains(&stride) {
+            return Err(BlindMarkError::InvalidConfig(format!("无效的选块间隔: {}", stride)));
+        }
+        Self::text_to_bits_with_magic(text, [TEXT_WATERMARK_MAGIC[0], STRIDE_MAGIC_TAG | stride.trailing_zeros() as u8])
+    }
+
+    /// 编码按 Hilbert 顺序扫描块嵌入的文本水印（见 `dct::ScanOrder::Hilbert`）
+    ///
+    /// 格式同 `text_to_bits`，但魔数为 `TEXT_HILBERT_MAGIC`，提取端据此确认扫描顺序。
+    pub fn text_to_bits_for_hilbert(text: &str) -> Result<Vec<u8>, BlindMarkError> {
+        Self::text_to_bits_with_magic(text, TEXT_HILBERT_MAGIC)
+    }
+
+    /// 编码结构化水印的 CBOR payload（见 `cbor::to_cbor`）
+    ///
+    /// 格式同 `text_to_bits`，但魔数为 `TEXT_STRUCTURED_MAGIC`，payload 为任意字节；
+    /// 超过 `TEXT_WATERMARK_MAX_BYTES` 时报错。
+    pub fn structured_to_bits(payload: &[u8]) -> Result<Vec<u8>, BlindMarkError> {
+        if payload.len() > TEXT_WATERMARK_MAX_BYTES {
+            return Err(BlindMarkError::InvalidConfig(format!(
+                "结构化水印超出最大长度（{} 字节），CBOR 编码后 {} 字节",
+                TEXT_WATERMARK_MAX_BYTES, payload.len()
+            )));
+        }
+        Ok(Self::payload_to_bits(payload, TEXT_STRUCTURED_MAGIC))
+    }
+
+    /// 编码带 Reed–Solomon 纠错的文本水印，仍为 544 位
+    ///
+    /// 格式：[魔数 2B: 0x57 0x52][长度 2B u16 大端序][UTF-8文本][零填充][校验 16B]，
+    /// 校验字节覆盖前面全部 52 字节（含头部），提取时最多纠正 8 个字节错误（见 `decode_text_ecc`）。
+    /// 文本最长 `TEXT_ECC_MAX_BYTES`（48）字节，超出时报错。
+    pub fn encode_text_ecc(text: &str) -> Result<Vec<u8>, BlindMarkError> {
+        let bytes = text.as_bytes();
+        if bytes.len() > TEXT_ECC_MAX_BYTES {
+            return Err(BlindMarkError::InvalidConfig(format!(
+                "水印文本超出纠错格式最大长度（{} 字节），当前 {} 字节（UTF-8 编码后）",
+                TEXT_ECC_MAX_BYTES, bytes.len()
+            )));
+        }
+        let frame = Self::bits_to_bytes(&Self::payload_to_bits(bytes, TEXT_ECC_MAGIC));
+        let data_len = frame.len() - TEXT_ECC_PARITY_BYTES;
+        Ok(Self::bytes_to_bits(&reed_solomon::encode(&frame[..data_len], TEXT_ECC_PARITY_BYTES)))
+    }
+
+    /// 解析 `encode_text_ecc` 编码的 544 位比特序列：先纠正字节错误，再校验魔数并解码 UTF-8
+    ///
+    /// 错误超出纠错能力、魔数不匹配或 UTF-8 无效时返回 `None`
+    pub fn decode_text_ecc(bits: &[u8]) -> Option<String> {
+        if bits.len() != TEXT_WATERMARK_TOTAL_BITS {
+            return None;
+        }
+        let mut frame = Self::bits_to_bytes(bits);
+        reed_solomon::decode(&mut frame, TEXT_ECC_PARITY_BYTES)?;
+        match Self::parse_text_bits(&Self::bytes_to_bits(&frame))? {
+            (TEXT_ECC_MAGIC, text) if text.len() <= TEXT_ECC_MAX_BYTES => Some(text),
+            _ => None,
+        }
+    }
+
+    /// 将嵌入强度（0.1 ~ 1.0）换算为头部记录的强度档位，按 0.1 取整（见 `STRENGTH_STEP_FACTORS`）
+    ///
+    /// 对应默认步长的强度返回 0，嵌入结果与未引入强度档位前完全相同。
+    pub fn strength_level(strength: f32) -> u8 {
+        let level = (strength * 10.0).round().clamp(1.0, STRENGTH_STEP_FACTORS.len() as f32) as usize;
+        if STRENGTH_STEP_FACTORS[level - 1] == 1.0 { 0 } else { level as u8 }
+    }
+
+    /// 强度档位对应的 QIM 步长倍数；档位 0 为默认步长，无效档位返回 `None`
+    pub fn step_factor(level: u8) -> Option<f64> {
+        match level {
+            0 => Some(1.0),
+            n => STRENGTH_STEP_FACTORS.get(n as usize - 1).copied(),
+        }
+    }
+
+    /// 在每个 544 位单元（冗余副本、链式槽位）的头部写入强度档位（长度字段的高字节）
+    pub fn mark_strength_level(bits: &mut [u8], level: u8) {
+        for unit in bits.chunks_mut(TEXT_WATERMARK_TOTAL_BITS) {
+            for (i, bit) in unit.iter_mut().skip(16).take(8).enumerate() {
+                *bit = (level >> (7 - i)) & 1;
+            }
+        }
+    }
+
+    /// 读取首个 544 位单元头部记录的强度档位；比特数不足头部长度时返回 `None`
+    pub fn recorded_strength_level(bits: &[u8]) -> Option<u8> {
+        (bits.len() >= TEXT_WATERMARK_HEADER_BITS)
+            .then(|| bits[16..24].iter().fold(0u8, |b, &bit| (b << 1) | (bit & 1)))
+    }
+
+    /// 将比特序列（MSB 优先）按 8 位一组转换为字节，末尾不足 8 位的部分舍弃
+    fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
+        bits.chunks_exact(8)
+            .map(|byte| byte.iter().fold(0u8, |b, &bit| (b << 1) | (bit & 1)))
+            .collect()
+    }
+
+    /// 将字节展开为比特序列（MSB 优先）
+    fn bytes_to_bits(bytes: &[u8]) -> Vec<u8> {
+        bytes.iter().flat_map(|&b| (0..8).rev().map(move |i| (b >> i) & 1)).collect()
+    }
+
+    /// 将选块比例（0 ~ 1）换算为选块间隔：取比例不低于 `fraction` 的最大间隔
+    ///
+    /// 例如 0.25 → 4，0.3 → 2；`fraction` ≥ 1（或非法值）返回 1，即使用全部块。
+    pub fn block_stride_for_fraction(fraction: f32) -> usize {
+        TEXT_BLOCK_STRIDES
+            .iter()
+            .rev()
+            .copied()
+            .find(|&stride| 1.0 / stride as f32 >= fraction && fraction < 1.0)
+            .unwrap_or(1)
+    }
+
+    fn text_to_bits_with_magic(text: &str, magic: [u8; 2]) -> Result<Vec<u8>, BlindMarkError> {
         let bytes = text.as_bytes();
         if bytes.len() > TEXT_WATERMARK_MAX_BYTES {
             return Err(BlindMarkError::InvalidConfig(format!(
@@ -93,43 +305,261 @@ impl WatermarkEncoder {
                 TEXT_WATERMARK_MAX_BYTES, bytes.len()
             )));
         }
-        let len = bytes.len() as u16;
+        Ok(Self::payload_to_bits(bytes, magic))
+    }
+
+    /// 编码 544 位的一个槽位：[魔数 2B][长度 2B u16 大端序][payload][零填充]，调用方保证 payload 不超过 64 字节
+    fn payload_to_bits(payload: &[u8], magic: [u8; 2]) -> Vec<u8> {
+        let len = payload.len() as u16;
         let mut bits = Vec::with_capacity(TEXT_WATERMARK_TOTAL_BITS);
 
         // 魔数（2 字节，MSB 优先）
-        for &b in &TEXT_WATERMARK_MAGIC {
+        for &b in &magic {
             for i in (0..8usize).rev() { bits.push((b >> i) & 1); }
         }
         // 文本长度（u16 大端序，16 位，MSB 优先）
         for i in (0..16usize).rev() { bits.push(((len >> i) & 1) as u8); }
         // 文本字节（MSB 优先）
-        for &b in bytes {
+        for &b in payload {
             for i in (0..8usize).rev() { bits.push((b >> i) & 1); }
         }
         // 零填充至 544 位
         bits.resize(TEXT_WATERMARK_TOTAL_BITS, 0);
+        bits
+    }
+
+    /// 将超过 64 字节的文本编码为多个首尾相接的 544 位槽位（链式水印）
+    ///
+    /// 文本按字节切分为每段至多 `TEXT_CHAIN_SLOT_BYTES` 字节，每个槽位格式同 `text_to_bits`，
+    /// 但魔数为 `TEXT_CHAIN_MAGIC`，payload 前两字节为 `[槽位序号, 槽位总数]`。
+    /// 嵌入时整段序列循环铺满所有块，各槽位落在不同的块上；图片块数须不少于 `槽位数 × 544`。
+    ///
+    /// # 返回
+    /// 长度为 `槽位数 × 544` 的比特序列；文本需要超过 `TEXT_CHAIN_MAX_SLOTS` 个槽位时报错
+    pub fn text_to_chained_bits(text: &str) -> Result<Vec<u8>, BlindMarkError> {
+        let bytes = text.as_bytes();
+        let slots = bytes.len().div_ceil(TEXT_CHAIN_SLOT_BYTES).max(1);
+        if slots > TEXT_CHAIN_MAX_SLOTS {
+            return Err(BlindMarkError::InvalidConfig(format!(
+                "水印文本超出链式水印最大长度（{} 字节），当前 {} 字节（UTF-8 编码后）",
+                TEXT_CHAIN_MAX_SLOTS * TEXT_CHAIN_SLOT_BYTES, bytes.len()
+            )));
+        }
+
+        let mut bits = Vec::with_capacity(slots * TEXT_WATERMARK_TOTAL_BITS);
+        for index in 0..slots {
+            let start = (index * TEXT_CHAIN_SLOT_BYTES).min(bytes.len());
+            let end = (start + TEXT_CHAIN_SLOT_BYTES).min(bytes.len());
+            let mut payload = vec![index as u8, slots as u8];
+            payload.extend_from_slice(&bytes[start..end]);
+            bits.extend(Self::payload_to_bits(&payload, TEXT_CHAIN_MAGIC));
+        }
         Ok(bits)
     }
 
+    /// 解析 `text_to_chained_bits` 编码的 `slots` 个槽位并拼接还原文本
+    ///
+    /// 任一槽位魔数、序号或总数不符，或拼接后 UTF-8 无效时返回 `None`
+    pub fn chained_bits_to_text(bits: &[u8], slots: usize) -> Option<String> {
+        if slots == 0 || bits.len() != slots * TEXT_WATERMARK_TOTAL_BITS {
+            return None;
+        }
+        let mut bytes = Vec::new();
+        for (index, slot) in bits.chunks(TEXT_WATERMARK_TOTAL_BITS).enumerate() {
+            match Self::parse_payload_bits(slot)? {
+                (TEXT_CHAIN_MAGIC, payload)
+                    if payload.len() >= CHAIN_SLOT_HEADER_BYTES
+                        && payload[0] as usize == index
+                        && payload[1] as usize == slots =>
+                {
+                    bytes.extend_from_slice(&payload[CHAIN_SLOT_HEADER_BYTES..]);
+                }
+                _ => return None,
+            }
+        }
+        String::from_utf8(bytes).ok()
+    }
+
+    /// 计算能容纳 `text` 文本盲水印的最小正方形图片尺寸 `(宽, 高)`
+    ///
+    /// 1 级 DWT 后 LL 子带边长减半，每个 4×4 块嵌入 1 位，即原图每 8×8 像素 1 位。
+    /// 不超过 `TEXT_WATERMARK_MAX_BYTES` 的文本需 `544 × redundancy` 个块（同 `repeat_bits`）；
+    /// 更长的文本按链式水印计算（`槽位数 × 544` 位，链式嵌入不重复冗余份数）。
+    /// 边长向上取整为 2 的幂，与常见贴图尺寸一致，如短文本返回 256×256。
+    ///
+    /// 文本超出链式水印最大长度时报错。
+    pub fn min_dimensions_for_text(text: &str, redundancy: usize) -> Result<(u32, u32), BlindMarkError> {
+        let bits = if text.len() > TEXT_WATERMARK_MAX_BYTES {
+            Self::text_to_chained_bits(text)?.len()
+        } else {
+            TEXT_WATERMARK_TOTAL_BITS * redundancy.max(1)
+        };
+        let mut blocks_per_side = bits.isqrt();
+        if blocks_per_side * blocks_per_side < bits {
+            blocks_per_side += 1;
+        }
+        let side = (blocks_per_side * PIXELS_PER_BLOCK_SIDE).next_power_of_two() as u32;
+        Ok((side, side))
+    }
+
+    /// 为水印文本追加批次序号后缀，形如 `{text} [1/50]`（用于一包多卖的分批发货）
+    ///
+    /// 后缀始终完整保留；若拼接后超出 `TEXT_WATERMARK_MAX_BYTES`，
+    /// 则按字符边界截断原文本，保证结果仍可嵌入图片。
+    ///
+    /// # 参数
+    /// * `text`  - 原始水印文本
+    /// * `index` - 当前序号（1 起始）
+    /// * `total` - 总数
+    pub fn append_index(text: &str, index: usize, total: usize) -> String {
+        let suffix = format!(" [{}/{}]", index, total);
+        let budget = TEXT_WATERMARK_MAX_BYTES.saturating_sub(suffix.len());
+        let mut end = text.len().min(budget);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}{}", &text[..end], suffix)
+    }
+
+    /// 按水印模式预检文本长度（嵌入前调用）
+    ///
+    /// - 图片盲水印始终嵌入原始文本：仅在 `process_images` 时要求 UTF-8 编码后不超过
+    ///   `TEXT_WATERMARK_MAX_BYTES`，超出时报错
+    /// - JSON 的 plaintext / aes 模式不限长度
+    /// - md5 模式只存储 32 位十六进制摘要：文本超过 `MD5_HEX_LEN` 个字符时返回警告，
+    ///   水印中无法直接读出原文，只能凭候选列表反查
+    ///
+    /// # 返回
+    /// 需要提示用户的警告（无则为 `None`）
+    pub fn validate_watermark_text(
+        text: &str,
+        mode: &str,
+        process_images: bool,
+    ) -> Result<Option<String>, BlindMarkError> {
+        if process_images && text.len() > TEXT_WATERMARK_MAX_BYTES {
+            return Err(BlindMarkError::InvalidConfig(format!(
+                "水印文本「{}」超出图片水印最大长度（{} 字节），当前 {} 字节（UTF-8 编码后）；可缩短文本或关闭图片处理",
+                text, TEXT_WATERMARK_MAX_BYTES, text.len()
+            )));
+        }
+        let chars = text.chars().count();
+        if mode == "md5" && chars > MD5_HEX_LEN {
+            return Ok(Some(format!(
+                "水印文本「{}」共 {} 个字符，MD5 模式仅存储 {} 位摘要，原文只能通过候选列表反查",
+                text, chars, MD5_HEX_LEN
+            )));
+        }
+        Ok(None)
+    }
+
     /// 从比特序列中尝试解析原始文本水印
     ///
     /// 若魔数不匹配或 UTF-8 无效则返回 `None`（表示图片中无此格式水印）
     pub fn bits_to_text(bits: &[u8]) -> Option<String> {
+        match Self::parse_text_bits(bits)? {
+            (TEXT_WATERMARK_MAGIC, text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// 解析 `text_to_bits_for_channels` 编码的比特序列，返回 `(文本, 所用通道)`
+    ///
+    /// 标准三通道格式或魔数不匹配时返回 `None`
+    pub fn bits_to_text_for_channels(bits: &[u8]) -> Option<(String, [usize; 2])> {
+        let ([first, second], text) = Self::parse_text_bits(bits)?;
+        if first != TEXT_WATERMARK_MAGIC[0] || second & !0x07 != CHANNEL_MAGIC_TAG {
+            return None;
+        }
+        let channels: Vec<usize> = (0..3).filter(|c| second & (1 << c) != 0).collect();
+        match channels[..] {
+            [a, b] => Some((text, [a, b])),
+            _ => None,
+        }
+    }
+
+    /// 解析 `text_to_bits_for_stride` 编码的比特序列，返回 `(文本, 选块间隔)`
+    ///
+    /// 其他格式或魔数不匹配时返回 `None`
+    pub fn bits_to_text_for_stride(bits: &[u8]) -> Option<(String, usize)> {
+        let ([first, second], text) = Self::parse_text_bits(bits)?;
+        if first != TEXT_WATERMARK_MAGIC[0] || second & !0x07 != STRIDE_MAGIC_TAG {
+            return None;
+        }
+        let stride = 1usize << (second & 0x07);
+        TEXT_BLOCK_STRIDES.contains(&stride).then_some((text, stride))
+    }
+
+    /// 解析 `text_to_bits_for_hilbert` 编码的比特序列；其他格式或魔数不匹配时返回 `None`
+    pub fn bits_to_text_for_hilbert(bits: &[u8]) -> Option<String> {
+        match Self::parse_text_bits(bits)? {
+            (TEXT_HILBERT_MAGIC, text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// 解析 `structured_to_bits` 编码的比特序列，返回 CBOR payload；魔数不匹配时返回 `None`
+    pub fn bits_to_structured(bits: &[u8]) -> Option<Vec<u8>> {
+        match Self::parse_payload_bits(bits)? {
+            (TEXT_STRUCTURED_MAGIC, payload) => Some(payload),
+            _ => None,
+        }
+    }
+
+    /// 将比特序列首尾相接重复 `redundancy` 份（显式冗余，0 视为 1）
+    ///
+    /// 嵌入时要求图片至少有 `bits.len() × redundancy` 个块，保证每位至少有
+    /// `redundancy` 个副本，而不取决于图片尺寸。
+    pub fn repeat_bits(bits: &[u8], redundancy: usize) -> Vec<u8> {
+        bits.repeat(redundancy.max(1))
+    }
+
+    /// 统计 `repeat_bits` 序列中每位的得票率：第 i 位各副本中为 1 的比例（值域 [0, 1]）
+    ///
+    /// `payload_len` 为单份长度；末尾不足一份的部分同样计票。
+    pub fn vote_shares(bits: &[u8], payload_len: usize) -> Vec<f64> {
+        let mut ones = vec![0usize; payload_len];
+        let mut votes = vec![0usize; payload_len];
+        for (i, &bit) in bits.iter().enumerate() {
+            ones[i % payload_len] += bit as usize;
+            votes[i % payload_len] += 1;
+        }
+        ones.iter()
+            .zip(&votes)
+            .map(|(&o, &v)| if v == 0 { 0.5 } else { o as f64 / v as f64 })
+            .collect()
+    }
+
+    /// 对 `repeat_bits` 序列逐位多数表决，还原单份比特（平票判为 0）
+    pub fn majority_decode(bits: &[u8], payload_len: usize) -> Vec<u8> {
+        Self::vote_shares(bits, payload_len)
+            .into_iter()
+            .map(|share| (share > 0.5) as u8)
+            .collect()
+    }
+
+    /// 读取头部与文本，返回 `(魔数, 文本)`；魔数由调用方校验
+    fn parse_text_bits(bits: &[u8]) -> Option<([u8; 2], String)> {
+        let (magic, bytes) = Self::parse_payload_bits(bits)?;
+        String::from_utf8(bytes).ok().map(|text| (magic, text))
+    }
+
+    /// 读取头部与 payload 字节，返回 `(魔数, payload)`
+    fn parse_payload_bits(bits: &[u8]) -> Option<([u8; 2], Vec<u8>)> {
         if bits.len() < TEXT_WATERMARK_HEADER_BITS { return None; }
 
-        // 验证魔数（前 16 位）
+        // 读取魔数（前 16 位）
         let mut magic = [0u8; 2];
         for (mi, m) in magic.iter_mut().enumerate() {
             for j in 0..8 {
                 *m = (*m << 1) | bits[mi * 8 + j];
             }
         }
-        if magic != TEXT_WATERMARK_MAGIC { return None; }
 
-        // 读取长度（位 16-31，u16 大端序）
+        // 读取长度（位 16-31，u16 大端序）；高字节为强度档位（见 `mark_strength_level`）
         let mut len = 0u16;
         for j in 0..16 { len = (len << 1) | (bits[16 + j] as u16); }
-        let len = len as usize;
+        Self::step_factor((len >> 8) as u8)?;
+        let len = (len & 0xFF) as usize;
         if len > TEXT_WATERMARK_MAX_BYTES { return None; }
 
         // 读取文本字节
@@ -144,7 +574,7 @@ impl WatermarkEncoder {
             bytes.push(byte);
         }
 
-        String::from_utf8(bytes).ok()
+        Some((magic, bytes))
     }
 }
 
@@ -152,6 +582,85 @@ impl WatermarkEncoder {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_validate_watermark_text_by_mode() {
+        let long = "x".repeat(TEXT_WATERMARK_MAX_BYTES + 1);
+        let cjk = "买家".repeat(11); // 22 个字符，66 字节
+
+        // 图片处理时按字节限制，与模式无关
+        for mode in ["md5", "plaintext", "aes"] {
+            assert!(WatermarkEncoder::validate_watermark_text(&long, mode, true).is_err());
+            assert!(WatermarkEncoder::validate_watermark_text(&cjk, mode, true).is_err());
+        }
+        assert!(WatermarkEncoder::validate_watermark_text(&"x".repeat(64), "plaintext", true).unwrap().is_none());
+
+        // 不处理图片时 plaintext / aes 不限长度
+        assert_eq!(WatermarkEncoder::validate_watermark_text(&long, "plaintext", false).unwrap(), None);
+        assert_eq!(WatermarkEncoder::validate_watermark_text(&long.repeat(10), "aes", false).unwrap(), None);
+
+        // md5 模式：超过 32 个字符时警告（按字符计），不报错
+        let warning = WatermarkEncoder::validate_watermark_text(&long, "md5", false).unwrap().unwrap();
+        assert!(warning.contains("65 个字符"), "{}", warning);
+        assert!(WatermarkEncoder::validate_watermark_text(&"x".repeat(32), "md5", true).unwrap().is_none());
+        assert!(WatermarkEncoder::validate_watermark_text(&cjk, "md5", false).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_text_ecc_recovers_flipped_bits() {
+        use rand::{rngs::SmallRng, seq::index::sample, Rng, SeedableRng};
+
+        let text = "买家张三 #20240601";
+        let bits = WatermarkEncoder::encode_text_ecc(text).unwrap();
+        assert_eq!(bits.len(), TEXT_WATERMARK_TOTAL_BITS);
+        assert_eq!(WatermarkEncoder::decode_text_ecc(&bits).as_deref(), Some(text));
+        // 不影响原有格式：标准解码不识别纠错格式
+        assert_eq!(WatermarkEncoder::bits_to_text(&bits), None);
+
+        let frame_bytes = TEXT_WATERMARK_TOTAL_BITS / 8;
+        let mut rng = SmallRng::seed_from_u64(752);
+        let corrupt = |bad_bytes: usize, rng: &mut SmallRng| {
+            let mut noisy = bits.clone();
+            for byte in sample(rng, frame_bytes, bad_bytes) {
+                // 每个坏字节随机翻转 1 ~ 8 位
+                let mask: u8 = rng.gen_range(1..=255);
+                for i in 0..8 {
+                    noisy[byte * 8 + i] ^= (mask >> (7 - i)) & 1;
+                }
+            }
+            noisy
+        };
+        for _ in 0..50 {
+            let bad_bytes = rng.gen_range(1..=TEXT_ECC_PARITY_BYTES / 2);
+            let noisy = corrupt(bad_bytes, &mut rng);
+            assert_eq!(WatermarkEncoder::decode_text_ecc(&noisy).as_deref(), Some(text), "{} 个坏字节应可纠正", bad_bytes);
+        }
+        // 超出纠错能力时不会还原出原文
+        for _ in 0..20 {
+            let noisy = corrupt(TEXT_ECC_PARITY_BYTES / 2 + 1, &mut rng);
+            assert_ne!(WatermarkEncoder::decode_text_ecc(&noisy).as_deref(), Some(text));
+        }
+
+        assert!(WatermarkEncoder::encode_text_ecc(&"x".repeat(TEXT_ECC_MAX_BYTES)).is_ok());
+        assert!(WatermarkEncoder::encode_text_ecc(&"x".repeat(TEXT_ECC_MAX_BYTES + 1)).is_err());
+    }
+
+    #[test]
+    fn test_salted_md5_and_resolve() {
+        let plain = WatermarkEncoder::encode("alice").md5_hash;
+        let salted = WatermarkEncoder::encode_salted("alice", Some("pepper")).md5_hash;
+        assert_ne!(plain, salted, "加盐后哈希应不同");
+        assert_eq!(salted, WatermarkEncoder::encode("pepperalice").md5_hash);
+        assert_eq!(WatermarkEncoder::encode_salted("alice", Some("")).md5_hash, plain);
+        assert_eq!(WatermarkEncoder::encode_salted("alice", None).md5_hash, plain);
+
+        let buyers = ["bob", "alice", "carol"];
+        assert_eq!(WatermarkEncoder::resolve_md5(&salted, &buyers, Some("pepper")), Some("alice"));
+        assert_eq!(WatermarkEncoder::resolve_md5(&salted.to_uppercase(), &buyers, Some("pepper")), Some("alice"));
+        // 缺少盐值时无法通过字典反查
+        assert_eq!(WatermarkEncoder::resolve_md5(&salted, &buyers, None), None);
+        assert_eq!(WatermarkEncoder::resolve_md5(&plain, &buyers, None), Some("alice"));
+    }
+
     #[test]
     fn test_encode_known_text() {
         let text = "Hello, World!";
@@ -232,6 +741,94 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_append_index_suffix() {
+        let text = WatermarkEncoder::append_index("购买者:张三", 1, 50);
+        assert_eq!(text, "购买者:张三 [1/50]");
+
+        // 追加序号后的文本应能完整嵌入并还原
+        let bits = WatermarkEncoder::text_to_bits(&text).unwrap();
+        let decoded = WatermarkEncoder::bits_to_text(&bits).unwrap();
+        assert!(decoded.ends_with(" [1/50]"), "嵌入值应包含序号后缀: {}", decoded);
+    }
+
+    #[test]
+    fn test_append_index_truncates_to_fit() {
+        // 21 个汉字 = 63 字节，追加后缀后超限，应截断原文本并保留完整后缀
+        let long_text = "张".repeat(21);
+        let text = WatermarkEncoder::append_index(&long_text, 12, 50);
+        assert!(text.len() <= TEXT_WATERMARK_MAX_BYTES, "结果应不超过最大字节数");
+        assert!(text.ends_with(" [12/50]"));
+        assert!(WatermarkEncoder::text_to_bits(&text).is_ok());
+    }
+
+    #[test]
+    fn test_text_roundtrip_for_channels() {
+        let bits = WatermarkEncoder::text_to_bits_for_channels("alice", [0, 1]).unwrap();
+        assert_eq!(bits.len(), TEXT_WATERMARK_TOTAL_BITS);
+        assert_eq!(
+            WatermarkEncoder::bits_to_text_for_channels(&bits),
+            Some(("alice".to_string(), [0, 1]))
+        );
+        // 两种格式互不识别
+        assert!(WatermarkEncoder::bits_to_text(&bits).is_none());
+        let standard = WatermarkEncoder::text_to_bits("alice").unwrap();
+        assert!(WatermarkEncoder::bits_to_text_for_channels(&standard).is_none());
+
+        assert!(WatermarkEncoder::text_to_bits_for_channels("alice", [1, 1]).is_err());
+        assert!(WatermarkEncoder::text_to_bits_for_channels("alice", [0, 3]).is_err());
+    }
+
+    #[test]
+    fn test_redundancy_survives_bit_flips() {
+        let bits = WatermarkEncoder::text_to_bits("redundant").unwrap();
+        // 重复 `redundancy` 份后，在前 `damaged` 份的相同位置各翻转 21 位（模拟局部损坏）
+        let decode_damaged = |redundancy: usize, damaged: usize| {
+            let mut repeated = WatermarkEncoder::repeat_bits(&bits, redundancy);
+            assert_eq!(repeated.len(), TEXT_WATERMARK_TOTAL_BITS * redundancy);
+            for copy in 0..damaged {
+                for i in (0..TEXT_WATERMARK_TOTAL_BITS).step_by(27) {
+                    repeated[copy * TEXT_WATERMARK_TOTAL_BITS + i] ^= 1;
+                }
+            }
+            let decoded = WatermarkEncoder::majority_decode(&repeated, TEXT_WATERMARK_TOTAL_BITS);
+            WatermarkEncoder::bits_to_text(&decoded)
+        };
+
+        assert_eq!(decode_damaged(1, 0).as_deref(), Some("redundant"));
+        assert_ne!(decode_damaged(1, 1).as_deref(), Some("redundant"), "单份损坏后无法还原");
+        assert_ne!(decode_damaged(3, 2).as_deref(), Some("redundant"), "损坏副本占多数时无法还原");
+        assert_eq!(decode_damaged(5, 2).as_deref(), Some("redundant"), "5 份中 2 份损坏仍可多数表决还原");
+    }
+
+    #[test]
+    fn test_min_dimensions_for_text() {
+        assert_eq!(WatermarkEncoder::min_dimensions_for_text("alice", 1).unwrap(), (256, 256));
+        assert_eq!(WatermarkEncoder::min_dimensions_for_text(&"x".repeat(TEXT_WATERMARK_MAX_BYTES), 1).unwrap(), (256, 256));
+        // 3 份冗余需 1632 块，256×256 仅 1024 块
+        assert_eq!(WatermarkEncoder::min_dimensions_for_text("alice", 3).unwrap(), (512, 512));
+
+        // 接近链式上限的长文本：16 个槽位共 8704 位
+        let near_max = "x".repeat(TEXT_CHAIN_MAX_SLOTS * TEXT_CHAIN_SLOT_BYTES - 1);
+        assert_eq!(WatermarkEncoder::min_dimensions_for_text(&near_max, 1).unwrap(), (1024, 1024));
+        let too_long = "x".repeat(TEXT_CHAIN_MAX_SLOTS * TEXT_CHAIN_SLOT_BYTES + 1);
+        assert!(WatermarkEncoder::min_dimensions_for_text(&too_long, 1).is_err());
+    }
+
+    #[test]
+    fn test_chained_bits_roundtrip() {
+        let text = "长".repeat(40); // 120 字节，跨越槽位边界切分多字节字符
+        let bits = WatermarkEncoder::text_to_chained_bits(&text).unwrap();
+        assert_eq!(bits.len(), 2 * TEXT_WATERMARK_TOTAL_BITS);
+        assert_eq!(WatermarkEncoder::chained_bits_to_text(&bits, 2).as_deref(), Some(text.as_str()));
+        // 槽位数不符或普通格式都不被接受
+        assert!(WatermarkEncoder::chained_bits_to_text(&bits[..TEXT_WATERMARK_TOTAL_BITS], 1).is_none());
+        assert!(WatermarkEncoder::bits_to_text(&bits[..TEXT_WATERMARK_TOTAL_BITS]).is_none());
+
+        let too_long = "x".repeat(TEXT_CHAIN_MAX_SLOTS * TEXT_CHAIN_SLOT_BYTES + 1);
+        assert!(WatermarkEncoder::text_to_chained_bits(&too_long).is_err());
+    }
+
     #[test]
     fn test_bits_to_text_invalid_magic() {
         let mut bits = vec![0u8; TEXT_WATERMARK_TOTAL_BITS];
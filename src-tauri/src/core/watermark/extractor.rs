@@ -1,12 +1,89 @@
-use image::DynamicImage;
+use image::{DynamicImage, RgbImage};
 use ndarray::Array2;
 use crate::models::BlindMarkError;
 use crate::core::watermark::{
-    dwt::DWTProcessor,
-    dct::DCTProcessor,
-    encoder::{WatermarkEncoder, TEXT_WATERMARK_TOTAL_BITS},
+    cbor,
+    dwt::{DWTProcessor, Subband},
+    dct::{DCTProcessor, ScanOrder},
+    jpeg::{self, JPEG_QIM_STEP},
+    embedder::{downscale_dimensions, DOWNSCALE_FILTER},
+    profile::Profile,
+    encoder::{WatermarkEncoder, STRENGTH_STEP_FACTORS, TEXT_BLOCK_STRIDES, TEXT_CHAIN_MAX_SLOTS, TEXT_WATERMARK_HEADER_BITS, TEXT_WATERMARK_MAGIC, TEXT_WATERMARK_MAX_BYTES, TEXT_WATERMARK_TOTAL_BITS},
 };
 
+/// 文本水印某一格式的块布局：`(按该格式顺序排列的三通道逐块奇异值, 循环周期, 冗余份数)`，见 `header_matches`
+type HeaderLayout<'a> = (&'a [Vec<Option<[f64; 2]>>; 3], usize, usize);
+
+/// 容错模式下依次尝试的 gamma 校正系数
+///
+/// 覆盖 Mac 1.8 ↔ sRGB 2.2 的转换以及常见工具保存时的小幅 gamma 偏移。
+const TOLERANT_GAMMA_CANDIDATES: [f64; 6] = [1.05, 1.0 / 1.05, 1.1, 1.0 / 1.1, 2.2 / 1.8, 1.8 / 2.2];
+
+/// 容错模式下视为可信、可提前结束搜索的置信度
+const TOLERANT_ACCEPT_CONFIDENCE: f32 = 0.9;
+
+/// `extract_any` 接受 MD5 水印所需的最低置信度
+///
+/// MD5 没有魔数校验，任意图片都能解出 128 位；未嵌入水印的图片置信度约 0.3，
+/// 已嵌入的接近 1.0，以此区分。
+const MD5_ACCEPT_CONFIDENCE: f32 = 0.6;
+
+/// `quick_detect` 每个通道抽样的块数
+const QUICK_DETECT_SAMPLE_BLOCKS: usize = 256;
+
+/// `quick_detect` 视为单个通道可能有水印的最低 QIM 余数聚集度
+///
+/// 未嵌入水印的纹理图片聚集度接近 0，已嵌入的在 0.9 以上。
+const QUICK_DETECT_CLUSTERING: f64 = 0.5;
+
+/// `quick_detect` 要求达到聚集度阈值的最少通道数
+///
+/// 纯色通道的各块奇异值相同，单个通道也会呈现聚集；而水印至少嵌入两个通道（见通道选择）。
+const QUICK_DETECT_MIN_CHANNELS: usize = 2;
+
+/// `extract_any` 的结果：自动识别图片中嵌入的水印类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractedWatermark {
+    /// 原始文本水印（已通过魔数校验）
+    Text(String),
+    /// MD5 水印哈希（32 位十六进制）
+    Md5(String),
+    /// 未找到可信的水印
+    None,
+}
+
+/// `WatermarkExtractor::diagnose` 的结论：文本水印提取成功，或失败在哪一步
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum ExtractionDiagnosis {
+    /// 图片宽或高为奇数，无法做 DWT
+    OddDimensions { width: u32, height: u32 },
+    /// 图片可用块数不足以容纳文本水印载荷
+    TooSmall { capacity: usize, required: usize },
+    /// 解出的比特中没有文本水印魔数
+    NoMagic,
+    /// 魔数匹配，但 payload 无法还原为文本（不是合法的 UTF-8，或长度字段越界）
+    InvalidUtf8,
+    /// 成功提取文本水印
+    Valid { text: String },
+}
+
+/// 各 RGB 通道单独判决的诊断结果（见 `WatermarkExtractor::extract_channel_diagnostics`）
+///
+/// 正常嵌入的图片三通道完全一致；某一通道误码率明显偏高，说明该通道被滤镜、
+/// 调色等处理破坏。
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerChannelDiagnostics {
+    /// 三通道软判决和按阈值 1.5 得到的多数判决比特
+    pub majority_bits: Vec<u8>,
+    /// 各通道单独按阈值 0.5 判决的比特，顺序为 R、G、B
+    pub channel_bits: [Vec<u8>; 3],
+    /// 各通道相对多数判决的误码率，值域 [0, 1]
+    pub bit_error_rates: [f64; 3],
+    /// 误码率最低的通道（0 = R，1 = G，2 = B；并列时取序号小者）
+    pub cleanest_channel: usize,
+}
+
 /// 完整的水印提取流水线
 ///
 /// ## 算法（与 Python blind_watermark 完全一致）
@@ -20,6 +97,11 @@ use crate::core::watermark::{
 pub struct WatermarkExtractor {
     dwt: DWTProcessor,
     dct: DCTProcessor,
+    redundancy: usize,
+    downscale: Option<u32>,
+    min_valid_margin: f32,
+    block_stride: usize,
+    subband: Subband,
 }
 
 impl WatermarkExtractor {
@@ -27,9 +109,79 @@ impl WatermarkExtractor {
         Self {
             dwt: DWTProcessor::new(),
             dct: DCTProcessor::new(),
+            redundancy: 1,
+            downscale: None,
+            min_valid_margin: 0.0,
+            block_stride: 1,
+            subband: Subband::Ll,
+        }
+    }
+
+    /// 使用自定义密码（打乱种子，见 `dct::password_seed`）创建，嵌入与提取须使用相同密码
+    pub fn with_password(password: u64) -> Self {
+        Self {
+            dwt: DWTProcessor::new(),
+            dct: DCTProcessor::with_password(password),
+            redundancy: 1,
+            downscale: None,
+            min_valid_margin: 0.0,
+            block_stride: 1,
+            subband: Subband::Ll,
         }
     }
 
+    /// 应用预设档位（见 `Profile`、`WatermarkEmbedder::with_profile`）：QIM 步长、提取子带与冗余份数
+    ///
+    /// 通道选择由头部记录，提取端自动识别。
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        let [d1, d2] = profile.qim_steps();
+        self.dct = self.dct.with_qim_steps(d1, d2);
+        self.subband = profile.subband();
+        self.with_redundancy(profile.redundancy())
+    }
+
+    /// 设置静默边框：距图片边缘 `margin` 像素内的区域不嵌入水印，
+    /// 使裁掉少量边框后仍保留完整的水印副本。图片中不记录该值，嵌入与提取须使用相同边框
+    pub fn with_quiet_zone(mut self, margin: u32) -> Self {
+        // 1 级 DWT 后 LL 子带尺寸减半
+        self.dct = self.dct.with_margin((margin as usize).div_ceil(2));
+        self
+    }
+
+    /// 设置文本水印的冗余份数（见 `WatermarkEmbedder::with_redundancy`，默认 1）
+    ///
+    /// 大于 1 时按 `544 × redundancy` 位提取，各通道对每位的副本逐个判决后多数表决，
+    /// 图片可用块数不足时视为无水印。
+    pub fn with_redundancy(mut self, redundancy: usize) -> Self {
+        self.redundancy = redundancy.max(1);
+        self
+    }
+
+    /// 设置降采样提取（见 `WatermarkEmbedder::with_downscale_embed`）：长边超过 `max_dim`
+    /// 的图片先缩小到与嵌入端相同的尺寸再提取 RGB 通道水印，须与嵌入端使用相同的 `max_dim`
+    pub fn with_downscale(mut self, max_dim: u32) -> Self {
+        self.downscale = Some(max_dim);
+        self
+    }
+
+    /// 设置按比例选块提取（见 `WatermarkEmbedder::with_block_fraction`）：只读取该比例对应的块
+    ///
+    /// 未设置时读取全部块并自动识别头部记录的比例；已知嵌入比例时设置此项可减少 DCT / SVD 计算量，
+    /// 但只能识别以相同比例嵌入的文本水印。MD5 水印不受影响。
+    pub fn with_block_fraction(mut self, fraction: f32) -> Self {
+        self.block_stride = WatermarkEncoder::block_stride_for_fraction(fraction);
+        self
+    }
+
+    /// 设置文本水印魔数的最低判决裕度（见 `magic_margin`，值域 [0, 1]，默认 0 即不限制）
+    ///
+    /// 魔数各位须以不低于该裕度的把握解出才接受，用于批量扫描时排除未嵌入水印的图片
+    /// 偶然解出魔数且 UTF-8 合法的误报。干净水印的裕度接近 1，0.3 左右即可排除绝大多数巧合。
+    pub fn with_min_valid_margin(mut self, margin: f32) -> Self {
+        self.min_valid_margin = margin.clamp(0.0, 1.0);
+        self
+    }
+
     /// 从图片中提取 MD5 水印哈希字符串
     pub fn extract(&self, image: &DynamicImage) -> Result<String, BlindMarkError> {
         let soft_sum = self.extract_soft_sum(image, 128)?;
@@ -40,10 +192,24 @@ impl WatermarkExtractor {
         WatermarkEncoder::decode(&bits)
     }
 
-    /// 提取 MD5 水印并返回置信度（保留接口，置信度固定为 1.0）
+    /// 提取 `wm_size` 位原始比特序列（`WatermarkEmbedder::embed_bits_public` 的逆操作）
+    ///
+    /// 不做任何格式校验：未嵌入水印的图片同样返回 `wm_size` 位（近似随机）结果，
+    /// 调用方需自行校验 payload。图片可用块数少于 `wm_size` 时返回错误。
+    pub fn extract_bits(&self, image: &DynamicImage, wm_size: usize) -> Result<Vec<u8>, BlindMarkError> {
+        let soft_sum = self.extract_soft_sum(image, wm_size)?;
+        Ok(soft_sum.iter().map(|&v| (v > 1.5) as u8).collect())
+    }
+
+    /// 提取 MD5 水印并返回置信度（见 `soft_confidence`）
     pub fn extract_with_confidence(&self, image: &DynamicImage) -> Result<(String, f32), BlindMarkError> {
-        let md5_hash = self.extract(image)?;
-        Ok((md5_hash, 1.0))
+        let soft_sum = self.extract_soft_sum(image, 128)?;
+        let bits: Vec<u8> = soft_sum
+            .iter()
+            .map(|&v| if v > 1.5 { 1u8 } else { 0u8 })
+            .collect();
+        let md5_hash = WatermarkEncoder::decode(&bits)?;
+        Ok((md5_hash, soft_confidence(&soft_sum)))
     }
 
     /// 尝试从图片中提取原始文本盲水印
@@ -53,27 +219,231 @@ impl WatermarkExtractor {
     /// * `Ok(None)` — 图片没有此格式水印（魔数不匹配、图片太小等）
     /// * `Err(...)` — 图片处理本身失败
     pub fn try_extract_text(&self, image: &DynamicImage) -> Result<Option<String>, BlindMarkError> {
-        let soft_sum = match self.extract_soft_sum(image, TEXT_WATERMARK_TOTAL_BITS) {
+        Ok(self.decode_text(image).map(|(text, _)| text))
+    }
+
+    /// 尝试提取原始文本盲水印，并返回置信度（0.0 - 1.0）
+    ///
+    /// 返回值语义同 `try_extract_text`；置信度用于过滤未嵌入水印的图片中
+    /// 偶然出现魔数的误报。
+    pub fn try_extract_text_with_confidence(
+        &self,
+        image: &DynamicImage,
+    ) -> Result<Option<(String, f32)>, BlindMarkError> {
+        Ok(self.decode_text(image).map(|(text, soft_sum)| (text, soft_confidence(&soft_sum))))
+    }
+
+    /// 容错提取原始文本盲水印：适用于被其他工具重新保存、像素值发生轻微 gamma 偏移的图片
+    ///
+    /// 1. 先归一化为 8 位 sRGB（丢弃 alpha / 16 位深度；`image` 解码时不应用 ICC 与 gAMA，
+    ///    即忽略嵌入的色彩配置）后直接提取
+    /// 2. 置信度不足时，依次对像素做 `TOLERANT_GAMMA_CANDIDATES` 中的 gamma 逆校正再提取，
+    ///    返回置信度最高的结果
+    ///
+    /// 返回值语义同 `try_extract_text_with_confidence`。
+    pub fn try_extract_text_tolerant(
+        &self,
+        image: &DynamicImage,
+    ) -> Result<Option<(String, f32)>, BlindMarkError> {
+        let rgb = image.to_rgb8();
+
+        let mut best = self.try_extract_text_with_confidence(&DynamicImage::ImageRgb8(rgb.clone()))?;
+        for gamma in TOLERANT_GAMMA_CANDIDATES {
+            if best.as_ref().is_some_and(|(_, c)| *c >= TOLERANT_ACCEPT_CONFIDENCE) {
+                break;
+            }
+            let corrected = DynamicImage::ImageRgb8(apply_gamma(&rgb, gamma));
+            if let Some((text, confidence)) = self.try_extract_text_with_confidence(&corrected)? {
+                if best.as_ref().is_none_or(|(_, c)| confidence > *c) {
+                    best = Some((text, confidence));
+                }
+            }
+        }
+
+        Ok(best)
+    }
+
+    /// 尝试提取 `WatermarkEmbedder::embed_raw_text_jpeg` 嵌入的 JPEG DCT 域文本水印
+    ///
+    /// 返回值语义同 `try_extract_text`。头部记录的强度档位自动识别（同 `decode_text`）；
+    /// 魔数裕度低于 `with_min_valid_margin` 的结果视为巧合。不适用降采样、子带与冗余设置。
+    pub fn try_extract_text_jpeg(&self, image: &DynamicImage) -> Result<Option<String>, BlindMarkError> {
+        Ok(self.try_extract_text_jpeg_with_confidence(image)?.map(|(text, _)| text))
+    }
+
+    /// 同 `try_extract_text_jpeg`，并返回置信度（见 `soft_confidence`）
+    pub fn try_extract_text_jpeg_with_confidence(
+        &self,
+        image: &DynamicImage,
+    ) -> Result<Option<(String, f32)>, BlindMarkError> {
+        let coefficients = jpeg::extract_block_coefficients(&image.to_rgb8(), self.dct.password());
+        if coefficients.len() < TEXT_WATERMARK_TOTAL_BITS {
+            return Ok(None);
+        }
+        Ok(text_step_factors().find_map(|factor| {
+            // 先只解码头部，档位与所试步长不符时跳过完整解码
+            let header: Vec<u8> = (0..TEXT_WATERMARK_HEADER_BITS)
+                .map(|i| {
+                    let (sum, count) = coefficients[i..]
+                        .iter()
+                        .step_by(TEXT_WATERMARK_TOTAL_BITS)
+                        .fold((0.0, 0usize), |(sum, count), c| (sum + jpeg::block_soft(c, JPEG_QIM_STEP * factor), count + 1));
+                    (sum / count as f64 > 0.5) as u8
+                })
+                .collect();
+            if header_step_factor(&header) != Some(factor) {
+                return None;
+            }
+            let block_softs = jpeg::block_softs(&coefficients, JPEG_QIM_STEP * factor);
+            let soft = DCTProcessor::cyclic_average(&block_softs, TEXT_WATERMARK_TOTAL_BITS).ok()?;
+            let bits: Vec<u8> = soft.iter().map(|&v| (v > 0.5) as u8).collect();
+            let text = WatermarkEncoder::bits_to_text(&bits)?;
+            let recorded = WatermarkEncoder::recorded_strength_level(&bits).and_then(WatermarkEncoder::step_factor);
+            // 单平面软判决值域 [0, 1]，换算到三通道和的 [0, 3] 以复用裕度与置信度
+            let soft_sum: Vec<f64> = soft.iter().map(|v| v * 3.0).collect();
+            (recorded == Some(factor) && magic_margin(&soft_sum) >= self.min_valid_margin)
+                .then(|| (text, soft_confidence(&soft_sum)))
+        }))
+    }
+
+    /// 自动识别并提取水印：先尝试原始文本水印（魔数校验），失败后回退到 MD5
+    ///
+    /// MD5 结果仅在置信度不低于 `MD5_ACCEPT_CONFIDENCE` 时返回，否则视为无水印。
+    /// 图片本身无法处理（奇数尺寸、过小）时返回错误。
+    pub fn extract_any(&self, image: &DynamicImage) -> Result<ExtractedWatermark, BlindMarkError> {
+        if let Some(text) = self.try_extract_text(image)? {
+            return Ok(ExtractedWatermark::Text(text));
+        }
+        let (md5_hash, confidence) = self.extract_with_confidence(image)?;
+        if confidence >= MD5_ACCEPT_CONFIDENCE {
+            Ok(ExtractedWatermark::Md5(md5_hash))
+        } else {
+            Ok(ExtractedWatermark::None)
+        }
+    }
+
+    /// 尝试从 alpha 平面提取第二个原始文本水印（见 `WatermarkEmbedder::embed_raw_text_dual`）
+    ///
+    /// 无 alpha、alpha 全不透明或未找到水印时返回 `Ok(None)`。
+    pub fn try_extract_alpha_text(&self, image: &DynamicImage) -> Result<Option<String>, BlindMarkError> {
+        if !image.color().has_alpha() {
+            return Ok(None);
+        }
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        if width % 2 != 0 || height % 2 != 0 {
+            return Ok(None);
+        }
+        let mut alpha: Array2<f64> = Array2::zeros((height as usize, width as usize));
+        for (x, y, p) in rgba.enumerate_pixels() {
+            alpha[[y as usize, x as usize]] = p[3] as f64;
+        }
+        if alpha.iter().all(|&a| a >= 255.0) {
+            return Ok(None);
+        }
+
+        let soft = match self.extract_plane_soft(&alpha, TEXT_WATERMARK_TOTAL_BITS) {
             Ok(s) => s,
             Err(_) => return Ok(None),
         };
-
-        // 三通道各贡献 [0,1]，总和在 [0,3]，阈值 1.5
-        let bits: Vec<u8> = soft_sum
+        // 单平面软判决值域 [0,1]，阈值 0.5
+        let bits: Vec<u8> = soft
             .iter()
-            .map(|&v| if v > 1.5 { 1u8 } else { 0u8 })
+            .map(|&v| if v > 0.5 { 1u8 } else { 0u8 })
             .collect();
-
         Ok(WatermarkEncoder::bits_to_text(&bits))
     }
 
+    /// 同时读取两个数据平面：`(RGB 平面文本水印, alpha 平面文本水印)`
+    pub fn try_extract_text_planes(
+        &self,
+        image: &DynamicImage,
+    ) -> Result<(Option<String>, Option<String>), BlindMarkError> {
+        Ok((self.try_extract_text(image)?, self.try_extract_alpha_text(image)?))
+    }
+
     /// 提取原始文本水印（若无则返回错误）
     pub fn extract_text(&self, image: &DynamicImage) -> Result<String, BlindMarkError> {
         self.try_extract_text(image)?.ok_or_else(|| {
-            BlindMarkError::ExtractionFailed("图片中未找到原始文本盲水印".to_string())
+            BlindMarkError::WatermarkNotFound("图片中未找到原始文本盲水印".to_string())
         })
     }
 
+    /// 提取 `WatermarkEmbedder::embed_structured` 嵌入的结构化水印并解码为 JSON 值
+    ///
+    /// 魔数不匹配或判决裕度不足时返回 `WatermarkNotFound`，CBOR 无法解析时返回 `ExtractionFailed`。
+    pub fn extract_structured(&self, image: &DynamicImage) -> Result<serde_json::Value, BlindMarkError> {
+        let blocks = self.extract_channel_block_softs(image, 1)?;
+        let softs = self.text_softs(&blocks)?;
+        let soft_sum: Vec<f64> = (0..softs[0].len()).map(|i| softs.iter().map(|s| s[i]).sum()).collect();
+        let bits: Vec<u8> = soft_sum.iter().map(|&v| (v > 1.5) as u8).collect();
+        let payload = WatermarkEncoder::bits_to_structured(&bits)
+            .filter(|_| magic_margin(&soft_sum) >= self.min_valid_margin)
+            .ok_or_else(|| BlindMarkError::WatermarkNotFound("图片中未找到结构化盲水印".to_string()))?;
+        cbor::from_cbor(&payload)
+    }
+
+    /// 分通道提取 `wm_size` 位并与多数判决比较（诊断用）
+    ///
+    /// 与 `extract_soft_sum` 读取相同的软判决值，但不求和：每个通道单独判决，
+    /// 再统计其与三通道多数判决不一致的位数。`wm_size` 取 128 诊断 MD5 水印，
+    /// 取 `TEXT_WATERMARK_TOTAL_BITS` 诊断原始文本水印。
+    pub fn extract_channel_diagnostics(
+        &self,
+        image: &DynamicImage,
+        wm_size: usize,
+    ) -> Result<PerChannelDiagnostics, BlindMarkError> {
+        let softs = self.extract_channel_softs(image, wm_size)?;
+        let majority_bits: Vec<u8> = (0..wm_size)
+            .map(|i| (softs.iter().map(|s| s[i]).sum::<f64>() > 1.5) as u8)
+            .collect();
+        let channel_bits = softs.map(|soft| soft.iter().map(|&v| (v > 0.5) as u8).collect::<Vec<u8>>());
+        let bit_error_rates = channel_bits.each_ref().map(|bits| {
+            let errors = bits.iter().zip(&majority_bits).filter(|(a, b)| a != b).count();
+            errors as f64 / wm_size.max(1) as f64
+        });
+        let cleanest_channel = (0..3)
+            .min_by(|&a, &b| bit_error_rates[a].total_cmp(&bit_error_rates[b]))
+            .unwrap_or(0);
+        Ok(PerChannelDiagnostics { majority_bits, channel_bits, bit_error_rates, cleanest_channel })
+    }
+
+    /// 诊断图片的文本水印提取结果，说明失败的具体原因（见 `ExtractionDiagnosis`）
+    ///
+    /// 依次检查：尺寸是否为偶数 → 可用块数是否足够 → 能否按任一格式解出文本（同 `try_extract_text`）→
+    /// 标准格式的魔数是否匹配。Hilbert、按比例选块、链式等其他格式解码失败时按 `NoMagic` 报告；
+    /// 魔数裕度低于 `with_min_valid_margin` 的结果视为巧合，同样按 `NoMagic` 报告。
+    pub fn diagnose(&self, image: &DynamicImage) -> Result<ExtractionDiagnosis, BlindMarkError> {
+        let (width, height) = (image.width(), image.height());
+        // 降采样提取时缩小后的尺寸总为偶数
+        let downscaled = self.downscale.and_then(|max_dim| downscale_dimensions(width, height, max_dim));
+        if downscaled.is_none() && (width % 2 != 0 || height % 2 != 0) {
+            return Ok(ExtractionDiagnosis::OddDimensions { width, height });
+        }
+        let capacity = self.dct.with_block_stride(self.block_stride).capacity(self.ll_dim(image));
+        let required = TEXT_WATERMARK_TOTAL_BITS * self.redundancy;
+        if capacity < required {
+            return Ok(ExtractionDiagnosis::TooSmall { capacity, required });
+        }
+        if let Some((text, _)) = self.decode_text(image) {
+            return Ok(ExtractionDiagnosis::Valid { text });
+        }
+
+        let softs = self.text_softs(&self.extract_channel_block_softs(image, self.block_stride)?)?;
+        let bits: Vec<u8> = (0..TEXT_WATERMARK_TOTAL_BITS)
+            .map(|i| (softs.iter().map(|s| s[i]).sum::<f64>() > 1.5) as u8)
+            .collect();
+        let magic: Vec<u8> = bits[..TEXT_WATERMARK_MAGIC.len() * 8]
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, &bit| (acc << 1) | bit))
+            .collect();
+        // 能解出文本却未通过 `decode_text`，说明魔数裕度不足
+        if magic != TEXT_WATERMARK_MAGIC || WatermarkEncoder::bits_to_text(&bits).is_some() {
+            return Ok(ExtractionDiagnosis::NoMagic);
+        }
+        Ok(ExtractionDiagnosis::InvalidUtf8)
+    }
+
     // ─── 核心提取逻辑 ─────────────────────────────────────────────────────────
 
     /// 对三个 RGB 通道提取软判决值并求和
@@ -84,7 +454,190 @@ impl WatermarkExtractor {
         image: &DynamicImage,
         wm_size: usize,
     ) -> Result<Vec<f64>, BlindMarkError> {
-        let rgb_image = image.to_rgb8();
+        let softs = self.extract_channel_softs(image, wm_size)?;
+        Ok((0..wm_size).map(|i| softs.iter().map(|s| s[i]).sum()).collect())
+    }
+
+    /// 解码原始文本水印，返回 `(文本, 软判决和)`；图片无法处理或未找到水印时返回 `None`
+    ///
+    /// 先按单份 544 位格式解码，失败时依次尝试 Hilbert 扫描顺序（`WatermarkEmbedder::with_scan_order`）、
+    /// 按比例选块的格式（`WatermarkEmbedder::with_block_fraction`，各选块间隔）与链式水印
+    /// （`WatermarkEmbedder::with_chained_payload`，2 ~ `TEXT_CHAIN_MAX_SLOTS` 个槽位）。
+    /// 以上格式按各嵌入强度的 QIM 步长依次尝试（默认步长在前，见 `text_step_factors`），
+    /// 头部记录的强度档位须与所试步长一致：每个步长先只解码各格式的 32 位头部（见 `header_matches`），
+    /// 档位相符时才完整尝试各格式，无水印的图片几乎只付出头部解码的代价。
+    /// 逐块奇异值只计算一次，各次尝试仅重新解码 / 排序 / 选块 / 求平均。
+    /// 设置了 `with_block_fraction` 时只读取对应的块，且只识别该比例的格式。
+    ///
+    /// 魔数裕度低于 `with_min_valid_margin` 设置值的结果视为巧合，按未找到处理。
+    fn decode_text(&self, image: &DynamicImage) -> Option<(String, Vec<f64>)> {
+        let singulars = self.extract_channel_block_singulars(image, self.block_stride).ok()?;
+        let ll_dim = self.ll_dim(image);
+
+        // 各格式的头部布局与步长无关，只排列一次：单份 / Hilbert / 各选块间隔 / 各链式槽位数
+        let text_size = TEXT_WATERMARK_TOTAL_BITS * self.redundancy;
+        let rearranged: Vec<[Vec<Option<[f64; 2]>>; 3]> = if self.block_stride > 1 {
+            Vec::new()
+        } else {
+            std::iter::once(singulars.each_ref().map(|s| self.dct.reorder_blocks(ll_dim, s, ScanOrder::Hilbert)))
+                .chain(TEXT_BLOCK_STRIDES.iter().map(|&stride| {
+                    singulars.each_ref().map(|s| DCTProcessor::subsample_blocks(s, stride))
+                }))
+                .collect()
+        };
+        let mut layouts: Vec<HeaderLayout> = vec![(&singulars, text_size, self.redundancy)];
+        layouts.extend(rearranged.iter().map(|blocks| (blocks, text_size, self.redundancy)));
+        if self.block_stride == 1 {
+            layouts.extend((2..=TEXT_CHAIN_MAX_SLOTS).map(|slots| (&singulars, slots * TEXT_WATERMARK_TOTAL_BITS, 1)));
+        }
+
+        text_step_factors().find_map(|factor| {
+            let dct = self.dct.scale_qim_steps(factor);
+            if !layouts.iter().any(|&layout| header_matches(&dct, layout, factor)) {
+                return None;
+            }
+            let blocks = singulars.each_ref().map(|s| dct.block_softs(s));
+            let found = self.decode_text_blocks(&blocks, ll_dim)?;
+            let bits: Vec<u8> = found.1.iter().map(|&v| (v > 1.5) as u8).collect();
+            let recorded = WatermarkEncoder::recorded_strength_level(&bits).and_then(WatermarkEncoder::step_factor);
+            (recorded == Some(factor) && magic_margin(&found.1) >= self.min_valid_margin).then_some(found)
+        })
+    }
+
+    /// 按同一组 QIM 步长得到的逐块软判决值依次尝试各文本水印格式（见 `decode_text`）
+    fn decode_text_blocks(&self, blocks: &[Vec<Option<f64>>; 3], ll_dim: (usize, usize)) -> Option<(String, Vec<f64>)> {
+        if self.block_stride > 1 {
+            return self.decode_strided_text(blocks, self.block_stride);
+        }
+        self.text_softs(blocks)
+            .ok()
+            .and_then(|softs| decode_text_softs(&softs))
+            .or_else(|| self.decode_hilbert_text(blocks, ll_dim))
+            .or_else(|| {
+                TEXT_BLOCK_STRIDES.iter().find_map(|&stride| {
+                    let strided = blocks.each_ref().map(|b| DCTProcessor::subsample_blocks(b, stride));
+                    self.decode_strided_text(&strided, stride)
+                })
+            })
+            .or_else(|| decode_chained_softs(blocks))
+    }
+
+    /// 按选块间隔 `stride` 的格式解码（`blocks` 须已是该间隔选中的块），头部记录的间隔须一致
+    fn decode_strided_text(&self, blocks: &[Vec<Option<f64>>; 3], stride: usize) -> Option<(String, Vec<f64>)> {
+        let softs = self.text_softs(blocks).ok()?;
+        let soft_sum: Vec<f64> = (0..softs[0].len()).map(|i| softs.iter().map(|s| s[i]).sum()).collect();
+        let bits: Vec<u8> = soft_sum.iter().map(|&v| (v > 1.5) as u8).collect();
+        match WatermarkEncoder::bits_to_text_for_stride(&bits)? {
+            (text, recorded) if recorded == stride => Some((text, soft_sum)),
+            _ => None,
+        }
+    }
+
+    /// 按 Hilbert 扫描顺序的格式解码（`blocks` 为行优先顺序的全部块），头部须为 `TEXT_HILBERT_MAGIC`
+    fn decode_hilbert_text(&self, blocks: &[Vec<Option<f64>>; 3], ll_dim: (usize, usize)) -> Option<(String, Vec<f64>)> {
+        let reordered = blocks.each_ref().map(|b| self.dct.reorder_blocks(ll_dim, b, ScanOrder::Hilbert));
+        let softs = self.text_softs(&reordered).ok()?;
+        let soft_sum: Vec<f64> = (0..softs[0].len()).map(|i| softs.iter().map(|s| s[i]).sum()).collect();
+        let bits: Vec<u8> = soft_sum.iter().map(|&v| (v > 1.5) as u8).collect();
+        WatermarkEncoder::bits_to_text_for_hilbert(&bits).map(|text| (text, soft_sum))
+    }
+
+    /// 快速判断图片是否可能嵌入过水印，用于批量扫描时跳过明显无水印的图片
+    ///
+    /// 每个通道只抽样至多 `QUICK_DETECT_SAMPLE_BLOCKS` 个块，测量主奇异值的 QIM 余数聚集度
+    /// （见 `DCTProcessor::qim_clustering`），至少 `QUICK_DETECT_MIN_CHANNELS` 个通道达到
+    /// `QUICK_DETECT_CLUSTERING` 即视为可能有水印；抽样块的奇异值只计算一次，再按各嵌入强度的 QIM 步长依次测量。
+    /// 不解码比特，耗时远低于完整提取；
+    /// 大面积纯色的图片可能误判为 `true`，返回 `true` 时仍需完整提取确认内容。
+    /// 选块间隔按 `with_block_fraction` 的设置抽样，无法 DWT 的图片（奇数尺寸等）返回 `false`。
+    pub fn quick_detect(&self, image: &DynamicImage) -> bool {
+        let Ok(bands) = self.channel_bands(image) else {
+            return false;
+        };
+        let dct = self.dct.with_block_stride(self.block_stride);
+        let samples = bands.map(|ll| dct.sample_principal_singulars(&ll, QUICK_DETECT_SAMPLE_BLOCKS));
+        text_step_factors().any(|factor| {
+            let dct = dct.scale_qim_steps(factor);
+            samples
+                .iter()
+                .filter_map(|s| dct.clustering_of(s))
+                .filter(|&clustering| clustering >= QUICK_DETECT_CLUSTERING)
+                .count()
+                >= QUICK_DETECT_MIN_CHANNELS
+        })
+    }
+
+    /// 提取 RGB 通道水印时 LL 子带的尺寸（已考虑降采样提取）
+    fn ll_dim(&self, image: &DynamicImage) -> (usize, usize) {
+        let (width, height) = (image.width(), image.height());
+        let (width, height) = self
+            .downscale
+            .and_then(|max_dim| downscale_dimensions(width, height, max_dim))
+            .unwrap_or((width, height));
+        ((height / 2) as usize, (width / 2) as usize)
+    }
+
+    /// 由逐块软判决值计算文本水印各通道的 544 位软判决值
+    ///
+    /// 冗余份数大于 1 时，每通道每位取各副本硬判决的得票率（多数表决），值域仍为 [0, 1]。
+    fn text_softs(&self, blocks: &[Vec<Option<f64>>; 3]) -> Result<[Vec<f64>; 3], BlindMarkError> {
+        let wm_size = TEXT_WATERMARK_TOTAL_BITS * self.redundancy;
+        let mut softs: [Vec<f64>; 3] = Default::default();
+        for (soft, block_softs) in softs.iter_mut().zip(blocks) {
+            *soft = DCTProcessor::cyclic_average(block_softs, wm_size)?;
+        }
+        if self.redundancy == 1 {
+            return Ok(softs);
+        }
+        Ok(softs.map(|soft| {
+            let bits: Vec<u8> = soft.iter().map(|&v| (v > 0.5) as u8).collect();
+            WatermarkEncoder::vote_shares(&bits, TEXT_WATERMARK_TOTAL_BITS)
+        }))
+    }
+
+    /// 对三个 RGB 通道分别提取软判决值（每位值域 [0, 1]）
+    fn extract_channel_softs(
+        &self,
+        image: &DynamicImage,
+        wm_size: usize,
+    ) -> Result<[Vec<f64>; 3], BlindMarkError> {
+        let blocks = self.extract_channel_block_softs(image, 1)?;
+        let mut softs: [Vec<f64>; 3] = Default::default();
+        for (soft, block_softs) in softs.iter_mut().zip(&blocks) {
+            *soft = DCTProcessor::cyclic_average(block_softs, wm_size)?;
+        }
+        Ok(softs)
+    }
+
+    /// 对三个 RGB 通道分别提取逐块软判决值（见 `DCTProcessor::extract_block_softs`），只读取选块间隔为 `stride` 的块
+    fn extract_channel_block_softs(
+        &self,
+        image: &DynamicImage,
+        stride: usize,
+    ) -> Result<[Vec<Option<f64>>; 3], BlindMarkError> {
+        let dct = self.dct.with_block_stride(stride);
+        Ok(self.channel_bands(image)?.map(|ll| dct.extract_block_softs(&ll)))
+    }
+
+    /// 对三个 RGB 通道分别提取逐块奇异值（见 `DCTProcessor::extract_block_singulars`），只读取选块间隔为 `stride` 的块
+    fn extract_channel_block_singulars(
+        &self,
+        image: &DynamicImage,
+        stride: usize,
+    ) -> Result<[Vec<Option<[f64; 2]>>; 3], BlindMarkError> {
+        let dct = self.dct.with_block_stride(stride);
+        Ok(self.channel_bands(image)?.map(|ll| dct.extract_block_singulars(&ll)))
+    }
+
+    /// 对三个 RGB 通道分别做 DWT，返回各通道的嵌入子带（默认 LL，见 `with_profile`；已考虑降采样提取）
+    fn channel_bands(&self, image: &DynamicImage) -> Result<[Array2<f64>; 3], BlindMarkError> {
+        let mut rgb_image = image.to_rgb8();
+        if let Some((small_w, small_h)) = self
+            .downscale
+            .and_then(|max_dim| downscale_dimensions(rgb_image.width(), rgb_image.height(), max_dim))
+        {
+            rgb_image = image.resize_exact(small_w, small_h, DOWNSCALE_FILTER).to_rgb8();
+        }
         let (width, height) = rgb_image.dimensions();
         let (w, h) = (width as usize, height as usize);
 
@@ -95,9 +648,8 @@ impl WatermarkExtractor {
             ));
         }
 
-        let mut soft_sum = vec![0.0f64; wm_size];
-
-        for ch in 0..3usize {
+        let mut bands: [Array2<f64>; 3] = Default::default();
+        for (ch, band) in bands.iter_mut().enumerate() {
             let mut ch_data = Array2::zeros((h, w));
             for y in 0..h {
                 for x in 0..w {
@@ -106,22 +658,176 @@ impl WatermarkExtractor {
                 }
             }
 
-            let (ll, _, _, _) = match self.dwt.decompose_1level(ch_data.view()) {
-                Ok(c) => c,
-                Err(_) => return Err(BlindMarkError::ImageProcessing(
-                    "DWT 分解失败".to_string()
-                )),
-            };
+            let mut subbands = self.dwt.decompose_1level(ch_data.view()).map_err(|_| {
+                BlindMarkError::ImageProcessing("DWT 分解失败".to_string())
+            })?;
+            *band = std::mem::take(self.subband.select(&mut subbands));
+        }
 
-            let soft = self.dct.extract_watermark_blocks_soft(&ll, wm_size)?;
+        Ok(bands)
+    }
 
-            for (i, &v) in soft.iter().enumerate() {
-                soft_sum[i] += v;
-            }
+    /// 对单个平面做 DWT 并从嵌入子带提取软判决值（每位值域 [0, 1]）
+    fn extract_plane_soft(&self, plane: &Array2<f64>, wm_size: usize) -> Result<Vec<f64>, BlindMarkError> {
+        let mut subbands = match self.dwt.decompose_1level(plane.view()) {
+            Ok(c) => c,
+            Err(_) => return Err(BlindMarkError::ImageProcessing(
+                "DWT 分解失败".to_string()
+            )),
+        };
+        self.dct.extract_watermark_blocks_soft(self.subband.select(&mut subbands), wm_size)
+    }
+}
+
+impl Default for WatermarkExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 对 RGB 像素做 gamma 变换：`v' = 255 · (v / 255)^gamma`
+pub fn apply_gamma(image: &RgbImage, gamma: f64) -> RgbImage {
+    let lut: Vec<u8> = (0..=255u32)
+        .map(|v| (255.0 * (v as f64 / 255.0).powf(gamma)).round() as u8)
+        .collect();
+    let mut out = image.clone();
+    for p in out.pixels_mut() {
+        for c in p.0.iter_mut() {
+            *c = lut[*c as usize];
         }
+    }
+    out
+}
 
-        Ok(soft_sum)
+/// 文本水印提取时依次尝试的 QIM 步长倍数：默认步长在前，其余为各嵌入强度档位（见 `STRENGTH_STEP_FACTORS`）
+fn text_step_factors() -> impl Iterator<Item = f64> {
+    std::iter::once(1.0).chain(STRENGTH_STEP_FACTORS.into_iter().filter(|&factor| factor != 1.0))
+}
+
+/// 头部（首个 544 位单元的前 32 位）记录的强度档位对应的 QIM 步长倍数
+///
+/// 魔数首字节须为各文本格式共用的 `TEXT_WATERMARK_MAGIC[0]`，长度不超过 `TEXT_WATERMARK_MAX_BYTES`。
+fn header_step_factor(header: &[u8]) -> Option<f64> {
+    let byte = |k: usize| header[k * 8..k * 8 + 8].iter().fold(0u8, |b, &bit| (b << 1) | bit);
+    if header.len() < TEXT_WATERMARK_HEADER_BITS
+        || byte(0) != TEXT_WATERMARK_MAGIC[0]
+        || byte(3) as usize > TEXT_WATERMARK_MAX_BYTES
+    {
+        return None;
     }
+    WatermarkEncoder::recorded_strength_level(header).and_then(WatermarkEncoder::step_factor)
+}
+
+/// 只解码 `layout` 的 32 位头部，判断其是否记录了与 `factor` 相符的强度档位（见 `decode_text`）
+///
+/// 只访问头部所在的块，代价约为完整解码的 32 / 544。各通道的平均与冗余表决同 `text_softs`，
+/// 三通道和（阈值 1.5）与各通道对的和（阈值 1.0，选择通道格式）均会尝试，
+/// 因此完整解码能够接受的结果，其头部在这里一定相符。
+fn header_matches(dct: &DCTProcessor, (blocks, wm_size, copies): HeaderLayout, factor: f64) -> bool {
+    if blocks.iter().any(|b| b.len() < wm_size) {
+        return false;
+    }
+    let cyclic = |channel: &[Option<[f64; 2]>], position: usize| {
+        let (sum, count) = channel[position..]
+            .iter()
+            .step_by(wm_size)
+            .flatten()
+            .fold((0.0, 0usize), |(sum, count), &s| (sum + dct.block_soft(s), count + 1));
+        if count > 0 { sum / count as f64 } else { 0.5 }
+    };
+    // 每通道头部各位的软判决值；冗余时为各副本硬判决的得票率
+    let softs: Vec<Vec<f64>> = blocks
+        .iter()
+        .map(|channel| {
+            (0..TEXT_WATERMARK_HEADER_BITS)
+                .map(|i| {
+                    if copies == 1 {
+                        return cyclic(channel, i);
+                    }
+                    let ones = (0..copies)
+                        .filter(|k| cyclic(channel, k * TEXT_WATERMARK_TOTAL_BITS + i) > 0.5)
+                        .count();
+                    ones as f64 / copies as f64
+                })
+                .collect()
+        })
+        .collect();
+    let matches = |channels: &[usize], threshold: f64| {
+        let header: Vec<u8> = (0..TEXT_WATERMARK_HEADER_BITS)
+            .map(|i| (channels.iter().map(|&c| softs[c][i]).sum::<f64>() > threshold) as u8)
+            .collect();
+        header_step_factor(&header) == Some(factor)
+    };
+    matches(&[0, 1, 2], 1.5) || [[0, 1], [0, 2], [1, 2]].iter().any(|pair| matches(pair, 1.0))
+}
+
+/// 由各通道软判决值解码原始文本水印，返回 `(文本, 换算到 [0, 3] 的软判决和)`
+///
+/// 先按三通道格式解码（和的阈值 1.5）；失败时依次尝试每对通道（和的阈值 1.0），
+/// 仅当头部记录的通道与所试通道一致时才接受（见 `WatermarkEmbedder::with_channel_selection`）。
+fn decode_text_softs(softs: &[Vec<f64>; 3]) -> Option<(String, Vec<f64>)> {
+    let len = softs[0].len();
+    let threshold = |soft_sum: &[f64], t: f64| -> Vec<u8> {
+        soft_sum.iter().map(|&v| if v > t { 1u8 } else { 0u8 }).collect()
+    };
+
+    // 三通道各贡献 [0,1]，总和在 [0,3]，阈值 1.5
+    let soft_sum: Vec<f64> = (0..len).map(|i| softs[0][i] + softs[1][i] + softs[2][i]).collect();
+    if let Some(text) = WatermarkEncoder::bits_to_text(&threshold(&soft_sum, 1.5)) {
+        return Some((text, soft_sum));
+    }
+
+    [[0, 1], [0, 2], [1, 2]].into_iter().find_map(|pair| {
+        let pair_sum: Vec<f64> = (0..len).map(|i| softs[pair[0]][i] + softs[pair[1]][i]).collect();
+        match WatermarkEncoder::bits_to_text_for_channels(&threshold(&pair_sum, 1.0))? {
+            (text, channels) if channels == pair => Some((text, pair_sum.iter().map(|v| v * 1.5).collect())),
+            _ => None,
+        }
+    })
+}
+
+/// 按链式水印格式解码：依次假设 2 ~ `TEXT_CHAIN_MAX_SLOTS` 个槽位求平均并校验各槽位头部
+///
+/// 返回 `(拼接后的文本, 全部槽位的三通道软判决和)`
+fn decode_chained_softs(blocks: &[Vec<Option<f64>>; 3]) -> Option<(String, Vec<f64>)> {
+    (2..=TEXT_CHAIN_MAX_SLOTS).find_map(|slots| {
+        let wm_size = slots * TEXT_WATERMARK_TOTAL_BITS;
+        let softs: Vec<Vec<f64>> = blocks
+            .iter()
+            .map(|block_softs| DCTProcessor::cyclic_average(block_softs, wm_size).ok())
+            .collect::<Option<_>>()?;
+        let soft_sum: Vec<f64> = (0..wm_size).map(|i| softs.iter().map(|s| s[i]).sum()).collect();
+        let bits: Vec<u8> = soft_sum.iter().map(|&v| (v > 1.5) as u8).collect();
+        WatermarkEncoder::chained_bits_to_text(&bits, slots).map(|text| (text, soft_sum))
+    })
+}
+
+/// 文本水印魔数的判决裕度：各 544 位单元（链式水印的每个槽位）魔数位到阈值 1.5 的
+/// 最小归一化距离，值域 [0, 1]
+///
+/// 与 `soft_confidence` 取全部位的平均不同，这里取最小值：任一魔数位接近阈值
+/// 都说明魔数可能只是噪声巧合解出的。
+pub fn magic_margin(soft_sum: &[f64]) -> f32 {
+    let magic_bits = TEXT_WATERMARK_MAGIC.len() * 8;
+    soft_sum
+        .chunks(TEXT_WATERMARK_TOTAL_BITS)
+        .flat_map(|unit| unit.iter().take(magic_bits))
+        .map(|&v| ((v - 1.5).abs() / 1.5).min(1.0))
+        .fold(None, |min: Option<f64>, d| Some(min.map_or(d, |m| m.min(d))))
+        .unwrap_or(0.0) as f32
+}
+
+/// 由三通道软判决之和计算置信度
+///
+/// 每位的软判决和值域 [0, 3]，阈值 1.5；取各位到阈值距离的平均值并归一化到 [0, 1]。
+/// 干净的水印图片各通道完全一致（和为 0 或 3），置信度为 1.0；
+/// 随机比特的置信度明显偏低。
+pub fn soft_confidence(soft_sum: &[f64]) -> f32 {
+    if soft_sum.is_empty() {
+        return 0.0;
+    }
+    let total: f64 = soft_sum.iter().map(|&v| ((v - 1.5).abs() / 1.5).min(1.0)).sum();
+    (total / soft_sum.len() as f64) as f32
 }
 
 #[cfg(test)]
@@ -195,6 +901,99 @@ mod tests {
         assert_eq!(extracted_strong, expected_hash, "强水印应能提取");
     }
 
+    /// 对每个像素通道叠加标准差为 `sigma` 的高斯噪声（Box–Muller，固定种子）
+    fn add_gaussian_noise(image: &DynamicImage, sigma: f64, seed: u64) -> DynamicImage {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let mut rgb = image.to_rgb8();
+        for p in rgb.pixels_mut() {
+            for c in p.0.iter_mut() {
+                let (u1, u2): (f64, f64) = (rng.gen_range(f64::EPSILON..1.0), rng.gen());
+                let n = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos() * sigma;
+                *c = (*c as f64 + n).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        DynamicImage::ImageRgb8(rgb)
+    }
+
+    #[test]
+    fn test_text_strength_scales_qim_steps() {
+        let embedder = WatermarkEmbedder::new();
+        let extractor = WatermarkExtractor::new();
+        let original = create_test_image(256, 256);
+        let text = "strength";
+
+        let weak = png_roundtrip(&embedder.embed_raw_text(&original, text, 0.1, false).unwrap());
+        let strong = png_roundtrip(&embedder.embed_raw_text(&original, text, 1.0, false).unwrap());
+        // 强度档位记录在头部，提取端无需指定强度
+        assert_eq!(extractor.try_extract_text(&weak).unwrap().as_deref(), Some(text));
+        assert_eq!(extractor.try_extract_text(&strong).unwrap().as_deref(), Some(text));
+
+        // 强度越高，对像素的修改越明显
+        let distortion = |marked: &DynamicImage| -> u64 {
+            marked.to_rgb8().as_raw().iter().zip(original.to_rgb8().as_raw())
+                .map(|(&a, &b)| (a as i64 - b as i64).unsigned_abs())
+                .sum()
+        };
+        assert!(distortion(&strong) > distortion(&weak) * 2);
+
+        // 强度越高，可承受的噪声越重
+        let noisy_weak = add_gaussian_noise(&weak, 6.0, 7);
+        let noisy_strong = add_gaussian_noise(&strong, 6.0, 7);
+        assert_ne!(extractor.try_extract_text(&noisy_weak).unwrap().as_deref(), Some(text));
+        assert_eq!(extractor.try_extract_text(&noisy_strong).unwrap().as_deref(), Some(text));
+    }
+
+    #[test]
+    fn test_header_probe_selects_only_recorded_strength() {
+        let extractor = WatermarkExtractor::new();
+        let original = create_test_image(256, 256);
+        let header_factors = |image: &DynamicImage| -> Vec<f64> {
+            let singulars = extractor.extract_channel_block_singulars(image, 1).unwrap();
+            text_step_factors()
+                .filter(|&factor| {
+                    let dct = extractor.dct.scale_qim_steps(factor);
+                    header_matches(&dct, (&singulars, TEXT_WATERMARK_TOTAL_BITS, 1), factor)
+                })
+                .collect()
+        };
+
+        // 未嵌入水印的图片：没有任何步长需要完整解码
+        assert!(header_factors(&original).is_empty());
+        // 强度 0.8 记录档位 8，只有对应的步长倍数通过头部检查
+        let marked = png_roundtrip(&WatermarkEmbedder::new().embed_raw_text(&original, "probe", 0.8, false).unwrap());
+        assert_eq!(header_factors(&marked), [STRENGTH_STEP_FACTORS[7]]);
+        assert_eq!(extractor.try_extract_text(&marked).unwrap().as_deref(), Some("probe"));
+    }
+
+    #[test]
+    fn test_jpeg_dct_watermark_survives_reencode() {
+        use image::codecs::jpeg::JpegEncoder;
+        let reencode = |img: &DynamicImage, quality: u8| -> DynamicImage {
+            let mut buffer = Vec::new();
+            JpegEncoder::new_with_quality(&mut buffer, quality).encode_image(&img.to_rgb8()).unwrap();
+            image::load_from_memory(&buffer).unwrap()
+        };
+        let embedder = WatermarkEmbedder::new();
+        let extractor = WatermarkExtractor::new();
+        let original = create_test_image(256, 256);
+        let text = "买家 jpeg-42";
+
+        let bytes = embedder.embed_jpeg_to_bytes(&original, text, 0.5).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), image::ImageFormat::Jpeg);
+        let loaded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!(extractor.try_extract_text_jpeg(&loaded).unwrap().as_deref(), Some(text));
+
+        // 以质量 90 另存后重新读取
+        let saved = reencode(&loaded, 90);
+        assert_eq!(extractor.try_extract_text_jpeg(&saved).unwrap().as_deref(), Some(text));
+        assert_eq!(extractor.try_extract_text_jpeg(&reencode(&saved, 85)).unwrap().as_deref(), Some(text));
+
+        // 未嵌入的 JPEG 与不足 544 块的小图均无结果
+        assert_eq!(extractor.try_extract_text_jpeg(&reencode(&original, 90)).unwrap(), None);
+        assert!(embedder.embed_raw_text_jpeg(&create_test_image(160, 160), text, 0.5).is_err());
+    }
+
     #[test]
     fn test_raw_text_roundtrip_multichannel() {
         let embedder = WatermarkEmbedder::new();
@@ -254,6 +1053,315 @@ mod tests {
         assert!(result.unwrap().is_none(), "小图片应返回 None");
     }
 
+    #[test]
+    fn test_text_confidence_high_for_watermarked() {
+        let embedder = WatermarkEmbedder::new();
+        let extractor = WatermarkExtractor::new();
+
+        let watermarked = embedder.embed_raw_text(&create_test_image(256, 256), "Confident", 0.5, false).unwrap();
+        let (text, confidence) = extractor
+            .try_extract_text_with_confidence(&png_roundtrip(&watermarked))
+            .unwrap()
+            .expect("应提取到水印");
+        assert_eq!(text, "Confident");
+        assert!(confidence > 0.9, "干净水印的置信度应接近 1，得 {}", confidence);
+    }
+
+    #[test]
+    fn test_tolerant_extraction_recovers_gamma_shift() {
+        let embedder = WatermarkEmbedder::new();
+        let extractor = WatermarkExtractor::new();
+
+        let watermarked = embedder.embed_raw_text(&create_test_image(256, 256), "Gamma", 0.5, false).unwrap();
+        // 模拟其他工具保存时做了 gamma 转换（1.8 → 2.2 的逆向偏移）
+        let shifted = DynamicImage::ImageRgb8(apply_gamma(&png_roundtrip(&watermarked).to_rgb8(), 1.8 / 2.2));
+
+        let plain = extractor.try_extract_text_with_confidence(&shifted).unwrap();
+        let (text, confidence) = extractor
+            .try_extract_text_tolerant(&shifted)
+            .unwrap()
+            .expect("容错模式应能恢复水印");
+        assert_eq!(text, "Gamma");
+        assert!(plain.is_none_or(|(t, _)| t != "Gamma"), "gamma 偏移后直接提取应失败");
+        assert!(confidence >= TOLERANT_ACCEPT_CONFIDENCE, "逆校正后置信度应恢复，得 {}", confidence);
+    }
+
+    #[test]
+    fn test_password_required_for_extraction() {
+        let seed = crate::core::watermark::dct::password_seed("s3cret");
+        let embedder = WatermarkEmbedder::with_password(seed);
+
+        // 带纹理的图片：平滑区域的 4×4 块近似秩 1，打乱系数几乎不改变奇异值
+        let textured = DynamicImage::ImageRgb8(ImageBuffer::from_fn(256, 256, |x, y| {
+            let v = (x.wrapping_mul(73) ^ y.wrapping_mul(151)).wrapping_mul(2654435761) >> 24;
+            Rgb([v as u8, (v as u8).wrapping_add(85), (v as u8).wrapping_add(170)])
+        }));
+        let watermarked = png_roundtrip(&embedder.embed_raw_text(&textured, "Keyed", 0.5, false).unwrap());
+
+        let correct = WatermarkExtractor::with_password(seed).try_extract_text(&watermarked).unwrap();
+        assert_eq!(correct.as_deref(), Some("Keyed"), "相同密码应能提取");
+
+        let wrong_seed = crate::core::watermark::dct::password_seed("guess");
+        for extractor in [WatermarkExtractor::new(), WatermarkExtractor::with_password(wrong_seed)] {
+            let wrong = extractor.try_extract_text(&watermarked).unwrap();
+            assert_ne!(wrong.as_deref(), Some("Keyed"), "错误密码不应提取到水印");
+        }
+    }
+
+    #[test]
+    fn test_extract_any_detects_md5() {
+        let original = create_test_image(256, 256);
+        let watermarked = WatermarkEmbedder::new().embed(&original, "md5 owner", 0.5).unwrap();
+        let expected = WatermarkEncoder::encode("md5 owner").md5_hash;
+        assert_eq!(
+            WatermarkExtractor::new().extract_any(&watermarked).unwrap(),
+            ExtractedWatermark::Md5(expected)
+        );
+    }
+
+    #[test]
+    fn test_extract_any_detects_text() {
+        let original = create_test_image(256, 256);
+        let watermarked = WatermarkEmbedder::new()
+            .embed_raw_text(&original, "text owner", 0.5, false)
+            .unwrap();
+        assert_eq!(
+            WatermarkExtractor::new().extract_any(&watermarked).unwrap(),
+            ExtractedWatermark::Text("text owner".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_any_none_on_unwatermarked_image() {
+        let original = create_test_image(256, 256);
+        assert_eq!(WatermarkExtractor::new().extract_any(&original).unwrap(), ExtractedWatermark::None);
+        assert!(WatermarkExtractor::new().extract_any(&create_test_image(63, 63)).is_err(), "奇数尺寸应失败");
+    }
+
+    fn create_rgba_test_image(width: u32, height: u32, alpha: impl Fn(u32, u32) -> u8) -> DynamicImage {
+        let mut img = ImageBuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let r = ((x * 255) / width) as u8;
+                let g = ((y * 255) / height) as u8;
+                img.put_pixel(x, y, image::Rgba([r, g, 128u8, alpha(x, y)]));
+            }
+        }
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn test_alpha_plane_roundtrip_gradient_alpha() {
+        // alpha 从 64 渐变到 191，留出 QIM 调整余量
+        let original = create_rgba_test_image(256, 256, |x, _| 64 + (x / 2) as u8);
+        let (watermarked, alpha_embedded) = WatermarkEmbedder::new()
+            .embed_raw_text_dual(&original, "rgb owner", "alpha owner", 0.5)
+            .unwrap();
+        assert!(alpha_embedded, "渐变 alpha 应写入第二平面");
+        assert!(watermarked.color().has_alpha(), "输出应保留 alpha");
+
+        let reloaded = png_roundtrip(&watermarked);
+        let planes = WatermarkExtractor::new().try_extract_text_planes(&reloaded).unwrap();
+        assert_eq!(planes, (Some("rgb owner".to_string()), Some("alpha owner".to_string())));
+    }
+
+    #[test]
+    fn test_alpha_plane_skipped_when_fully_opaque() {
+        let original = create_rgba_test_image(256, 256, |_, _| 255);
+        let (watermarked, alpha_embedded) = WatermarkEmbedder::new()
+            .embed_raw_text_dual(&original, "rgb owner", "alpha owner", 0.5)
+            .unwrap();
+        assert!(!alpha_embedded, "全不透明 alpha 应跳过");
+        assert!(watermarked.to_rgba8().pixels().all(|p| p[3] == 255), "alpha 应保持不变");
+
+        let planes = WatermarkExtractor::new().try_extract_text_planes(&watermarked).unwrap();
+        assert_eq!(planes, (Some("rgb owner".to_string()), None));
+    }
+
+    #[test]
+    fn test_hilbert_scan_order_roundtrip() {
+        let original = create_test_image(320, 256);
+        let embedder = WatermarkEmbedder::new().with_scan_order(ScanOrder::Hilbert);
+        let watermarked = png_roundtrip(&embedder.embed_raw_text(&original, "hilbert buyer", 0.5, false).unwrap());
+
+        // 头部记录扫描顺序，默认提取器自动识别
+        assert_eq!(WatermarkExtractor::new().extract_text(&watermarked).unwrap(), "hilbert buyer");
+
+        // 按行优先顺序读取时头部不匹配
+        let blocks = WatermarkExtractor::new().extract_channel_block_softs(&watermarked, 1).unwrap();
+        let softs = WatermarkExtractor::new().text_softs(&blocks).unwrap();
+        assert!(decode_text_softs(&softs).is_none());
+    }
+
+    #[test]
+    fn test_structured_payload_roundtrip() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Attribution {
+            buyer: String,
+            order_id: u64,
+            ts: i64,
+        }
+
+        let attribution = Attribution { buyer: "张三".to_string(), order_id: 20240601, ts: 1_717_200_000 };
+        let value = serde_json::to_value(&attribution).unwrap();
+        let original = create_test_image(256, 256);
+        let watermarked = png_roundtrip(&WatermarkEmbedder::new().embed_structured(&original, &value).unwrap());
+
+        let extractor = WatermarkExtractor::new();
+        let decoded: Attribution = serde_json::from_value(extractor.extract_structured(&watermarked).unwrap()).unwrap();
+        assert_eq!(decoded, attribution);
+        // 结构化水印不会被当作文本水印，文本水印也不会被当作结构化水印
+        assert!(extractor.try_extract_text(&watermarked).unwrap().is_none());
+        let text = WatermarkEmbedder::new().embed_raw_text(&original, "buyer", 0.5, false).unwrap();
+        assert!(matches!(extractor.extract_structured(&text), Err(BlindMarkError::WatermarkNotFound(_))));
+
+        // CBOR 编码后超过 64 字节
+        let too_big = serde_json::json!({ "buyer": "x".repeat(60) });
+        assert!(WatermarkEmbedder::new().embed_structured(&original, &too_big).is_err());
+    }
+
+    #[test]
+    fn test_chained_utf8_payload_roundtrip_through_png() {
+        // 200 字节，多字节字符跨越槽位边界（每槽 62 字节）
+        let text = format!("{}-{}", "张三李四".repeat(10), "x".repeat(79));
+        assert_eq!(text.len(), 200);
+        let original = create_test_image(512, 512);
+        let embedder = WatermarkEmbedder::new().with_chained_payload();
+        let watermarked = png_roundtrip(&embedder.embed_raw_text(&original, &text, 0.5, false).unwrap());
+
+        assert_eq!(WatermarkExtractor::new().extract_text(&watermarked).unwrap(), text);
+        // 单帧格式不受影响
+        let single = png_roundtrip(&embedder.embed_raw_text(&original, "短文本", 0.5, false).unwrap());
+        assert_eq!(WatermarkExtractor::new().extract_text(&single).unwrap(), "短文本");
+    }
+
+    #[test]
+    fn test_channel_diagnostics_agree_on_clean_image() {
+        let original = create_test_image(256, 256);
+        let watermarked = png_roundtrip(&WatermarkEmbedder::new().embed_raw_text(&original, "diag buyer", 0.5, false).unwrap());
+
+        let extractor = WatermarkExtractor::new();
+        let diag = extractor.extract_channel_diagnostics(&watermarked, TEXT_WATERMARK_TOTAL_BITS).unwrap();
+        assert_eq!(diag.bit_error_rates, [0.0; 3], "all channels agree on a normally embedded image");
+        assert!(diag.channel_bits.iter().all(|bits| *bits == diag.majority_bits));
+        assert_eq!(diag.cleanest_channel, 0);
+        assert_eq!(WatermarkEncoder::bits_to_text(&diag.majority_bits).as_deref(), Some("diag buyer"));
+
+        // 蓝色通道被强噪声破坏后误码率最高，但不影响多数判决
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(7);
+        let mut degraded = watermarked.to_rgb8();
+        for p in degraded.pixels_mut() {
+            p[2] = (p[2] as i32 + rng.gen_range(-40..=40)).clamp(0, 255) as u8;
+        }
+        let diag = extractor.extract_channel_diagnostics(&DynamicImage::ImageRgb8(degraded), TEXT_WATERMARK_TOTAL_BITS).unwrap();
+        assert!(diag.bit_error_rates[2] > diag.bit_error_rates[0].max(diag.bit_error_rates[1]), "{:?}", diag.bit_error_rates);
+        assert_ne!(diag.cleanest_channel, 2);
+        assert_eq!(WatermarkEncoder::bits_to_text(&diag.majority_bits).as_deref(), Some("diag buyer"));
+    }
+
+    #[test]
+    fn test_quiet_zone_roundtrip() {
+        let original = create_test_image(512, 512);
+        let watermarked = WatermarkEmbedder::new()
+            .with_quiet_zone(16)
+            .embed_raw_text(&original, "center only", 0.5, false)
+            .unwrap();
+
+        // 边框内像素未被修改（仅允许浮点重建带来的 ±1 误差）
+        let (a, b) = (original.to_rgb8(), watermarked.to_rgb8());
+        for (x, y, p) in a.enumerate_pixels() {
+            if x < 16 || y < 16 || x >= 496 || y >= 496 {
+                let q = b.get_pixel(x, y);
+                assert!((0..3).all(|c| p[c].abs_diff(q[c]) <= 1), "边框像素 ({}, {}) 被修改", x, y);
+            }
+        }
+
+        let reloaded = png_roundtrip(&watermarked);
+        let extractor = WatermarkExtractor::new().with_quiet_zone(16);
+        assert_eq!(extractor.try_extract_text(&reloaded).unwrap(), Some("center only".to_string()));
+        assert_eq!(
+            WatermarkExtractor::new().try_extract_text(&reloaded).unwrap(),
+            None,
+            "边框配置不一致时不应提取出水印"
+        );
+    }
+
+    #[test]
+    fn test_soft_confidence_range() {
+        assert_eq!(soft_confidence(&[0.0, 3.0, 0.0]), 1.0);
+        assert_eq!(soft_confidence(&[1.5, 1.5]), 0.0);
+        let mid = soft_confidence(&[0.75, 2.25]);
+        assert!((mid - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_min_valid_margin_reduces_false_positives() {
+        use rand::{Rng, SeedableRng};
+        use rand::rngs::SmallRng;
+
+        let mut rng = SmallRng::seed_from_u64(711);
+        let strict = WatermarkExtractor::new().with_min_valid_margin(0.3);
+
+        // 随机噪声图：严格模式下不应接受任何结果
+        for _ in 0..6 {
+            let noise = DynamicImage::ImageRgb8(ImageBuffer::from_fn(256, 256, |_, _| Rgb(rng.gen())));
+            assert!(strict.try_extract_text(&noise).unwrap().is_none());
+        }
+
+        // 模拟噪声巧合解出合法魔数：各位只比阈值多出一点随机偏移
+        let bits = WatermarkEncoder::text_to_bits("coincidence").unwrap();
+        let trials = 200;
+        let accepted_at = |threshold: f32, rng: &mut SmallRng| {
+            (0..trials)
+                .filter(|_| {
+                    let soft_sum: Vec<f64> = bits
+                        .iter()
+                        .map(|&b| {
+                            let offset = rng.gen_range(0.01..0.6);
+                            if b == 1 { 1.5 + offset } else { 1.5 - offset }
+                        })
+                        .collect();
+                    let decoded = WatermarkEncoder::bits_to_text(
+                        &soft_sum.iter().map(|&v| (v > 1.5) as u8).collect::<Vec<_>>(),
+                    );
+                    decoded.is_some() && magic_margin(&soft_sum) >= threshold
+                })
+                .count()
+        };
+        let relaxed = accepted_at(0.0, &mut rng);
+        let strict_count = accepted_at(0.3, &mut rng);
+        assert_eq!(relaxed, trials, "不限制裕度时巧合匹配全部被接受");
+        assert!(strict_count * 10 < relaxed, "提高裕度后误报率应显著下降: {} / {}", strict_count, relaxed);
+
+        // 真实水印的裕度接近 1，严格模式下仍可提取
+        let watermarked = WatermarkEmbedder::new()
+            .embed_raw_text(&create_test_image(256, 256), "strict", 0.5, false)
+            .unwrap();
+        assert_eq!(strict.try_extract_text(&png_roundtrip(&watermarked)).unwrap().as_deref(), Some("strict"));
+    }
+
+    #[test]
+    fn test_raw_bits_roundtrip() {
+        use rand::{Rng, SeedableRng};
+        use rand::rngs::SmallRng;
+
+        let mut rng = SmallRng::seed_from_u64(712);
+        let bits: Vec<u8> = (0..256).map(|_| rng.gen_range(0..=1)).collect();
+
+        let embedder = WatermarkEmbedder::new();
+        let watermarked = embedder
+            .embed_bits_public(&create_test_image(256, 256), &bits, 0.5)
+            .unwrap();
+        let extracted = WatermarkExtractor::new()
+            .extract_bits(&png_roundtrip(&watermarked), bits.len())
+            .unwrap();
+        assert_eq!(extracted, bits);
+
+        let invalid = embedder.embed_bits_public(&create_test_image(256, 256), &[0, 1, 2], 0.5);
+        assert!(matches!(invalid, Err(BlindMarkError::InvalidConfig(_))));
+    }
+
     #[test]
     fn test_extract_with_confidence() {
         let embedder = WatermarkEmbedder::new();
@@ -380,4 +1488,98 @@ mod tests {
             "噪声图片经 PNG roundtrip 后应能提取水印（新 QIM 算法应通过此测试）"
         );
     }
+
+    #[test]
+    fn test_channel_selection_blue_dominant() {
+        // 蓝色主导：B 通道几乎恒定，R / G 通道有纹理
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_fn(256, 256, |x, y| {
+            Rgb([((x * 7 + y * 3) % 96) as u8 + 20, ((x * 2 + y * 5) % 80) as u8 + 30, 220 + ((x + y) % 2) as u8])
+        }));
+        assert_eq!(WatermarkEmbedder::select_channels(&image.to_rgb8()), [0, 1], "应选择红 / 绿通道");
+
+        let embedder = WatermarkEmbedder::new().with_channel_selection();
+        let watermarked = png_roundtrip(&embedder.embed_raw_text(&image, "sky", 0.5, false).unwrap());
+
+        // 蓝色通道未被修改
+        let (before, after) = (image.to_rgb8(), watermarked.to_rgb8());
+        assert!(before.pixels().zip(after.pixels()).all(|(a, b)| a[2] == b[2]), "蓝色通道应保持原样");
+
+        let extractor = WatermarkExtractor::new();
+        assert_eq!(extractor.try_extract_text(&watermarked).unwrap().as_deref(), Some("sky"));
+        let (_, confidence) = extractor.try_extract_text_with_confidence(&watermarked).unwrap().unwrap();
+        assert!(confidence > 0.8, "置信度应较高，得 {}", confidence);
+    }
+
+    #[test]
+    fn test_redundancy_roundtrip() {
+        let image = create_test_image(512, 512);
+        let embedder = WatermarkEmbedder::new().with_redundancy(3);
+        let watermarked = png_roundtrip(&embedder.embed_raw_text(&image, "triple", 0.5, false).unwrap());
+
+        let extractor = WatermarkExtractor::new().with_redundancy(3);
+        let (text, confidence) = extractor.try_extract_text_with_confidence(&watermarked).unwrap().unwrap();
+        assert_eq!(text, "triple");
+        assert!(confidence > 0.8, "置信度应较高，得 {}", confidence);
+        // 副本与块的对应关系不变，未设置冗余也能读取
+        assert_eq!(WatermarkExtractor::new().try_extract_text(&watermarked).unwrap().as_deref(), Some("triple"));
+
+        // 256×256 仅有 1024 个块，不足以容纳 3 份 544 位载荷
+        let small = create_test_image(256, 256);
+        assert!(embedder.embed_raw_text(&small, "triple", 0.5, false).is_err());
+        assert!(extractor.try_extract_text(&small).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_quick_detect_separates_watermarked_from_clean() {
+        let embedder = WatermarkEmbedder::new();
+        let extractor = WatermarkExtractor::new();
+
+        let gradient = create_test_image(512, 512);
+        let noisy = DynamicImage::ImageRgb8(ImageBuffer::from_fn(512, 512, |x, y| {
+            let v = x.wrapping_mul(2654435761).wrapping_add(y.wrapping_mul(40503)) >> 7;
+            Rgb([v as u8, (v >> 8) as u8, (x ^ y) as u8])
+        }));
+        for clean in [&gradient, &noisy] {
+            let marked = png_roundtrip(&embedder.embed_raw_text(clean, "alice", 0.5, false).unwrap());
+            assert!(extractor.quick_detect(&marked), "已嵌入的图片应判为可能有水印");
+            assert!(!extractor.quick_detect(clean), "未嵌入的图片应判为无水印");
+        }
+        assert!(!extractor.quick_detect(&create_test_image(63, 63)));
+
+        // 只抽样少量块，应明显快于完整提取
+        let marked = png_roundtrip(&embedder.embed_raw_text(&noisy, "alice", 0.5, false).unwrap());
+        let start = std::time::Instant::now();
+        assert!(extractor.quick_detect(&marked));
+        let quick = start.elapsed();
+        let start = std::time::Instant::now();
+        assert_eq!(extractor.try_extract_text(&marked).unwrap().as_deref(), Some("alice"));
+        let full = start.elapsed();
+        assert!(quick < full, "quick_detect {:?} 应快于完整提取 {:?}", quick, full);
+    }
+
+    #[test]
+    fn test_diagnose_reports_each_failure_category() {
+        let extractor = WatermarkExtractor::new();
+        assert_eq!(
+            extractor.diagnose(&create_test_image(63, 64)).unwrap(),
+            ExtractionDiagnosis::OddDimensions { width: 63, height: 64 }
+        );
+        assert_eq!(
+            extractor.diagnose(&create_test_image(64, 64)).unwrap(),
+            ExtractionDiagnosis::TooSmall { capacity: 64, required: TEXT_WATERMARK_TOTAL_BITS }
+        );
+
+        let original = create_test_image(256, 256);
+        assert_eq!(extractor.diagnose(&original).unwrap(), ExtractionDiagnosis::NoMagic);
+
+        let embedder = WatermarkEmbedder::new();
+        let marked = png_roundtrip(&embedder.embed_raw_text(&original, "alice", 0.5, false).unwrap());
+        assert_eq!(extractor.diagnose(&marked).unwrap(), ExtractionDiagnosis::Valid { text: "alice".to_string() });
+
+        // 魔数与长度正确，payload 首字节 0xFF 不是合法的 UTF-8
+        let mut bits = WatermarkEncoder::text_to_bits("ab").unwrap();
+        bits[32..40].fill(1);
+        let corrupt = png_roundtrip(&embedder.embed_bits_public(&original, &bits, 0.5).unwrap());
+        assert_eq!(extractor.diagnose(&corrupt).unwrap(), ExtractionDiagnosis::InvalidUtf8);
+    }
 }
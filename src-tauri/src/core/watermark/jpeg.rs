@@ -0,0 +1,153 @@
+// ─── JPEG DCT 域文本水印 ──────────────────────────────────────────────────────────
+//
+// 空间域的 DWT + DCT + SVD 水印经不起 JPEG 量化。这里直接在与 JPEG 编码网格对齐的
+// 8×8 亮度块上工作：对每块的几个中频 DCT 系数做 QIM，每块携带一位，比特按密码打乱的
+// 块顺序循环铺满整张图片。JPEG 以同样的 8×8 DCT 量化亮度，质量 ≥ 85 时中频系数的
+// 量化误差远小于 QIM 容差，重新编码后水印仍可读出。
+//
+// 修改只作用于亮度：三个颜色通道加上相同的差值，Y 随之改变而 Cb / Cr 不变，
+// 因此不受色度下采样影响。
+
+use std::f64::consts::PI;
+use image::RgbImage;
+use ndarray::Array2;
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand::rngs::SmallRng;
+use crate::models::BlindMarkError;
+use super::dct::{qim_decode_soft, qim_encode};
+
+/// JPEG 分块边长
+pub const JPEG_BLOCK: usize = 8;
+/// 默认强度下的 QIM 量化步长（按嵌入强度缩放，见 `WatermarkEncoder::strength_level`）
+///
+/// 质量 85 时标准亮度量化表在所选中频位置的步长约为 4 ~ 5，量化误差不超过其一半，
+/// 远小于 QIM 容差（步长的 1/4）。
+pub const JPEG_QIM_STEP: f64 = 24.0;
+/// 每块嵌入同一比特的中频系数位置（行, 列）
+const MID_FREQUENCY_COEFFS: [(usize, usize); 5] = [(0, 3), (1, 2), (2, 1), (3, 0), (2, 2)];
+
+/// 图片可嵌入的 8×8 块数（即可嵌入的最大位数），不足一块的右侧 / 底部边缘不参与
+pub fn capacity(width: u32, height: u32) -> usize {
+    (width as usize / JPEG_BLOCK) * (height as usize / JPEG_BLOCK)
+}
+
+/// 将 `bits` 嵌入 RGB 图片的亮度中频系数，每块一位，按 `block_order` 顺序循环分配
+///
+/// 可用块数少于 `bits.len()` 时返回错误。
+pub fn embed_bits(image: &RgbImage, bits: &[u8], step: f64, password: u64) -> Result<RgbImage, BlindMarkError> {
+    let (width, height) = image.dimensions();
+    let block_num = capacity(width, height);
+    if block_num < bits.len() {
+        return Err(BlindMarkError::ImageProcessing(format!(
+            "图片太小：{}×{} 仅能划分 {} 个 8×8 块，不足以嵌入 {} 位水印",
+            width, height, block_num, bits.len()
+        )));
+    }
+
+    let luma = luma_plane(image);
+    let basis = basis_table();
+    let (w, h) = (width as usize, height as usize);
+    let mut channels: [Array2<f64>; 3] = std::array::from_fn(|_| Array2::zeros((h, w)));
+    for (x, y, p) in image.enumerate_pixels() {
+        for (c, channel) in channels.iter_mut().enumerate() {
+            channel[[y as usize, x as usize]] = p[c] as f64;
+        }
+    }
+
+    let blocks_per_row = w / JPEG_BLOCK;
+    for (k, &block) in block_order(block_num, password).iter().enumerate() {
+        let bit = bits[k % bits.len()];
+        let (by, bx) = ((block / blocks_per_row) * JPEG_BLOCK, (block % blocks_per_row) * JPEG_BLOCK);
+        let coeffs = block_coefficients(&luma, &basis, by, bx);
+
+        // 系数的修改量换算回像素：Δ · 基函数，三通道相同
+        let mut delta = [0.0f64; JPEG_BLOCK * JPEG_BLOCK];
+        for (coeff, b) in coeffs.iter().zip(&basis) {
+            let diff = qim_encode(*coeff, bit, step) - coeff;
+            for (d, weight) in delta.iter_mut().zip(b) {
+                *d += diff * weight;
+            }
+        }
+        for channel in channels.iter_mut() {
+            for (i, d) in delta.iter().enumerate() {
+                channel[[by + i / JPEG_BLOCK, bx + i % JPEG_BLOCK]] += d;
+            }
+        }
+    }
+
+    Ok(RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb(std::array::from_fn(|c| channels[c][[y as usize, x as usize]].round().clamp(0.0, 255.0) as u8))
+    }))
+}
+
+/// 逐块读取中频系数（按嵌入顺序），可对同一组系数按不同 QIM 步长解码（见 `block_softs`）
+pub fn extract_block_coefficients(image: &RgbImage, password: u64) -> Vec<[f64; MID_FREQUENCY_COEFFS.len()]> {
+    let (width, height) = image.dimensions();
+    let luma = luma_plane(image);
+    let basis = basis_table();
+    let blocks_per_row = width as usize / JPEG_BLOCK;
+    block_order(capacity(width, height), password)
+        .into_iter()
+        .map(|block| {
+            let (by, bx) = ((block / blocks_per_row) * JPEG_BLOCK, (block % blocks_per_row) * JPEG_BLOCK);
+            block_coefficients(&luma, &basis, by, bx)
+        })
+        .collect()
+}
+
+/// 按 QIM 步长 `step` 将逐块系数解码为逐块软判决值（各系数判决的平均，值域 [0, 1]）
+pub fn block_softs(coefficients: &[[f64; MID_FREQUENCY_COEFFS.len()]], step: f64) -> Vec<Option<f64>> {
+    coefficients.iter().map(|coeffs| Some(block_soft(coeffs, step))).collect()
+}
+
+/// 单个块的软判决值（见 `block_softs`）
+pub fn block_soft(coeffs: &[f64; MID_FREQUENCY_COEFFS.len()], step: f64) -> f64 {
+    let sum: f64 = coeffs.iter().map(|&c| qim_decode_soft(c, step)).sum();
+    sum / coeffs.len() as f64
+}
+
+/// 亮度平面 Y = 0.299 R + 0.587 G + 0.114 B（与 JPEG 编码器的 RGB → YCbCr 一致）
+fn luma_plane(image: &RgbImage) -> Array2<f64> {
+    let (width, height) = image.dimensions();
+    let mut luma = Array2::zeros((height as usize, width as usize));
+    for (x, y, p) in image.enumerate_pixels() {
+        luma[[y as usize, x as usize]] = 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64;
+    }
+    luma
+}
+
+/// 所选中频系数的 8×8 正交 DCT 基函数（与 JPEG FDCT 的系数尺度一致），按块内行优先展平
+fn basis_table() -> [[f64; JPEG_BLOCK * JPEG_BLOCK]; MID_FREQUENCY_COEFFS.len()] {
+    let n = JPEG_BLOCK as f64;
+    let weight = |k: usize| if k == 0 { (1.0 / n).sqrt() } else { (2.0 / n).sqrt() };
+    let cos = |k: usize, i: usize| ((2 * i + 1) as f64 * k as f64 * PI / (2.0 * n)).cos();
+    MID_FREQUENCY_COEFFS.map(|(u, v)| {
+        std::array::from_fn(|i| {
+            let (y, x) = (i / JPEG_BLOCK, i % JPEG_BLOCK);
+            weight(u) * weight(v) * cos(u, y) * cos(v, x)
+        })
+    })
+}
+
+/// 左上角为 `(by, bx)` 的块在各基函数上的投影，即该块的中频 DCT 系数
+fn block_coefficients(
+    luma: &Array2<f64>,
+    basis: &[[f64; JPEG_BLOCK * JPEG_BLOCK]; MID_FREQUENCY_COEFFS.len()],
+    by: usize,
+    bx: usize,
+) -> [f64; MID_FREQUENCY_COEFFS.len()] {
+    basis.map(|b| {
+        b.iter()
+            .enumerate()
+            .map(|(i, weight)| luma[[by + i / JPEG_BLOCK, bx + i % JPEG_BLOCK]] * weight)
+            .sum()
+    })
+}
+
+/// 由密码决定的块顺序：比特 k 嵌入第 `order[k % 块数]` 个块（块按行优先编号）
+fn block_order(block_num: usize, password: u64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..block_num).collect();
+    order.shuffle(&mut SmallRng::seed_from_u64(password));
+    order
+}
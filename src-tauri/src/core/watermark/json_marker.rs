@@ -1,10 +1,12 @@
 use serde_json::Value;
 use rand::Rng;
+use rand_chacha::ChaCha8Rng;
 use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit},
     Aes256Gcm, Key, Nonce,
 };
 use sha2::{Sha256, Digest};
+use hmac::{Hmac, Mac};
 use crate::models::BlindMarkError;
 use crate::core::watermark::encoder::WatermarkEncoder;
 
@@ -21,6 +23,13 @@ pub struct JsonWatermarker;
 /// 默认水印字段名（未自定义时使用）
 pub const DEFAULT_WATERMARK_KEY: &str = "_watermark";
 
+/// 半混淆模式默认候选字段名（按顺序尝试，未自定义候选列表时使用）
+///
+/// 字段名看起来像普通元数据，但固定且有序，提取时按名称依次查找即可，无需全量扫描。
+pub const SEMI_OBFUSCATED_KEYS: &[&str] = &[
+    "contentHash", "checksum", "fileHash", "dataHash", "assetId", "buildVersion",
+];
+
 // ─── 私有工具函数 ──────────────────────────────────────────────────────────────
 
 /// 将字节序列解码为 UTF-8 字符串。
@@ -30,7 +39,7 @@ pub const DEFAULT_WATERMARK_KEY: &str = "_watermark";
 ///   2. 尝试按 UTF-8 解码
 ///   3. UTF-8 失败时回退到 GBK 解码（兼容中文 Windows 系统生成的文件）
 ///   4. 两者均失败则返回错误
-fn decode_text_bytes(bytes: &[u8]) -> Result<String, BlindMarkError> {
+pub(crate) fn decode_text_bytes(bytes: &[u8]) -> Result<String, BlindMarkError> {
     let stripped = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
     // 优先尝试 UTF-8
     if let Ok(s) = std::str::from_utf8(stripped) {
@@ -57,14 +66,83 @@ fn encode_with_bom(content: &str) -> Vec<u8> {
     out
 }
 
+/// 宽松修复手工编辑产生的非标准 JSON：去除 `//` / `/* */` 注释与尾随逗号。
+///
+/// 字符串字面量内部的内容保持不变（正确处理转义引号）。
+fn relax_json(content: &str) -> String {
+    // 第一遍：去除注释
+    let chars: Vec<char> = content.chars().collect();
+    let mut no_comments = String::with_capacity(content.len());
+    let mut i = 0;
+    let mut in_string = false;
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            no_comments.push(c);
+            if c == '\\' && i + 1 < chars.len() {
+                no_comments.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            if c == '"' {
+                in_string = false;
+            }
+            i += 1;
+        } else if c == '"' {
+            in_string = true;
+            no_comments.push(c);
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else {
+            no_comments.push(c);
+            i += 1;
+        }
+    }
+
+    // 第二遍：去除 `}` / `]` 前的尾随逗号
+    let chars: Vec<char> = no_comments.chars().collect();
+    let mut out = String::with_capacity(no_comments.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &c) in chars.iter().enumerate() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = chars[i + 1..].iter().find(|ch| !ch.is_whitespace());
+            if matches!(next, Some('}') | Some(']')) {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
 /// 判断字符串是否符合 MD5 格式（32 位小写十六进制）
 fn is_md5_like(s: &str) -> bool {
     s.len() == 32 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
 }
 
 /// 判断字符串是否是任意一种水印值格式
-fn is_watermark_value(s: &str) -> bool {
-    is_md5_like(s) || s.starts_with("txt:") || s.starts_with("aes:")
+pub(crate) fn is_watermark_value(s: &str) -> bool {
+    is_md5_like(s) || s.starts_with("txt:") || s.starts_with("aes:") || s.starts_with("hmac:")
 }
 
 /// 字节数组转十六进制字符串
@@ -126,12 +204,36 @@ fn aes_decrypt(encoded: &str, key_bytes: &[u8; 32]) -> Result<String, BlindMarkE
         .map_err(|e| BlindMarkError::ImageProcessing(format!("解密结果不是有效 UTF-8: {}", e)))
 }
 
+/// HMAC-SHA256 签名：返回 `hmac:<hex(hmac_sha256(key, text))>:<text>`
+fn hmac_sign(text: &str, key: &str) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_bytes()).expect("HMAC 接受任意长度的密钥");
+    mac.update(text.as_bytes());
+    format!("hmac:{}:{}", bytes_to_hex(&mac.finalize().into_bytes()), text)
+}
+
+/// 拆分 `hmac:<hex>:<text>` 为 (签名十六进制, 原文)；原文本身可以包含 `:`
+fn split_hmac(encoded: &str) -> Option<(&str, &str)> {
+    encoded.strip_prefix("hmac:")?.split_once(':')
+}
+
+/// 用 `key` 重新计算原文的 HMAC 并与签名比较（常量时间），签名格式无效时返回 false
+fn hmac_verify(signature_hex: &str, text: &str, key: &str) -> bool {
+    if !signature_hex.is_ascii() {
+        return false;
+    }
+    let Ok(signature) = hex_to_bytes(signature_hex) else {
+        return false;
+    };
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_bytes()).expect("HMAC 接受任意长度的密钥");
+    mac.update(text.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
 /// 根据已有字段名随机生成伪装字段名，并返回用于定位插入位置的基础字段名。
 ///
 /// 策略：随机选取某个已有字段的小写前缀，再随机拼接中性后缀（Hash/Id/Code 等），
-/// 使其在视觉上融入原有字段风格。每次调用均独立随机，同一水印文本处理不同文件时结果各异。
-fn make_disguised_key<'a>(existing_keys: &[&'a str]) -> (String, Option<&'a str>) {
-    let mut rng = rand::thread_rng();
+/// 使其在视觉上融入原有字段风格。随机性来自 `rng`：使用线程随机数时同一水印文本处理不同文件时结果各异。
+fn make_disguised_key<'a>(existing_keys: &[&'a str], rng: &mut impl Rng) -> (String, Option<&'a str>) {
     let suffixes = ["Hash", "Id", "Code", "Key", "Sig", "Ref"];
 
     if !existing_keys.is_empty() {
@@ -187,6 +289,8 @@ impl JsonWatermarker {
     /// # 模式
     /// * `"plaintext"` → `txt:<text>`
     /// * `"aes"`       → `aes:<hex(nonce||ciphertext||tag)>`（需要 `aes_key`）
+    /// * `"hmac"`      → `hmac:<hex(hmac_sha256(aes_key, text))>:<text>`（需要 `aes_key` 作为签名密钥；
+    ///   原文可直接读出，持有密钥者可证明其未被伪造或篡改）
     /// * `"md5"` 或其他 → `<32位小写MD5哈希>`（默认）
     pub fn encode_watermark(
         text: &str,
@@ -202,6 +306,12 @@ impl JsonWatermarker {
                 let key_bytes = derive_aes_key(key_str);
                 aes_encrypt(text, &key_bytes)
             }
+            "hmac" => {
+                let key_str = aes_key.ok_or_else(|| {
+                    BlindMarkError::ImageProcessing("HMAC 模式需要提供密钥".to_string())
+                })?;
+                Ok(hmac_sign(text, key_str))
+            }
             _ => Ok(WatermarkEncoder::encode(text).md5_hash),
         }
     }
@@ -211,6 +321,8 @@ impl JsonWatermarker {
     /// * `"plaintext"` → (原文, "plaintext", true)
     /// * `"aes"` 且有正确密钥 → (解密原文, "aes", true)
     /// * `"aes"` 且无密钥或密钥错误 → (原始aes:...字符串, "aes", false)
+    /// * `"hmac"` → (原文, "hmac", 签名是否校验通过)；无密钥、密钥错误或原文在签名后被修改时为 false，
+    ///   签名格式无效时显示值为原始字符串
     /// * MD5 格式 → (MD5哈希, "md5", true)
     /// * 其他 → (原值, "unknown", false)
     pub fn decode_watermark(raw: &str, aes_key: Option<&str>) -> (String, String, bool) {
@@ -226,6 +338,14 @@ impl JsonWatermarker {
             } else {
                 (raw.to_string(), "aes".to_string(), false)
             }
+        } else if raw.starts_with("hmac:") {
+            match split_hmac(raw) {
+                Some((signature, text)) => {
+                    let verified = aes_key.is_some_and(|key| hmac_verify(signature, text, key));
+                    (text.to_string(), "hmac".to_string(), verified)
+                }
+                None => (raw.to_string(), "hmac".to_string(), false),
+            }
         } else if is_md5_like(raw) {
             (raw.to_string(), "md5".to_string(), true)
         } else {
@@ -239,8 +359,8 @@ impl JsonWatermarker {
     /// * `content`        - 原始 JSON 字符串（UTF-8）
     /// * `watermark_text` - 要嵌入的明文
     /// * `key`            - 水印字段名
-    /// * `mode`           - 编码模式（"md5" / "plaintext" / "aes"）
-    /// * `aes_key`        - AES 模式下的用户密钥
+    /// * `mode`           - 编码模式（"md5" / "plaintext" / "aes" / "hmac"）
+    /// * `aes_key`        - AES 模式下的用户密钥（HMAC 模式下为签名密钥）
     pub fn embed(
         content: &str,
         watermark_text: &str,
@@ -316,6 +436,70 @@ impl JsonWatermarker {
         Self::extract(&content, key)
     }
 
+    /// 半混淆模式：按顺序从候选列表中选出水印字段名
+    ///
+    /// 选取第一个不存在于根对象、或其值已是水印值（重复处理时覆盖旧水印）的候选；
+    /// 所有候选均被原有字段占用时回退到 `DEFAULT_WATERMARK_KEY`。
+    /// 同一份 JSON 与候选列表总是得到相同结果。
+    pub fn semi_obfuscated_key<S: AsRef<str>>(content: &str, candidates: &[S]) -> Result<String, BlindMarkError> {
+        let content = content.trim_start_matches('\u{FEFF}');
+        let json: Value = serde_json::from_str(content).map_err(|e| {
+            BlindMarkError::ImageProcessing(format!("JSON 解析失败: {}", e))
+        })?;
+        let key = candidates
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|k| !k.trim().is_empty())
+            .find(|k| match json.get(*k) {
+                None => true,
+                Some(Value::String(s)) => is_watermark_value(s),
+                Some(_) => false,
+            })
+            .unwrap_or(DEFAULT_WATERMARK_KEY);
+        Ok(key.to_string())
+    }
+
+    /// 半混淆模式嵌入（字节版本）
+    ///
+    /// 字段名由 `semi_obfuscated_key` 从候选列表中确定，之后与 `embed_bytes` 相同。
+    /// 返回 `(嵌入后的字节, 实际使用的字段名)`。
+    pub fn embed_semi_obfuscated_bytes<S: AsRef<str>>(
+        bytes: &[u8],
+        watermark_text: &str,
+        candidates: &[S],
+        mode: &str,
+        aes_key: Option<&str>,
+    ) -> Result<(Vec<u8>, String), BlindMarkError> {
+        let content = decode_text_bytes(bytes)?;
+        let key = Self::semi_obfuscated_key(&content, candidates)?;
+        let result = Self::embed(&content, watermark_text, &key, mode, aes_key)?;
+        Ok((encode_with_bom(&result), key))
+    }
+
+    /// 半混淆模式提取：按候选顺序查找第一个值为水印格式的字段
+    ///
+    /// 返回 `(字段名, 原始水印值)`；候选之外回退检查 `DEFAULT_WATERMARK_KEY`。
+    pub fn extract_semi_obfuscated<S: AsRef<str>>(
+        content: &str,
+        candidates: &[S],
+    ) -> Result<(String, String), BlindMarkError> {
+        let content = content.trim_start_matches('\u{FEFF}');
+        let json: Value = serde_json::from_str(content).map_err(|e| {
+            BlindMarkError::ImageProcessing(format!("JSON 解析失败: {}", e))
+        })?;
+        candidates
+            .iter()
+            .map(AsRef::as_ref)
+            .chain(std::iter::once(DEFAULT_WATERMARK_KEY))
+            .find_map(|k| match json.get(k) {
+                Some(Value::String(s)) if is_watermark_value(s) => Some((k.to_string(), s.clone())),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                BlindMarkError::ExtractionFailed("未在 JSON 中找到半混淆水印字段".to_string())
+            })
+    }
+
     /// 混淆模式嵌入（字节版本）
     ///
     /// 与 embed_bytes 相同，但使用混淆模式（字段名伪装）：
@@ -332,6 +516,74 @@ impl JsonWatermarker {
         Ok(encode_with_bom(&result))
     }
 
+    /// 宽松模式：修复手工编辑的非标准 JSON 字节（尾随逗号、注释）
+    ///
+    /// 自动检测编码后去除注释与尾随逗号，修复结果可被严格解析时
+    /// 返回 UTF-8 字节（可直接交给 `embed_bytes` / `embed_obfuscated_bytes`），
+    /// 否则返回错误，调用方应原样保留该文件。
+    pub fn repair_bytes(bytes: &[u8]) -> Result<Vec<u8>, BlindMarkError> {
+        let content = decode_text_bytes(bytes)?;
+        let relaxed = relax_json(&content);
+        serde_json::from_str::<Value>(&relaxed).map_err(|e| {
+            BlindMarkError::ImageProcessing(format!("JSON 宽松修复失败: {}", e))
+        })?;
+        Ok(relaxed.into_bytes())
+    }
+
+    /// 移除根对象中的指定字段（如构建时间戳等易变字段），使输出可复现
+    ///
+    /// 仅处理根对象的直接字段，与水印字段所在层级一致；其余字段顺序不变。
+    /// 嵌入前调用，可与任意嵌入模式（含混淆 / 半混淆）组合。非 Object 根节点原样返回。
+    pub fn strip_fields<S: AsRef<str>>(content: &str, fields: &[S]) -> Result<String, BlindMarkError> {
+        let mut json: Value = serde_json::from_str(content).map_err(|e| {
+            BlindMarkError::ImageProcessing(format!("JSON 解析失败: {}", e))
+        })?;
+        if let Some(obj) = json.as_object_mut() {
+            for field in fields {
+                obj.shift_remove(field.as_ref());
+            }
+        }
+        serde_json::to_string_pretty(&json).map_err(|e| {
+            BlindMarkError::ImageProcessing(format!("JSON 序列化失败: {}", e))
+        })
+    }
+
+    /// 移除指定字段（字节版本）：自动检测编码，返回 UTF-8 字节（可直接交给各 `embed_*_bytes`）
+    pub fn strip_fields_bytes<S: AsRef<str>>(bytes: &[u8], fields: &[S]) -> Result<Vec<u8>, BlindMarkError> {
+        let content = decode_text_bytes(bytes)?;
+        Ok(Self::strip_fields(&content, fields)?.into_bytes())
+    }
+
+    /// 移除根对象中所有水印字段，返回格式化后的干净 JSON
+    ///
+    /// 按值识别（MD5 / `txt:` / `aes:` / `hmac:`，见 `is_watermark_value`），因此默认字段名、
+    /// 半混淆字段名与 `embed_obfuscated` 生成的伪装字段名都会被移除；一个文件中有多个
+    /// 水印字段时全部移除，其余字段顺序不变。仅处理根对象的直接字段，与各嵌入模式的层级一致。
+    /// `aes:` / `hmac:` 值按前缀识别，无需密钥。
+    /// 非 Object 根节点原样返回。
+    pub fn remove(content: &str) -> Result<String, BlindMarkError> {
+        let trimmed = content.trim_start_matches('\u{FEFF}');
+        let json: Value = serde_json::from_str(trimmed).map_err(|e| {
+            BlindMarkError::ImageProcessing(format!("JSON 解析失败: {}", e))
+        })?;
+        let Value::Object(map) = json else {
+            return Ok(content.to_string());
+        };
+        let clean: serde_json::Map<String, Value> = map
+            .into_iter()
+            .filter(|(_, v)| !v.as_str().map(is_watermark_value).unwrap_or(false))
+            .collect();
+        serde_json::to_string_pretty(&Value::Object(clean)).map_err(|e| {
+            BlindMarkError::ImageProcessing(format!("JSON 序列化失败: {}", e))
+        })
+    }
+
+    /// 移除水印字段（字节版本）：自动检测编码，写回时统一添加 UTF-8 BOM（同各 `embed_*_bytes`）
+    pub fn remove_bytes(bytes: &[u8]) -> Result<Vec<u8>, BlindMarkError> {
+        let content = decode_text_bytes(bytes)?;
+        Ok(encode_with_bom(&Self::remove(&content)?))
+    }
+
     /// 对纯文本字节序列做 UTF-8 BOM 规范化
     ///
     /// 适用于 .cslist 等非 JSON 纯文本文件：
@@ -345,6 +597,20 @@ impl JsonWatermarker {
         Ok(encode_with_bom(&content))
     }
 
+    /// 稳定混淆模式嵌入（字节版本）：同一输入总是得到相同输出，见 `embed_obfuscated_stable`
+    ///
+    /// 编码处理同 `embed_obfuscated_bytes`。
+    pub fn embed_obfuscated_stable_bytes(
+        bytes: &[u8],
+        watermark_text: &str,
+        mode: &str,
+        aes_key: Option<&str>,
+    ) -> Result<Vec<u8>, BlindMarkError> {
+        let content = decode_text_bytes(bytes)?;
+        let result = Self::embed_obfuscated_stable(&content, watermark_text, mode, aes_key)?;
+        Ok(encode_with_bom(&result))
+    }
+
     /// 混淆模式嵌入：
     /// 1. 遍历已有字段名，生成与之风格一致的伪装字段名
     /// 2. 将水印插入到基础字段附近而非末尾
@@ -354,6 +620,37 @@ impl JsonWatermarker {
         watermark_text: &str,
         mode: &str,
         aes_key: Option<&str>,
+    ) -> Result<String, BlindMarkError> {
+        Self::embed_obfuscated_with_rng(content, watermark_text, mode, aes_key, &mut rand::thread_rng())
+    }
+
+    /// 稳定混淆模式嵌入：同 `embed_obfuscated`，但伪装字段名与插入位置由
+    /// SHA-256(JSON 内容 || 水印文本) 作为种子的 ChaCha8 随机数决定，重复处理同一文件得到相同结果
+    ///
+    /// 生成器须显式指定：`StdRng` 的算法可能随 `rand` 版本改变，升级后输出会悄然变化。
+    /// AES 模式的随机 nonce 不受影响，密文每次仍不同。
+    pub fn embed_obfuscated_stable(
+        content: &str,
+        watermark_text: &str,
+        mode: &str,
+        aes_key: Option<&str>,
+    ) -> Result<String, BlindMarkError> {
+        use rand::SeedableRng;
+
+        let mut hasher = Sha256::new();
+        hasher.update(content.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(watermark_text.as_bytes());
+        let mut rng = ChaCha8Rng::from_seed(hasher.finalize().into());
+        Self::embed_obfuscated_with_rng(content, watermark_text, mode, aes_key, &mut rng)
+    }
+
+    fn embed_obfuscated_with_rng(
+        content: &str,
+        watermark_text: &str,
+        mode: &str,
+        aes_key: Option<&str>,
+        rng: &mut impl Rng,
     ) -> Result<String, BlindMarkError> {
         let json: Value = serde_json::from_str(content).map_err(|e| {
             BlindMarkError::ImageProcessing(format!("JSON 解析失败: {}", e))
@@ -368,14 +665,14 @@ impl JsonWatermarker {
 
         let encoded = Self::encode_watermark(watermark_text, mode, aes_key)?;
 
-        // 过滤掉所有值为水印格式的旧水印字段（兼容三种格式）
+        // 过滤掉所有值为水印格式的旧水印字段（兼容全部格式）
         let clean_entries: Vec<(String, Value)> = map
             .into_iter()
             .filter(|(_, v)| !v.as_str().map(is_watermark_value).unwrap_or(false))
             .collect();
 
         let existing_key_refs: Vec<&str> = clean_entries.iter().map(|(k, _)| k.as_str()).collect();
-        let (disguised_key, base_key) = make_disguised_key(&existing_key_refs);
+        let (disguised_key, base_key) = make_disguised_key(&existing_key_refs, rng);
 
         // 插入位置：紧靠基础字段之后；否则在中段随机选位（避免放在末尾）
         let n = clean_entries.len();
@@ -384,7 +681,7 @@ impl JsonWatermarker {
             .map(|p| p + 1)
             .unwrap_or_else(|| {
                 if n <= 2 { n.saturating_sub(1) }
-                else { rand::thread_rng().gen_range(1..n) }
+                else { rng.gen_range(1..n) }
             });
 
         let mut new_map = serde_json::Map::new();
@@ -405,7 +702,9 @@ impl JsonWatermarker {
         })
     }
 
-    /// 扫描 JSON 内容，提取所有水印值（兼容明文、MD5、AES 三种格式）
+    /// 扫描 JSON 内容，提取所有水印值（兼容明文、MD5、AES、HMAC 四种格式）
+    ///
+    /// 仅检查顶层字段，速度最快；需覆盖嵌套对象/数组时使用 `scan_watermark_values_nested`。
     ///
     /// # 返回
     /// 每个元素为 `(显示值, 模式名称, 是否已成功解码)`
@@ -413,19 +712,103 @@ impl JsonWatermarker {
         content: &str,
         aes_key: Option<&str>,
     ) -> Vec<(String, String, bool)> {
+        Self::scan_watermark_locations(content, aes_key, false)
+            .into_iter()
+            .map(|loc| (loc.value, loc.mode, loc.decrypted))
+            .collect()
+    }
+
+    /// 递归扫描整个 JSON 树（嵌套对象与数组），提取所有水印值
+    ///
+    /// 返回格式同 `scan_watermark_values`，顺序为深度优先的文档顺序。
+    pub fn scan_watermark_values_nested(
+        content: &str,
+        aes_key: Option<&str>,
+    ) -> Vec<(String, String, bool)> {
+        Self::scan_watermark_locations(content, aes_key, true)
+            .into_iter()
+            .map(|loc| (loc.value, loc.mode, loc.decrypted))
+            .collect()
+    }
+
+    /// 扫描 JSON 内容，提取所有水印值并记录其 JSON Pointer（RFC 6901）位置
+    ///
+    /// `recursive = false` 时仅扫描顶层字段（与 `scan_watermark_values` 一致）；
+    /// `recursive = true` 时递归进入嵌套对象与数组，例如返回 `/meta/info/xHash`。
+    pub fn scan_watermark_locations(
+        content: &str,
+        aes_key: Option<&str>,
+        recursive: bool,
+    ) -> Vec<JsonWatermarkLocation> {
         // 剥离可能由 read_to_string 保留的 UTF-8 BOM 字符（\u{FEFF}）
         let content = content.trim_start_matches('\u{FEFF}');
         let Ok(json) = serde_json::from_str::<Value>(content) else {
             return vec![];
         };
-        let Some(obj) = json.as_object() else {
+        if !json.is_object() {
             return vec![];
-        };
-        obj.values()
-            .filter_map(|v| v.as_str())
-            .filter(|s| is_watermark_value(s))
-            .map(|s| Self::decode_watermark(s, aes_key))
-            .collect()
+        }
+        let mut found = Vec::new();
+        collect_watermark_locations(&json, "", None, aes_key, recursive, &mut found);
+        found
+    }
+}
+
+/// JSON 中单个水印字段的位置与解码结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonWatermarkLocation {
+    /// 字段的 JSON Pointer，如 `/meta/info/xHash`
+    pub pointer: String,
+    /// 叶子字段名（位于数组中时为 None）
+    pub key: Option<String>,
+    /// 解码后的显示值
+    pub value: String,
+    /// 水印编码模式
+    pub mode: String,
+    /// 是否已成功解码
+    pub decrypted: bool,
+}
+
+/// 按 RFC 6901 转义 JSON Pointer 片段（`~` → `~0`，`/` → `~1`）
+fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// 深度优先收集水印字段；对象按原有字段顺序遍历
+fn collect_watermark_locations(
+    value: &Value,
+    pointer: &str,
+    key: Option<&str>,
+    aes_key: Option<&str>,
+    recursive: bool,
+    out: &mut Vec<JsonWatermarkLocation>,
+) {
+    // 根节点总是展开；其余容器仅在递归模式下展开
+    let descend = pointer.is_empty() || recursive;
+    match value {
+        Value::String(s) if !pointer.is_empty() && is_watermark_value(s) => {
+            let (value, mode, decrypted) = JsonWatermarker::decode_watermark(s, aes_key);
+            out.push(JsonWatermarkLocation {
+                pointer: pointer.to_string(),
+                key: key.map(str::to_string),
+                value,
+                mode,
+                decrypted,
+            });
+        }
+        Value::Object(map) if descend => {
+            for (k, v) in map {
+                let child = format!("{}/{}", pointer, escape_pointer_token(k));
+                collect_watermark_locations(v, &child, Some(k), aes_key, recursive, out);
+            }
+        }
+        Value::Array(items) if descend && !pointer.is_empty() => {
+            for (i, v) in items.iter().enumerate() {
+                let child = format!("{}/{}", pointer, i);
+                collect_watermark_locations(v, &child, None, aes_key, recursive, out);
+            }
+        }
+        _ => {}
     }
 }
 
@@ -433,6 +816,59 @@ impl JsonWatermarker {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_scan_watermark_locations_nested_pointer() {
+        let encoded = WatermarkEncoder::encode("nested owner").md5_hash;
+        let json = format!(
+            r#"{{"name": "pkg", "meta": {{"info": {{"xHash": "{}", "title": "t"}}, "tags": ["a"]}}}}"#,
+            encoded
+        );
+
+        // 默认仅扫描顶层，嵌套水印不可见
+        assert!(JsonWatermarker::scan_watermark_values(&json, None).is_empty());
+        assert!(JsonWatermarker::scan_watermark_locations(&json, None, false).is_empty());
+
+        let found = JsonWatermarker::scan_watermark_locations(&json, None, true);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pointer, "/meta/info/xHash");
+        assert_eq!(found[0].key.as_deref(), Some("xHash"));
+        assert_eq!(found[0].mode, "md5");
+        assert_eq!(found[0].value, encoded);
+    }
+
+    #[test]
+    fn test_scan_watermark_values_nested_arrays_and_objects() {
+        let md5 = WatermarkEncoder::encode("deep").md5_hash;
+        let json = format!(
+            r#"{{
+                "top": "txt:root",
+                "items": [1, {{"id": "{md5}"}}, ["txt:in-array"]],
+                "cfg": {{"inner": {{"list": ["plain", "txt:deepest"]}}}}
+            }}"#
+        );
+
+        let top = JsonWatermarker::scan_watermark_values(&json, None);
+        assert_eq!(top, vec![("root".to_string(), "plaintext".to_string(), true)]);
+
+        let all = JsonWatermarker::scan_watermark_values_nested(&json, None);
+        let values: Vec<&str> = all.iter().map(|(v, _, _)| v.as_str()).collect();
+        assert_eq!(values, vec!["root", md5.as_str(), "in-array", "deepest"]);
+
+        let pointers: Vec<String> = JsonWatermarker::scan_watermark_locations(&json, None, true)
+            .into_iter()
+            .map(|loc| loc.pointer)
+            .collect();
+        assert_eq!(pointers, vec!["/top", "/items/1/id", "/items/2/0", "/cfg/inner/list/1"]);
+    }
+
+    #[test]
+    fn test_scan_watermark_locations_escapes_pointer() {
+        let embedded = JsonWatermarker::embed(r#"{"a": 1}"#, "top", "a/b~c", "md5", None).unwrap();
+        let found = JsonWatermarker::scan_watermark_locations(&embedded, None, false);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pointer, "/a~1b~0c");
+    }
+
     #[test]
     fn test_embed_md5_mode() {
         let json = r#"{"name": "test", "version": "1.0"}"#;
@@ -445,6 +881,16 @@ mod tests {
         assert!(parsed.get("version").is_some());
     }
 
+    #[test]
+    #[cfg(feature = "preserve_numbers")]
+    fn test_embed_preserves_number_formatting() {
+        let content = r#"{"scale": 1.2200000, "weight": 1.0, "id": 123456789012345678901234567890}"#;
+        let result = JsonWatermarker::embed(content, "alice", DEFAULT_WATERMARK_KEY, "plaintext", None).unwrap();
+        assert!(result.contains("\"scale\": 1.2200000"), "{}", result);
+        assert!(result.contains("\"weight\": 1.0"), "{}", result);
+        assert!(result.contains("\"id\": 123456789012345678901234567890"), "{}", result);
+    }
+
     #[test]
     fn test_embed_plaintext_mode() {
         let json = r#"{"name": "test"}"#;
@@ -492,6 +938,48 @@ mod tests {
         assert!(!decrypted, "错误密钥应导致解密失败");
     }
 
+    #[test]
+    fn test_hmac_correct_key_verifies() {
+        let json = r#"{"name": "test"}"#;
+        let watermarked = JsonWatermarker::embed(json, "购买者:李四", DEFAULT_WATERMARK_KEY, "hmac", Some("secret")).unwrap();
+        let raw = JsonWatermarker::extract(&watermarked, DEFAULT_WATERMARK_KEY).unwrap();
+        assert!(raw.starts_with("hmac:") && raw.ends_with(":购买者:李四"));
+
+        let findings = JsonWatermarker::scan_watermark_values(&watermarked, Some("secret"));
+        assert_eq!(findings, vec![("购买者:李四".to_string(), "hmac".to_string(), true)]);
+    }
+
+    #[test]
+    fn test_hmac_wrong_key_fails_verification() {
+        let json = r#"{"name": "test"}"#;
+        let watermarked = JsonWatermarker::embed(json, "alice", DEFAULT_WATERMARK_KEY, "hmac", Some("correct")).unwrap();
+
+        // 原文仍可读出，但与明文模式不同，标记为未通过校验
+        for key in [Some("wrong"), None] {
+            let findings = JsonWatermarker::scan_watermark_values(&watermarked, key);
+            assert_eq!(findings, vec![("alice".to_string(), "hmac".to_string(), false)]);
+        }
+    }
+
+    #[test]
+    fn test_hmac_detects_text_edited_after_signing() {
+        let signed = JsonWatermarker::encode_watermark("alice", "hmac", Some("secret")).unwrap();
+        let forged = signed.replace(":alice", ":mallory");
+        assert_ne!(forged, signed);
+        assert_eq!(
+            JsonWatermarker::decode_watermark(&forged, Some("secret")),
+            ("mallory".to_string(), "hmac".to_string(), false)
+        );
+        // 签名本身被改动或格式无效
+        let bad_signature = signed.replacen("hmac:", "hmac:00", 1);
+        assert!(!JsonWatermarker::decode_watermark(&bad_signature, Some("secret")).2);
+        assert_eq!(
+            JsonWatermarker::decode_watermark("hmac:签名", Some("secret")),
+            ("hmac:签名".to_string(), "hmac".to_string(), false)
+        );
+        assert!(!JsonWatermarker::decode_watermark("hmac:签名:alice", Some("secret")).2);
+    }
+
     #[test]
     fn test_decode_watermark_plaintext() {
         let (val, mode, ok) = JsonWatermarker::decode_watermark("txt:hello", None);
@@ -590,6 +1078,36 @@ mod tests {
         assert!(result.is_err(), "GBK 也无法解码时应返回 Err");
     }
 
+    #[test]
+    fn test_lenient_trailing_comma_and_comments() {
+        let malformed = br#"{
+  // hand-edited scene
+  "name": "scene, with comma",
+  "atoms": [1, 2, 3,],
+  /* note */ "url": "http://example.com/a",
+}"#;
+        // 严格模式解析失败
+        assert!(JsonWatermarker::embed_bytes(malformed, "hello", DEFAULT_WATERMARK_KEY, "md5", None).is_err());
+
+        // 宽松修复后可正常嵌入
+        let repaired = JsonWatermarker::repair_bytes(malformed).unwrap();
+        let out = JsonWatermarker::embed_bytes(&repaired, "hello", DEFAULT_WATERMARK_KEY, "md5", None).unwrap();
+        let extracted = JsonWatermarker::extract_bytes(&out, DEFAULT_WATERMARK_KEY).unwrap();
+        assert_eq!(extracted, WatermarkEncoder::encode("hello").md5_hash);
+
+        // 字符串内的逗号与 // 保持不变
+        let parsed: Value = serde_json::from_slice(&out[3..]).unwrap();
+        assert_eq!(parsed["name"], "scene, with comma");
+        assert_eq!(parsed["url"], "http://example.com/a");
+        assert_eq!(parsed["atoms"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_lenient_unrecoverable_json() {
+        let garbage = br#"{"name": "unterminated"#;
+        assert!(JsonWatermarker::repair_bytes(garbage).is_err(), "无法修复的 JSON 应返回错误");
+    }
+
     #[test]
     fn test_non_object_json() {
         let json = r#"[1, 2, 3]"#;
@@ -648,6 +1166,106 @@ mod tests {
         assert!(findings3[0].2);
     }
 
+    #[test]
+    fn test_obfuscated_stable_is_deterministic() {
+        let meta = r#"{"licenseType": "CC BY-NC-SA", "creatorName": "Dnaddr", "version": 3, "tags": []}"#;
+        let first = JsonWatermarker::embed_obfuscated_stable(meta, "张三", "plaintext", None).unwrap();
+        for _ in 0..5 {
+            assert_eq!(JsonWatermarker::embed_obfuscated_stable(meta, "张三", "plaintext", None).unwrap(), first);
+        }
+        assert_eq!(JsonWatermarker::scan_watermark_values(&first, None)[0].0, "张三");
+        // 固定输出：依赖库升级导致伪装字段名变化时应在此发现
+        assert_eq!(JsonWatermarker::scan_watermark_locations(&first, None, false)[0].pointer, "/versionCode");
+        // 再次处理已嵌入的结果：旧水印被替换，结果依然稳定
+        let again = JsonWatermarker::embed_obfuscated_stable(&first, "张三", "plaintext", None).unwrap();
+        assert_eq!(JsonWatermarker::embed_obfuscated_stable(&first, "张三", "plaintext", None).unwrap(), again);
+        assert_eq!(JsonWatermarker::scan_watermark_values(&again, None).len(), 1);
+    }
+
+    #[test]
+    fn test_strip_fields_before_watermark() {
+        let content = "\u{FEFF}{\"name\": \"pose\", \"buildTime\": \"2024-06-01T12:00:00Z\", \"version\": 2}";
+        let stripped = JsonWatermarker::strip_fields_bytes(content.as_bytes(), &["buildTime", "missing"]).unwrap();
+
+        let marked = JsonWatermarker::embed_bytes(&stripped, "buyer", DEFAULT_WATERMARK_KEY, "plaintext", None).unwrap();
+        let json: Value = serde_json::from_slice(&marked[UTF8_BOM.len()..]).unwrap();
+        assert!(json.get("buildTime").is_none());
+        assert_eq!(json["name"], "pose");
+        assert_eq!(JsonWatermarker::extract_bytes(&marked, DEFAULT_WATERMARK_KEY).unwrap(), "txt:buyer");
+        // 不同构建时间的文件处理后结果一致
+        let other = content.replace("2024-06-01T12:00:00Z", "2025-01-01T00:00:00Z");
+        let other = JsonWatermarker::strip_fields_bytes(other.as_bytes(), &["buildTime"]).unwrap();
+        assert_eq!(JsonWatermarker::embed_bytes(&other, "buyer", DEFAULT_WATERMARK_KEY, "plaintext", None).unwrap(), marked);
+
+        // 与混淆模式组合：被移除的字段也不会成为伪装字段名的基础
+        let obfuscated = JsonWatermarker::embed_obfuscated_bytes(&stripped, "buyer", "plaintext", None).unwrap();
+        let content = decode_text_bytes(&obfuscated).unwrap();
+        assert!(!content.contains("buildTime"));
+        let values = JsonWatermarker::scan_watermark_values(&content, None);
+        assert_eq!(values.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_strips_every_watermark_field() {
+        let meta = r#"{"creatorName":"Dnaddr","packageName":"Pose","licenseType":"CC BY"}"#;
+        let obfuscated = JsonWatermarker::embed_obfuscated(meta, "buyer", "aes", Some("secret")).unwrap();
+        // 再手工追加默认字段名的明文水印与 MD5 水印：多个水印字段应全部移除
+        let mut json: Value = serde_json::from_str(&obfuscated).unwrap();
+        json[DEFAULT_WATERMARK_KEY] = Value::String("txt:other".to_string());
+        json["fileHash"] = Value::String(WatermarkEncoder::encode("other").md5_hash);
+        let marked = serde_json::to_string(&json).unwrap();
+        assert_eq!(JsonWatermarker::scan_watermark_values(&marked, None).len(), 3);
+
+        let clean = JsonWatermarker::remove(&marked).unwrap();
+        assert!(JsonWatermarker::scan_watermark_values(&clean, None).is_empty());
+        let expected: Value = serde_json::from_str(meta).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&clean).unwrap(), expected);
+        assert_eq!(clean, serde_json::to_string_pretty(&expected).unwrap());
+
+        // 字节版本剥离输入 BOM、写回时统一添加
+        let bytes = JsonWatermarker::remove_bytes(format!("\u{FEFF}{}", marked).as_bytes()).unwrap();
+        assert_eq!(decode_text_bytes(&bytes).unwrap(), clean);
+        assert!(bytes.starts_with(UTF8_BOM));
+
+        // 非 Object 根节点原样返回
+        let array = r#"["txt:buyer", 1]"#;
+        assert_eq!(JsonWatermarker::remove(array).unwrap(), array);
+    }
+
+    #[test]
+    fn test_semi_obfuscated_key_is_fixed_and_extractable() {
+        // checksum 已被原有字段占用（非水印值），应跳过并选用下一个候选
+        let meta = r#"{"creatorName":"Dnaddr","checksum":"not-a-watermark"}"#;
+        let candidates = ["checksum", "releaseId", "assetId"];
+
+        let (bytes, key) =
+            JsonWatermarker::embed_semi_obfuscated_bytes(meta.as_bytes(), "alice", &candidates, "plaintext", None).unwrap();
+        assert_eq!(key, "releaseId");
+        let content = decode_text_bytes(&bytes).unwrap();
+        assert_eq!(JsonWatermarker::extract(&content, "releaseId").unwrap(), "txt:alice");
+        let parsed: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(parsed["checksum"], "not-a-watermark");
+        assert!(parsed.get(DEFAULT_WATERMARK_KEY).is_none());
+
+        // 重复处理时覆盖原水印字段，字段名保持不变
+        let (again, key2) =
+            JsonWatermarker::embed_semi_obfuscated_bytes(&bytes, "bob", &candidates, "plaintext", None).unwrap();
+        assert_eq!(key2, "releaseId");
+        let content = decode_text_bytes(&again).unwrap();
+        let (found_key, value) = JsonWatermarker::extract_semi_obfuscated(&content, &candidates).unwrap();
+        assert_eq!((found_key.as_str(), value.as_str()), ("releaseId", "txt:bob"));
+
+        // 默认候选列表
+        let (bytes, key) =
+            JsonWatermarker::embed_semi_obfuscated_bytes(b"{}", "alice", SEMI_OBFUSCATED_KEYS, "md5", None).unwrap();
+        assert_eq!(key, SEMI_OBFUSCATED_KEYS[0]);
+        let content = decode_text_bytes(&bytes).unwrap();
+        assert_eq!(
+            JsonWatermarker::extract_semi_obfuscated(&content, SEMI_OBFUSCATED_KEYS).unwrap().1,
+            WatermarkEncoder::encode("alice").md5_hash
+        );
+    }
+
     #[test]
     fn test_meta_json_simulation() {
         let meta = r#"{
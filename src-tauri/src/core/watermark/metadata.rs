@@ -0,0 +1,343 @@
+// 元数据水印（非盲水印）：写入 PNG tEXt/iTXt 块或 JPEG EXIF UserComment
+//
+// 用于无法可靠嵌入盲水印的图片（尺寸过小、JPEG 有损压缩）的兜底方案。
+// 元数据可被轻易移除，仅用于保留归属信息，不具备抗攻击性。
+
+use crate::models::BlindMarkError;
+
+/// 元数据水印的关键字（PNG tEXt/iTXt keyword）
+pub const METADATA_WATERMARK_KEYWORD: &str = "BlindMark";
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const EXIF_TAG_EXIF_IFD: u16 = 0x8769;
+const EXIF_TAG_USER_COMMENT: u16 = 0x9286;
+/// JPEG 段长度字段上限（含自身 2 字节）
+const JPEG_MAX_SEGMENT_LEN: usize = 0xFFFF;
+
+/// 将水印文本写入图片元数据，返回新的文件字节
+///
+/// - PNG：在 IEND 前插入 tEXt 块（纯 ASCII）或 iTXt 块（UTF-8），
+///   同关键字的旧块会被替换
+/// - JPEG：在 SOI/APP0 之后插入仅含 UserComment 的 EXIF APP1 段；
+///   已有 EXIF 时返回错误，不改写原有元数据
+pub fn embed_metadata_watermark(bytes: &[u8], text: &str) -> Result<Vec<u8>, BlindMarkError> {
+    if bytes.starts_with(&PNG_SIGNATURE) {
+        embed_png_text(bytes, text)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        embed_jpeg_user_comment(bytes, text)
+    } else {
+        Err(BlindMarkError::UnsupportedImage("元数据水印仅支持 PNG / JPEG".to_string()))
+    }
+}
+
+/// 从图片元数据中读取水印文本（无则返回 `None`）
+pub fn read_metadata_watermark(bytes: &[u8]) -> Option<String> {
+    if bytes.starts_with(&PNG_SIGNATURE) {
+        read_png_text(bytes)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        read_jpeg_user_comment(bytes)
+    } else {
+        None
+    }
+}
+
+// ─── PNG ────────────────────────────────────────────────────────────────────────
+
+/// 遍历 PNG 块，返回 `(块类型, 数据, 块在文件中的起止位置)`
+fn png_chunks(bytes: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8], std::ops::Range<usize>)> {
+    let mut pos = PNG_SIGNATURE.len();
+    std::iter::from_fn(move || {
+        let header = bytes.get(pos..pos + 8)?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = [header[4], header[5], header[6], header[7]];
+        let data = bytes.get(pos + 8..pos + 8 + len)?;
+        let end = pos + 12 + len;
+        if end > bytes.len() {
+            return None;
+        }
+        let range = pos..end;
+        pos = end;
+        Some((kind, data, range))
+    })
+}
+
+/// 解析 tEXt/iTXt 块，返回 `(keyword, text)`
+fn parse_png_text_chunk(kind: &[u8; 4], data: &[u8]) -> Option<(String, String)> {
+    let nul = data.iter().position(|&b| b == 0)?;
+    let keyword = String::from_utf8_lossy(&data[..nul]).to_string();
+    let rest = &data[nul + 1..];
+    match kind {
+        // tEXt：Latin-1 文本
+        b"tEXt" => Some((keyword, rest.iter().map(|&b| b as char).collect())),
+        // iTXt：压缩标志、压缩方法、语言标签\0、翻译关键字\0、UTF-8 文本（仅支持未压缩）
+        b"iTXt" => {
+            if rest.len() < 2 || rest[0] != 0 {
+                return None;
+            }
+            let rest = &rest[2..];
+            let lang_end = rest.iter().position(|&b| b == 0)?;
+            let rest = &rest[lang_end + 1..];
+            let trans_end = rest.iter().position(|&b| b == 0)?;
+            let text = String::from_utf8(rest[trans_end + 1..].to_vec()).ok()?;
+            Some((keyword, text))
+        }
+        _ => None,
+    }
+}
+
+fn embed_png_text(bytes: &[u8], text: &str) -> Result<Vec<u8>, BlindMarkError> {
+    let (kind, data): (&[u8; 4], Vec<u8>) = if text.is_ascii() {
+        let mut data = METADATA_WATERMARK_KEYWORD.as_bytes().to_vec();
+        data.push(0);
+        data.extend_from_slice(text.as_bytes());
+        (b"tEXt", data)
+    } else {
+        let mut data = METADATA_WATERMARK_KEYWORD.as_bytes().to_vec();
+        // keyword\0 + 未压缩(0) + 方法(0) + 空语言标签\0 + 空翻译关键字\0
+        data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        data.extend_from_slice(text.as_bytes());
+        (b"iTXt", data)
+    };
+
+    let mut chunk = Vec::with_capacity(data.len() + 12);
+    chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(&data);
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(&data);
+    chunk.extend_from_slice(&hasher.finalize().to_be_bytes());
+
+    let mut out = Vec::with_capacity(bytes.len() + chunk.len());
+    out.extend_from_slice(&PNG_SIGNATURE);
+    let mut found_iend = false;
+    for (chunk_kind, chunk_data, range) in png_chunks(bytes) {
+        let is_ours = parse_png_text_chunk(&chunk_kind, chunk_data)
+            .is_some_and(|(keyword, _)| keyword == METADATA_WATERMARK_KEYWORD);
+        if is_ours {
+            continue;
+        }
+        if &chunk_kind == b"IEND" {
+            out.extend_from_slice(&chunk);
+            found_iend = true;
+        }
+        out.extend_from_slice(&bytes[range]);
+    }
+
+    if !found_iend {
+        return Err(BlindMarkError::ImageProcessing("PNG 结构不完整：缺少 IEND 块".to_string()));
+    }
+    Ok(out)
+}
+
+fn read_png_text(bytes: &[u8]) -> Option<String> {
+    png_chunks(bytes)
+        .filter_map(|(kind, data, _)| parse_png_text_chunk(&kind, data))
+        .find(|(keyword, _)| keyword == METADATA_WATERMARK_KEYWORD)
+        .map(|(_, text)| text)
+}
+
+// ─── JPEG / EXIF ────────────────────────────────────────────────────────────────
+
+/// 遍历 JPEG 头部标记段（至 SOS 为止），返回 `(标记, 段数据, 段起止位置)`
+fn jpeg_segments(bytes: &[u8]) -> impl Iterator<Item = (u8, &[u8], std::ops::Range<usize>)> {
+    let mut pos = 2;
+    std::iter::from_fn(move || {
+        let header = bytes.get(pos..pos + 4)?;
+        if header[0] != 0xFF || header[1] == 0xDA {
+            return None;
+        }
+        let marker = header[1];
+        let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let data = bytes.get(pos + 4..pos + 2 + len)?;
+        let range = pos..pos + 2 + len;
+        pos += 2 + len;
+        Some((marker, data, range))
+    })
+}
+
+fn embed_jpeg_user_comment(bytes: &[u8], text: &str) -> Result<Vec<u8>, BlindMarkError> {
+    let mut insert_at = 2;
+    for (marker, data, range) in jpeg_segments(bytes) {
+        if marker == 0xE1 && data.starts_with(EXIF_HEADER) {
+            return Err(BlindMarkError::UnsupportedImage(
+                "JPEG 已包含 EXIF，不覆盖原有元数据".to_string(),
+            ));
+        }
+        // JFIF 要求 APP0 紧随 SOI，EXIF 段放在其后
+        if marker == 0xE0 && range.start == 2 {
+            insert_at = range.end;
+        }
+    }
+
+    // UserComment：8 字节字符编码标识 + UCS-2（按 TIFF 字节序，此处为大端）
+    let mut comment = b"UNICODE\0".to_vec();
+    for unit in text.encode_utf16() {
+        comment.extend_from_slice(&unit.to_be_bytes());
+    }
+
+    // TIFF（大端 "MM"）：IFD0 仅含 Exif IFD 指针，Exif IFD 仅含 UserComment
+    const IFD0_OFFSET: u32 = 8;
+    const EXIF_IFD_OFFSET: u32 = IFD0_OFFSET + 2 + 12 + 4;
+    const COMMENT_OFFSET: u32 = EXIF_IFD_OFFSET + 2 + 12 + 4;
+    let mut tiff = Vec::new();
+    tiff.extend_from_slice(b"MM\0\x2A");
+    tiff.extend_from_slice(&IFD0_OFFSET.to_be_bytes());
+    push_ifd_entry(&mut tiff, EXIF_TAG_EXIF_IFD, 4, 1, EXIF_IFD_OFFSET);
+    push_ifd_entry(&mut tiff, EXIF_TAG_USER_COMMENT, 7, comment.len() as u32, COMMENT_OFFSET);
+    tiff.extend_from_slice(&comment);
+
+    let segment_len = 2 + EXIF_HEADER.len() + tiff.len();
+    if segment_len > JPEG_MAX_SEGMENT_LEN {
+        return Err(BlindMarkError::InvalidConfig("水印文本过长，无法写入 EXIF".to_string()));
+    }
+
+    let mut out = Vec::with_capacity(bytes.len() + segment_len + 2);
+    out.extend_from_slice(&bytes[..insert_at]);
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&(segment_len as u16).to_be_bytes());
+    out.extend_from_slice(EXIF_HEADER);
+    out.extend_from_slice(&tiff);
+    out.extend_from_slice(&bytes[insert_at..]);
+    Ok(out)
+}
+
+/// 将 `source` 中标记属于 `markers` 的头部段（如 APP1 EXIF/XMP、APP2 ICC）按原顺序复制到 `target`
+///
+/// 插入位置同 EXIF UserComment（SOI/APP0 之后），`target` 中同标记的段被丢弃。
+/// 用于重新编码 JPEG 后保留原图的元数据；`source` 不是 JPEG 时原样返回 `target`。
+pub fn copy_jpeg_segments(source: &[u8], target: &[u8], markers: &[u8]) -> Vec<u8> {
+    if !source.starts_with(&[0xFF, 0xD8]) || !target.starts_with(&[0xFF, 0xD8]) {
+        return target.to_vec();
+    }
+    let mut copied = Vec::new();
+    for (marker, _, range) in jpeg_segments(source) {
+        if markers.contains(&marker) {
+            copied.extend_from_slice(&source[range]);
+        }
+    }
+
+    let mut out = Vec::with_capacity(target.len() + copied.len());
+    out.extend_from_slice(&target[..2]);
+    let mut pending = Some(copied);
+    let mut tail_start = 2;
+    for (marker, _, range) in jpeg_segments(target) {
+        if !(marker == 0xE0 && range.start == 2) {
+            out.extend(pending.take().unwrap_or_default());
+        }
+        if !markers.contains(&marker) {
+            out.extend_from_slice(&target[range.clone()]);
+        }
+        tail_start = range.end;
+    }
+    out.extend(pending.take().unwrap_or_default());
+    out.extend_from_slice(&target[tail_start..]);
+    out
+}
+
+/// 写入只含一个条目的 IFD（条目数 + 条目 + 下一 IFD 偏移 0）
+fn push_ifd_entry(tiff: &mut Vec<u8>, tag: u16, field_type: u16, count: u32, value: u32) {
+    tiff.extend_from_slice(&1u16.to_be_bytes());
+    tiff.extend_from_slice(&tag.to_be_bytes());
+    tiff.extend_from_slice(&field_type.to_be_bytes());
+    tiff.extend_from_slice(&count.to_be_bytes());
+    tiff.extend_from_slice(&value.to_be_bytes());
+    tiff.extend_from_slice(&0u32.to_be_bytes());
+}
+
+fn read_jpeg_user_comment(bytes: &[u8]) -> Option<String> {
+    let tiff = jpeg_segments(bytes)
+        .find(|(marker, data, _)| *marker == 0xE1 && data.starts_with(EXIF_HEADER))
+        .map(|(_, data, _)| &data[EXIF_HEADER.len()..])?;
+
+    let big_endian = match tiff.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |pos: usize| -> Option<u16> {
+        let b = tiff.get(pos..pos + 2)?;
+        Some(if big_endian { u16::from_be_bytes([b[0], b[1]]) } else { u16::from_le_bytes([b[0], b[1]]) })
+    };
+    let u32_at = |pos: usize| -> Option<u32> {
+        let b = tiff.get(pos..pos + 4)?;
+        let b = [b[0], b[1], b[2], b[3]];
+        Some(if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) })
+    };
+    // 在 IFD 中查找标签，返回 (count, value/offset)
+    let find_tag = |ifd: usize, tag: u16| -> Option<(usize, usize)> {
+        let count = u16_at(ifd)? as usize;
+        (0..count).map(|i| ifd + 2 + i * 12).find_map(|entry| {
+            (u16_at(entry)? == tag).then_some(())?;
+            Some((u32_at(entry + 4)? as usize, u32_at(entry + 8)? as usize))
+        })
+    };
+
+    let ifd0 = u32_at(4)? as usize;
+    let (_, exif_ifd) = find_tag(ifd0, EXIF_TAG_EXIF_IFD)?;
+    let (count, offset) = find_tag(exif_ifd, EXIF_TAG_USER_COMMENT)?;
+    // 长度 ≤ 4 时值内联于条目中，无法容纳 8 字节编码标识，视为空
+    let raw = tiff.get(offset..offset.checked_add(count)?)?;
+    let (code, payload) = raw.split_at_checked(8)?;
+
+    let text = match code {
+        b"UNICODE\0" => {
+            let units: Vec<u16> = payload
+                .chunks_exact(2)
+                .map(|c| if big_endian { u16::from_be_bytes([c[0], c[1]]) } else { u16::from_le_bytes([c[0], c[1]]) })
+                .collect();
+            String::from_utf16(&units).ok()?
+        }
+        _ => String::from_utf8_lossy(payload).to_string(),
+    };
+    let text = text.trim_end_matches('\0').to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    fn encode(format: ImageFormat) -> Vec<u8> {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(16, 16, image::Rgb([10, 20, 30])));
+        let mut buf = Cursor::new(Vec::new());
+        img.write_to(&mut buf, format).unwrap();
+        buf.into_inner()
+    }
+
+    #[test]
+    fn test_png_text_roundtrip() {
+        let png = encode(ImageFormat::Png);
+        assert_eq!(read_metadata_watermark(&png), None);
+
+        let marked = embed_metadata_watermark(&png, "buyer-42").unwrap();
+        assert_eq!(read_metadata_watermark(&marked).as_deref(), Some("buyer-42"));
+        // 写入元数据后图片仍可正常解码
+        assert!(image::load_from_memory(&marked).is_ok());
+
+        // 非 ASCII 文本走 iTXt；重复写入替换旧值
+        let remarked = embed_metadata_watermark(&marked, "购买者:张三").unwrap();
+        assert_eq!(read_metadata_watermark(&remarked).as_deref(), Some("购买者:张三"));
+        assert_eq!(png_chunks(&remarked).filter(|(k, _, _)| k == b"tEXt" || k == b"iTXt").count(), 1);
+    }
+
+    #[test]
+    fn test_jpeg_user_comment_roundtrip() {
+        let jpeg = encode(ImageFormat::Jpeg);
+        let marked = embed_metadata_watermark(&jpeg, "购买者:张三").unwrap();
+        assert_eq!(read_metadata_watermark(&marked).as_deref(), Some("购买者:张三"));
+        assert!(image::load_from_memory(&marked).is_ok());
+
+        // 已有 EXIF 时不覆盖
+        assert!(embed_metadata_watermark(&marked, "other").is_err());
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert!(embed_metadata_watermark(b"GIF89a", "x").is_err());
+        assert_eq!(read_metadata_watermark(b"GIF89a"), None);
+    }
+}
@@ -1,9 +1,20 @@
 // Watermarking algorithm modules
+pub mod cbor;
+pub mod config_marker;
 pub mod encoder;
 pub mod dwt;
 pub mod dct;
 pub mod embedder;
 pub mod extractor;
+pub mod jpeg;
 pub mod json_marker;
+pub mod metadata;
+pub mod phash;
+pub mod profile;
+pub mod recipe;
+pub mod reed_solomon;
+pub mod svg_marker;
 
 pub use json_marker::JsonWatermarker;
+pub use svg_marker::SvgWatermarker;
+pub use config_marker::{IniWatermarker, TomlWatermarker};
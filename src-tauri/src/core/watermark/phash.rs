@@ -0,0 +1,175 @@
+// 感知哈希（pHash）篡改检测
+//
+// 嵌入时计算原图的 64 位 DCT 感知哈希，并以 `phash:<16 位十六进制>` 文本盲水印写入图片；
+// 校验时提取存储的哈希，与当前图片重新计算的哈希比较汉明距离，得到相似度。
+// 轻微改动（重新编码、水印本身）相似度接近 1，大幅编辑后相似度明显下降。
+
+use image::{imageops::FilterType, DynamicImage};
+use serde::Serialize;
+use crate::models::BlindMarkError;
+use crate::core::watermark::{embedder::WatermarkEmbedder, extractor::WatermarkExtractor};
+
+/// 水印文本前缀，用于区分 pHash 水印与普通文本水印
+pub const PHASH_WATERMARK_PREFIX: &str = "phash:";
+
+/// 计算哈希前缩放到的边长
+const HASH_INPUT_SIZE: usize = 32;
+/// 参与哈希的低频系数边长（8×8 = 64 位）
+const HASH_LOW_FREQ: usize = 8;
+
+/// pHash 校验结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhashVerification {
+    /// 嵌入时存储的原图哈希（十六进制）
+    pub stored_hash: String,
+    /// 当前图片的哈希（十六进制）
+    pub current_hash: String,
+    /// 两个哈希间不同的位数（0-64）
+    pub distance: u32,
+    /// 相似度：`1 - distance / 64`，1.0 表示感知上完全一致
+    pub similarity: f32,
+}
+
+/// 计算图片的 64 位 DCT 感知哈希
+///
+/// 灰度化并缩放到 32×32，做 2D DCT-II，取左上角 8×8 低频系数，
+/// 以除直流分量外的系数中位数为阈值逐位判决。
+pub fn compute_phash(image: &DynamicImage) -> u64 {
+    let n = HASH_INPUT_SIZE;
+    let gray = image
+        .resize_exact(n as u32, n as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = gray.pixels().map(|p| p[0] as f64).collect();
+
+    // 可分离 DCT：只需左上角 8×8，先对行、再对列各做一次
+    let cos = |k: usize, x: usize| {
+        (std::f64::consts::PI * (2 * x + 1) as f64 * k as f64 / (2 * n) as f64).cos()
+    };
+    let mut rows = vec![0.0; n * HASH_LOW_FREQ];
+    for y in 0..n {
+        for u in 0..HASH_LOW_FREQ {
+            rows[y * HASH_LOW_FREQ + u] = (0..n).map(|x| pixels[y * n + x] * cos(u, x)).sum();
+        }
+    }
+    let mut coeffs = Vec::with_capacity(HASH_LOW_FREQ * HASH_LOW_FREQ);
+    for v in 0..HASH_LOW_FREQ {
+        for u in 0..HASH_LOW_FREQ {
+            coeffs.push((0..n).map(|y| rows[y * HASH_LOW_FREQ + u] * cos(v, y)).sum::<f64>());
+        }
+    }
+
+    let mut ac: Vec<f64> = coeffs[1..].to_vec();
+    ac.sort_by(|a, b| a.total_cmp(b));
+    let median = ac[ac.len() / 2];
+
+    coeffs
+        .iter()
+        .enumerate()
+        .filter(|(_, &c)| c > median)
+        .fold(0u64, |hash, (i, _)| hash | (1 << i))
+}
+
+/// 两个哈希的相似度（0.0 - 1.0）
+pub fn phash_similarity(a: u64, b: u64) -> f32 {
+    1.0 - (a ^ b).count_ones() as f32 / 64.0
+}
+
+/// 计算原图 pHash 并作为文本盲水印嵌入
+///
+/// `strength` 同 `WatermarkEmbedder::embed_raw_text`，须在 [0.1, 1.0] 范围内
+pub fn embed_phash(
+    embedder: &WatermarkEmbedder,
+    image: &DynamicImage,
+    strength: f32,
+) -> Result<DynamicImage, BlindMarkError> {
+    let text = format!("{}{:016x}", PHASH_WATERMARK_PREFIX, compute_phash(image));
+    embedder.embed_raw_text(image, &text, strength, false)
+}
+
+/// 从图片中提取存储的 pHash（无 pHash 水印时返回 `None`）
+pub fn extract_phash(
+    extractor: &WatermarkExtractor,
+    image: &DynamicImage,
+) -> Result<Option<u64>, BlindMarkError> {
+    Ok(extractor
+        .try_extract_text(image)?
+        .and_then(|text| {
+            let hex = text.strip_prefix(PHASH_WATERMARK_PREFIX)?;
+            u64::from_str_radix(hex, 16).ok()
+        }))
+}
+
+/// 将图片与已知的原图哈希比较
+pub fn compare_phash(stored: u64, image: &DynamicImage) -> PhashVerification {
+    let current = compute_phash(image);
+    PhashVerification {
+        stored_hash: format!("{:016x}", stored),
+        current_hash: format!("{:016x}", current),
+        distance: (stored ^ current).count_ones(),
+        similarity: phash_similarity(stored, current),
+    }
+}
+
+/// 提取图片中存储的 pHash 并与当前图片比较
+///
+/// * `Ok(Some(_))` — 找到 pHash 水印，返回相似度
+/// * `Ok(None)` — 图片没有 pHash 水印（或水印已被破坏，无法读取）
+pub fn verify_phash(
+    extractor: &WatermarkExtractor,
+    image: &DynamicImage,
+) -> Result<Option<PhashVerification>, BlindMarkError> {
+    Ok(extract_phash(extractor, image)?.map(|stored| compare_phash(stored, image)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    fn create_test_image(width: u32, height: u32) -> DynamicImage {
+        // 不对称的低频图案，避免大量系数恰好落在中位数上
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| {
+            let (fx, fy) = (x as f64 / width as f64, y as f64 / height as f64);
+            let v = 128.0
+                + 60.0 * (5.0 * fx + 1.3).sin() * (3.0 * fy).cos()
+                + 40.0 * (7.0 * fx * fy + 2.0 * fy).sin();
+            let v = v.clamp(0.0, 255.0) as u8;
+            Rgb([v, v.saturating_add(20), v.saturating_sub(20)])
+        }))
+    }
+
+    #[test]
+    fn test_phash_roundtrip_high_similarity() {
+        let image = create_test_image(512, 512);
+        let watermarked = embed_phash(&WatermarkEmbedder::new(), &image, 0.5).unwrap();
+
+        let result = verify_phash(&WatermarkExtractor::new(), &watermarked).unwrap().expect("应能提取 pHash");
+        assert_eq!(result.stored_hash, format!("{:016x}", compute_phash(&image)));
+        assert!(result.similarity >= 0.9, "水印本身不应明显改变感知哈希，得 {}", result.similarity);
+    }
+
+    #[test]
+    fn test_phash_heavy_edit_low_similarity() {
+        let image = create_test_image(512, 512);
+        let watermarked = embed_phash(&WatermarkEmbedder::new(), &image, 0.5).unwrap();
+        let stored = extract_phash(&WatermarkExtractor::new(), &watermarked).unwrap().unwrap();
+
+        // 大幅编辑：左右翻转并反色
+        let mut edited = watermarked.fliph();
+        edited.invert();
+
+        let result = compare_phash(stored, &edited);
+        assert!(result.similarity < 0.6, "大幅编辑后相似度应明显下降，得 {}", result.similarity);
+    }
+
+    #[test]
+    fn test_verify_phash_without_watermark() {
+        let image = create_test_image(256, 256);
+        assert!(verify_phash(&WatermarkExtractor::new(), &image).unwrap().is_none());
+
+        // 普通文本水印不应被当作 pHash
+        let plain = WatermarkEmbedder::new().embed_raw_text(&image, "alice", 0.5, false).unwrap();
+        assert!(verify_phash(&WatermarkExtractor::new(), &plain).unwrap().is_none());
+    }
+}
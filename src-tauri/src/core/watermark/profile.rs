@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use crate::core::watermark::{
+    dct::{D1, D2},
+    dwt::Subband,
+};
+
+/// 图片盲水印的预设档位：把 QIM 步长、嵌入子带、冗余份数与通道选择打包为一个选项
+///
+/// 嵌入端用 `WatermarkEmbedder::with_profile`、提取端用 `WatermarkExtractor::with_profile`
+/// 设置相同档位；图片中不记录档位，按错误的档位提取会失败。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Profile {
+    /// 鲁棒性优先：QIM 步长加倍、LL 子带、3 份冗余
+    ///
+    /// 修改幅度最大，更能承受重新压缩、调色等处理；冗余需要至少 `544 × 3` 个块（原图约 324×324 以上）。
+    MaxRobustness,
+    /// 均衡（当前默认参数）：`[D1, D2]` 步长、LL 子带、不冗余
+    #[default]
+    Balanced,
+    /// 隐蔽性优先：小步长、HL 子带、仅嵌入方差最大的两个通道
+    ///
+    /// 修改集中在纹理丰富的通道与竖向细节上最不易察觉，但经不起模糊与有损压缩。
+    MaxImperceptibility,
+}
+
+impl Profile {
+    /// 主 / 次奇异值的 QIM 量化步长
+    pub fn qim_steps(self) -> [f64; 2] {
+        match self {
+            Profile::MaxRobustness => [D1 * 2.0, D2 * 2.0],
+            Profile::Balanced => [D1, D2],
+            Profile::MaxImperceptibility => [16.0, 8.0],
+        }
+    }
+
+    /// 嵌入子带
+    pub fn subband(self) -> Subband {
+        match self {
+            Profile::MaxImperceptibility => Subband::Hl,
+            Profile::MaxRobustness | Profile::Balanced => Subband::Ll,
+        }
+    }
+
+    /// 文本水印冗余份数（见 `WatermarkEmbedder::with_redundancy`）
+    pub fn redundancy(self) -> usize {
+        match self {
+            Profile::MaxRobustness => 3,
+            Profile::Balanced | Profile::MaxImperceptibility => 1,
+        }
+    }
+
+    /// 是否仅嵌入方差最大的两个通道（见 `WatermarkEmbedder::with_channel_selection`）
+    pub fn channel_selection(self) -> bool {
+        self == Profile::MaxImperceptibility
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageBuffer, Rgb};
+    use crate::core::watermark::{embedder::WatermarkEmbedder, extractor::WatermarkExtractor};
+
+    #[test]
+    fn test_each_profile_roundtrips_at_512() {
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_fn(512, 512, |x, y| {
+            Rgb([(x % 256) as u8, (y % 256) as u8, ((x * 3 + y * 5) % 256) as u8])
+        }));
+        for profile in [Profile::MaxRobustness, Profile::Balanced, Profile::MaxImperceptibility] {
+            let embedder = WatermarkEmbedder::new().with_profile(profile);
+            let marked = embedder.embed_raw_text(&image, "buyer-42", 0.5, false).unwrap();
+            let restored = WatermarkEmbedder::with_recipe(&embedder.recipe()).unwrap();
+            assert_eq!(restored.embed_raw_text(&image, "buyer-42", 0.5, false).unwrap().to_rgb8(), marked.to_rgb8());
+            let mut png = Vec::new();
+            marked.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+            let marked = image::load_from_memory(&png).unwrap();
+
+            let extractor = WatermarkExtractor::new().with_profile(profile);
+            assert_eq!(
+                extractor.try_extract_text(&marked).unwrap().as_deref(),
+                Some("buyer-42"),
+                "{:?} 档位应能完整还原文本",
+                profile
+            );
+        }
+        assert_eq!(Profile::default(), Profile::Balanced);
+
+        // 图片中不记录档位：HL 子带的水印无法按默认档位提取
+        let marked = WatermarkEmbedder::new()
+            .with_profile(Profile::MaxImperceptibility)
+            .embed_raw_text(&image, "buyer-42", 0.5, false)
+            .unwrap();
+        assert_eq!(WatermarkExtractor::new().try_extract_text(&marked).unwrap(), None);
+    }
+}
@@ -0,0 +1,151 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::models::BlindMarkError;
+use crate::core::watermark::{
+    dct::{ScanOrder, BLOCK_H, D1, D2, DEFAULT_PASSWORD},
+    dwt::Subband,
+};
+
+/// 嵌入所用的小波（1 级 Haar DWT）
+pub const RECIPE_WAVELET: &str = "haar";
+/// 图片盲水印的完整嵌入配置，用于日后复现水印或在团队间共享相同设置
+///
+/// 由 `WatermarkEmbedder::recipe` 导出，`WatermarkEmbedder::with_recipe` 还原；
+/// 以 JSON 保存 / 读取（见 `save` / `load`）。
+///
+/// 小波与分块大小当前固定，仅作记录：还原时与当前实现不一致的配方会被拒绝，
+/// 避免按错误的参数嵌入。其余字段对应 `WatermarkEmbedder` 的各 `with_*` 选项（子带与 QIM 步长见 `with_profile`）。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatermarkRecipe {
+    /// 小波类型（固定为 "haar"）
+    pub wavelet: String,
+    /// DWT 分解层数（固定为 1）
+    pub dwt_level: usize,
+    /// 嵌入子带（"LL" 或 "HL"）
+    pub subband: Subband,
+    /// 分块边长（固定为 4）
+    pub block_size: usize,
+    /// QIM 量化步长 `[d1, d2]`（默认 `[36, 20]`）
+    pub qim_steps: [f64; 2],
+    /// 命名空间：打乱种子（见 `dct::password_seed`）
+    pub password: u64,
+    /// 静默边框宽度（原图像素，见 `with_quiet_zone`）
+    pub quiet_zone: u32,
+    /// 文本水印仅嵌入方差最大的两个通道（见 `with_channel_selection`）
+    pub channel_selection: bool,
+    /// 块扫描顺序（见 `with_scan_order`）
+    pub scan_order: ScanOrder,
+    /// 文本水印冗余份数（见 `with_redundancy`）
+    pub redundancy: usize,
+    /// 选块间隔，1 为使用全部块（见 `with_block_fraction`）
+    pub block_stride: usize,
+    /// 启用链式水印（见 `with_chained_payload`）
+    pub chained: bool,
+    /// 降采样嵌入的长边上限（见 `with_downscale_embed`）
+    pub downscale_embed: Option<u32>,
+    /// MD5 盐值（见 `with_md5_salt`）
+    pub md5_salt: Option<String>,
+}
+
+impl Default for WatermarkRecipe {
+    fn default() -> Self {
+        Self {
+            wavelet: RECIPE_WAVELET.to_string(),
+            dwt_level: 1,
+            subband: Subband::Ll,
+            block_size: BLOCK_H,
+            qim_steps: [D1, D2],
+            password: DEFAULT_PASSWORD,
+            quiet_zone: 0,
+            channel_selection: false,
+            scan_order: ScanOrder::RowMajor,
+            redundancy: 1,
+            block_stride: 1,
+            chained: false,
+            downscale_embed: None,
+            md5_salt: None,
+        }
+    }
+}
+
+impl WatermarkRecipe {
+    /// 检查固定参数与当前实现一致、QIM 步长有效
+    pub fn validate(&self) -> Result<(), BlindMarkError> {
+        let fixed = Self::default();
+        let mismatch = |name: &str, found: String, expected: String| {
+            BlindMarkError::InvalidConfig(format!("不支持的配方参数 {}：{}（当前实现为 {}）", name, found, expected))
+        };
+        if !self.wavelet.eq_ignore_ascii_case(&fixed.wavelet) {
+            return Err(mismatch("wavelet", self.wavelet.clone(), fixed.wavelet));
+        }
+        if self.dwt_level != fixed.dwt_level {
+            return Err(mismatch("dwtLevel", self.dwt_level.to_string(), fixed.dwt_level.to_string()));
+        }
+        if self.block_size != fixed.block_size {
+            return Err(mismatch("blockSize", self.block_size.to_string(), fixed.block_size.to_string()));
+        }
+        if self.qim_steps.iter().any(|d| !d.is_finite() || *d <= 0.0) {
+            return Err(BlindMarkError::InvalidConfig(format!("QIM 步长必须为正数：{:?}", self.qim_steps)));
+        }
+        Ok(())
+    }
+
+    /// 以 JSON 格式保存到 `path`
+    pub fn save(&self, path: &Path) -> Result<(), BlindMarkError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| BlindMarkError::InvalidConfig(format!("配方序列化失败: {}", e)))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// 从 `path` 读取 JSON 配方，并检查固定参数
+    pub fn load(path: &Path) -> Result<Self, BlindMarkError> {
+        let content = std::fs::read_to_string(path)?;
+        let recipe: Self = serde_json::from_str(&content)
+            .map_err(|e| BlindMarkError::InvalidConfig(format!("配方解析失败: {}", e)))?;
+        recipe.validate()?;
+        Ok(recipe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageBuffer, Rgb};
+    use crate::core::watermark::embedder::WatermarkEmbedder;
+    use crate::core::watermark::dct::password_seed;
+
+    #[test]
+    fn test_recipe_json_roundtrip_reembeds_identically() {
+        let embedder = WatermarkEmbedder::with_password(password_seed("team"))
+            .with_quiet_zone(16)
+            .with_redundancy(2)
+            .with_scan_order(ScanOrder::Hilbert)
+            .with_md5_salt(Some("pepper"));
+        let recipe = embedder.recipe();
+        assert_eq!((recipe.quiet_zone, recipe.redundancy, recipe.scan_order), (16, 2, ScanOrder::Hilbert));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recipe.json");
+        recipe.save(&path).unwrap();
+        let loaded = WatermarkRecipe::load(&path).unwrap();
+        assert_eq!(loaded, recipe);
+
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_fn(512, 512, |x, y| {
+            Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+        }));
+        let restored = WatermarkEmbedder::with_recipe(&loaded).unwrap();
+        assert_eq!(restored.recipe(), recipe);
+        let original = embedder.embed_raw_text(&image, "alice", 0.5, false).unwrap();
+        let reproduced = restored.embed_raw_text(&image, "alice", 0.5, false).unwrap();
+        assert_eq!(original.to_rgb8(), reproduced.to_rgb8(), "same recipe must give identical pixels");
+        assert_eq!(
+            embedder.embed(&image, "alice", 0.5).unwrap().to_rgb8(),
+            restored.embed(&image, "alice", 0.5).unwrap().to_rgb8()
+        );
+
+        let unsupported = WatermarkRecipe { wavelet: "db4".to_string(), ..recipe };
+        assert!(WatermarkEmbedder::with_recipe(&unsupported).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+// ─── GF(2^8) 上的 Reed–Solomon 纠错码（系统码）──────────────────────────────────
+//
+// 本原多项式 0x11d，生成多项式根为 α^0 … α^(parity-1)。码字按高次项在前排列：
+// 前面是原始数据，末尾是 `parity` 个校验字节，最多纠正 `parity / 2` 个字节错误。
+// 码字总长不超过 255 字节（缩短码）。水印 payload 只有几十字节，不值得为此引入依赖。
+
+/// GF(2^8) 的本原多项式 x^8 + x^4 + x^3 + x^2 + 1
+const PRIMITIVE_POLY: u16 = 0x11d;
+/// 码字最大长度（字节）
+pub const MAX_CODEWORD_LEN: usize = 255;
+
+const fn build_tables() -> ([u8; 512], [u8; 256]) {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= PRIMITIVE_POLY;
+        }
+        i += 1;
+    }
+    // 指数表重复一遍，乘法时无需取模
+    while i < 512 {
+        exp[i] = exp[i - 255];
+        i += 1;
+    }
+    (exp, log)
+}
+
+const TABLES: ([u8; 512], [u8; 256]) = build_tables();
+static EXP: [u8; 512] = TABLES.0;
+static LOG: [u8; 256] = TABLES.1;
+
+fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+    }
+}
+
+/// 非零元素的乘法逆元
+fn inv(a: u8) -> u8 {
+    EXP[255 - LOG[a as usize] as usize]
+}
+
+/// α^n
+fn alpha_pow(n: usize) -> u8 {
+    EXP[n % 255]
+}
+
+/// 求多项式（高次项在前）在 `x` 处的值
+fn eval_high_first(poly: &[u8], x: u8) -> u8 {
+    poly.iter().fold(0, |y, &c| mul(y, x) ^ c)
+}
+
+/// 求多项式（低次项在前）在 `x` 处的值
+fn eval_low_first(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |y, &c| mul(y, x) ^ c)
+}
+
+/// 生成多项式 ∏ (x - α^i)，i = 0 … parity-1（高次项在前）
+fn generator(parity: usize) -> Vec<u8> {
+    let mut g = vec![1u8];
+    for i in 0..parity {
+        let root = alpha_pow(i);
+        let mut next = vec![0u8; g.len() + 1];
+        for (j, &c) in g.iter().enumerate() {
+            next[j] ^= c;
+            next[j + 1] ^= mul(c, root);
+        }
+        g = next;
+    }
+    g
+}
+
+/// 计算 `data` 的 `parity` 个校验字节，返回 `data` 后接校验字节的码字
+///
+/// 调用方保证码字总长不超过 `MAX_CODEWORD_LEN`。
+pub fn encode(data: &[u8], parity: usize) -> Vec<u8> {
+    debug_assert!(data.len() + parity <= MAX_CODEWORD_LEN);
+    let gen = generator(parity);
+    let mut remainder = data.to_vec();
+    remainder.resize(data.len() + parity, 0);
+    for i in 0..data.len() {
+        let coef = remainder[i];
+        if coef != 0 {
+            for (j, &g) in gen.iter().enumerate().skip(1) {
+                remainder[i + j] ^= mul(g, coef);
+            }
+        }
+    }
+    let mut codeword = data.to_vec();
+    codeword.extend_from_slice(&remainder[data.len()..]);
+    codeword
+}
+
+/// 原地纠正码字（末尾 `parity` 个校验字节）中的字节错误，返回纠正的字节数
+///
+/// 错误超过 `parity / 2` 个字节时返回 `None`（多数情况下能检测出来，但不保证）。
+pub fn decode(codeword: &mut [u8], parity: usize) -> Option<usize> {
+    let n = codeword.len();
+    if n > MAX_CODEWORD_LEN || parity >= n {
+        return None;
+    }
+    let syndromes: Vec<u8> = (0..parity).map(|j| eval_high_first(codeword, alpha_pow(j))).collect();
+    if syndromes.iter().all(|&s| s == 0) {
+        return Some(0);
+    }
+
+    // Berlekamp–Massey：求错误位置多项式 Λ(x)（低次项在前）
+    let mut lambda = vec![1u8];
+    let mut prev = vec![1u8];
+    let mut errors = 0usize;
+    let mut shift = 1usize;
+    let mut prev_discrepancy = 1u8;
+    for k in 0..parity {
+        let discrepancy = (1..=errors.min(k))
+            .filter(|&i| i < lambda.len())
+            .fold(syndromes[k], |d, i| d ^ mul(lambda[i], syndromes[k - i]));
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let scale = mul(discrepancy, inv(prev_discrepancy));
+        let mut next = lambda.clone();
+        next.resize(next.len().max(prev.len() + shift), 0);
+        for (i, &p) in prev.iter().enumerate() {
+            next[i + shift] ^= mul(scale, p);
+        }
+        if 2 * errors <= k {
+            prev = std::mem::replace(&mut lambda, next);
+            errors = k + 1 - errors;
+            prev_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            lambda = next;
+            shift += 1;
+        }
+    }
+    while lambda.len() > 1 && lambda.last() == Some(&0) {
+        lambda.pop();
+    }
+    if errors * 2 > parity || lambda.len() - 1 != errors {
+        return None;
+    }
+
+    // Chien 搜索：下标 k 对应 x^(n-1-k)，X = α^(n-1-k) 为错误位置时 Λ(X^-1) = 0
+    let positions: Vec<usize> = (0..n)
+        .filter(|&k| eval_low_first(&lambda, alpha_pow(255 - (n - 1 - k) % 255)) == 0)
+        .collect();
+    if positions.len() != errors {
+        return None;
+    }
+
+    // Forney：Ω(x) = S(x)Λ(x) mod x^parity，e = X · Ω(X^-1) / Λ'(X^-1)
+    let mut omega = vec![0u8; parity];
+    for (i, &s) in syndromes.iter().enumerate() {
+        for (j, &l) in lambda.iter().enumerate().take(parity - i) {
+            omega[i + j] ^= mul(s, l);
+        }
+    }
+    let derivative: Vec<u8> = lambda.iter().enumerate().skip(1).map(|(i, &l)| if i % 2 == 1 { l } else { 0 }).collect();
+    for &k in &positions {
+        let x = alpha_pow(n - 1 - k);
+        let x_inv = inv(x);
+        let denominator = eval_low_first(&derivative, x_inv);
+        if denominator == 0 {
+            return None;
+        }
+        codeword[k] ^= mul(x, mul(eval_low_first(&omega, x_inv), inv(denominator)));
+    }
+
+    (0..parity)
+        .all(|j| eval_high_first(codeword, alpha_pow(j)) == 0)
+        .then_some(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corrects_up_to_half_parity() {
+        let data: Vec<u8> = (0..52u8).map(|i| i.wrapping_mul(37)).collect();
+        let codeword = encode(&data, 16);
+        assert_eq!(codeword.len(), 68);
+        assert_eq!(&codeword[..52], &data[..]);
+
+        let mut clean = codeword.clone();
+        assert_eq!(decode(&mut clean, 16), Some(0));
+
+        // 8 个字节错误（含校验字节）可纠正
+        let mut corrupted = codeword.clone();
+        for (n, k) in [0usize, 7, 13, 30, 41, 51, 60, 67].iter().enumerate() {
+            corrupted[*k] ^= 0x5a ^ n as u8;
+        }
+        assert_eq!(decode(&mut corrupted, 16), Some(8));
+        assert_eq!(corrupted, codeword);
+
+        // 超出纠错能力（9 个字节错误）时检测为不可纠正
+        let mut hopeless = codeword.clone();
+        for k in 0..9 {
+            hopeless[k * 7] ^= 0xff;
+        }
+        assert_eq!(decode(&mut hopeless, 16), None);
+    }
+}
@@ -0,0 +1,350 @@
+use crate::models::BlindMarkError;
+use crate::core::watermark::json_marker::JsonWatermarker;
+
+/// UTF-8 BOM 字节序列（0xEF 0xBB 0xBF）
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// 水印属性所在的 XML 命名空间
+pub const SVG_WATERMARK_NAMESPACE: &str = "urn:blindmark:watermark";
+
+/// 水印属性名（前缀 `bm` 绑定到 `SVG_WATERMARK_NAMESPACE`）
+pub const SVG_WATERMARK_ATTRIBUTE: &str = "bm:watermark";
+
+/// 命名空间声明属性名
+const SVG_NAMESPACE_ATTRIBUTE: &str = "xmlns:bm";
+
+/// SVG 水印注入器
+///
+/// 在根 `<svg>` 元素上添加命名空间属性 `bm:watermark="<编码值>"`，
+/// 编码方式与 JSON 水印一致（md5 / plaintext / aes / hmac，见 `JsonWatermarker::encode_watermark`）。
+/// 只改写根元素的起始标签，XML 声明、注释、DOCTYPE 及文档其余部分逐字节保留。
+pub struct SvgWatermarker;
+
+/// 根元素起始标签在文档中的位置
+struct RootTag {
+    /// 元素名结束位置（`<svg` 之后）
+    name_end: usize,
+    /// 起始标签结束位置（`>` 或 `/>` 所在位置）
+    tag_end: usize,
+}
+
+/// 起始标签中的一个属性：`(属性名, 含前导空白的整个属性范围, 值范围)`
+type Attribute<'a> = (&'a str, std::ops::Range<usize>, std::ops::Range<usize>);
+
+impl SvgWatermarker {
+    /// 向 SVG 内容中注入水印（已有水印时替换）
+    ///
+    /// # 参数
+    /// * `content`        - 原始 SVG 文本
+    /// * `watermark_text` - 要嵌入的明文
+    /// * `mode`           - 编码模式（"md5" / "plaintext" / "aes" / "hmac"）
+    /// * `aes_key`        - AES 模式下的用户密钥
+    pub fn embed(
+        content: &str,
+        watermark_text: &str,
+        mode: &str,
+        aes_key: Option<&str>,
+    ) -> Result<String, BlindMarkError> {
+        let root = find_root_tag(content)?;
+        let encoded = JsonWatermarker::encode_watermark(watermark_text, mode, aes_key)?;
+        let attributes = parse_attributes(content, root.name_end, root.tag_end)?;
+
+        let has_namespace = attributes.iter().any(|(name, _, _)| *name == SVG_NAMESPACE_ATTRIBUTE);
+        let mut inserted = String::new();
+        if !has_namespace {
+            inserted.push_str(&format!(" {}=\"{}\"", SVG_NAMESPACE_ATTRIBUTE, SVG_WATERMARK_NAMESPACE));
+        }
+        inserted.push_str(&format!(" {}=\"{}\"", SVG_WATERMARK_ATTRIBUTE, escape_attribute(&encoded)));
+
+        // 新属性紧跟元素名插入，旧的水印属性整体删除
+        let mut out = String::with_capacity(content.len() + inserted.len());
+        out.push_str(&content[..root.name_end]);
+        out.push_str(&inserted);
+        let mut pos = root.name_end;
+        for (name, full, _) in &attributes {
+            if *name == SVG_WATERMARK_ATTRIBUTE {
+                out.push_str(&content[pos..full.start]);
+                pos = full.end;
+            }
+        }
+        out.push_str(&content[pos..]);
+        Ok(out)
+    }
+
+    /// 从 SVG 内容中提取水印的存储值（未解码，可交给 `JsonWatermarker::decode_watermark`）
+    pub fn extract(content: &str) -> Result<String, BlindMarkError> {
+        let root = find_root_tag(content)?;
+        parse_attributes(content, root.name_end, root.tag_end)?
+            .into_iter()
+            .find(|(name, _, _)| *name == SVG_WATERMARK_ATTRIBUTE)
+            .map(|(_, _, value)| unescape_attribute(&content[value]))
+            .ok_or_else(|| BlindMarkError::ExtractionFailed("未在 SVG 中找到水印属性".to_string()))
+    }
+
+    /// 检查 SVG 内容是否已包含水印属性
+    pub fn has_watermark(content: &str) -> bool {
+        Self::extract(content).is_ok()
+    }
+
+    /// 嵌入水印到 SVG 字节
+    ///
+    /// 仅支持 UTF-8（可带 BOM）；输入带 BOM 时输出同样带 BOM，其余字节不变。
+    pub fn embed_bytes(
+        bytes: &[u8],
+        watermark_text: &str,
+        mode: &str,
+        aes_key: Option<&str>,
+    ) -> Result<Vec<u8>, BlindMarkError> {
+        let (bom, content) = split_utf8(bytes)?;
+        let result = Self::embed(content, watermark_text, mode, aes_key)?;
+        let mut out = Vec::with_capacity(bom.len() + result.len());
+        out.extend_from_slice(bom);
+        out.extend_from_slice(result.as_bytes());
+        Ok(out)
+    }
+
+    /// 从 SVG 字节中提取水印存储值
+    pub fn extract_bytes(bytes: &[u8]) -> Result<String, BlindMarkError> {
+        let (_, content) = split_utf8(bytes)?;
+        Self::extract(content)
+    }
+
+    /// 扫描 SVG 内容中的水印并解码
+    ///
+    /// 返回 `Some((显示值, 模式名称, 是否已成功解密))`，无水印时返回 `None`，
+    /// 解码规则同 `JsonWatermarker::decode_watermark`。
+    pub fn scan_watermark_value(content: &str, aes_key: Option<&str>) -> Option<(String, String, bool)> {
+        let raw = Self::extract(content).ok()?;
+        Some(JsonWatermarker::decode_watermark(&raw, aes_key))
+    }
+}
+
+// ─── 私有工具函数 ──────────────────────────────────────────────────────────────
+
+/// 拆分 UTF-8 BOM 与正文；非 UTF-8 内容返回错误
+///
+/// 不回退 GBK：XML 声明中的 encoding 与重新编码后的字节会不一致。
+fn split_utf8(bytes: &[u8]) -> Result<(&[u8], &str), BlindMarkError> {
+    let (bom, body) = match bytes.strip_prefix(UTF8_BOM) {
+        Some(body) => (UTF8_BOM, body),
+        None => (&bytes[..0], bytes),
+    };
+    let content = std::str::from_utf8(body).map_err(|_| {
+        BlindMarkError::ImageProcessing("SVG 文件须为 UTF-8 编码".to_string())
+    })?;
+    Ok((bom, content))
+}
+
+/// 定位根元素的起始标签，跳过 XML 声明、处理指令、注释与 DOCTYPE
+fn find_root_tag(content: &str) -> Result<RootTag, BlindMarkError> {
+    let not_svg = || BlindMarkError::ImageProcessing("未找到 SVG 根元素".to_string());
+    let mut pos = 0;
+    loop {
+        let start = pos + content[pos..].find('<').ok_or_else(not_svg)?;
+        let rest = &content[start..];
+        if rest.starts_with("<?") {
+            pos = start + rest.find("?>").ok_or_else(not_svg)? + 2;
+        } else if rest.starts_with("<!--") {
+            pos = start + rest.find("-->").ok_or_else(not_svg)? + 3;
+        } else if rest.starts_with("<!") {
+            // DOCTYPE 可能带 [...] 内部子集，方括号外的第一个 `>` 才是结尾
+            let mut depth = 0usize;
+            let end = rest
+                .char_indices()
+                .find(|&(_, c)| match c {
+                    '[' => { depth += 1; false }
+                    ']' => { depth = depth.saturating_sub(1); false }
+                    '>' => depth == 0,
+                    _ => false,
+                })
+                .map(|(i, _)| i)
+                .ok_or_else(not_svg)?;
+            pos = start + end + 1;
+        } else {
+            let name_len = rest[1..]
+                .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .ok_or_else(not_svg)?;
+            let name = &rest[1..1 + name_len];
+            // 允许带前缀的根元素，如 `<svg:svg>`
+            if name.rsplit(':').next() != Some("svg") {
+                return Err(not_svg());
+            }
+            let name_end = start + 1 + name_len;
+            let tag_end = find_tag_end(content, name_end).ok_or_else(not_svg)?;
+            return Ok(RootTag { name_end, tag_end });
+        }
+    }
+}
+
+/// 从 `from` 开始查找起始标签的结束位置（引号内的 `>` 不算）
+fn find_tag_end(content: &str, from: usize) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (i, c) in content[from..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => {
+                let end = from + i;
+                return Some(if content[..end].ends_with('/') { end - 1 } else { end });
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 解析 `content[from..to]` 中的属性列表
+fn parse_attributes(content: &str, from: usize, to: usize) -> Result<Vec<Attribute<'_>>, BlindMarkError> {
+    let malformed = || BlindMarkError::ImageProcessing("SVG 根元素属性格式错误".to_string());
+    let bytes = content.as_bytes();
+    let mut attributes = Vec::new();
+    let mut pos = from;
+    loop {
+        let full_start = pos;
+        while pos < to && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos >= to {
+            return Ok(attributes);
+        }
+        let name_start = pos;
+        while pos < to && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'=' {
+            pos += 1;
+        }
+        let name = &content[name_start..pos];
+        while pos < to && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos >= to || bytes[pos] != b'=' {
+            return Err(malformed());
+        }
+        pos += 1;
+        while pos < to && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let quote = *bytes.get(pos).filter(|q| **q == b'"' || **q == b'\'').ok_or_else(malformed)?;
+        let value_start = pos + 1;
+        let value_end = value_start + content[value_start..to].find(quote as char).ok_or_else(malformed)?;
+        pos = value_end + 1;
+        attributes.push((name, full_start..pos, value_start..value_end));
+    }
+}
+
+/// 转义属性值中的 XML 特殊字符
+fn escape_attribute(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 还原属性值中的预定义实体与数字字符引用
+fn unescape_attribute(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let after = &rest[amp + 1..];
+        let decoded = after.find(';').and_then(|semi| {
+            let entity = &after[..semi];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                    .and_then(|n| n.ok())
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, semi))
+        });
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &after[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_SVG: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <!-- icon <svg> -->\n\
+        <!DOCTYPE svg PUBLIC \"-//W3C//DTD SVG 1.1//EN\" \"http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd\" [\n  <!ENTITY ns \"x\">\n]>\n\
+        <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"16\" height=\"16\" data-note='a > b'>\n\
+          <rect x=\"1\" y=\"1\" width=\"14\" height=\"14\" fill=\"#f00\"/>\n\
+        </svg>\n";
+
+    #[test]
+    fn test_svg_roundtrip_preserves_document() {
+        let marked = SvgWatermarker::embed(SAMPLE_SVG, "alice <&> \"bob\"", "plaintext", None).unwrap();
+        assert!(marked.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!-- icon <svg> -->"));
+        assert!(marked.contains("xmlns:bm=\"urn:blindmark:watermark\""));
+        assert_eq!(SvgWatermarker::extract(&marked).unwrap(), "txt:alice <&> \"bob\"");
+
+        // 去掉注入的属性后与原文完全一致
+        let injected = format!(
+            " xmlns:bm=\"{}\" bm:watermark=\"txt:alice &lt;&amp;&gt; &quot;bob&quot;\"",
+            SVG_WATERMARK_NAMESPACE
+        );
+        assert_eq!(marked.replacen(&injected, "", 1), SAMPLE_SVG);
+
+        // 再次嵌入替换旧水印，不重复声明命名空间
+        let remarked = SvgWatermarker::embed(&marked, "carol", "plaintext", None).unwrap();
+        assert_eq!(remarked.matches("bm:watermark=").count(), 1);
+        assert_eq!(remarked.matches("xmlns:bm=").count(), 1);
+        assert_eq!(
+            SvgWatermarker::scan_watermark_value(&remarked, None),
+            Some(("carol".to_string(), "plaintext".to_string(), true))
+        );
+    }
+
+    #[test]
+    fn test_svg_modes_and_bytes() {
+        let bytes = [UTF8_BOM, b"<svg viewBox=\"0 0 1 1\"/>".as_slice()].concat();
+
+        let marked = SvgWatermarker::embed_bytes(&bytes, "alice", "aes", Some("secret")).unwrap();
+        assert!(marked.starts_with(UTF8_BOM), "BOM 应保留");
+        assert!(marked.ends_with(b"/>"), "自闭合根元素应保持自闭合");
+        let content = std::str::from_utf8(&marked[UTF8_BOM.len()..]).unwrap();
+        assert_eq!(
+            SvgWatermarker::scan_watermark_value(content, Some("secret")),
+            Some(("alice".to_string(), "aes".to_string(), true))
+        );
+
+        let md5 = SvgWatermarker::embed_bytes(b"<svg/>", "alice", "md5", None).unwrap();
+        let raw = SvgWatermarker::extract_bytes(&md5).unwrap();
+        assert_eq!(raw.len(), 32);
+        assert_eq!(String::from_utf8(md5).unwrap(), format!("<svg xmlns:bm=\"{}\" bm:watermark=\"{}\"/>", SVG_WATERMARK_NAMESPACE, raw));
+    }
+
+    #[test]
+    fn test_svg_rejects_non_svg() {
+        assert!(SvgWatermarker::embed("<html></html>", "a", "plaintext", None).is_err());
+        assert!(SvgWatermarker::embed("plain text", "a", "plaintext", None).is_err());
+        assert!(!SvgWatermarker::has_watermark("<svg width=\"1\"></svg>"));
+        assert!(SvgWatermarker::embed_bytes(b"<svg>\xff</svg>", "a", "plaintext", None).is_err());
+    }
+}
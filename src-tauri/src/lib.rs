@@ -1,19 +1,26 @@
 // Module declarations
-mod models;
-mod core;
+// core / models / utils 不依赖 Tauri，关闭默认的 `tauri` feature 后可作为纯库使用
+pub mod models;
+pub mod core;
+#[cfg(feature = "tauri")]
 mod commands;
-mod utils;
+pub mod utils;
 
-use commands::watermark::{embed_watermark_single, extract_watermark, get_image_dimensions, get_cpu_count};
+#[cfg(feature = "tauri")]
+use commands::watermark::{embed_watermark_single, embed_preview_diff, extract_watermark, extract_watermark_from_rgba, verify_image_watermark, resolve_watermark, render_watermark_bits, diagnose_extraction, stress_test_watermark, get_image_dimensions, min_dimensions_for_text, get_cpu_count};
+#[cfg(feature = "tauri")]
 use commands::excel::read_excel_watermarks;
-use commands::archive::{process_archive, extract_json_watermark_from_archive, scan_watermarks_in_archive, list_images_in_archive, scan_image_watermarks_in_archive, scan_all_watermarks_in_archive};
+#[cfg(feature = "tauri")]
+use commands::archive::{process_archive, get_task_progress, process_archives_batch, process_directory, extract_json_watermark_from_archive, scan_watermarks_in_archive, list_images_in_archive, scan_image_watermarks_in_archive, scan_all_watermarks_in_archive, scan_multiple_archives, summarize_archive_watermarks, list_encrypted_watermarks, detect_duplicate_image_watermarks, detect_archive_type, validate_var_package, read_file_from_archive, batch_verify, verify_archive_consistency, classify_json_watermarks, remove_watermarks_from_archive};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+#[cfg(feature = "tauri")]
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+#[cfg(feature = "tauri")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -29,16 +36,38 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             embed_watermark_single,
+            embed_preview_diff,
             extract_watermark,
+            extract_watermark_from_rgba,
+            verify_image_watermark,
+            resolve_watermark,
+            render_watermark_bits,
+            diagnose_extraction,
+            stress_test_watermark,
             get_image_dimensions,
+            min_dimensions_for_text,
             get_cpu_count,
             read_excel_watermarks,
             process_archive,
+            get_task_progress,
+            process_archives_batch,
+            process_directory,
             extract_json_watermark_from_archive,
             scan_watermarks_in_archive,
+            classify_json_watermarks,
             list_images_in_archive,
             scan_image_watermarks_in_archive,
             scan_all_watermarks_in_archive,
+            scan_multiple_archives,
+            summarize_archive_watermarks,
+            list_encrypted_watermarks,
+            detect_duplicate_image_watermarks,
+            detect_archive_type,
+            validate_var_package,
+            read_file_from_archive,
+            batch_verify,
+            verify_archive_consistency,
+            remove_watermarks_from_archive,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
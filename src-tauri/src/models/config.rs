@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use crate::models::BlindMarkError;
 
 /// Watermark configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +22,55 @@ impl WatermarkConfig {
             watermark_key: None,
         }
     }
+
+    /// Check the config together with the JSON encoding options before any work starts
+    ///
+    /// `mode` is the JSON watermark mode ("md5" | "plaintext" | "aes" | "hmac"); `aes_key` is
+    /// required (non-blank) in AES and HMAC modes, where it is the encryption / signing key.
+    pub fn validate(&self, mode: &str, aes_key: Option<&str>) -> Result<(), BlindMarkError> {
+        if !(0.1..=1.0).contains(&self.strength) {
+            return Err(BlindMarkError::InvalidConfig(format!(
+                "水印强度须在 0.1 - 1.0 之间，当前为 {}", self.strength
+            )));
+        }
+        match &self.watermark_source {
+            WatermarkSource::SingleText { content } if content.trim().is_empty() => {
+                return Err(BlindMarkError::InvalidConfig("水印文本不能为空".to_string()));
+            }
+            WatermarkSource::ExcelFile { path, .. } if path.trim().is_empty() => {
+                return Err(BlindMarkError::InvalidConfig("未指定 Excel 文件".to_string()));
+            }
+            WatermarkSource::JsonFile { path } if path.trim().is_empty() => {
+                return Err(BlindMarkError::InvalidConfig("未指定 JSON 水印列表文件".to_string()));
+            }
+            _ => {}
+        }
+        match mode {
+            "md5" | "plaintext" => {}
+            "aes" => {
+                if aes_key.is_none_or(|k| k.trim().is_empty()) {
+                    return Err(BlindMarkError::InvalidConfig("AES 模式需要提供密钥".to_string()));
+                }
+            }
+            "hmac" => {
+                if aes_key.is_none_or(|k| k.trim().is_empty()) {
+                    return Err(BlindMarkError::InvalidConfig("HMAC 模式需要提供密钥".to_string()));
+                }
+            }
+            other => {
+                return Err(BlindMarkError::InvalidConfig(format!("未知的水印模式: {}", other)));
+            }
+        }
+        // 空白字段名表示使用默认值；自定义字段名不允许包含控制字符
+        if let Some(key) = &self.watermark_key {
+            if key.chars().any(char::is_control) {
+                return Err(BlindMarkError::InvalidConfig(format!(
+                    "水印字段名包含非法字符: {:?}", key
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Source of watermark data
@@ -30,7 +80,78 @@ pub enum WatermarkSource {
     /// Single text watermark for all images
     SingleText { content: String },
     /// Excel file with one watermark per row (sequential mapping)
-    ExcelFile { path: String },
+    ///
+    /// Row 0 is a header unless `hasHeader` is `false` (absent means `true`).
+    ExcelFile {
+        path: String,
+        #[serde(default, rename = "hasHeader")]
+        has_header: Option<bool>,
+    },
+    /// JSON file holding an array of strings or `{text, folder}` objects (one watermark per element)
+    JsonFile { path: String },
+}
+
+/// How to assign watermarks when an Excel list is shorter than the image list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortfallPolicy {
+    /// Remaining images reuse the last watermark (legacy behavior)
+    #[default]
+    RepeatLast,
+    /// Fail before processing, reporting both counts
+    Error,
+    /// Wrap around to the first watermark again
+    Cycle,
+}
+
+impl ShortfallPolicy {
+    /// Index of the watermark to use for image `index`, given `count` watermarks (> 0)
+    ///
+    /// For `Error`, callers reject the shortfall up front, so it maps like `RepeatLast`.
+    pub fn watermark_index(self, index: usize, count: usize) -> usize {
+        match self {
+            ShortfallPolicy::Cycle => index % count,
+            ShortfallPolicy::RepeatLast | ShortfallPolicy::Error => index.min(count - 1),
+        }
+    }
+}
+
+/// How the text watermark mode is chosen per file type
+///
+/// Images always carry the raw text blind watermark; the policy decides the
+/// encoding ("md5" | "plaintext" | "aes") of the JSON / SVG / config file watermarks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModePolicy {
+    /// Every file type uses the selected watermark mode (legacy behavior)
+    #[default]
+    Uniform,
+    /// JSON-like files (json / vaj / vmi / vam / vap) get AES for stealth; SVG and
+    /// config files (toml / ini), where reversibility isn't needed, get MD5
+    ContentAware,
+}
+
+impl ModePolicy {
+    /// Mode for files of `file_type` (e.g. "json", "svg"), given the user-selected `uniform_mode`
+    pub fn mode_for<'a>(self, file_type: &str, uniform_mode: &'a str) -> &'a str {
+        match self {
+            ModePolicy::Uniform => uniform_mode,
+            ModePolicy::ContentAware => match file_type {
+                "json" | "vaj" | "vmi" | "vam" | "vap" => "aes",
+                _ => "md5",
+            },
+        }
+    }
+
+    /// Check that the modes this policy picks can be used (AES needs a non-blank key)
+    pub fn validate(self, aes_key: Option<&str>) -> Result<(), BlindMarkError> {
+        if self == ModePolicy::ContentAware && aes_key.is_none_or(|k| k.trim().is_empty()) {
+            return Err(BlindMarkError::InvalidConfig(
+                "按文件类型选择模式时 JSON 使用 AES，需要提供密钥".to_string()
+            ));
+        }
+        Ok(())
+    }
 }
 
 /// Watermark data after encoding
@@ -50,3 +171,62 @@ impl WatermarkData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_policy_by_file_type() {
+        assert_eq!(ModePolicy::default().mode_for("json", "plaintext"), "plaintext");
+        assert_eq!(ModePolicy::Uniform.mode_for("svg", "md5"), "md5");
+        for json_like in ["json", "vaj", "vmi", "vam", "vap"] {
+            assert_eq!(ModePolicy::ContentAware.mode_for(json_like, "plaintext"), "aes");
+        }
+        for other in ["svg", "toml", "ini"] {
+            assert_eq!(ModePolicy::ContentAware.mode_for(other, "plaintext"), "md5");
+        }
+        assert!(ModePolicy::ContentAware.validate(None).is_err());
+        assert!(ModePolicy::ContentAware.validate(Some(" ")).is_err());
+        assert!(ModePolicy::ContentAware.validate(Some("key")).is_ok());
+        assert!(ModePolicy::Uniform.validate(None).is_ok());
+        assert_eq!(serde_json::from_str::<ModePolicy>(r#""contentAware""#).unwrap(), ModePolicy::ContentAware);
+    }
+
+    fn single(content: &str) -> WatermarkConfig {
+        WatermarkConfig::new(0.5, WatermarkSource::SingleText { content: content.to_string() })
+    }
+
+    #[test]
+    fn test_validate_accepts_valid_config() {
+        assert!(single("alice").validate("md5", None).is_ok());
+        assert!(single("alice").validate("aes", Some("secret")).is_ok());
+    }
+
+    #[test]
+    fn test_validate_requires_aes_key() {
+        let config = single("alice");
+        assert!(matches!(config.validate("aes", None), Err(BlindMarkError::InvalidConfig(_))));
+        assert!(matches!(config.validate("aes", Some("  ")), Err(BlindMarkError::InvalidConfig(_))));
+        assert!(matches!(config.validate("hmac", None), Err(BlindMarkError::InvalidConfig(_))));
+        assert!(config.validate("hmac", Some("secret")).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_text() {
+        assert!(matches!(single("").validate("md5", None), Err(BlindMarkError::InvalidConfig(_))));
+        assert!(matches!(single(" \t").validate("plaintext", None), Err(BlindMarkError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_mode_key_and_strength() {
+        assert!(single("alice").validate("rot13", None).is_err());
+
+        let mut config = single("alice");
+        config.watermark_key = Some("bad\nkey".to_string());
+        assert!(config.validate("md5", None).is_err());
+
+        let config = WatermarkConfig { strength: 2.0, ..single("alice") };
+        assert!(config.validate("md5", None).is_err());
+    }
+}
@@ -21,6 +21,10 @@ pub enum BlindMarkError {
     #[error("Watermark extraction failed: {0}")]
     ExtractionFailed(String),
 
+    /// 图片可正常读取，但其中没有（可信的）水印；区别于提取过程本身出错
+    #[error("No watermark found: {0}")]
+    WatermarkNotFound(String),
+
     #[error("Excel reading error: {0}")]
     ExcelError(String),
 
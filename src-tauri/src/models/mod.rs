@@ -5,4 +5,4 @@ pub mod config;
 // Re-export commonly used types
 pub use error::BlindMarkError;
 pub use task::ImageFile;
-pub use config::{WatermarkConfig, WatermarkSource, WatermarkData};
+pub use config::{WatermarkConfig, WatermarkSource, WatermarkData, ShortfallPolicy, ModePolicy};
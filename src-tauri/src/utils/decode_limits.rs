@@ -0,0 +1,143 @@
+use std::io::{BufRead, Seek};
+use std::path::Path;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageReader, Limits};
+use crate::models::BlindMarkError;
+
+/// Default maximum width and height (pixels) an image header may claim
+pub const DEFAULT_MAX_IMAGE_DIMENSION: u32 = 16384;
+
+/// Default maximum pixel count (width × height), e.g. 16384 × 8192
+pub const DEFAULT_MAX_IMAGE_PIXELS: u64 = 1 << 27;
+
+/// Upper bounds checked against an image's header before it is decoded
+/// (decompression-bomb protection)
+///
+/// A crafted header can claim enormous dimensions in a tiny file; checking them
+/// up front rejects such images before the pixel buffer is allocated. Decoding
+/// also keeps `image`'s default allocation limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum width in pixels
+    pub max_width: u32,
+    /// Maximum height in pixels
+    pub max_height: u32,
+    /// Maximum width × height
+    pub max_pixels: u64,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_width: DEFAULT_MAX_IMAGE_DIMENSION,
+            max_height: DEFAULT_MAX_IMAGE_DIMENSION,
+            max_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// The same bounds as `image` reader limits (width / height only)
+    fn image_limits(&self) -> Limits {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(self.max_width);
+        limits.max_image_height = Some(self.max_height);
+        limits
+    }
+
+    fn exceeded(&self, name: &str, claimed: Option<(u32, u32)>) -> BlindMarkError {
+        let claimed = claimed.map(|(w, h)| format!(" (header claims {}×{})", w, h)).unwrap_or_default();
+        BlindMarkError::ImageProcessing(format!(
+            "Image {} exceeds the decode limit of {}×{} pixels, {} pixels in total{}",
+            name, self.max_width, self.max_height, self.max_pixels, claimed
+        ))
+    }
+
+    /// Fail if header dimensions `width × height` exceed these limits
+    pub fn check(&self, width: u32, height: u32, name: &str) -> Result<(), BlindMarkError> {
+        if width > self.max_width || height > self.max_height || width as u64 * height as u64 > self.max_pixels {
+            return Err(self.exceeded(name, Some((width, height))));
+        }
+        Ok(())
+    }
+
+    /// Turn a reader (format already guessed) into a decoder whose header passed `check`
+    pub fn decoder<'a, R: BufRead + Seek + 'a>(
+        &self,
+        mut reader: ImageReader<R>,
+        name: &str,
+    ) -> Result<impl ImageDecoder + 'a, BlindMarkError> {
+        reader.limits(self.image_limits());
+        let decoder = reader.into_decoder().map_err(|e| self.decode_error(name, e))?;
+        let (width, height) = decoder.dimensions();
+        self.check(width, height, name)?;
+        Ok(decoder)
+    }
+
+    /// Map a decode error, reporting limit violations with the configured bounds
+    pub fn decode_error(&self, name: &str, e: ImageError) -> BlindMarkError {
+        match e {
+            ImageError::Limits(_) => self.exceeded(name, None),
+            e => BlindMarkError::ImageProcessing(format!("Failed to decode image {}: {}", name, e)),
+        }
+    }
+}
+
+/// Load an image file, rejecting it before decoding if its header exceeds `limits`
+pub fn open_limited(path: &Path, limits: &DecodeLimits) -> Result<DynamicImage, BlindMarkError> {
+    open_named(path, &path.display().to_string(), limits)
+}
+
+/// Same as `open_limited`, naming the image `name` in errors (e.g. its path inside an archive)
+pub fn open_named(path: &Path, name: &str, limits: &DecodeLimits) -> Result<DynamicImage, BlindMarkError> {
+    let reader = ImageReader::open(path)
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| BlindMarkError::ImageProcessing(format!("Failed to read {}: {}", name, e)))?;
+    let decoder = limits.decoder(reader, name)?;
+    DynamicImage::from_decoder(decoder).map_err(|e| limits.decode_error(name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PNG whose IHDR claims `width × height` but whose IDAT is empty (no pixel data)
+    fn png_header_only(width: u32, height: u32) -> Vec<u8> {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend(height.to_be_bytes());
+        ihdr.extend([8, 2, 0, 0, 0]); // 8-bit RGB, no interlace
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, data) in [(b"IHDR", &ihdr[..]), (b"IDAT", &[]), (b"IEND", &[])] {
+            png.extend((data.len() as u32).to_be_bytes());
+            let chunk = [&kind[..], data].concat();
+            png.extend(&chunk);
+            png.extend(crc32fast::hash(&chunk).to_be_bytes());
+        }
+        png
+    }
+
+    #[test]
+    fn test_rejects_image_exceeding_pixel_limit() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // A 100 000 × 100 000 header in a 57-byte file is rejected before allocation
+        let bomb = dir.path().join("bomb.png");
+        std::fs::write(&bomb, png_header_only(100_000, 100_000)).unwrap();
+        let err = open_limited(&bomb, &DecodeLimits::default()).unwrap_err().to_string();
+        assert!(err.contains("exceeds the decode limit"), "{}", err);
+
+        // Within the per-side bounds but over the pixel count
+        let wide = dir.path().join("wide.png");
+        std::fs::write(&wide, png_header_only(12000, 12000)).unwrap();
+        let err = open_limited(&wide, &DecodeLimits::default()).unwrap_err().to_string();
+        assert!(err.contains("header claims 12000×12000"), "{}", err);
+
+        // Configurable: a real 64×48 image passes the default limits but not tighter ones
+        let real = dir.path().join("real.png");
+        image::RgbImage::new(64, 48).save(&real).unwrap();
+        assert_eq!(open_limited(&real, &DecodeLimits::default()).unwrap().width(), 64);
+        let tight = DecodeLimits { max_width: 32, ..DecodeLimits::default() };
+        assert!(open_limited(&real, &tight).unwrap_err().to_string().contains("exceeds the decode limit"));
+        let few = DecodeLimits { max_pixels: 64 * 48 - 1, ..DecodeLimits::default() };
+        assert!(open_limited(&real, &few).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, GenericImageView, ImageBuffer, Rgb};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::Serialize;
+use crate::core::watermark::{embedder::WatermarkEmbedder, extractor::WatermarkExtractor};
+use crate::models::BlindMarkError;
+
+/// Fixed seed so noise attacks are reproducible between runs
+const NOISE_SEED: u64 = 0x5EED;
+
+/// A simulated real-world degradation applied to a watermarked image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Degradation {
+    /// No change (baseline: the watermark must always survive this)
+    Identity,
+    /// Re-encode as JPEG with the given quality (1-100)
+    Jpeg(u8),
+    /// Scale by the given factor, then back to the original size
+    ///
+    /// Extraction needs the original block grid, so this models a resize that
+    /// the user has undone before checking the watermark.
+    Resize(f32),
+    /// Add per-channel gaussian noise with the given standard deviation
+    GaussianNoise(f64),
+}
+
+impl Degradation {
+    /// Default robustness matrix: JPEG q50/q75/q90, resize 0.8/1.2, gaussian noise
+    pub fn standard_matrix() -> Vec<Degradation> {
+        vec![
+            Degradation::Identity,
+            Degradation::Jpeg(50),
+            Degradation::Jpeg(75),
+            Degradation::Jpeg(90),
+            Degradation::Resize(0.8),
+            Degradation::Resize(1.2),
+            Degradation::GaussianNoise(5.0),
+        ]
+    }
+
+    /// Short label used in reports, e.g. `jpeg_q75`, `resize_0.8`, `noise_5`
+    pub fn name(&self) -> String {
+        match self {
+            Degradation::Identity => "identity".to_string(),
+            Degradation::Jpeg(q) => format!("jpeg_q{}", q),
+            Degradation::Resize(f) => format!("resize_{}", f),
+            Degradation::GaussianNoise(sigma) => format!("noise_{}", sigma),
+        }
+    }
+
+    /// Apply the degradation, returning an RGB image of the original size
+    pub fn apply(&self, image: &DynamicImage) -> Result<DynamicImage, BlindMarkError> {
+        let (width, height) = image.dimensions();
+        match *self {
+            Degradation::Identity => Ok(image.clone()),
+            Degradation::Jpeg(quality) => {
+                let mut buffer = Vec::new();
+                JpegEncoder::new_with_quality(&mut buffer, quality)
+                    .encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))
+                    .map_err(|e| BlindMarkError::ImageProcessing(
+                        format!("Failed to encode JPEG: {}", e)
+                    ))?;
+                image::load_from_memory(&buffer).map_err(|e| BlindMarkError::ImageProcessing(
+                    format!("Failed to decode JPEG: {}", e)
+                ))
+            }
+            Degradation::Resize(factor) => {
+                let scaled_w = ((width as f32 * factor).round() as u32).max(1);
+                let scaled_h = ((height as f32 * factor).round() as u32).max(1);
+                let scaled = image.resize_exact(scaled_w, scaled_h, FilterType::Triangle);
+                Ok(scaled.resize_exact(width, height, FilterType::Triangle))
+            }
+            Degradation::GaussianNoise(sigma) => {
+                let mut rng = SmallRng::seed_from_u64(NOISE_SEED);
+                let rgb = image.to_rgb8();
+                let noisy = ImageBuffer::from_fn(width, height, |x, y| {
+                    let p = rgb.get_pixel(x, y);
+                    Rgb(std::array::from_fn(|c| {
+                        // Box-Muller transform
+                        let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+                        let u2: f64 = rng.gen();
+                        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                        (p[c] as f64 + z * sigma).round().clamp(0.0, 255.0) as u8
+                    }))
+                });
+                Ok(DynamicImage::ImageRgb8(noisy))
+            }
+        }
+    }
+}
+
+/// Outcome of one attack in a stress test
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttackOutcome {
+    /// Attack label (see `Degradation::name`)
+    pub attack: String,
+    /// Whether the exact watermark text was still extracted
+    pub passed: bool,
+    /// What was extracted, if anything
+    pub extracted: Option<String>,
+}
+
+/// Robustness report card for one image
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StressTestReport {
+    pub watermark_text: String,
+    /// One entry per attack, in the order they were run
+    pub results: Vec<AttackOutcome>,
+}
+
+impl StressTestReport {
+    /// Number of attacks the watermark survived
+    pub fn passed_count(&self) -> usize {
+        self.results.iter().filter(|r| r.passed).count()
+    }
+}
+
+/// Embed `watermark_text` as a text watermark, then check extraction after each attack
+///
+/// Fails only if the initial embed fails; a failing attack is recorded as `passed: false`.
+pub fn stress_test(
+    image: &DynamicImage,
+    watermark_text: &str,
+    strength: f32,
+    attacks: &[Degradation],
+) -> Result<StressTestReport, BlindMarkError> {
+    let watermarked = WatermarkEmbedder::new().embed_raw_text(image, watermark_text, strength, false)?;
+    let extractor = WatermarkExtractor::new();
+
+    let results = attacks
+        .iter()
+        .map(|attack| {
+            let extracted = attack
+                .apply(&watermarked)
+                .and_then(|degraded| extractor.try_extract_text(&degraded))
+                .ok()
+                .flatten();
+            AttackOutcome {
+                attack: attack.name(),
+                passed: extracted.as_deref() == Some(watermark_text),
+                extracted,
+            }
+        })
+        .collect();
+
+    Ok(StressTestReport { watermark_text: watermark_text.to_string(), results })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| {
+            Rgb([((x * 255) / width) as u8, ((y * 255) / height) as u8, 128])
+        }))
+    }
+
+    #[test]
+    fn test_identity_attack_passes() {
+        let image = create_test_image(256, 256);
+        let report = stress_test(&image, "robust", 0.5, &[Degradation::Identity]).unwrap();
+        assert_eq!(report.results.len(), 1);
+        assert_eq!(report.results[0].attack, "identity");
+        assert!(report.results[0].passed, "watermark must survive the identity attack");
+        assert_eq!(report.results[0].extracted.as_deref(), Some("robust"));
+    }
+
+    #[test]
+    fn test_degradations_preserve_dimensions() {
+        let image = create_test_image(128, 96);
+        for attack in Degradation::standard_matrix() {
+            let degraded = attack.apply(&image).unwrap();
+            assert_eq!(degraded.dimensions(), (128, 96), "{} must keep the original size", attack.name());
+        }
+    }
+}
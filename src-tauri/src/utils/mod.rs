@@ -1,3 +1,6 @@
 // Utility modules
 pub mod progress;
 pub mod parallel;
+pub mod degrade;
+pub mod orientation;
+pub mod decode_limits;
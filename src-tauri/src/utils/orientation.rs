@@ -0,0 +1,124 @@
+use std::io::Cursor;
+use std::path::Path;
+use image::{codecs::jpeg::JpegEncoder, metadata::Orientation, DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use crate::models::BlindMarkError;
+use crate::utils::decode_limits::DecodeLimits;
+
+/// JPEG quality used when a rotated JPEG has to be re-encoded
+const REENCODE_JPEG_QUALITY: u8 = 95;
+
+/// Decode an image and bake its EXIF orientation into the pixels
+///
+/// Images whose header exceeds `limits` are rejected before decoding.
+///
+/// # Returns
+/// * `(image, format, rotated)` — `rotated` is false when the image has no
+///   orientation tag or the tag is the identity
+pub fn decode_oriented(
+    bytes: &[u8],
+    limits: &DecodeLimits,
+) -> Result<(DynamicImage, Option<ImageFormat>, bool), BlindMarkError> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| BlindMarkError::ImageProcessing(format!("Failed to read image: {}", e)))?;
+    let format = reader.format();
+    let mut decoder = limits.decoder(reader, "(in memory)")?;
+    // A malformed EXIF block is treated like a missing one
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder)
+        .map_err(|e| BlindMarkError::ImageProcessing(format!("Failed to decode image: {}", e)))?;
+    image.apply_orientation(orientation);
+    Ok((image, format, orientation != Orientation::NoTransforms))
+}
+
+/// Load an image file with its EXIF orientation applied
+pub fn open_oriented(path: &Path, limits: &DecodeLimits) -> Result<DynamicImage, BlindMarkError> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| BlindMarkError::ImageProcessing(format!("Failed to read {}: {}", path.display(), e)))?;
+    decode_oriented(&bytes, limits).map(|(image, _, _)| image)
+}
+
+/// Re-encode `bytes` upright if they carry a non-identity EXIF orientation
+///
+/// The pixels are rotated/flipped and the file is re-encoded in its original
+/// format without metadata, which drops the orientation tag.
+///
+/// # Returns
+/// * `Ok(None)` — the image is already upright, keep the original bytes
+pub fn normalize_orientation(bytes: &[u8], limits: &DecodeLimits) -> Result<Option<Vec<u8>>, BlindMarkError> {
+    let (image, format, rotated) = decode_oriented(bytes, limits)?;
+    if !rotated {
+        return Ok(None);
+    }
+    let format = format.ok_or_else(|| BlindMarkError::UnsupportedImage("Unknown image format".to_string()))?;
+
+    let mut buffer = Vec::new();
+    let result = if format == ImageFormat::Jpeg {
+        JpegEncoder::new_with_quality(&mut buffer, REENCODE_JPEG_QUALITY).encode_image(&image.to_rgb8())
+    } else {
+        image.write_to(&mut Cursor::new(&mut buffer), format)
+    };
+    result.map_err(|e| BlindMarkError::ImageProcessing(format!("Failed to re-encode image: {}", e)))?;
+    Ok(Some(buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, ImageBuffer, Rgb};
+
+    /// Encode a 40×20 JPEG (left half red, right half blue) tagged with `orientation`
+    fn jpeg_with_orientation(orientation: u16) -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_fn(40, 20, |x, _| {
+            if x < 20 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) }
+        }));
+        let mut jpeg = Vec::new();
+        image.write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg).unwrap();
+
+        // APP1 "Exif" segment: little-endian TIFF with a single Orientation (0x0112) entry
+        let mut tiff = b"II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0".to_vec();
+        tiff.extend_from_slice(&orientation.to_le_bytes());
+        tiff.extend_from_slice(&[0; 6]);
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend_from_slice(&tiff);
+
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(&app1);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    fn is_red(image: &DynamicImage, x: u32, y: u32) -> bool {
+        let p = image.get_pixel(x, y);
+        p[0] > 200 && p[2] < 60
+    }
+
+    #[test]
+    fn test_normalize_rotated_jpeg() {
+        // 6 = stored image must be rotated 90° clockwise for display
+        let jpeg = jpeg_with_orientation(6);
+        let (decoded, _, rotated) = decode_oriented(&jpeg, &DecodeLimits::default()).unwrap();
+        assert!(rotated);
+        assert_eq!(decoded.dimensions(), (20, 40));
+
+        let normalized = normalize_orientation(&jpeg, &DecodeLimits::default()).unwrap().expect("should be re-encoded");
+        let (upright, format, rotated) = decode_oriented(&normalized, &DecodeLimits::default()).unwrap();
+        assert_eq!(format, Some(ImageFormat::Jpeg));
+        assert!(!rotated, "orientation tag should be dropped");
+        assert_eq!(upright.dimensions(), (20, 40));
+        // The left (red) half of the stored image ends up on top
+        assert!(is_red(&upright, 10, 5));
+        assert!(!is_red(&upright, 10, 35));
+    }
+
+    #[test]
+    fn test_normalize_upright_image_unchanged() {
+        assert!(normalize_orientation(&jpeg_with_orientation(1), &DecodeLimits::default()).unwrap().is_none());
+
+        let mut png = Vec::new();
+        DynamicImage::new_rgb8(8, 8).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        assert!(normalize_orientation(&png, &DecodeLimits::default()).unwrap().is_none());
+    }
+}
@@ -1,15 +1,25 @@
 use rayon::prelude::*;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use image::open;
-use crate::core::watermark::embedder::WatermarkEmbedder;
-use crate::models::{ImageFile, BlindMarkError};
-use crate::utils::progress::ProgressEmitter;
+use sha2::{Digest, Sha256};
+use crate::core::watermark::{dct::DEFAULT_PASSWORD, embedder::WatermarkEmbedder, extractor::{ExtractedWatermark, WatermarkExtractor}, metadata::{copy_jpeg_segments, embed_metadata_watermark}, profile::Profile};
+use crate::models::{ImageFile, BlindMarkError, ShortfallPolicy};
+use crate::utils::decode_limits::{open_named, DecodeLimits};
+use crate::utils::orientation::{normalize_orientation, open_oriented};
+use crate::utils::progress::{BatchFailure, ProgressSink, Warning};
 
 /// Parallel processor for batch watermarking
 ///
 /// Uses Rayon for CPU-bound parallel processing of images.
 pub struct ParallelProcessor {
     thread_count: usize,
+    metadata_fallback: bool,
+    normalize_orientation: bool,
+    decode_limits: DecodeLimits,
+    password: u64,
+    quick_prefilter: bool,
+    profile: Profile,
+    idempotent: bool,
 }
 
 impl ParallelProcessor {
@@ -17,14 +27,91 @@ impl ParallelProcessor {
     ///
     /// Uses all available CPU cores by default
     pub fn new() -> Self {
-        Self {
-            thread_count: num_cpus::get(),
-        }
+        Self::with_threads(num_cpus::get())
     }
 
     /// Create a parallel processor with custom thread count
     pub fn with_threads(thread_count: usize) -> Self {
-        Self { thread_count }
+        Self {
+            thread_count,
+            metadata_fallback: false,
+            normalize_orientation: false,
+            decode_limits: DecodeLimits::default(),
+            password: DEFAULT_PASSWORD,
+            quick_prefilter: false,
+            profile: Profile::Balanced,
+            idempotent: false,
+        }
+    }
+
+    /// Set the block-shuffle password used for embedding and scanning
+    ///
+    /// Extraction only succeeds with the password used at embed time
+    /// (see `dct::password_seed` for deriving it from user text).
+    pub fn with_password(mut self, password: u64) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// Set the embedding profile used for embedding and scanning (see `Profile`, defaults to `Balanced`)
+    ///
+    /// The profile is not recorded in the image, so scans must use the profile
+    /// the images were embedded with.
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Enable or disable the metadata watermark fallback (disabled by default)
+    ///
+    /// When enabled, images that cannot carry a blind watermark — those for which
+    /// embedding fails, e.g. too small or an undecodable JPEG — get the watermark text written
+    /// into their metadata (PNG tEXt/iTXt, JPEG EXIF UserComment) instead of being
+    /// copied unmarked. This is not blind, but preserves attribution.
+    pub fn with_metadata_fallback(mut self, enabled: bool) -> Self {
+        self.metadata_fallback = enabled;
+        self
+    }
+
+    /// Enable or disable EXIF orientation normalization (disabled by default)
+    ///
+    /// When enabled, images with an EXIF orientation tag are rotated/flipped
+    /// upright and re-encoded without the tag before embedding or copying, so the
+    /// watermark is laid out relative to the image as it is displayed.
+    pub fn with_orientation_normalization(mut self, enabled: bool) -> Self {
+        self.normalize_orientation = enabled;
+        self
+    }
+
+    /// Enable or disable the quick-detect prefilter for text scans (disabled by default)
+    ///
+    /// When enabled, `scan_batch_text` skips images that `WatermarkExtractor::quick_detect`
+    /// rejects instead of running the full extraction, which speeds up scans of
+    /// mostly unmarked archives. Images embedded with a block fraction below 1 may
+    /// be rejected, and tolerant scans always run the full extraction.
+    pub fn with_quick_prefilter(mut self, enabled: bool) -> Self {
+        self.quick_prefilter = enabled;
+        self
+    }
+
+    /// Enable or disable idempotent embedding (disabled by default)
+    ///
+    /// When enabled, images that already carry the requested text watermark
+    /// (extracted with this processor's password and profile) are copied unchanged
+    /// instead of being embedded again, so re-processing watermarked output
+    /// leaves it byte-identical.
+    pub fn with_idempotent(mut self, enabled: bool) -> Self {
+        self.idempotent = enabled;
+        self
+    }
+
+    /// Set the size guard applied to image headers before decoding
+    ///
+    /// Images claiming more than the allowed width, height or pixel count are
+    /// rejected without being decoded (defaults to `DecodeLimits::default()`).
+    pub fn with_decode_limits(mut self, limits: DecodeLimits) -> Self {
+        self.decode_limits = limits;
+        self
     }
 
     /// Process batch of images in parallel with single watermark text
@@ -34,71 +121,154 @@ impl ParallelProcessor {
     /// * `watermark_text` - Single watermark text for all images
     /// * `strength` - Embedding strength
     /// * `output_dir` - Output directory path
-    /// * `progress` - Optional progress emitter
+    /// * `progress` - Optional progress sink (e.g. `ProgressEmitter`)
     /// * `fast_mode` - When true, images with both dimensions > 512px are processed
     ///                 only in their top-left 512×512 ROI for faster throughput.
     ///
+    /// # Behavior
+    /// Byte-identical images (same texture stored under several paths) are embedded
+    /// only once; the watermarked result is copied to every other path.
+    ///
     /// # Returns
-    /// * Number of successfully processed images
+    /// * Number of successfully processed images; the first per-image failure
+    ///   is returned as an error
     pub fn process_batch_single(
         &self,
         images: &[ImageFile],
         watermark_text: &str,
         strength: f32,
         output_dir: &std::path::Path,
-        progress: Option<Arc<ProgressEmitter>>,
+        progress: Option<Arc<dyn ProgressSink>>,
         fast_mode: bool,
     ) -> Result<usize, BlindMarkError> {
+        let (processed, _, failures, _) =
+            self.process_batch_single_dedup(images, watermark_text, strength, output_dir, progress, fast_mode)?;
+        // The last entry of a failed group is its primary, carrying the original error
+        match failures.into_iter().last() {
+            Some((_, e)) => Err(e),
+            None => Ok(processed),
+        }
+    }
+
+    /// Like `process_batch_single`, but a failing image does not abort the batch
+    ///
+    /// Images that cannot be watermarked are copied to `output_dir` unchanged and
+    /// reported as failures; only batch-level problems (thread pool, unreadable
+    /// input while grouping) are returned as an error.
+    ///
+    /// # Returns
+    /// * `(processed, failures, warnings)` — processed image count, one `BatchFailure`
+    ///   per image kept as-is (`item` is the relative path) and one `Warning` per image
+    ///   written without a blind watermark (JPEG copy or metadata fallback)
+    pub fn process_batch_single_partial(
+        &self,
+        images: &[ImageFile],
+        watermark_text: &str,
+        strength: f32,
+        output_dir: &std::path::Path,
+        progress: Option<Arc<dyn ProgressSink>>,
+        fast_mode: bool,
+    ) -> Result<(usize, Vec<BatchFailure>, Vec<Warning>), BlindMarkError> {
+        let (processed, _, failures, warnings) =
+            self.process_batch_single_dedup(images, watermark_text, strength, output_dir, progress, fast_mode)?;
+        let failures = failures
+            .into_iter()
+            .map(|(image_file, e)| {
+                // Keep the original so the output stays complete
+                let output_path = output_dir.join(&image_file.relative_path);
+                let kept = std::fs::copy(&image_file.temp_path, &output_path).is_ok();
+                let reason = if kept {
+                    format!("{}（已原样保留）", e)
+                } else {
+                    format!("{}（未能保留原文件）", e)
+                };
+                BatchFailure { item: image_file.relative_path.clone(), reason }
+            })
+            .collect();
+        Ok((processed, failures, warnings))
+    }
+
+    /// Implementation of `process_batch_single`, returning `(processed, embedded, failures, warnings)`
+    /// where `embedded` is the number of distinct contents actually run through
+    /// the DWT/DCT pipeline, `failures` lists every image of each failed group and
+    /// `warnings` every image written without a blind watermark.
+    #[allow(clippy::type_complexity)]
+    fn process_batch_single_dedup<'a>(
+        &self,
+        images: &'a [ImageFile],
+        watermark_text: &str,
+        strength: f32,
+        output_dir: &std::path::Path,
+        progress: Option<Arc<dyn ProgressSink>>,
+        fast_mode: bool,
+    ) -> Result<(usize, usize, Vec<(&'a ImageFile, BlindMarkError)>, Vec<Warning>), BlindMarkError> {
         let total_files = images.len();
-        let processed_count = Arc::new(Mutex::new(0usize));
-        let embedder = WatermarkEmbedder::new();
+        let completed_count = Arc::new(Mutex::new(0usize));
+        let embedded_count = Arc::new(Mutex::new(0usize));
+        let failures: Mutex<Vec<(&ImageFile, BlindMarkError)>> = Mutex::new(Vec::new());
+        let warnings: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+        let embedder = WatermarkEmbedder::with_password(self.password).with_profile(self.profile);
 
         // Configure Rayon thread pool
-        rayon::ThreadPoolBuilder::new()
+        let pool = rayon::ThreadPoolBuilder::new()
             .num_threads(self.thread_count)
             .build()
             .map_err(|e| BlindMarkError::ImageProcessing(
                 format!("Failed to create thread pool: {}", e)
-            ))?
-            .install(|| {
-                images.par_iter().try_for_each(|image_file| {
-                    let output_path = output_dir.join(&image_file.relative_path);
-                    if let Some(parent) = output_path.parent() {
-                        std::fs::create_dir_all(parent)
-                            .map_err(|e| BlindMarkError::ImageProcessing(
-                                format!("Failed to create output directory: {}", e)
-                            ))?;
+            ))?;
+
+        pool.install(|| {
+            // Group images by content hash so identical files are embedded once
+            let groups = group_by_content(images)?;
+
+            groups.par_iter().for_each(|group| {
+                let result = (|| {
+                    let primary = group[0];
+                    let primary_output = output_dir.join(&primary.relative_path);
+                    for image_file in group {
+                        if let Some(parent) = output_dir.join(&image_file.relative_path).parent() {
+                            std::fs::create_dir_all(parent)
+                                .map_err(|e| BlindMarkError::ImageProcessing(
+                                    format!("Failed to create output directory: {}", e)
+                                ))?;
+                        }
                     }
 
-                    // Image watermark only supports PNG (lossless).
-                    // JPEG files are copied as-is without watermarking.
-                    let is_jpeg = output_path.extension()
-                        .and_then(|e| e.to_str())
-                        .map(|e| e.to_lowercase())
-                        .map(|e| e == "jpg" || e == "jpeg")
-                        .unwrap_or(false);
+                    let unmarked = self.embed_image_file(&embedder, primary, &primary_output, watermark_text, strength, fast_mode)?;
+                    if unmarked.is_none() {
+                        *embedded_count.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+                    }
 
-                    if is_jpeg {
-                        std::fs::copy(&image_file.temp_path, &output_path)
+                    // Duplicates reuse the primary's output bytes
+                    for image_file in &group[1..] {
+                        let output_path = output_dir.join(&image_file.relative_path);
+                        std::fs::copy(&primary_output, &output_path)
                             .map_err(|e| BlindMarkError::ImageProcessing(
                                 format!("Failed to copy {}: {}", image_file.relative_path, e)
                             ))?;
-                    } else {
-                        // Load image, embed watermark, save
-                        let img = open(&image_file.temp_path)
-                            .map_err(|e| BlindMarkError::ImageProcessing(
-                                format!("Failed to load {}: {}", image_file.relative_path, e)
-                            ))?;
-                        let watermarked = embedder.embed_raw_text(&img, watermark_text, strength, fast_mode)?;
-                        watermarked.save(&output_path)
-                            .map_err(|e| BlindMarkError::ImageProcessing(
-                                format!("Failed to save {}: {}", output_path.display(), e)
-                            ))?;
                     }
+                    Ok::<Option<&str>, BlindMarkError>(unmarked)
+                })();
 
-                    // Update processed count and emit progress after completion (1-based, monotonically increasing)
+                if let Ok(Some(reason)) = result {
+                    let mut warnings = warnings.lock().unwrap_or_else(|e| e.into_inner());
+                    for image_file in group {
+                        warnings.push(Warning { file: image_file.relative_path.clone(), reason: reason.to_string() });
+                    }
+                }
+                if let Err(e) = result {
+                    // Duplicates share the primary's failure
+                    let mut failures = failures.lock().unwrap_or_else(|e| e.into_inner());
+                    for image_file in &group[1..] {
+                        failures.push((*image_file, BlindMarkError::ImageProcessing(e.to_string())));
+                    }
+                    failures.push((group[0], e));
+                }
+
+                for image_file in group {
+                    // Update completed count and emit progress after completion (1-based, monotonically increasing)
                     let completed = {
-                        let mut count = processed_count.lock().unwrap_or_else(|e| e.into_inner());
+                        let mut count = completed_count.lock().unwrap_or_else(|e| e.into_inner());
                         *count += 1;
                         *count
                     };
@@ -111,13 +281,18 @@ impl ParallelProcessor {
                             "processing".to_string(),
                         );
                     }
+                }
+            });
 
-                    Ok::<(), BlindMarkError>(())
-                })
-            })?;
+            Ok::<(), BlindMarkError>(())
+        })?;
 
-        let final_count = *processed_count.lock().unwrap_or_else(|e| e.into_inner());
-        Ok(final_count)
+        let failures = failures.into_inner().unwrap_or_else(|e| e.into_inner());
+        let completed = *completed_count.lock().unwrap_or_else(|e| e.into_inner());
+        let embedded = *embedded_count.lock().unwrap_or_else(|e| e.into_inner());
+        let mut warnings = warnings.into_inner().unwrap_or_else(|e| e.into_inner());
+        warnings.sort_by(|a, b| a.file.cmp(&b.file));
+        Ok((completed - failures.len(), embedded, failures, warnings))
     }
 
     /// Process batch of images with Excel watermark mapping
@@ -127,30 +302,41 @@ impl ParallelProcessor {
     /// * `watermarks` - List of watermark texts from Excel
     /// * `strength` - Embedding strength
     /// * `output_dir` - Output directory path
-    /// * `progress` - Optional progress emitter
+    /// * `progress` - Optional progress sink (e.g. `ProgressEmitter`)
     /// * `fast_mode` - When true, large images (both dims > 512px) use ROI processing.
+    /// * `on_shortfall` - What to do when there are more images than watermarks
     ///
     /// # Behavior
     /// Maps watermarks sequentially: images[0] → watermarks[0], images[1] → watermarks[1], etc.
-    /// If there are more images than watermarks, remaining images get the last watermark.
+    /// If there are more images than watermarks, `on_shortfall` decides: reuse the last
+    /// watermark (`RepeatLast`), wrap around (`Cycle`), or fail up front (`Error`).
+    #[allow(clippy::too_many_arguments)]
     pub fn process_batch_excel(
         &self,
         images: &[ImageFile],
         watermarks: &[String],
         strength: f32,
         output_dir: &std::path::Path,
-        progress: Option<Arc<ProgressEmitter>>,
+        progress: Option<Arc<dyn ProgressSink>>,
         fast_mode: bool,
+        on_shortfall: ShortfallPolicy,
     ) -> Result<usize, BlindMarkError> {
         if watermarks.is_empty() {
             return Err(BlindMarkError::InvalidConfig(
                 "No watermarks provided".to_string()
             ));
         }
+        if on_shortfall == ShortfallPolicy::Error && images.len() > watermarks.len() {
+            return Err(BlindMarkError::InvalidConfig(format!(
+                "Not enough watermarks: {} images but only {} watermarks",
+                images.len(),
+                watermarks.len()
+            )));
+        }
 
         let total_files = images.len();
         let processed_count = Arc::new(Mutex::new(0usize));
-        let embedder = WatermarkEmbedder::new();
+        let embedder = WatermarkEmbedder::with_password(self.password).with_profile(self.profile);
 
         // Configure Rayon thread pool
         rayon::ThreadPoolBuilder::new()
@@ -161,8 +347,8 @@ impl ParallelProcessor {
             ))?
             .install(|| {
                 images.par_iter().enumerate().try_for_each(|(index, image_file)| {
-                    // Get watermark text (use last one if index exceeds watermarks)
-                    let watermark_index = index.min(watermarks.len() - 1);
+                    // Get watermark text (shortfall handled per `on_shortfall`)
+                    let watermark_index = on_shortfall.watermark_index(index, watermarks.len());
                     let watermark_text = &watermarks[watermark_index];
 
                     let output_path = output_dir.join(&image_file.relative_path);
@@ -173,30 +359,7 @@ impl ParallelProcessor {
                             ))?;
                     }
 
-                    // Image watermark only supports PNG (lossless).
-                    // JPEG files are copied as-is without watermarking.
-                    let is_jpeg = output_path.extension()
-                        .and_then(|e| e.to_str())
-                        .map(|e| e.to_lowercase())
-                        .map(|e| e == "jpg" || e == "jpeg")
-                        .unwrap_or(false);
-
-                    if is_jpeg {
-                        std::fs::copy(&image_file.temp_path, &output_path)
-                            .map_err(|e| BlindMarkError::ImageProcessing(
-                                format!("Failed to copy {}: {}", image_file.relative_path, e)
-                            ))?;
-                    } else {
-                        let img = open(&image_file.temp_path)
-                            .map_err(|e| BlindMarkError::ImageProcessing(
-                                format!("Failed to load {}: {}", image_file.relative_path, e)
-                            ))?;
-                        let watermarked = embedder.embed_raw_text(&img, watermark_text, strength, fast_mode)?;
-                        watermarked.save(&output_path)
-                            .map_err(|e| BlindMarkError::ImageProcessing(
-                                format!("Failed to save {}: {}", output_path.display(), e)
-                            ))?;
-                    }
+                    self.embed_image_file(&embedder, image_file, &output_path, watermark_text, strength, fast_mode)?;
 
                     // Update processed count and emit progress after completion (1-based, monotonically increasing)
                     let completed = {
@@ -222,12 +385,283 @@ impl ParallelProcessor {
         Ok(final_count)
     }
 
+    /// Watermark a single image into `output_path`
+    ///
+    /// Lossless formats (PNG, BMP, TGA, PPM) get the spatial-domain watermark and are
+    /// re-encoded in their own format. JPEG files get the JPEG DCT-domain watermark
+    /// (see `WatermarkEmbedder::embed_raw_text_jpeg`) and are re-encoded as JPEG; those
+    /// that cannot carry it (e.g. too small, undecodable) are copied as-is, unless the
+    /// metadata fallback is enabled (see `with_metadata_fallback`).
+    /// With `with_orientation_normalization`, all of them are made upright first.
+    ///
+    /// # Returns
+    /// * `None` if the blind watermark was embedded, otherwise why the file was
+    ///   copied or only received a metadata watermark
+    fn embed_image_file(
+        &self,
+        embedder: &WatermarkEmbedder,
+        image_file: &ImageFile,
+        output_path: &std::path::Path,
+        watermark_text: &str,
+        strength: f32,
+        fast_mode: bool,
+    ) -> Result<Option<&'static str>, BlindMarkError> {
+        if is_jpeg(output_path) {
+            match self.embed_jpeg_file(embedder, image_file, output_path, watermark_text, strength) {
+                Ok(()) => return Ok(None),
+                // Configuration errors must not be hidden by the fallback
+                Err(e @ BlindMarkError::InvalidConfig(_)) => return Err(e),
+                Err(_) => {}
+            }
+            let bytes = self.read_source(image_file)?;
+            // Fall back to a plain copy if the metadata can't be written (e.g. existing EXIF)
+            if self.metadata_fallback && self.write_metadata_watermark(&bytes, output_path, watermark_text) {
+                return Ok(Some("JPEG 无法嵌入盲水印（如尺寸过小），仅写入元数据水印"));
+            }
+            std::fs::write(output_path, &bytes)
+                .map_err(|e| BlindMarkError::ImageProcessing(
+                    format!("Failed to copy {}: {}", image_file.relative_path, e)
+                ))?;
+            return Ok(Some("JPEG 无法嵌入盲水印（如尺寸过小），已原样复制"));
+        }
+
+        // Load image, embed watermark, save
+        let img = self.open_image(image_file)?;
+        if self.idempotent {
+            let extractor = WatermarkExtractor::with_password(self.password).with_profile(self.profile);
+            if extractor.try_extract_text(&img).ok().flatten().as_deref() == Some(watermark_text) {
+                std::fs::copy(&image_file.temp_path, output_path)
+                    .map_err(|e| BlindMarkError::ImageProcessing(
+                        format!("Failed to copy {}: {}", image_file.relative_path, e)
+                    ))?;
+                return Ok(None);
+            }
+        }
+        match embedder.embed_raw_text(&img, watermark_text, strength, fast_mode) {
+            Ok(watermarked) => {
+                watermarked.save(output_path)
+                    .map_err(|e| BlindMarkError::ImageProcessing(
+                        format!("Failed to save {}: {}", output_path.display(), e)
+                    ))?;
+                Ok(None)
+            }
+            // Configuration errors must not be hidden by the fallback
+            Err(e @ BlindMarkError::InvalidConfig(_)) => Err(e),
+            Err(e) => {
+                let written = self.metadata_fallback
+                    && self
+                        .read_source(image_file)
+                        .is_ok_and(|bytes| self.write_metadata_watermark(&bytes, output_path, watermark_text));
+                if written {
+                    Ok(Some("无法嵌入盲水印（如尺寸过小），仅写入元数据水印"))
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    /// Embed the JPEG DCT-domain watermark and write the result as JPEG
+    ///
+    /// The source's APP1 (EXIF/XMP) and APP2 (ICC profile) segments are copied into
+    /// the re-encoded file; with `with_orientation_normalization` only APP2 is kept.
+    /// With `with_idempotent`, a JPEG already carrying the same text is copied unchanged.
+    fn embed_jpeg_file(
+        &self,
+        embedder: &WatermarkEmbedder,
+        image_file: &ImageFile,
+        output_path: &std::path::Path,
+        watermark_text: &str,
+        strength: f32,
+    ) -> Result<(), BlindMarkError> {
+        let img = self.open_image(image_file)?;
+        if self.idempotent {
+            let extractor = WatermarkExtractor::with_password(self.password);
+            if extractor.try_extract_text_jpeg(&img).ok().flatten().as_deref() == Some(watermark_text) {
+                std::fs::copy(&image_file.temp_path, output_path)
+                    .map_err(|e| BlindMarkError::ImageProcessing(
+                        format!("Failed to copy {}: {}", image_file.relative_path, e)
+                    ))?;
+                return Ok(());
+            }
+        }
+        let encoded = embedder.embed_jpeg_to_bytes(&img, watermark_text, strength)?;
+        let source = std::fs::read(&image_file.temp_path)
+            .map_err(|e| BlindMarkError::ImageProcessing(
+                format!("Failed to read {}: {}", image_file.relative_path, e)
+            ))?;
+        // Normalized pixels are already upright, so the EXIF orientation must not be carried over
+        let markers: &[u8] = if self.normalize_orientation { &[JPEG_APP2] } else { &[JPEG_APP1, JPEG_APP2] };
+        let bytes = copy_jpeg_segments(&source, &encoded, markers);
+        std::fs::write(output_path, bytes)
+            .map_err(|e| BlindMarkError::ImageProcessing(
+                format!("Failed to save {}: {}", output_path.display(), e)
+            ))
+    }
+
+    /// Decode the source image, made upright first when orientation normalization is enabled
+    fn open_image(&self, image_file: &ImageFile) -> Result<image::DynamicImage, BlindMarkError> {
+        if self.normalize_orientation {
+            open_oriented(&image_file.temp_path, &self.decode_limits)
+        } else {
+            open_named(&image_file.temp_path, &image_file.relative_path, &self.decode_limits)
+        }
+    }
+
+    /// Read the source file, normalizing its EXIF orientation when enabled
+    fn read_source(&self, image_file: &ImageFile) -> Result<Vec<u8>, BlindMarkError> {
+        let bytes = std::fs::read(&image_file.temp_path)
+            .map_err(|e| BlindMarkError::ImageProcessing(
+                format!("Failed to read {}: {}", image_file.relative_path, e)
+            ))?;
+        if !self.normalize_orientation {
+            return Ok(bytes);
+        }
+        Ok(normalize_orientation(&bytes, &self.decode_limits)?.unwrap_or(bytes))
+    }
+
+    /// Write the metadata watermark fallback; returns whether it succeeded
+    fn write_metadata_watermark(&self, bytes: &[u8], output_path: &std::path::Path, watermark_text: &str) -> bool {
+        embed_metadata_watermark(bytes, watermark_text)
+            .is_ok_and(|marked| std::fs::write(output_path, marked).is_ok())
+    }
+
+    /// Extract raw-text blind watermarks from a batch of images in parallel
+    ///
+    /// Runs inside this processor's own Rayon pool, so scanning can be throttled
+    /// independently of embedding. Images that fail to load or carry no text
+    /// watermark are skipped. With `tolerant`, extraction also tries gamma
+    /// correction (see `WatermarkExtractor::try_extract_text_tolerant`).
+    /// JPEG files are read with `WatermarkExtractor::try_extract_text_jpeg`
+    /// (neither the quick prefilter nor `tolerant` applies to them).
+    ///
+    /// # Returns
+    /// * `(relative_path, text, confidence)` tuples, sorted by relative path
+    pub fn scan_batch_text(&self, images: &[ImageFile], tolerant: bool) -> Result<Vec<(String, String, f32)>, BlindMarkError> {
+        let extractor = WatermarkExtractor::with_password(self.password).with_profile(self.profile);
+
+        let mut findings: Vec<(String, String, f32)> = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.thread_count)
+            .build()
+            .map_err(|e| BlindMarkError::ImageProcessing(
+                format!("Failed to create thread pool: {}", e)
+            ))?
+            .install(|| {
+                images
+                    .par_iter()
+                    .filter_map(|image_file| {
+                        let img = open_named(&image_file.temp_path, &image_file.relative_path, &self.decode_limits).ok()?;
+                        if is_jpeg(std::path::Path::new(&image_file.relative_path)) {
+                            let (text, confidence) = extractor.try_extract_text_jpeg_with_confidence(&img).ok()??;
+                            return Some((image_file.relative_path.clone(), text, confidence));
+                        }
+                        if self.quick_prefilter && !tolerant && !extractor.quick_detect(&img) {
+                            return None;
+                        }
+                        let extracted = if tolerant {
+                            extractor.try_extract_text_tolerant(&img)
+                        } else {
+                            extractor.try_extract_text_with_confidence(&img)
+                        };
+                        let (text, confidence) = extracted.ok()??;
+                        Some((image_file.relative_path.clone(), text, confidence))
+                    })
+                    .collect()
+            });
+
+        findings.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(findings)
+    }
+
+    /// Extract the watermark of every image in parallel, text or MD5 (see `WatermarkExtractor::extract_any`)
+    ///
+    /// Unlike `scan_batch_text`, every image gets an entry: load and extraction
+    /// failures are returned as `Err` so callers can report them per file.
+    /// JPEG files are read with `WatermarkExtractor::try_extract_text_jpeg`
+    /// (they never carry an MD5 watermark).
+    ///
+    /// # Returns
+    /// * `(relative_path, result)` tuples, sorted by relative path
+    #[allow(clippy::type_complexity)]
+    pub fn scan_batch_any(
+        &self,
+        images: &[ImageFile],
+    ) -> Result<Vec<(String, Result<ExtractedWatermark, BlindMarkError>)>, BlindMarkError> {
+        let extractor = WatermarkExtractor::with_password(self.password).with_profile(self.profile);
+
+        let mut results: Vec<(String, Result<ExtractedWatermark, BlindMarkError>)> = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.thread_count)
+            .build()
+            .map_err(|e| BlindMarkError::ImageProcessing(
+                format!("Failed to create thread pool: {}", e)
+            ))?
+            .install(|| {
+                images
+                    .par_iter()
+                    .map(|image_file| {
+                        let jpeg = is_jpeg(std::path::Path::new(&image_file.relative_path));
+                        let extracted = open_named(&image_file.temp_path, &image_file.relative_path, &self.decode_limits)
+                            .and_then(|img| if jpeg {
+                                Ok(extractor.try_extract_text_jpeg(&img)?
+                                    .map_or(ExtractedWatermark::None, ExtractedWatermark::Text))
+                            } else {
+                                extractor.extract_any(&img)
+                            });
+                        (image_file.relative_path.clone(), extracted)
+                    })
+                    .collect()
+            });
+
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(results)
+    }
+
     /// Get configured thread count
     pub fn thread_count(&self) -> usize {
         self.thread_count
     }
 }
 
+/// JPEG APP1 marker (EXIF / XMP)
+const JPEG_APP1: u8 = 0xE1;
+/// JPEG APP2 marker (ICC profile)
+const JPEG_APP2: u8 = 0xE2;
+
+/// Whether `path` has a JPEG extension (case-insensitive)
+fn is_jpeg(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"))
+}
+
+/// Group images by SHA-256 of their file content, preserving input order
+/// (both of the groups and of the images within each group).
+fn group_by_content(images: &[ImageFile]) -> Result<Vec<Vec<&ImageFile>>, BlindMarkError> {
+    let hashes: Vec<String> = images
+        .par_iter()
+        .map(|image_file| {
+            let bytes = std::fs::read(&image_file.temp_path)
+                .map_err(|e| BlindMarkError::ImageProcessing(
+                    format!("Failed to read {}: {}", image_file.relative_path, e)
+                ))?;
+            Ok(format!("{:x}", Sha256::digest(&bytes)))
+        })
+        .collect::<Result<Vec<_>, BlindMarkError>>()?;
+
+    let mut groups: Vec<Vec<&ImageFile>> = Vec::new();
+    let mut index_by_hash: HashMap<String, usize> = HashMap::new();
+    for (image_file, hash) in images.iter().zip(hashes) {
+        match index_by_hash.get(&hash) {
+            Some(&i) => groups[i].push(image_file),
+            None => {
+                index_by_hash.insert(hash, groups.len());
+                groups.push(vec![image_file]);
+            }
+        }
+    }
+    Ok(groups)
+}
+
 impl Default for ParallelProcessor {
     fn default() -> Self {
         Self::new()
@@ -237,6 +671,7 @@ impl Default for ParallelProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
     use tempfile::TempDir;
 
     fn create_test_image(path: &std::path::Path, width: u32, height: u32) {
@@ -293,8 +728,131 @@ mod tests {
         assert!(output_dir.path().join("img2.png").exists());
     }
 
+    /// Records progress events; stands in for the Tauri emitter so this test
+    /// also runs under `--no-default-features`
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<(usize, usize)>>,
+    }
+
+    impl ProgressSink for RecordingSink {
+        fn emit_progress(&self, current_file: usize, total_files: usize, _: String, _: f32, _: String) -> Result<(), String> {
+            self.events.lock().unwrap().push((current_file, total_files));
+            Ok(())
+        }
+    }
+
     #[test]
-    fn test_process_batch_jpeg_copied_as_is() {
+    fn test_process_batch_reports_to_custom_sink() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+        let images: Vec<_> = (0..2)
+            .map(|i| {
+                let path = temp_dir.path().join(format!("img{}.png", i));
+                create_test_image(&path, 256, 256);
+                ImageFile::new(format!("img{}.png", i), path)
+            })
+            .collect();
+
+        let sink = Arc::new(RecordingSink::default());
+        ParallelProcessor::with_threads(2)
+            .process_batch_single(&images, "sink", 0.5, output_dir.path(), Some(sink.clone() as Arc<dyn ProgressSink>), false)
+            .unwrap();
+
+        let mut events = sink.events.lock().unwrap().clone();
+        events.sort();
+        assert_eq!(events, vec![(1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn test_idempotent_rerun_keeps_watermarked_images() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        let path = temp_dir.path().join("skin.png");
+        create_test_image(&path, 256, 256);
+
+        let processor = ParallelProcessor::with_threads(1).with_idempotent(true);
+        let images = vec![ImageFile::new("skin.png".to_string(), path)];
+        processor.process_batch_single(&images, "Stable", 0.5, first.path(), None, false).unwrap();
+
+        // Re-processing the output with the same text leaves it byte-identical
+        let rerun = vec![ImageFile::new("skin.png".to_string(), first.path().join("skin.png"))];
+        processor.process_batch_single(&rerun, "Stable", 0.5, second.path(), None, false).unwrap();
+        let marked = fs::read(first.path().join("skin.png")).unwrap();
+        assert_eq!(fs::read(second.path().join("skin.png")).unwrap(), marked);
+
+        // A different text is still embedded
+        processor.process_batch_single(&rerun, "Other", 0.5, second.path(), None, false).unwrap();
+        assert_ne!(fs::read(second.path().join("skin.png")).unwrap(), marked);
+    }
+
+    #[test]
+    fn test_process_batch_single_dedups_identical_images() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+
+        // Same texture stored under two different paths
+        fs::create_dir_all(temp_dir.path().join("a")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("b")).unwrap();
+        let path_a = temp_dir.path().join("a/skin.png");
+        let path_b = temp_dir.path().join("b/skin.png");
+        create_test_image(&path_a, 256, 256);
+        fs::copy(&path_a, &path_b).unwrap();
+
+        let images = vec![
+            ImageFile::new("a/skin.png".to_string(), path_a),
+            ImageFile::new("b/skin.png".to_string(), path_b),
+        ];
+
+        let processor = ParallelProcessor::new();
+        let (processed, embedded, _, _) = processor
+            .process_batch_single_dedup(&images, "Dedup", 0.5, output_dir.path(), None, false)
+            .unwrap();
+        assert_eq!(processed, 2, "Both paths should be reported as processed");
+        assert_eq!(embedded, 1, "Identical content should be embedded only once");
+
+        let extractor = crate::core::watermark::extractor::WatermarkExtractor::new();
+        for rel in ["a/skin.png", "b/skin.png"] {
+            let out = image::open(output_dir.path().join(rel)).unwrap();
+            assert_eq!(extractor.try_extract_text(&out).unwrap().as_deref(), Some("Dedup"));
+        }
+    }
+
+    #[test]
+    fn test_scan_batch_text_thread_count_independent() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+
+        let images: Vec<_> = (0..3)
+            .map(|i| {
+                let path = temp_dir.path().join(format!("img{}.png", i));
+                create_test_image(&path, 256, 256);
+                ImageFile::new(format!("img{}.png", i), path)
+            })
+            .collect();
+        ParallelProcessor::new()
+            .process_batch_single(&images, "Scan me", 0.5, output_dir.path(), None, false)
+            .unwrap();
+
+        let watermarked: Vec<_> = (0..3)
+            .map(|i| ImageFile::new(format!("img{}.png", i), output_dir.path().join(format!("img{}.png", i))))
+            .collect();
+
+        let all_cores = ParallelProcessor::new().scan_batch_text(&watermarked, false).unwrap();
+        let two_threads = ParallelProcessor::with_threads(2).scan_batch_text(&watermarked, false).unwrap();
+        assert_eq!(all_cores.len(), 3);
+        assert_eq!(all_cores, two_threads, "Findings should not depend on thread count");
+        assert!(two_threads.iter().all(|(_, text, _)| text == "Scan me"));
+
+        let prefiltered = ParallelProcessor::new().with_quick_prefilter(true).scan_batch_text(&watermarked, false).unwrap();
+        assert_eq!(prefiltered, all_cores, "Prefilter must keep watermarked images");
+        let clean = ParallelProcessor::new().with_quick_prefilter(true).scan_batch_text(&images, false).unwrap();
+        assert!(clean.is_empty());
+    }
+
+    #[test]
+    fn test_process_batch_jpeg_watermarked_in_dct_domain() {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = TempDir::new().unwrap();
 
@@ -320,9 +878,132 @@ mod tests {
         );
 
         assert!(result.is_ok(), "JPEG processing should succeed: {:?}", result.err());
-        // JPEG should be copied as-is (no format change, no watermark)
-        assert!(output_dir.path().join("img1.jpg").exists(), "JPEG should be copied as-is with .jpg extension");
+        // JPEG stays a JPEG (no format change) and carries the DCT-domain watermark
+        let output = output_dir.path().join("img1.jpg");
         assert!(!output_dir.path().join("img1.png").exists(), "No .png conversion should occur");
+        assert_eq!(image::ImageFormat::from_path(&output).unwrap(), image::ImageFormat::Jpeg);
+        assert_eq!(&std::fs::read(&output).unwrap()[..2], b"\xFF\xD8");
+        let img = image::open(&output).unwrap();
+        assert_eq!(
+            WatermarkExtractor::new().try_extract_text_jpeg(&img).unwrap().as_deref(),
+            Some("JPEG test watermark")
+        );
+
+        // Batch scanning reads it back as well
+        let output = [ImageFile::new("img1.jpg".to_string(), output)];
+        let scanned = ParallelProcessor::new().scan_batch_text(&output, false).unwrap();
+        assert_eq!(scanned.len(), 1);
+        assert_eq!(scanned[0].1, "JPEG test watermark");
+        let scanned = ParallelProcessor::new().scan_batch_any(&output).unwrap();
+        assert!(matches!(&scanned[0].1, Ok(ExtractedWatermark::Text(t)) if t == "JPEG test watermark"));
+    }
+
+    #[test]
+    fn test_process_batch_jpeg_keeps_exif_and_icc() {
+        use image::ImageDecoder;
+
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+        let png_path = temp_dir.path().join("src.png");
+        create_test_image(&png_path, 256, 256);
+        let mut jpeg = Vec::new();
+        image::open(&png_path).unwrap()
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+
+        // APP1 EXIF with Orientation (0x0112) = 6, APP2 with a single-chunk ICC profile
+        let icc = b"fake icc profile".to_vec();
+        let mut app1 = b"Exif\0\0II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0\x06\0\0\0\0\0\0\0".to_vec();
+        let mut app2 = b"ICC_PROFILE\0\x01\x01".to_vec();
+        app2.extend_from_slice(&icc);
+        let mut tagged = jpeg[..2].to_vec();
+        for (marker, data) in [(0xE1u8, &mut app1), (0xE2u8, &mut app2)] {
+            tagged.extend_from_slice(&[0xFF, marker]);
+            tagged.extend_from_slice(&((data.len() + 2) as u16).to_be_bytes());
+            tagged.append(data);
+        }
+        tagged.extend_from_slice(&jpeg[2..]);
+        let jpg_path = temp_dir.path().join("photo.jpg");
+        fs::write(&jpg_path, &tagged).unwrap();
+
+        ParallelProcessor::new()
+            .process_batch_single(
+                &[ImageFile::new("photo.jpg".to_string(), jpg_path)],
+                "keep metadata",
+                0.5,
+                output_dir.path(),
+                None,
+                false,
+            )
+            .unwrap();
+
+        let output = fs::read(output_dir.path().join("photo.jpg")).unwrap();
+        let mut decoder = image::codecs::jpeg::JpegDecoder::new(std::io::Cursor::new(&output)).unwrap();
+        assert_eq!(decoder.orientation().unwrap(), image::metadata::Orientation::Rotate90);
+        assert_eq!(decoder.icc_profile().unwrap(), Some(icc));
+        let img = image::load_from_memory(&output).unwrap();
+        assert_eq!(
+            WatermarkExtractor::new().try_extract_text_jpeg(&img).unwrap().as_deref(),
+            Some("keep metadata")
+        );
+    }
+
+    #[test]
+    fn test_process_batch_bmp_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+
+        let png_path = temp_dir.path().join("src.png");
+        create_test_image(&png_path, 256, 256);
+        let bmp_path = temp_dir.path().join("skin.bmp");
+        image::open(&png_path).unwrap().save(&bmp_path).unwrap();
+
+        let images = vec![ImageFile::new("textures/skin.bmp".to_string(), bmp_path)];
+        let (processed, failures, warnings) = ParallelProcessor::new()
+            .process_batch_single_partial(&images, "BMP mark", 0.5, output_dir.path(), None, false)
+            .unwrap();
+        assert_eq!((processed, failures.len(), warnings.len()), (1, 0, 0));
+
+        // Still a BMP file, and the watermark survives the lossless re-encode
+        let output = output_dir.path().join("textures/skin.bmp");
+        assert_eq!(image::ImageFormat::from_path(&output).unwrap(), image::ImageFormat::Bmp);
+        assert_eq!(&std::fs::read(&output).unwrap()[..2], b"BM");
+        let img = image::open(&output).unwrap();
+        assert_eq!(WatermarkExtractor::new().try_extract_text(&img).unwrap().as_deref(), Some("BMP mark"));
+    }
+
+    #[test]
+    fn test_metadata_fallback_for_small_and_jpeg() {
+        use crate::core::watermark::metadata::read_metadata_watermark;
+
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+
+        // 32×32 is too small for the 544-bit blind watermark
+        let small_path = temp_dir.path().join("small.png");
+        create_test_image(&small_path, 32, 32);
+        let jpg_path = temp_dir.path().join("photo.jpg");
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(32, 32)).save(&jpg_path).unwrap();
+
+        let images = vec![
+            ImageFile::new("small.png".to_string(), small_path),
+            ImageFile::new("photo.jpg".to_string(), jpg_path),
+        ];
+
+        // Without the fallback the small image fails
+        assert!(ParallelProcessor::with_threads(1)
+            .process_batch_single(&images, "buyer", 0.5, output_dir.path(), None, false)
+            .is_err());
+
+        let processor = ParallelProcessor::with_threads(1).with_metadata_fallback(true);
+        let count = processor
+            .process_batch_single(&images, "buyer", 0.5, output_dir.path(), None, false)
+            .unwrap();
+        assert_eq!(count, 2);
+        for name in ["small.png", "photo.jpg"] {
+            let bytes = fs::read(output_dir.path().join(name)).unwrap();
+            assert_eq!(read_metadata_watermark(&bytes).as_deref(), Some("buyer"), "{} should carry the metadata watermark", name);
+        }
     }
 
     #[test]
@@ -351,47 +1032,72 @@ mod tests {
             output_dir.path(),
             None,
             false,
+            ShortfallPolicy::default(),
         );
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 2);
     }
 
-    #[test]
-    fn test_process_batch_excel_more_images_than_watermarks() {
+    /// Process 3 images with 2 watermarks under `policy` and return the mark extracted from each image
+    fn run_shortfall(policy: ShortfallPolicy) -> Result<Vec<Option<String>>, BlindMarkError> {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = TempDir::new().unwrap();
 
-        // Create 3 test images
-        let img_paths: Vec<_> = (0..3)
+        let images: Vec<_> = (0..3)
             .map(|i| {
                 let path = temp_dir.path().join(format!("img{}.png", i));
                 create_test_image(&path, 256, 256);
-                path
+                ImageFile::new(format!("img{}.png", i), path)
             })
             .collect();
 
-        let images: Vec<_> = img_paths
-            .iter()
-            .enumerate()
-            .map(|(i, path)| ImageFile::new(format!("img{}.png", i), path.clone()))
-            .collect();
-
         // Only 2 watermarks for 3 images
         let watermarks = vec!["Mark 1".to_string(), "Mark 2".to_string()];
 
         let processor = ParallelProcessor::new();
-        let result = processor.process_batch_excel(
+        let count = processor.process_batch_excel(
             &images,
             &watermarks,
             0.5,
             output_dir.path(),
             None,
             false,
-        );
+            policy,
+        )?;
+        assert_eq!(count, 3);
 
-        // Should succeed, 3rd image gets last watermark
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 3);
+        let extractor = WatermarkExtractor::new();
+        Ok((0..3)
+            .map(|i| {
+                let img = image::open(output_dir.path().join(format!("img{}.png", i))).unwrap();
+                extractor.try_extract_text(&img).unwrap()
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_process_batch_excel_more_images_than_watermarks() {
+        // Default: 3rd image gets last watermark
+        let marks = run_shortfall(ShortfallPolicy::RepeatLast).unwrap();
+        assert_eq!(marks[2].as_deref(), Some("Mark 2"));
+    }
+
+    #[test]
+    fn test_process_batch_excel_shortfall_cycle() {
+        let marks = run_shortfall(ShortfallPolicy::Cycle).unwrap();
+        assert_eq!(marks[0].as_deref(), Some("Mark 1"));
+        assert_eq!(marks[1].as_deref(), Some("Mark 2"));
+        assert_eq!(marks[2].as_deref(), Some("Mark 1"), "Cycle should wrap around to the first watermark");
+    }
+
+    #[test]
+    fn test_process_batch_excel_shortfall_error() {
+        match run_shortfall(ShortfallPolicy::Error) {
+            Err(BlindMarkError::InvalidConfig(msg)) => {
+                assert!(msg.contains('3') && msg.contains('2'), "error should mention both counts: {}", msg);
+            }
+            other => panic!("Error policy should fail, got {:?}", other),
+        }
     }
 }
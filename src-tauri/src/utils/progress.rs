@@ -1,6 +1,282 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use serde::Serialize;
+#[cfg(feature = "tauri")]
 use tauri::{AppHandle, Emitter};
 
+/// Receiver for progress reported by the parallel processor and the watermark pipeline
+///
+/// Decouples `ParallelProcessor` from Tauri: the app passes a `ProgressEmitter`,
+/// library users (benchmarks, CLIs) can supply their own implementation.
+/// Only image-level progress is required; the other events are ignored by default.
+pub trait ProgressSink: Send + Sync {
+    /// Report that `current_file` of `total_files` has completed
+    fn emit_progress(
+        &self,
+        current_file: usize,
+        total_files: usize,
+        filename: String,
+        progress: f32,
+        status: String,
+    ) -> Result<(), String>;
+
+    /// Report the end-of-run batch summary (ignored by default)
+    fn emit_batch_summary(&self, summary: BatchSummaryEvent) -> Result<(), String> {
+        let _ = summary;
+        Ok(())
+    }
+
+    /// Report an overall status update (ignored by default)
+    fn emit_status(&self, status: String, message: String) -> Result<(), String> {
+        let _ = (status, message);
+        Ok(())
+    }
+
+    /// Report per-type file counts found by the scan (ignored by default)
+    fn emit_scan_summary(&self, summary: ScanSummaryEvent) -> Result<(), String> {
+        let _ = summary;
+        Ok(())
+    }
+
+    /// Report the file about to be processed (ignored by default)
+    fn emit_detail_progress(&self, detail: DetailProgressEvent) -> Result<(), String> {
+        let _ = detail;
+        Ok(())
+    }
+
+    /// Report bytes written while packaging an output archive (ignored by default)
+    fn emit_packaging_progress(&self, progress: PackagingProgressEvent) -> Result<(), String> {
+        let _ = progress;
+        Ok(())
+    }
+}
+
+/// Default minimum gap between forwarded progress events (~30 per second)
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(33);
+
+/// Per-type file count up to which every detail-progress event is forwarded
+pub const DETAIL_PROGRESS_UNTHROTTLED_MAX: usize = 200;
+
+/// `ProgressSink` wrapper that coalesces high-frequency updates
+///
+/// Image progress is forwarded at most once per `interval`; the final update
+/// (`current_file == total_files`) always goes through so the UI reaches 100%.
+/// Detail-progress events are forwarded unchanged for categories of up to
+/// `DETAIL_PROGRESS_UNTHROTTLED_MAX` files and throttled the same way above that.
+/// Packaging progress is throttled like image progress, always keeping the final update.
+/// Status, scan and summary events are never dropped.
+pub struct ThrottledSink {
+    inner: Arc<dyn ProgressSink>,
+    interval: Duration,
+    last_progress: Mutex<Option<Instant>>,
+    last_detail: Mutex<Option<Instant>>,
+    last_packaging: Mutex<Option<Instant>>,
+}
+
+impl ThrottledSink {
+    pub fn new(inner: Arc<dyn ProgressSink>) -> Self {
+        Self::with_interval(inner, DEFAULT_PROGRESS_INTERVAL)
+    }
+
+    pub fn with_interval(inner: Arc<dyn ProgressSink>, interval: Duration) -> Self {
+        Self {
+            inner,
+            interval,
+            last_progress: Mutex::new(None),
+            last_detail: Mutex::new(None),
+            last_packaging: Mutex::new(None),
+        }
+    }
+
+    /// Returns true (and records the time) if an event may be forwarded now
+    fn admit(&self, last: &Mutex<Option<Instant>>, force: bool) -> bool {
+        let mut last = last.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if force || last.is_none_or(|t| now.duration_since(t) >= self.interval) {
+            *last = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl ProgressSink for ThrottledSink {
+    fn emit_progress(
+        &self,
+        current_file: usize,
+        total_files: usize,
+        filename: String,
+        progress: f32,
+        status: String,
+    ) -> Result<(), String> {
+        if !self.admit(&self.last_progress, current_file >= total_files) {
+            return Ok(());
+        }
+        self.inner.emit_progress(current_file, total_files, filename, progress, status)
+    }
+
+    fn emit_batch_summary(&self, summary: BatchSummaryEvent) -> Result<(), String> {
+        self.inner.emit_batch_summary(summary)
+    }
+
+    fn emit_status(&self, status: String, message: String) -> Result<(), String> {
+        self.inner.emit_status(status, message)
+    }
+
+    fn emit_scan_summary(&self, summary: ScanSummaryEvent) -> Result<(), String> {
+        self.inner.emit_scan_summary(summary)
+    }
+
+    fn emit_detail_progress(&self, detail: DetailProgressEvent) -> Result<(), String> {
+        let force = detail.type_total <= DETAIL_PROGRESS_UNTHROTTLED_MAX || detail.type_current >= detail.type_total;
+        if !self.admit(&self.last_detail, force) {
+            return Ok(());
+        }
+        self.inner.emit_detail_progress(detail)
+    }
+
+    fn emit_packaging_progress(&self, progress: PackagingProgressEvent) -> Result<(), String> {
+        if !self.admit(&self.last_packaging, progress.bytes_written >= progress.total_bytes) {
+            return Ok(());
+        }
+        self.inner.emit_packaging_progress(progress)
+    }
+}
+
+/// Latest known progress of a running task, kept independently of the event stream
+///
+/// Lets a frontend that reloaded mid-run re-attach: it queries the snapshot by task id
+/// instead of waiting for the next event.
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskProgress {
+    /// Last overall status and its message (e.g. "processing_images")
+    pub status: String,
+    pub message: String,
+    /// Current watermark index (1-based) and total watermarks, from detail progress
+    pub batch_current: usize,
+    pub batch_total: usize,
+    /// Image-level progress of the current watermark
+    pub current_file: usize,
+    pub total_files: usize,
+    /// Category, position and filename of the file being processed
+    pub file_type: String,
+    pub type_current: usize,
+    pub type_total: usize,
+    pub filename: String,
+}
+
+/// Process-wide store of `TaskProgress` snapshots keyed by task id
+#[derive(Default)]
+pub struct ProgressRegistry {
+    tasks: Mutex<HashMap<String, TaskProgress>>,
+}
+
+impl ProgressRegistry {
+    /// Registry shared by all commands
+    pub fn shared() -> &'static ProgressRegistry {
+        static SHARED: OnceLock<ProgressRegistry> = OnceLock::new();
+        SHARED.get_or_init(ProgressRegistry::default)
+    }
+
+    /// Snapshot of `task_id`, or `None` if the task is unknown or already finished
+    pub fn get(&self, task_id: &str) -> Option<TaskProgress> {
+        self.lock().get(task_id).cloned()
+    }
+
+    /// Forget `task_id` (called when the task completes)
+    pub fn remove(&self, task_id: &str) {
+        self.lock().remove(task_id);
+    }
+
+    fn update(&self, task_id: &str, f: impl FnOnce(&mut TaskProgress)) {
+        f(self.lock().entry(task_id.to_string()).or_default());
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, TaskProgress>> {
+        self.tasks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// `ProgressSink` wrapper that records every event into a `ProgressRegistry` before forwarding
+///
+/// Wrap it around the throttled sink so the snapshot never misses an update.
+/// The task's entry is removed when the sink is dropped, i.e. once the run completes.
+pub struct TaskProgressSink {
+    task_id: String,
+    registry: &'static ProgressRegistry,
+    inner: Arc<dyn ProgressSink>,
+}
+
+impl TaskProgressSink {
+    pub fn new(task_id: impl Into<String>, inner: Arc<dyn ProgressSink>) -> Self {
+        Self::with_registry(task_id, ProgressRegistry::shared(), inner)
+    }
+
+    pub fn with_registry(task_id: impl Into<String>, registry: &'static ProgressRegistry, inner: Arc<dyn ProgressSink>) -> Self {
+        let task_id = task_id.into();
+        registry.update(&task_id, |_| {});
+        Self { task_id, registry, inner }
+    }
+}
+
+impl Drop for TaskProgressSink {
+    fn drop(&mut self) {
+        self.registry.remove(&self.task_id);
+    }
+}
+
+impl ProgressSink for TaskProgressSink {
+    fn emit_progress(
+        &self,
+        current_file: usize,
+        total_files: usize,
+        filename: String,
+        progress: f32,
+        status: String,
+    ) -> Result<(), String> {
+        self.registry.update(&self.task_id, |state| {
+            state.current_file = current_file;
+            state.total_files = total_files;
+        });
+        self.inner.emit_progress(current_file, total_files, filename, progress, status)
+    }
+
+    fn emit_batch_summary(&self, summary: BatchSummaryEvent) -> Result<(), String> {
+        self.inner.emit_batch_summary(summary)
+    }
+
+    fn emit_status(&self, status: String, message: String) -> Result<(), String> {
+        self.registry.update(&self.task_id, |state| {
+            state.status = status.clone();
+            state.message = message.clone();
+        });
+        self.inner.emit_status(status, message)
+    }
+
+    fn emit_scan_summary(&self, summary: ScanSummaryEvent) -> Result<(), String> {
+        self.inner.emit_scan_summary(summary)
+    }
+
+    fn emit_detail_progress(&self, detail: DetailProgressEvent) -> Result<(), String> {
+        self.registry.update(&self.task_id, |state| {
+            state.batch_current = detail.batch_current;
+            state.batch_total = detail.batch_total;
+            state.file_type = detail.file_type.clone();
+            state.type_current = detail.type_current;
+            state.type_total = detail.type_total;
+            state.filename = detail.filename.clone();
+        });
+        self.inner.emit_detail_progress(detail)
+    }
+
+    fn emit_packaging_progress(&self, progress: PackagingProgressEvent) -> Result<(), String> {
+        self.inner.emit_packaging_progress(progress)
+    }
+}
+
 /// Progress event for image-level updates (existing, used by parallel processor)
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -31,6 +307,9 @@ pub struct ScanSummaryEvent {
     pub image_count: usize,
     pub vam_count: usize,
     pub vap_count: usize,
+    pub svg_count: usize,
+    pub toml_count: usize,
+    pub ini_count: usize,
 }
 
 /// Emitted for each individual file as it starts being processed.
@@ -51,10 +330,81 @@ pub struct DetailProgressEvent {
     pub filename: String,
 }
 
+/// Byte progress while an output archive is being written
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackagingProgressEvent {
+    /// Output archive filename (not full path)
+    pub filename: String,
+    /// Source bytes written into the archive so far
+    pub bytes_written: u64,
+    /// Total source bytes to write
+    pub total_bytes: u64,
+}
+
+/// A single item that could not be processed normally during a batch run
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFailure {
+    /// Relative path of the affected file (prefixed with `[i/N]` in multi-watermark runs)
+    pub item: String,
+    /// Why it failed / was skipped
+    pub reason: String,
+}
+
+/// A non-fatal issue worth surfacing: the file was processed, but not fully watermarked
+///
+/// Unlike `BatchFailure`, warnings do not turn a run into a partial success.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Warning {
+    /// Relative path of the affected file (prefixed with `[i/N]` in multi-watermark runs)
+    pub file: String,
+    /// What happened to the file instead (e.g. copied without a blind watermark)
+    pub reason: String,
+}
+
+/// Emitted once at the end of `process_archive`, for the frontend results panel.
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummaryEvent {
+    /// Watermarks processed (one output archive each)
+    pub watermark_count: usize,
+    /// Files watermarked per type: "image" | "json" | "vaj" | "vmi" | "vam" | "vap"
+    pub watermarked_by_type: BTreeMap<String, usize>,
+    /// Other files copied unchanged
+    pub copied_count: usize,
+    /// Wall-clock duration of the whole run in milliseconds
+    pub elapsed_ms: u64,
+    /// Per-item failures (files kept as-is, skipped symlinks)
+    pub failures: Vec<BatchFailure>,
+    /// Non-fatal issues (JPEGs copied unwatermarked, metadata-only images, repaired JSON)
+    pub warnings: Vec<Warning>,
+}
+
+impl BatchSummaryEvent {
+    /// Add `count` watermarked files of `file_type`
+    pub fn record_watermarked(&mut self, file_type: &str, count: usize) {
+        *self.watermarked_by_type.entry(file_type.to_string()).or_default() += count;
+    }
+
+    /// Record a per-item failure
+    pub fn record_failure(&mut self, item: impl Into<String>, reason: impl Into<String>) {
+        self.failures.push(BatchFailure { item: item.into(), reason: reason.into() });
+    }
+
+    /// Record a non-fatal warning
+    pub fn record_warning(&mut self, file: impl Into<String>, reason: impl Into<String>) {
+        self.warnings.push(Warning { file: file.into(), reason: reason.into() });
+    }
+}
+
+#[cfg(feature = "tauri")]
 pub struct ProgressEmitter {
     app: AppHandle,
 }
 
+#[cfg(feature = "tauri")]
 impl ProgressEmitter {
     pub fn new(app: AppHandle) -> Self {
         Self { app }
@@ -84,6 +434,7 @@ impl ProgressEmitter {
     }
 
     /// Emit scan summary (once per archive run, after scanning)
+    #[allow(clippy::too_many_arguments)]
     pub fn emit_scan_summary(
         &self,
         json_count: usize,
@@ -92,11 +443,14 @@ impl ProgressEmitter {
         image_count: usize,
         vam_count: usize,
         vap_count: usize,
+        svg_count: usize,
+        toml_count: usize,
+        ini_count: usize,
     ) -> Result<(), String> {
-        let event = ScanSummaryEvent { json_count, vaj_count, vmi_count, image_count, vam_count, vap_count };
-        self.app
-            .emit("watermark-scan-summary", event)
-            .map_err(|e| format!("Failed to emit scan summary: {}", e))
+        let event = ScanSummaryEvent {
+            json_count, vaj_count, vmi_count, image_count, vam_count, vap_count, svg_count, toml_count, ini_count,
+        };
+        ProgressSink::emit_scan_summary(self, event)
     }
 
     /// Emit per-file detail progress
@@ -117,9 +471,14 @@ impl ProgressEmitter {
             type_total,
             filename: filename.to_string(),
         };
+        ProgressSink::emit_detail_progress(self, event)
+    }
+
+    /// Emit the end-of-run batch summary
+    pub fn emit_batch_summary(&self, summary: BatchSummaryEvent) -> Result<(), String> {
         self.app
-            .emit("watermark-detail-progress", event)
-            .map_err(|e| format!("Failed to emit detail progress: {}", e))
+            .emit("watermark-batch-summary", summary)
+            .map_err(|e| format!("Failed to emit batch summary: {}", e))
     }
 
     /// Emit completion event
@@ -132,3 +491,136 @@ impl ProgressEmitter {
         self.emit_status("error".to_string(), error)
     }
 }
+
+#[cfg(feature = "tauri")]
+impl ProgressSink for ProgressEmitter {
+    fn emit_progress(
+        &self,
+        current_file: usize,
+        total_files: usize,
+        filename: String,
+        progress: f32,
+        status: String,
+    ) -> Result<(), String> {
+        ProgressEmitter::emit_progress(self, current_file, total_files, filename, progress, status)
+    }
+
+    fn emit_batch_summary(&self, summary: BatchSummaryEvent) -> Result<(), String> {
+        ProgressEmitter::emit_batch_summary(self, summary)
+    }
+
+    fn emit_status(&self, status: String, message: String) -> Result<(), String> {
+        ProgressEmitter::emit_status(self, status, message)
+    }
+
+    fn emit_scan_summary(&self, summary: ScanSummaryEvent) -> Result<(), String> {
+        self.app
+            .emit("watermark-scan-summary", summary)
+            .map_err(|e| format!("Failed to emit scan summary: {}", e))
+    }
+
+    fn emit_detail_progress(&self, detail: DetailProgressEvent) -> Result<(), String> {
+        self.app
+            .emit("watermark-detail-progress", detail)
+            .map_err(|e| format!("Failed to emit detail progress: {}", e))
+    }
+
+    fn emit_packaging_progress(&self, progress: PackagingProgressEvent) -> Result<(), String> {
+        self.app
+            .emit("watermark-packaging-progress", progress)
+            .map_err(|e| format!("Failed to emit packaging progress: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct CountingSink {
+        progress: Mutex<Vec<usize>>,
+        details: Mutex<usize>,
+    }
+
+    impl ProgressSink for CountingSink {
+        fn emit_progress(&self, current_file: usize, _: usize, _: String, _: f32, _: String) -> Result<(), String> {
+            self.progress.lock().unwrap().push(current_file);
+            Ok(())
+        }
+
+        fn emit_detail_progress(&self, _: DetailProgressEvent) -> Result<(), String> {
+            *self.details.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    fn detail(type_current: usize, type_total: usize) -> DetailProgressEvent {
+        DetailProgressEvent {
+            batch_current: 1,
+            batch_total: 1,
+            file_type: "image".to_string(),
+            type_current,
+            type_total,
+            filename: "a.png".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_throttled_sink_coalesces_progress() {
+        let inner = Arc::new(CountingSink::default());
+        let sink = ThrottledSink::with_interval(inner.clone(), Duration::from_secs(3600));
+
+        let total = 1000;
+        for i in 1..=total {
+            sink.emit_progress(i, total, "a.png".to_string(), i as f32 / total as f32, "processing".to_string())
+                .unwrap();
+        }
+
+        let progress = inner.progress.lock().unwrap();
+        assert!(progress.len() < total, "expected fewer events than files, got {}", progress.len());
+        assert_eq!(progress.first(), Some(&1), "first update goes through");
+        assert_eq!(progress.last(), Some(&total), "final 100% update must always be emitted");
+    }
+
+    #[test]
+    fn test_task_progress_queryable_mid_run() {
+        let registry: &'static ProgressRegistry = Box::leak(Box::default());
+        let inner = Arc::new(CountingSink::default());
+        let sink = TaskProgressSink::with_registry("task-1", registry, inner.clone());
+        assert_eq!(registry.get("task-1"), Some(TaskProgress::default()), "registered before the first event");
+
+        sink.emit_status("processing_images".to_string(), "正在处理 2 张图片...".to_string()).unwrap();
+        sink.emit_detail_progress(DetailProgressEvent { batch_current: 2, batch_total: 3, ..detail(1, 2) }).unwrap();
+        sink.emit_progress(1, 2, "a.png".to_string(), 0.5, "processing".to_string()).unwrap();
+
+        let state = registry.get("task-1").expect("running task is queryable");
+        assert_eq!(state.status, "processing_images");
+        assert_eq!((state.batch_current, state.batch_total), (2, 3));
+        assert_eq!((state.current_file, state.total_files), (1, 2));
+        assert_eq!((state.file_type.as_str(), state.filename.as_str()), ("image", "a.png"));
+        assert_eq!(*inner.progress.lock().unwrap(), vec![1], "events are still forwarded");
+        assert!(registry.get("task-2").is_none());
+
+        drop(sink);
+        assert!(registry.get("task-1").is_none(), "state is cleaned up on completion");
+    }
+
+    #[test]
+    fn test_throttled_sink_keeps_small_batch_details() {
+        let inner = Arc::new(CountingSink::default());
+        let sink = ThrottledSink::with_interval(inner.clone(), Duration::from_secs(3600));
+
+        for i in 1..=10 {
+            sink.emit_detail_progress(detail(i, 10)).unwrap();
+        }
+        assert_eq!(*inner.details.lock().unwrap(), 10, "small batches keep every detail event");
+
+        let inner = Arc::new(CountingSink::default());
+        let sink = ThrottledSink::with_interval(inner.clone(), Duration::from_secs(3600));
+        let large = DETAIL_PROGRESS_UNTHROTTLED_MAX * 5;
+        for i in 1..=large {
+            sink.emit_detail_progress(detail(i, large)).unwrap();
+        }
+        assert_eq!(*inner.details.lock().unwrap(), 2, "large batches only forward first and last");
+    }
+}
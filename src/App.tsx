@@ -22,6 +22,9 @@ interface ScanSummary {
   imageCount: number;
   vamCount: number;
   vapCount: number;
+  svgCount: number;
+  tomlCount: number;
+  iniCount: number;
 }
 
 interface DetailProgress {
@@ -33,6 +36,17 @@ interface DetailProgress {
   filename: string;
 }
 
+interface BatchFailure {
+  item: string;
+  reason: string;
+}
+
+// Result of process_archive: failed files are kept as-is instead of aborting the batch
+type ProcessOutcome =
+  | { status: 'success'; output: string }
+  | { status: 'partial'; output: string; failures: BatchFailure[] }
+  | { status: 'failed'; failures: BatchFailure[] };
+
 // Tracks how far each file type has progressed (persists across detail events)
 interface TypeCounters {
   json: number;
@@ -41,6 +55,9 @@ interface TypeCounters {
   image: number;
   vam: number;
   vap: number;
+  svg: number;
+  toml: number;
+  ini: number;
 }
 
 interface EmbedState {
@@ -54,11 +71,14 @@ interface EmbedState {
   processVmi: boolean;
   processVam: boolean;
   processVap: boolean;
+  processSvg: boolean;
+  processConfig: boolean;
   watermarkKey: string;
   processObfuscation: boolean;
   watermarkMode: 'md5' | 'plaintext' | 'aes';
   aesKey: string;
   fastMode: boolean;
+  normalizeOrientation: boolean;
   outputDir: string | null;
   isProcessing: boolean;
   statusMessage: string;
@@ -86,6 +106,8 @@ interface WatermarkFinding {
   value: string;
   mode: string;
   decrypted: boolean;
+  pointer: string;
+  fileType: 'json' | 'svg' | 'config';
 }
 
 interface ImageWatermarkFinding {
@@ -99,6 +121,7 @@ interface ExtractState {
   result: WatermarkFinding[] | null;
   imageFindings: ImageWatermarkFinding[] | null;
   scannedPngCount: number | null;
+  aesKeyWarning: string | null;
   error: string | null;
   aesKey: string;
   scanImages: boolean;
@@ -127,11 +150,14 @@ function App() {
     processVmi: true,
     processVam: true,
     processVap: true,
+    processSvg: false,
+    processConfig: false,
     watermarkKey: '',
     processObfuscation: false,
     watermarkMode: 'md5',
     aesKey: '',
     fastMode: false,
+    normalizeOrientation: false,
     outputDir: null,
     isProcessing: false,
     statusMessage: '',
@@ -141,7 +167,7 @@ function App() {
     progressFilename: '',
     scan: null,
     detail: null,
-    typeCounters: { json: 0, vaj: 0, vmi: 0, image: 0, vam: 0, vap: 0 },
+    typeCounters: { json: 0, vaj: 0, vmi: 0, image: 0, vam: 0, vap: 0, svg: 0, toml: 0, ini: 0 },
     outputPath: null,
     error: null,
     imageList: [],
@@ -155,6 +181,7 @@ function App() {
     result: null,
     imageFindings: null,
     scannedPngCount: null,
+    aesKeyWarning: null,
     error: null,
     aesKey: '',
     scanImages: true,
@@ -204,7 +231,7 @@ function App() {
         setEmbed((prev) => ({
           ...prev,
           scan: event.payload,
-          typeCounters: { json: 0, vaj: 0, vmi: 0, image: 0, vam: 0, vap: 0 },
+          typeCounters: { json: 0, vaj: 0, vmi: 0, image: 0, vam: 0, vap: 0, svg: 0, toml: 0, ini: 0 },
         }));
       });
 
@@ -317,10 +344,10 @@ function App() {
   }, []);
 
   const handleProcess = useCallback(async () => {
-    const { archivePath, sourceType, singleText, excelPath, processImages, processJson, processVaj, processVmi, processVam, processVap, watermarkKey, outputDir, processObfuscation, watermarkMode, aesKey, selectedImages, fastMode } = embed;
+    const { archivePath, sourceType, singleText, excelPath, processImages, processJson, processVaj, processVmi, processVam, processVap, processSvg, processConfig, watermarkKey, outputDir, processObfuscation, watermarkMode, aesKey, selectedImages, fastMode, normalizeOrientation } = embed;
 
     if (!archivePath) { setEmbed((prev) => ({ ...prev, error: '请先选择压缩包' })); return; }
-    if (!processImages && !processJson && !processVaj && !processVmi && !processVam && !processVap) {
+    if (!processImages && !processJson && !processVaj && !processVmi && !processVam && !processVap && !processSvg && !processConfig) {
       setEmbed((prev) => ({ ...prev, error: '请至少选择一种水印类型' })); return;
     }
     if (sourceType === 'singleText' && !singleText.trim()) {
@@ -355,21 +382,31 @@ function App() {
       progressTotal: 0,
       scan: null,
       detail: null,
-      typeCounters: { json: 0, vaj: 0, vmi: 0, image: 0, vam: 0, vap: 0 },
+      typeCounters: { json: 0, vaj: 0, vmi: 0, image: 0, vam: 0, vap: 0, svg: 0, toml: 0, ini: 0 },
     }));
 
     try {
-      const outputPath = await invoke<string>('process_archive', {
-        archivePath, config, processImages, processJson, processVaj, processVmi,
-        processVam, processVap,
-        outputDir: outputDir ?? null,
-        obfuscate: processObfuscation,
-        watermarkMode,
-        aesKey: aesKey.trim() || null,
-        selectedImages: processImages && selectedImages.length > 0 ? selectedImages : null,
-        fastMode,
+      const outcome = await invoke<ProcessOutcome>('process_archive', {
+        archivePath,
+        config,
+        request: {
+          processImages, processJson, processVaj, processVmi,
+          processVam, processVap, processSvg,
+          processConfig,
+          outputDir: outputDir ?? null,
+          obfuscate: processObfuscation,
+          watermarkMode,
+          aesKey: aesKey.trim() || null,
+          selectedImages: processImages && selectedImages.length > 0 ? selectedImages : null,
+          fastMode,
+          normalizeOrientation,
+        },
       });
-      setEmbed((prev) => ({ ...prev, isProcessing: false, outputPath, statusCode: 'complete', statusMessage: '处理完成' }));
+      if (outcome.status === 'failed') {
+        throw new Error(outcome.failures.map((f) => `${f.item}: ${f.reason}`).join('\n') || '所有文件均处理失败');
+      }
+      const statusMessage = outcome.status === 'partial' ? `处理完成（${outcome.failures.length} 项失败）` : '处理完成';
+      setEmbed((prev) => ({ ...prev, isProcessing: false, outputPath: outcome.output, statusCode: 'complete', statusMessage }));
     } catch (err) {
       setEmbed((prev) => ({ ...prev, isProcessing: false, error: String(err), statusCode: 'error', statusMessage: String(err) }));
     }
@@ -390,18 +427,19 @@ function App() {
     const { archivePath, aesKey, scanImages } = extract;
     if (!archivePath) { setExtract((prev) => ({ ...prev, error: '请先选择压缩包' })); return; }
 
-    setExtract((prev) => ({ ...prev, isExtracting: true, result: null, imageFindings: null, scannedPngCount: null, error: null }));
+    setExtract((prev) => ({ ...prev, isExtracting: true, result: null, imageFindings: null, scannedPngCount: null, aesKeyWarning: null, error: null }));
     try {
-      const { jsonFindings, imageFindings, scannedPngCount } = await invoke<{
+      const { jsonFindings, imageFindings, scannedPngCount, aesKeyWarning } = await invoke<{
         jsonFindings: WatermarkFinding[];
         imageFindings: ImageWatermarkFinding[];
         scannedPngCount: number;
+        aesKeyWarning: string | null;
       }>('scan_all_watermarks_in_archive', {
         archivePath,
         aesKey: aesKey.trim() || null,
         scanImages,
       });
-      setExtract((prev) => ({ ...prev, isExtracting: false, result: jsonFindings, imageFindings, scannedPngCount }));
+      setExtract((prev) => ({ ...prev, isExtracting: false, result: jsonFindings, imageFindings, scannedPngCount, aesKeyWarning }));
     } catch (err) {
       setExtract((prev) => ({ ...prev, isExtracting: false, error: String(err) }));
     }
@@ -696,6 +734,8 @@ function App() {
                     { key: 'processVmi',    label: 'VMI' },
                     { key: 'processVam',    label: 'VAM' },
                     { key: 'processVap',    label: 'VAP' },
+                    { key: 'processSvg',    label: 'SVG' },
+                    { key: 'processConfig', label: 'TOML/INI' },
                     { key: 'processImages', label: '图片*' },
                   ] as { key: keyof EmbedState; label: string }[]).map(({ key, label }) => {
                     const checked = embed[key] as boolean;
@@ -751,6 +791,28 @@ function App() {
                 </div>
               )}
 
+              {/* EXIF orientation toggle */}
+              {embed.processImages && (
+                <div className="flex items-center justify-between">
+                  <div>
+                    <p className="text-sm" style={{ color: t.text }}>校正图片方向</p>
+                    <p className="text-xs mt-0.5" style={{ color: t.textDim }}>
+                      按 EXIF 方向旋转图片后再嵌入 / 复制，并移除方向标记
+                    </p>
+                  </div>
+                  <button
+                    role="switch"
+                    aria-checked={embed.normalizeOrientation}
+                    onClick={() => setEmbed((prev) => ({ ...prev, normalizeOrientation: !prev.normalizeOrientation }))}
+                    disabled={embed.isProcessing}
+                    className="relative inline-flex h-6 w-11 shrink-0 items-center rounded-full transition-colors cursor-pointer focus:outline-none disabled:opacity-50"
+                    style={{ background: embed.normalizeOrientation ? '#00f5ff' : t.toggleOff, boxShadow: embed.normalizeOrientation ? '0 0 10px rgba(0,245,255,0.5)' : 'none' }}
+                  >
+                    <span className={`inline-block h-4 w-4 transform rounded-full bg-white shadow transition-transform ${embed.normalizeOrientation ? 'translate-x-6' : 'translate-x-1'}`} />
+                  </button>
+                </div>
+              )}
+
               {/* Image selection list */}
               {embed.processImages && (
                 <div>
@@ -968,6 +1030,9 @@ function App() {
                         { key: 'vmi'    as keyof TypeCounters, label: 'VMI',    total: embed.scan.vmiCount,    show: embed.processVmi },
                         { key: 'vam'    as keyof TypeCounters, label: 'VAM',    total: embed.scan.vamCount,    show: embed.processVam },
                         { key: 'vap'    as keyof TypeCounters, label: 'VAP',    total: embed.scan.vapCount,    show: embed.processVap },
+                        { key: 'svg'    as keyof TypeCounters, label: 'SVG',    total: embed.scan.svgCount,    show: embed.processSvg },
+                        { key: 'toml'   as keyof TypeCounters, label: 'TOML',   total: embed.scan.tomlCount,   show: embed.processConfig },
+                        { key: 'ini'    as keyof TypeCounters, label: 'INI',    total: embed.scan.iniCount,    show: embed.processConfig },
                         { key: 'image'  as keyof TypeCounters, label: '图片',   total: embed.scan.imageCount,  show: embed.processImages },
                       ])
                         .filter(({ show, total }) => show && total > 0)
@@ -1185,6 +1250,14 @@ function App() {
                 );
               })()}
 
+              {extract.aesKeyWarning && (
+                <div className="p-3 rounded-xl" style={{ background: 'rgba(255,200,0,0.06)', border: '1px solid rgba(255,200,0,0.2)' }}>
+                  <p className="text-xs" style={{ color: 'rgba(255,200,0,0.7)' }}>
+                    {extract.aesKeyWarning}
+                  </p>
+                </div>
+              )}
+
               {/* Image watermark findings */}
               {extract.scanImages && extract.scannedPngCount !== null && extract.scannedPngCount === 0 && (
                 <div className="p-3 rounded-xl" style={{ background: 'rgba(255,200,0,0.06)', border: '1px solid rgba(255,200,0,0.2)' }}>
@@ -8,7 +8,8 @@ export type WatermarkSourceType = 'singleText' | 'excelFile';
 /** Matches Rust WatermarkSource enum (tagged union with "type" field) */
 export type WatermarkSource =
   | { type: 'singleText'; content: string }
-  | { type: 'excelFile'; path: string };
+  | { type: 'excelFile'; path: string; hasHeader?: boolean }
+  | { type: 'jsonFile'; path: string };
 
 /** Matches Rust WatermarkConfig struct */
 export interface WatermarkConfig {